@@ -11,6 +11,7 @@ fn criterion_benchmark(c: &mut Criterion) {
         init_logger();
         b.iter(|| test_decode_from())
     });
+    c.bench_function("decode_from_ref", |b| b.iter(|| test_decode_from_ref()));
 }
 
 criterion_group!(benches, criterion_benchmark);
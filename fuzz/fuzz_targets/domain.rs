@@ -0,0 +1,19 @@
+#![no_main]
+
+use dns_core::dns::types::base::{MAX_LABEL_LEN, MAX_NAME_LEN, RawDomain};
+use dns_core::dns::utils::SliceReader;
+use libfuzzer_sys::fuzz_target;
+
+// 第一个字节为名称在报文中的起始位置，以便覆盖指向前方的压缩指针
+fuzz_target!(|data: &[u8]| {
+    let Some((&start, packet)) = data.split_first() else {
+        return;
+    };
+    let mut reader = SliceReader::from_slice(packet);
+    reader.set_pos(start as usize);
+    if let Ok(name) = RawDomain::from_reader(&mut reader) {
+        assert!(name.as_ref().len() < MAX_NAME_LEN);
+        assert!(name.labels().all(|label| label.len() <= MAX_LABEL_LEN));
+        assert!(reader.pos() <= packet.len());
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use dns_core::dns::types::parts::Record;
+use dns_core::dns::utils::SliceReader;
+use libfuzzer_sys::fuzz_target;
+
+// 第一个字节为记录在报文中的起始位置
+fuzz_target!(|data: &[u8]| {
+    let Some((&start, packet)) = data.split_first() else {
+        return;
+    };
+    let mut reader = SliceReader::from_slice(packet);
+    reader.set_pos(start as usize);
+    if Record::new(&mut reader).is_ok() {
+        assert!(reader.pos() <= packet.len());
+    }
+});
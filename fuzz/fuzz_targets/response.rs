@@ -0,0 +1,8 @@
+#![no_main]
+
+use dns_core::dns::types::parts::Response;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Response::from_slice_uncheck(data);
+});
@@ -1,5 +1,5 @@
 use crate::dns::types::base::{DnsTypeNum, RawDomain};
-use crate::dns::types::parts::{Request, Response};
+use crate::dns::types::parts::{Request, Response, ResponseRef};
 use std::rc::Rc;
 
 pub fn test_encode_into() {
@@ -14,18 +14,33 @@ pub fn test_encode_into() {
     }
 }
 
+// www.baidu.com A的响应，一条CNAME和两条A，也供各模块的测试使用
+pub(crate) const DECODE_PACKET: [u8; 90] = [
+    0xb9, 0xde, 0x80, 0x80, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x03, 0x77, 0x77, 0x77,
+    0x05, 0x62, 0x61, 0x69, 0x64, 0x75, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x01, 0x00, 0x01, 0xc0,
+    0x0c, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x0f, 0x03, 0x77, 0x77, 0x77, 0x01,
+    0x61, 0x06, 0x73, 0x68, 0x69, 0x66, 0x65, 0x6e, 0xc0, 0x16, 0xc0, 0x2b, 0x00, 0x01, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x1d, 0x00, 0x04, 0xb7, 0x02, 0xac, 0xb9, 0xc0, 0x2b, 0x00, 0x01, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x1d, 0x00, 0x04, 0xb7, 0x02, 0xac, 0x2a,
+];
+
 pub fn test_decode_from() {
-    let arr = &[
-        0xb9_u8, 0xde, 0x80, 0x80, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x03, 0x77,
-        0x77, 0x77, 0x05, 0x62, 0x61, 0x69, 0x64, 0x75, 0x03, 0x63, 0x6f, 0x6d, 0x00, 0x00, 0x01,
-        0x00, 0x01, 0xc0, 0x0c, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x0f, 0x03,
-        0x77, 0x77, 0x77, 0x01, 0x61, 0x06, 0x73, 0x68, 0x69, 0x66, 0x65, 0x6e, 0xc0, 0x16, 0xc0,
-        0x2b, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x04, 0xb7, 0x02, 0xac, 0xb9,
-        0xc0, 0x2b, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x04, 0xb7, 0x02, 0xac,
-        0x2a,
-    ][..];
+    let arr = &DECODE_PACKET;
     for _ in 0..20000 {
         let response = Response::from_slice_uncheck(arr).unwrap();
         assert_eq!(response.header.id, 0xb9de);
     }
 }
+
+// 与test_decode_from相同的报文，使用借用的ResponseRef取出A记录
+pub fn test_decode_from_ref() {
+    for _ in 0..20000 {
+        let response = ResponseRef::from_slice_uncheck(&DECODE_PACKET).unwrap();
+        assert_eq!(response.header.id, 0xb9de);
+        let count = response
+            .answers()
+            .filter_map(|record| record.ok()?.a())
+            .count();
+        assert_eq!(count, 2);
+    }
+}
@@ -3,3 +3,4 @@ mod net;
 mod resolver;
 pub mod types;
 pub mod utils;
+pub mod zone;
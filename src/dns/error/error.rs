@@ -48,6 +48,17 @@ pub enum NetError {
     WriteTcpConnectError(TraceErrorFormat),
     ConnectUdpAddrError(TraceErrorFormat),
     BindUdpAddrError(TraceErrorFormat),
+    DecodeResponseError(TraceErrorFormat),
+    // 服务器的传输方式尚未实现，或域名没有可用的连接地址
+    UnsupportedServer(TraceErrorFormat),
+    #[cfg(feature = "tls")]
+    TlsError(TraceErrorFormat),
+    #[cfg(feature = "https")]
+    HttpsError(TraceErrorFormat),
+    #[cfg(feature = "quic")]
+    QuicError(TraceErrorFormat),
+    #[cfg(feature = "dnscrypt")]
+    DnsCryptError(TraceErrorFormat),
 }
 
 #[cfg(feature = "result_error")]
@@ -78,6 +89,28 @@ impl Display for NetError {
             NetError::BindUdpAddrError(err) => {
                 write!(f, "BindUdpAddrError {}", err.info)
             }
+            NetError::DecodeResponseError(err) => {
+                write!(f, "DecodeResponseError {}", err.info)
+            }
+            NetError::UnsupportedServer(err) => {
+                write!(f, "UnsupportedServer {}", err.info)
+            }
+            #[cfg(feature = "tls")]
+            NetError::TlsError(err) => {
+                write!(f, "TlsError {}", err.info)
+            }
+            #[cfg(feature = "https")]
+            NetError::HttpsError(err) => {
+                write!(f, "HttpsError {}", err.info)
+            }
+            #[cfg(feature = "quic")]
+            NetError::QuicError(err) => {
+                write!(f, "QuicError {}", err.info)
+            }
+            #[cfg(feature = "dnscrypt")]
+            NetError::DnsCryptError(err) => {
+                write!(f, "DnsCryptError {}", err.info)
+            }
         }
     }
 }
@@ -142,6 +175,44 @@ impl Debug for NetError {
                     err.info, err.trace
                 )
             }
+            NetError::DecodeResponseError(err) => {
+                write!(
+                    f,
+                    "NetError::DecodeResponseError {}\ntrace:\n{}",
+                    err.info, err.trace
+                )
+            }
+            NetError::UnsupportedServer(err) => {
+                write!(
+                    f,
+                    "NetError::UnsupportedServer {}\ntrace:\n{}",
+                    err.info, err.trace
+                )
+            }
+            #[cfg(feature = "tls")]
+            NetError::TlsError(err) => {
+                write!(f, "NetError::TlsError {}\ntrace:\n{}", err.info, err.trace)
+            }
+            #[cfg(feature = "https")]
+            NetError::HttpsError(err) => {
+                write!(
+                    f,
+                    "NetError::HttpsError {}\ntrace:\n{}",
+                    err.info, err.trace
+                )
+            }
+            #[cfg(feature = "quic")]
+            NetError::QuicError(err) => {
+                write!(f, "NetError::QuicError {}\ntrace:\n{}", err.info, err.trace)
+            }
+            #[cfg(feature = "dnscrypt")]
+            NetError::DnsCryptError(err) => {
+                write!(
+                    f,
+                    "NetError::DnsCryptError {}\ntrace:\n{}",
+                    err.info, err.trace
+                )
+            }
         }
     }
 }
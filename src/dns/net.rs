@@ -1,5 +1,29 @@
+mod bind;
+#[cfg(feature = "dnscrypt")]
+mod dnscrypt;
+#[cfg(feature = "https")]
+mod https;
 mod query;
+#[cfg(feature = "quic")]
+mod quic;
+pub(crate) mod tcp_pool;
+#[cfg(feature = "tls")]
+mod tls;
+mod udp_pool;
 
+pub use bind::LocalBind;
+#[cfg(feature = "dnscrypt")]
+pub use dnscrypt::DnsCryptTransport;
+#[cfg(feature = "https")]
+#[allow(unused_imports)]
+pub use https::{DohMethod, HttpsTransport};
 pub use query::NetQuery;
 #[cfg(feature = "result_error")]
 pub use query::NetQueryError;
+#[cfg(feature = "quic")]
+pub use quic::QuicTransport;
+pub use tcp_pool::TcpPool;
+#[cfg(feature = "tls")]
+pub use tls::TlsTransport;
+#[allow(unused_imports)]
+pub use udp_pool::{UdpPool, UdpStats};
@@ -0,0 +1,157 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+// 查询时使用的本地地址与网卡。按上游地址的地址族分别绑定，未设置的地址族使用通配地址，
+// IPv6 socket设置IPV6_V6ONLY，不会通过IPv4映射地址发出查询
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalBind {
+    v4: Option<Ipv4Addr>,
+    v6: Option<Ipv6Addr>,
+    interface: Option<String>,
+}
+
+impl LocalBind {
+    pub fn new() -> LocalBind {
+        Self::default()
+    }
+
+    // IPv4与IPv6地址可以各设置一个
+    pub fn with_addr(mut self, addr: IpAddr) -> LocalBind {
+        match addr {
+            IpAddr::V4(addr) => self.v4 = Some(addr),
+            IpAddr::V6(addr) => self.v6 = Some(addr),
+        }
+        self
+    }
+
+    // SO_BINDTODEVICE，只在Linux上可用
+    pub fn with_interface(mut self, interface: &str) -> LocalBind {
+        self.interface = Some(interface.to_string());
+        self
+    }
+
+    fn local_addr(&self, ipv6: bool) -> SocketAddr {
+        if ipv6 {
+            SocketAddr::from((self.v6.unwrap_or(Ipv6Addr::UNSPECIFIED), 0))
+        } else {
+            SocketAddr::from((self.v4.unwrap_or(Ipv4Addr::UNSPECIFIED), 0))
+        }
+    }
+
+    fn socket(&self, ipv6: bool, ty: Type, protocol: Protocol) -> io::Result<Socket> {
+        let domain = if ipv6 { Domain::IPV6 } else { Domain::IPV4 };
+        let socket = Socket::new(domain, ty, Some(protocol))?;
+        if ipv6 {
+            socket.set_only_v6(true)?;
+        }
+        if let Some(interface) = &self.interface {
+            bind_device(&socket, interface)?;
+        }
+        Ok(socket)
+    }
+
+    pub(crate) fn udp_socket(&self, ipv6: bool) -> io::Result<UdpSocket> {
+        let socket = self.socket(ipv6, Type::DGRAM, Protocol::UDP)?;
+        socket.bind(&SockAddr::from(self.local_addr(ipv6)))?;
+        Ok(socket.into())
+    }
+
+    // 尚未连接的TCP socket，异步传输转换后自行connect。
+    // 没有设置本地地址时由系统在connect时选择
+    pub(crate) fn tcp_socket(&self, addr: SocketAddr) -> io::Result<Socket> {
+        let socket = self.socket(addr.is_ipv6(), Type::STREAM, Protocol::TCP)?;
+        let local = if addr.is_ipv6() {
+            self.v6.map(IpAddr::V6)
+        } else {
+            self.v4.map(IpAddr::V4)
+        };
+        if let Some(local) = local {
+            socket.bind(&SockAddr::from(SocketAddr::new(local, 0)))?;
+        }
+        Ok(socket)
+    }
+
+    pub(crate) fn tcp_connect(
+        &self,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> io::Result<TcpStream> {
+        let socket = self.tcp_socket(addr)?;
+        let addr = SockAddr::from(addr);
+        match timeout {
+            Some(timeout) => socket.connect_timeout(&addr, timeout)?,
+            None => socket.connect(&addr)?,
+        }
+        Ok(socket.into())
+    }
+}
+
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &Socket, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_socket: &Socket, interface: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("binding to interface {} is not supported", interface),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_udp_socket() {
+        let socket = LocalBind::new().udp_socket(true).unwrap();
+        assert!(socket.local_addr().unwrap().is_ipv6());
+        let socket = LocalBind::new().udp_socket(false).unwrap();
+        assert!(socket.local_addr().unwrap().is_ipv4());
+
+        let bind = LocalBind::new()
+            .with_addr("::1".parse().unwrap())
+            .with_addr("127.0.0.1".parse().unwrap());
+        let v6 = bind.udp_socket(true).unwrap().local_addr().unwrap();
+        assert_eq!(v6.ip(), "::1".parse::<IpAddr>().unwrap());
+        let v4 = bind.udp_socket(false).unwrap().local_addr().unwrap();
+        assert_eq!(v4.ip(), "127.0.0.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_tcp_connect() {
+        let listener = std::net::TcpListener::bind("[::1]:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bind = LocalBind::new().with_addr("::1".parse().unwrap());
+        let stream = bind
+            .tcp_connect(addr, Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+        assert_eq!(
+            stream.local_addr().unwrap().ip(),
+            "::1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_interface() {
+        assert!(
+            LocalBind::new()
+                .with_interface("lo")
+                .udp_socket(false)
+                .is_ok()
+        );
+        assert!(
+            LocalBind::new()
+                .with_interface("no-such-if0")
+                .udp_socket(false)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,524 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::LocalBind;
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::types::parts::{RecordDataType, Request, Response};
+use crate::dns::utils::DnsCryptProvider;
+use crypto_box::aead::Aead;
+use crypto_box::{Nonce, PublicKey, SalsaBox, SecretKey};
+use rand::{Rng, rng};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CERT_MAGIC: &[u8; 4] = b"DNSC";
+// X25519-XSalsa20Poly1305
+const ES_VERSION: [u8; 2] = [0x00, 0x01];
+const RESOLVER_MAGIC: &[u8; 8] = b"r6fnvWj8";
+// magic、版本、签名与签名覆盖的resolver公钥、client magic、serial、有效期
+const CERT_SIZE: usize = 124;
+const CLIENT_MAGIC_SIZE: usize = 8;
+const HALF_NONCE_SIZE: usize = 12;
+// UDP查询填充后的最小长度
+const MIN_UDP_QUERY_SIZE: usize = 256;
+const TAG_SIZE: usize = 16;
+
+// 已通过provider签名校验的resolver证书
+struct Certificate {
+    client_magic: [u8; CLIENT_MAGIC_SIZE],
+    serial: u32,
+    ts_end: u32,
+    cipher: SalsaBox,
+}
+
+// DNSCrypt v2，先以TXT查询<provider name>取得resolver证书并用provider公钥校验，
+// 之后的查询用X25519-XSalsa20Poly1305加密并填充到64字节的整数倍。
+// 默认使用UDP，响应被截断时改用TCP重试，证书在过期前按地址与provider缓存
+pub struct DnsCryptTransport {
+    secret_key: SecretKey,
+    tcp: bool,
+    bind: LocalBind,
+    certificates: RefCell<HashMap<(SocketAddr, String), Rc<Certificate>>>,
+}
+
+impl Default for DnsCryptTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DnsCryptTransport {
+    // 客户端密钥对在创建时随机生成
+    pub fn new() -> DnsCryptTransport {
+        let mut secret_key = [0_u8; 32];
+        rng().fill(&mut secret_key);
+        DnsCryptTransport {
+            secret_key: SecretKey::from(secret_key),
+            tcp: false,
+            bind: LocalBind::new(),
+            certificates: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // 总是使用TCP，适用于UDP被阻断的网络
+    pub fn with_tcp(mut self, tcp: bool) -> DnsCryptTransport {
+        self.tcp = tcp;
+        self
+    }
+
+    pub fn with_local_bind(mut self, bind: LocalBind) -> DnsCryptTransport {
+        self.bind = bind;
+        self
+    }
+
+    // 返回解密并去除填充后的响应报文
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        provider: &DnsCryptProvider,
+        timeout: Option<Duration>,
+        request: &Request,
+    ) -> io::Result<Vec<u8>> {
+        let certificate = self.certificate(addr, provider, timeout)?;
+        let mut buf = [0_u8; 1500];
+        let query = &request.encode_to_tcp(&mut buf)[2..];
+        if !self.tcp {
+            let (packet, nonce) = self.encrypt(&certificate, query, MIN_UDP_QUERY_SIZE)?;
+            let response = udp_exchange(&self.bind, addr, timeout, &packet)?;
+            let message = decrypt(&certificate, &nonce, &response)?;
+            // TC位，改用TCP重新发送
+            if message.get(2).is_none_or(|flags| flags & 0x02 == 0) {
+                return Ok(message);
+            }
+        }
+        let (packet, nonce) = self.encrypt(&certificate, query, 0)?;
+        let response = tcp_exchange(&self.bind, addr, timeout, &packet)?;
+        decrypt(&certificate, &nonce, &response)
+    }
+
+    fn certificate(
+        &self,
+        addr: SocketAddr,
+        provider: &DnsCryptProvider,
+        timeout: Option<Duration>,
+    ) -> io::Result<Rc<Certificate>> {
+        let key = (addr, provider.name.clone());
+        let now = unix_time();
+        if let Some(certificate) = self.certificates.borrow().get(&key)
+            && now <= certificate.ts_end
+        {
+            return Ok(certificate.clone());
+        }
+        let certificate = Rc::new(self.fetch_certificate(addr, provider, timeout, now)?);
+        self.certificates
+            .borrow_mut()
+            .insert(key, certificate.clone());
+        Ok(certificate)
+    }
+
+    // 证书以TXT记录发布，同时存在多张有效证书时使用serial最大的
+    fn fetch_certificate(
+        &self,
+        addr: SocketAddr,
+        provider: &DnsCryptProvider,
+        timeout: Option<Duration>,
+        now: u32,
+    ) -> io::Result<Certificate> {
+        let name = RawDomain::from_str(&provider.name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid provider name"))?;
+        let request = Request::new(Rc::new(name), DnsTypeNum::TXT);
+        let mut buf = [0_u8; 1500];
+        let query = &request.encode_to_tcp(&mut buf)[2..];
+        let mut message = match self.tcp {
+            true => tcp_exchange(&self.bind, addr, timeout, query)?,
+            false => udp_exchange(&self.bind, addr, timeout, query)?,
+        };
+        if !self.tcp && message.get(2).is_some_and(|flags| flags & 0x02 != 0) {
+            message = tcp_exchange(&self.bind, addr, timeout, query)?;
+        }
+        let response = Response::from_slice(&message, &request)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        response
+            .answers()
+            .iter()
+            .filter_map(|record| match &record.data {
+                RecordDataType::TXT(txt) => {
+                    let data: Vec<u8> = txt
+                        .get_index()
+                        .iter()
+                        .flat_map(|s| s.as_bytes().iter().copied())
+                        .collect();
+                    parse_certificate(&data, &provider.public_key, now, &self.secret_key)
+                }
+                _ => None,
+            })
+            .max_by_key(|certificate| certificate.serial)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "no valid certificate signed by the provider key",
+                )
+            })
+    }
+
+    // <client-magic> <client-pk> <client-nonce> <encrypted-query>，返回数据包与完整的nonce
+    fn encrypt(
+        &self,
+        certificate: &Certificate,
+        query: &[u8],
+        min_size: usize,
+    ) -> io::Result<(Vec<u8>, [u8; 24])> {
+        let mut nonce = [0_u8; 24];
+        rng().fill(&mut nonce[..HALF_NONCE_SIZE]);
+        let encrypted = certificate
+            .cipher
+            .encrypt(&Nonce::from(nonce), pad(query, min_size).as_slice())
+            .map_err(|_| io::Error::other("failed to encrypt query"))?;
+        let mut packet =
+            Vec::with_capacity(CLIENT_MAGIC_SIZE + 32 + HALF_NONCE_SIZE + encrypted.len());
+        packet.extend_from_slice(&certificate.client_magic);
+        packet.extend_from_slice(self.secret_key.public_key().as_bytes());
+        packet.extend_from_slice(&nonce[..HALF_NONCE_SIZE]);
+        packet.extend_from_slice(&encrypted);
+        Ok((packet, nonce))
+    }
+}
+
+// cert-magic es-version protocol-minor-version signature resolver-pk client-magic serial ts-start ts-end
+fn parse_certificate(
+    data: &[u8],
+    provider_key: &[u8; 32],
+    now: u32,
+    secret_key: &SecretKey,
+) -> Option<Certificate> {
+    if data.len() < CERT_SIZE || &data[..4] != CERT_MAGIC || data[4..6] != ES_VERSION {
+        return None;
+    }
+    let (signature, signed) = data[8..].split_at(64);
+    UnparsedPublicKey::new(&ED25519, provider_key)
+        .verify(signed, signature)
+        .ok()?;
+    let resolver_pk: [u8; 32] = signed[..32].try_into().ok()?;
+    let u32_at = |pos: usize| u32::from_be_bytes(signed[pos..pos + 4].try_into().unwrap());
+    let (ts_start, ts_end) = (u32_at(44), u32_at(48));
+    if now < ts_start || now > ts_end {
+        return None;
+    }
+    Some(Certificate {
+        client_magic: signed[32..40].try_into().ok()?,
+        serial: u32_at(40),
+        ts_end,
+        cipher: SalsaBox::new(&PublicKey::from(resolver_pk), secret_key),
+    })
+}
+
+// ISO/IEC 7816-4填充，0x80后补0到64字节的整数倍，且不短于min_size
+fn pad(query: &[u8], min_size: usize) -> Vec<u8> {
+    let size = (query.len() + 1).max(min_size).next_multiple_of(64);
+    let mut padded = Vec::with_capacity(size);
+    padded.extend_from_slice(query);
+    padded.push(0x80);
+    padded.resize(size, 0);
+    padded
+}
+
+fn unpad(mut padded: Vec<u8>) -> Option<Vec<u8>> {
+    let end = padded.iter().rposition(|&b| b != 0)?;
+    (padded[end] == 0x80).then(|| {
+        padded.truncate(end);
+        padded
+    })
+}
+
+// <resolver-magic> <nonce> <encrypted-response>，nonce的前半部分必须与查询相同
+fn decrypt(certificate: &Certificate, nonce: &[u8; 24], response: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |info: &str| io::Error::new(io::ErrorKind::InvalidData, info.to_string());
+    if response.len() < RESOLVER_MAGIC.len() + 24 + TAG_SIZE
+        || &response[..8] != RESOLVER_MAGIC
+        || response[8..8 + HALF_NONCE_SIZE] != nonce[..HALF_NONCE_SIZE]
+    {
+        return Err(invalid("unexpected DNSCrypt response"));
+    }
+    let nonce = Nonce::from_slice(&response[8..32]);
+    let padded = certificate
+        .cipher
+        .decrypt(nonce, &response[32..])
+        .map_err(|_| invalid("failed to decrypt response"))?;
+    unpad(padded).ok_or_else(|| invalid("invalid response padding"))
+}
+
+fn udp_exchange(
+    bind: &LocalBind,
+    addr: SocketAddr,
+    timeout: Option<Duration>,
+    packet: &[u8],
+) -> io::Result<Vec<u8>> {
+    let socket = bind.udp_socket(addr.is_ipv6())?;
+    socket.set_read_timeout(timeout)?;
+    socket.connect(addr)?;
+    socket.send(packet)?;
+    let mut buf = vec![0_u8; u16::MAX as usize];
+    let len = socket.recv(&mut buf)?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+fn tcp_exchange(
+    bind: &LocalBind,
+    addr: SocketAddr,
+    timeout: Option<Duration>,
+    packet: &[u8],
+) -> io::Result<Vec<u8>> {
+    let mut stream = bind.tcp_connect(addr, timeout)?;
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    let len = u16::try_from(packet.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "query too large"))?;
+    let mut framed = Vec::with_capacity(packet.len() + 2);
+    framed.extend_from_slice(&len.to_be_bytes());
+    framed.extend_from_slice(packet);
+    stream.write_all(&framed)?;
+    let mut len = [0_u8; 2];
+    stream.read_exact(&mut len)?;
+    let mut message = vec![0_u8; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+fn unix_time() -> u32 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    u32::try_from(secs).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::net::NetQuery;
+    use crate::dns::types::base::RawString;
+    use crate::dns::types::base::record::{A, TXT};
+    use crate::dns::types::parts::{MessageBuilder, Record};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CLIENT_MAGIC: [u8; 8] = *b"testmagc";
+
+    #[derive(Default)]
+    struct Counters {
+        certificate_queries: AtomicUsize,
+        tcp_connections: AtomicUsize,
+    }
+
+    struct TestServer {
+        certificate: Vec<u8>,
+        secret_key: SecretKey,
+    }
+
+    impl TestServer {
+        // provider密钥签发的证书，有效期为[ts_start, ts_end]
+        fn new(provider: &Ed25519KeyPair, ts_start: u32, ts_end: u32) -> TestServer {
+            let mut secret_key = [0_u8; 32];
+            rng().fill(&mut secret_key);
+            let secret_key = SecretKey::from(secret_key);
+            let mut signed = Vec::new();
+            signed.extend_from_slice(secret_key.public_key().as_bytes());
+            signed.extend_from_slice(&CLIENT_MAGIC);
+            signed.extend_from_slice(&1_u32.to_be_bytes());
+            signed.extend_from_slice(&ts_start.to_be_bytes());
+            signed.extend_from_slice(&ts_end.to_be_bytes());
+            let mut certificate = Vec::from(*CERT_MAGIC);
+            certificate.extend_from_slice(&ES_VERSION);
+            certificate.extend_from_slice(&[0, 0]);
+            certificate.extend_from_slice(provider.sign(&signed).as_ref());
+            certificate.extend_from_slice(&signed);
+            TestServer {
+                certificate,
+                secret_key,
+            }
+        }
+
+        // 证书查询返回TXT，加密查询回复192.0.2.1，UDP下查询tc.example时设置TC位
+        fn respond(&self, packet: &[u8], udp: bool, counters: &Counters) -> Option<Vec<u8>> {
+            if packet[..8] != CLIENT_MAGIC {
+                counters.certificate_queries.fetch_add(1, Ordering::SeqCst);
+                let txt = TXT::new(vec![RawString::new(self.certificate.clone())?]);
+                return Some(build(packet, RecordDataType::TXT(txt), false));
+            }
+            let client_pk: [u8; 32] = packet[8..40].try_into().ok()?;
+            let cipher = SalsaBox::new(&PublicKey::from(client_pk), &self.secret_key);
+            let mut nonce = [0_u8; 24];
+            nonce[..HALF_NONCE_SIZE].copy_from_slice(&packet[40..52]);
+            let padded = cipher.decrypt(&Nonce::from(nonce), &packet[52..]).ok()?;
+            let query = unpad(padded)?;
+            let question = &Response::from_slice_uncheck(&query).ok()?.question[0];
+            let truncated = udp && RawDomain::to_string(&question.qname)?.starts_with("tc.example");
+            let a = RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 1)));
+            let message = build(&query, a, truncated);
+            rng().fill(&mut nonce[HALF_NONCE_SIZE..]);
+            let encrypted = cipher
+                .encrypt(&Nonce::from(nonce), pad(&message, 0).as_slice())
+                .ok()?;
+            let mut response = Vec::from(*RESOLVER_MAGIC);
+            response.extend_from_slice(&nonce);
+            response.extend_from_slice(&encrypted);
+            Some(response)
+        }
+    }
+
+    fn build(query: &[u8], data: RecordDataType, truncated: bool) -> Vec<u8> {
+        let query = Response::from_slice_uncheck(query).unwrap();
+        let question = &query.question[0];
+        let name = || RawDomain::from_raw(question.qname.as_ref().as_ref().clone());
+        let mut buf = [0_u8; 512];
+        MessageBuilder::new()
+            .id(query.header.id)
+            .response(true)
+            .rec_desired(true)
+            .rec_avail(true)
+            .truncated(truncated)
+            .question(Rc::new(name()), question.qtype, question.qclass)
+            .answer(Record::from_data(name(), 1, 60, data))
+            .encode_to_udp(&mut buf)
+            .to_vec()
+    }
+
+    // UDP与TCP监听同一端口
+    fn serve(server: TestServer) -> (SocketAddr, Arc<Counters>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let socket = UdpSocket::bind(addr).unwrap();
+        let server = Arc::new(server);
+        let counters = Arc::new(Counters::default());
+        let (udp_server, udp_counters) = (server.clone(), counters.clone());
+        std::thread::spawn(move || {
+            let mut buf = [0_u8; 1500];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                if let Some(response) = udp_server.respond(&buf[..len], true, &udp_counters) {
+                    let _ = socket.send_to(&response, peer);
+                }
+            }
+        });
+        let tcp_counters = counters.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                tcp_counters.tcp_connections.fetch_add(1, Ordering::SeqCst);
+                let mut len = [0_u8; 2];
+                if stream.read_exact(&mut len).is_err() {
+                    continue;
+                }
+                let mut packet = vec![0_u8; u16::from_be_bytes(len) as usize];
+                if stream.read_exact(&mut packet).is_err() {
+                    continue;
+                }
+                if let Some(response) = server.respond(&packet, false, &tcp_counters) {
+                    let _ = stream.write_all(&(response.len() as u16).to_be_bytes());
+                    let _ = stream.write_all(&response);
+                }
+            }
+        });
+        (addr, counters)
+    }
+
+    fn provider_key() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn provider(key: &Ed25519KeyPair) -> DnsCryptProvider {
+        DnsCryptProvider {
+            name: "2.dnscrypt-cert.example.test".to_string(),
+            public_key: key.public_key().as_ref().try_into().unwrap(),
+        }
+    }
+
+    fn query(
+        transport: &DnsCryptTransport,
+        addr: SocketAddr,
+        provider: &DnsCryptProvider,
+        qname: &str,
+    ) -> Option<Response> {
+        let request = Request::new(Rc::new(RawDomain::from_str(qname).unwrap()), 1);
+        let timeout = Some(Duration::from_secs(5));
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_dnscrypt(transport, addr, provider, timeout, request).into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_dnscrypt(transport, addr, provider, timeout, request)
+    }
+
+    #[test]
+    fn test_udp_and_tcp() {
+        let key = provider_key();
+        let now = unix_time();
+        let (addr, counters) = serve(TestServer::new(&key, now - 60, now + 3600));
+        let provider = provider(&key);
+        let transport = DnsCryptTransport::new();
+        for _ in 0..2 {
+            let response = query(&transport, addr, &provider, "example.com").unwrap();
+            assert_eq!(response.answers().len(), 1);
+        }
+        assert_eq!(counters.certificate_queries.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.tcp_connections.load(Ordering::SeqCst), 0);
+
+        // 截断后改用TCP
+        let response = query(&transport, addr, &provider, "tc.example").unwrap();
+        assert_eq!(response.header.truncated, 0);
+        assert_eq!(counters.tcp_connections.load(Ordering::SeqCst), 1);
+
+        let transport = DnsCryptTransport::new().with_tcp(true);
+        assert!(query(&transport, addr, &provider, "example.com").is_some());
+        assert_eq!(counters.tcp_connections.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_certificate() {
+        let key = provider_key();
+        let now = unix_time();
+        let transport = DnsCryptTransport::new();
+        // 签名公钥不匹配
+        let (addr, _) = serve(TestServer::new(&key, now - 60, now + 3600));
+        assert!(query(&transport, addr, &provider(&provider_key()), "example.com").is_none());
+        // 已过期
+        let (addr, _) = serve(TestServer::new(&key, now - 3600, now - 60));
+        assert!(query(&transport, addr, &provider(&key), "example.com").is_none());
+    }
+
+    #[test]
+    fn test_padding() {
+        let padded = pad(&[1, 2, 3], MIN_UDP_QUERY_SIZE);
+        assert_eq!(padded.len(), 256);
+        assert_eq!(padded[3], 0x80);
+        assert_eq!(pad(&[0; 63], 0).len(), 64);
+        assert_eq!(pad(&[0; 64], 0).len(), 128);
+        assert_eq!(unpad(padded), Some(vec![1, 2, 3]));
+        assert_eq!(unpad(vec![1, 2, 0]), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_local_bind() {
+        let key = provider_key();
+        let now = unix_time();
+        let (addr, counters) = serve(TestServer::new(&key, now - 60, now + 3600));
+        let provider = provider(&key);
+        let bind = LocalBind::new().with_addr("127.0.0.1".parse().unwrap());
+        for tcp in [false, true] {
+            let transport = DnsCryptTransport::new()
+                .with_tcp(tcp)
+                .with_local_bind(bind.clone());
+            assert!(query(&transport, addr, &provider, "example.com").is_some());
+            // 证书已缓存，查询仍然需要新的socket
+            let transport = transport.with_local_bind(bind.clone().with_interface("no-such-if0"));
+            assert!(query(&transport, addr, &provider, "example.com").is_none());
+        }
+        assert_eq!(counters.tcp_connections.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,664 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::{LocalBind, TlsTransport};
+use crate::dns::types::parts::Request;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use http::header::{ACCEPT, AGE, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use http::{HeaderMap, Method, StatusCode};
+use rustls::pki_types::ServerName;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::TcpSocket;
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::JoinSet;
+use tokio_rustls::TlsConnector;
+
+const DNS_MESSAGE: &str = "application/dns-message";
+
+// POST直接发送报文，GET把报文以base64url编码放在dns参数中，便于HTTP缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DohMethod {
+    #[default]
+    Post,
+    Get,
+}
+
+// DNS over HTTPS(RFC 8484)，通过ALPN协商HTTP/2，同一地址与SNI的连接在查询间复用。
+// GET的响应按Cache-Control的max-age缓存，返回时附带响应的Age，由调用方扣减TTL(RFC 8484 5.1)
+pub struct HttpsTransport {
+    runtime: Runtime,
+    connector: TlsConnector,
+    method: DohMethod,
+    bind: LocalBind,
+    connections: RefCell<HashMap<(SocketAddr, String), SendRequest<Bytes>>>,
+    cache: RefCell<HashMap<String, CachedMessage>>,
+}
+
+struct CachedMessage {
+    message: Vec<u8>,
+    stored: Instant,
+    age: u32,
+    max_age: u32,
+}
+
+// 响应报文与其已经缓存的秒数
+type Exchanged = io::Result<(Vec<u8>, u32)>;
+
+struct HttpsResponse {
+    message: Vec<u8>,
+    age: u32,
+    max_age: Option<u32>,
+}
+
+impl Default for HttpsTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpsTransport {
+    // 使用webpki-roots内置的根证书
+    pub fn new() -> HttpsTransport {
+        let runtime = Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .expect("failed to build DoH runtime");
+        HttpsTransport {
+            runtime,
+            connector: connector(&TlsTransport::new()),
+            method: DohMethod::default(),
+            bind: LocalBind::new(),
+            connections: RefCell::new(HashMap::new()),
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // 沿用TlsTransport的根证书与SPKI pin
+    pub fn with_tls(mut self, tls: &TlsTransport) -> HttpsTransport {
+        self.connector = connector(tls);
+        self.connections.get_mut().clear();
+        self
+    }
+
+    pub fn with_method(mut self, method: DohMethod) -> HttpsTransport {
+        self.method = method;
+        self
+    }
+
+    pub fn with_local_bind(mut self, bind: LocalBind) -> HttpsTransport {
+        self.bind = bind;
+        self.connections.get_mut().clear();
+        self
+    }
+
+    pub fn method(&self) -> DohMethod {
+        self.method
+    }
+
+    // 返回响应报文与其已经缓存的秒数
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        timeout: Option<Duration>,
+        request: &Request,
+    ) -> io::Result<(Vec<u8>, u32)> {
+        self.exchange_on(
+            addr,
+            server_name,
+            path,
+            timeout,
+            std::slice::from_ref(request),
+        )?
+        .remove(0)
+    }
+
+    // 未命中缓存的查询在同一连接上各占一条HTTP/2流并发发送，结果与requests一一对应
+    pub(crate) fn exchange_all(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> Vec<Exchanged> {
+        match self.exchange_on(addr, server_name, path, timeout, requests) {
+            Ok(results) => results,
+            Err(err) => requests
+                .iter()
+                .map(|_| Err(io::Error::new(err.kind(), err.to_string())))
+                .collect(),
+        }
+    }
+
+    // 外层的错误表示连接失败或超时
+    fn exchange_on(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> io::Result<Vec<Exchanged>> {
+        let authority = authority(server_name, addr.port());
+        let mut results: Vec<Option<Exchanged>> = Vec::new();
+        let mut pending = Vec::new();
+        for (i, request) in requests.iter().enumerate() {
+            let mut buf = [0_u8; 1500];
+            let message = request.encode_to_tcp(&mut buf)[2..].to_vec();
+            let uri = match self.method {
+                DohMethod::Post => format!("https://{}{}", authority, path),
+                DohMethod::Get => {
+                    let separator = if path.contains('?') { '&' } else { '?' };
+                    let dns = URL_SAFE_NO_PAD.encode(&message);
+                    format!("https://{}{}{}dns={}", authority, path, separator, dns)
+                }
+            };
+            let cached = match self.method {
+                DohMethod::Get => self.cached(&uri),
+                DohMethod::Post => None,
+            };
+            if cached.is_none() {
+                pending.push((i, uri, message));
+            }
+            results.push(cached.map(Ok));
+        }
+
+        if !pending.is_empty() {
+            let key = (addr, server_name.to_string());
+            let reused = self.connections.borrow_mut().remove(&key);
+            let is_reused = reused.is_some();
+            let exchange = async {
+                let sender = match reused {
+                    Some(sender) => sender,
+                    None => self.connect(addr, server_name).await?,
+                };
+                let responses = send_all(&sender, self.method, &pending).await;
+                // 复用的连接可能已被服务器关闭，重新建立一次
+                if is_reused && responses.iter().any(Result::is_err) {
+                    let sender = self.connect(addr, server_name).await?;
+                    let responses = send_all(&sender, self.method, &pending).await;
+                    return Ok::<_, io::Error>((sender, responses));
+                }
+                Ok((sender, responses))
+            };
+            let (sender, responses) = self.runtime.block_on(async {
+                match timeout {
+                    Some(timeout) => {
+                        tokio::time::timeout(timeout, exchange).await.map_err(|_| {
+                            io::Error::new(io::ErrorKind::TimedOut, "DoH query timed out")
+                        })?
+                    }
+                    None => exchange.await,
+                }
+            })?;
+            self.connections.borrow_mut().insert(key, sender);
+
+            for ((i, uri, _), response) in pending.into_iter().zip(responses) {
+                results[i] = Some(response.map(|response| {
+                    self.store(uri, &response);
+                    (response.message, response.age)
+                }));
+            }
+        }
+        Ok(results
+            .into_iter()
+            .map(|result| result.expect("every request is cached or sent"))
+            .collect())
+    }
+
+    // 只缓存GET的响应
+    fn store(&self, uri: String, response: &HttpsResponse) {
+        if let (DohMethod::Get, Some(max_age)) = (self.method, response.max_age)
+            && max_age > response.age
+        {
+            let mut cache = self.cache.borrow_mut();
+            cache.retain(|_, cached| cached.remaining().is_some());
+            cache.insert(
+                uri,
+                CachedMessage {
+                    message: response.message.clone(),
+                    stored: Instant::now(),
+                    age: response.age,
+                    max_age,
+                },
+            );
+        }
+    }
+
+    fn cached(&self, uri: &str) -> Option<(Vec<u8>, u32)> {
+        let cache = self.cache.borrow();
+        let cached = cache.get(uri)?;
+        let age = cached.remaining()?;
+        Some((cached.message.clone(), age))
+    }
+
+    async fn connect(&self, addr: SocketAddr, server_name: &str) -> io::Result<SendRequest<Bytes>> {
+        let name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let socket = self.bind.tcp_socket(addr)?;
+        socket.set_nonblocking(true)?;
+        let socket = TcpSocket::from_std_stream(socket.into())
+            .connect(addr)
+            .await?;
+        socket.set_nodelay(true)?;
+        let stream = self.connector.connect(name, socket).await?;
+        if stream.get_ref().1.alpn_protocol() != Some(b"h2") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "server did not negotiate HTTP/2",
+            ));
+        }
+        let (sender, connection) = h2::client::handshake(stream).await.map_err(h2_error)?;
+        // 连接在运行时上驱动，只在block_on期间推进
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+        Ok(sender)
+    }
+}
+
+impl CachedMessage {
+    // 仍然新鲜时返回当前的Age
+    fn remaining(&self) -> Option<u32> {
+        let elapsed = u32::try_from(self.stored.elapsed().as_secs()).unwrap_or(u32::MAX);
+        let age = self.age.saturating_add(elapsed);
+        (age < self.max_age).then_some(age)
+    }
+}
+
+fn connector(tls: &TlsTransport) -> TlsConnector {
+    TlsConnector::from(tls.client_config(vec![b"h2".to_vec()]))
+}
+
+// IPv6地址需要加方括号，443端口省略
+fn authority(server_name: &str, port: u16) -> String {
+    let host = match server_name.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{}]", ip),
+        _ => server_name.to_string(),
+    };
+    if port == 443 {
+        host
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// 每个查询在单独的任务中发送，结果与pending一一对应
+async fn send_all(
+    sender: &SendRequest<Bytes>,
+    method: DohMethod,
+    pending: &[(usize, String, Vec<u8>)],
+) -> Vec<io::Result<HttpsResponse>> {
+    let mut tasks = JoinSet::new();
+    for (i, (_, uri, message)) in pending.iter().enumerate() {
+        let sender = sender.clone();
+        let uri = uri.clone();
+        let message = message.clone();
+        tasks.spawn(async move { (i, send(&sender, method, &uri, &message).await) });
+    }
+    let mut responses: Vec<io::Result<HttpsResponse>> = pending
+        .iter()
+        .map(|_| Err(io::Error::other("DoH query task failed")))
+        .collect();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((i, response)) = joined {
+            responses[i] = response;
+        }
+    }
+    responses
+}
+
+async fn send(
+    sender: &SendRequest<Bytes>,
+    method: DohMethod,
+    uri: &str,
+    message: &[u8],
+) -> io::Result<HttpsResponse> {
+    let builder = http::Request::builder()
+        .uri(uri)
+        .header(ACCEPT, DNS_MESSAGE);
+    let request = match method {
+        DohMethod::Post => builder
+            .method(Method::POST)
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(CONTENT_LENGTH, message.len()),
+        DohMethod::Get => builder.method(Method::GET),
+    }
+    .body(())
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut sender = sender.clone().ready().await.map_err(h2_error)?;
+    let end_of_stream = method == DohMethod::Get;
+    let (response, mut stream) = sender
+        .send_request(request, end_of_stream)
+        .map_err(h2_error)?;
+    if !end_of_stream {
+        stream
+            .send_data(Bytes::copy_from_slice(message), true)
+            .map_err(h2_error)?;
+    }
+
+    let response = response.await.map_err(h2_error)?;
+    let (parts, mut body) = response.into_parts();
+    if parts.status != StatusCode::OK {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected HTTP status {}", parts.status),
+        ));
+    }
+    if header_str(&parts.headers, CONTENT_TYPE) != Some(DNS_MESSAGE) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected content type",
+        ));
+    }
+    let mut message = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(h2_error)?;
+        let _ = body.flow_control().release_capacity(chunk.len());
+        message.extend_from_slice(&chunk);
+        if message.len() > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response exceeds 65535 bytes",
+            ));
+        }
+    }
+    let age = header_str(&parts.headers, AGE)
+        .and_then(|age| age.trim().parse().ok())
+        .unwrap_or(0);
+    Ok(HttpsResponse {
+        message,
+        age,
+        max_age: max_age(header_str(&parts.headers, CACHE_CONTROL)),
+    })
+}
+
+fn header_str(headers: &HeaderMap, name: http::header::HeaderName) -> Option<&str> {
+    headers.get(name)?.to_str().ok()
+}
+
+// no-store与no-cache时不缓存
+fn max_age(cache_control: Option<&str>) -> Option<u32> {
+    let mut max_age = None;
+    for directive in cache_control?.split(',') {
+        let directive = directive.trim();
+        if directive.eq_ignore_ascii_case("no-store") || directive.eq_ignore_ascii_case("no-cache")
+        {
+            return None;
+        }
+        if let Some((name, value)) = directive.split_once('=')
+            && name.trim().eq_ignore_ascii_case("max-age")
+        {
+            max_age = value.trim().trim_matches('"').parse().ok();
+        }
+    }
+    max_age
+}
+
+fn h2_error(err: h2::Error) -> io::Error {
+    if err.is_io() {
+        err.into_io().expect("checked by is_io")
+    } else {
+        io::Error::other(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::net::NetQuery;
+    use crate::dns::net::tls::provider;
+    use crate::dns::net::tls::tests::{TestCa, answer, test_ca};
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::parts::Response;
+    use h2::RecvStream;
+    use h2::server::SendResponse;
+    use rustls::ServerConfig;
+    use rustls::pki_types::PrivateKeyDer;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio_rustls::TlsAcceptor;
+
+    // 收到batch个请求后才开始回复，返回监听地址、已接受的连接数与收到的请求数
+    fn serve(ca: &TestCa, batch: usize) -> (SocketAddr, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let mut config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![ca.cert.clone()],
+                PrivateKeyDer::Pkcs8(ca.key.clone().into()),
+            )
+            .unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(AtomicUsize::new(0));
+        let (connection_counter, request_counter) = (connections.clone(), requests.clone());
+        std::thread::spawn(move || {
+            let runtime = Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                while let Ok((socket, _)) = listener.accept().await {
+                    connection_counter.fetch_add(1, Ordering::SeqCst);
+                    let acceptor = acceptor.clone();
+                    let request_counter = request_counter.clone();
+                    tokio::spawn(async move {
+                        let Ok(stream) = acceptor.accept(socket).await else {
+                            return;
+                        };
+                        let Ok(mut connection) = h2::server::handshake(stream).await else {
+                            return;
+                        };
+                        while let Some(Ok((request, respond))) = connection.accept().await {
+                            request_counter.fetch_add(1, Ordering::SeqCst);
+                            tokio::spawn(handle(request, respond, request_counter.clone(), batch));
+                        }
+                    });
+                }
+            });
+        });
+        (addr, connections, requests)
+    }
+
+    // ID不为0或POST的Content-Type不正确时回复400，响应带有Age: 10
+    async fn handle(
+        request: http::Request<RecvStream>,
+        mut respond: SendResponse<Bytes>,
+        requests: Arc<AtomicUsize>,
+        batch: usize,
+    ) {
+        while requests.load(Ordering::SeqCst) < batch {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (parts, mut body) = request.into_parts();
+        let query = if parts.method == Method::GET {
+            parts
+                .uri
+                .query()
+                .and_then(|query| query.split('&').find_map(|p| p.strip_prefix("dns=")))
+                .and_then(|dns| URL_SAFE_NO_PAD.decode(dns).ok())
+                .unwrap_or_default()
+        } else {
+            let mut query = Vec::new();
+            while let Some(Ok(chunk)) = body.data().await {
+                let _ = body.flow_control().release_capacity(chunk.len());
+                query.extend_from_slice(&chunk);
+            }
+            if header_str(&parts.headers, CONTENT_TYPE) != Some(DNS_MESSAGE) {
+                query.clear();
+            }
+            query
+        };
+        if query.len() < 12 || query[..2] != [0, 0] {
+            let response = http::Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(())
+                .unwrap();
+            let _ = respond.send_response(response, true);
+            return;
+        }
+        let response = http::Response::builder()
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(CACHE_CONTROL, "max-age=60")
+            .header(AGE, "10")
+            .body(())
+            .unwrap();
+        if let Ok(mut stream) = respond.send_response(response, false) {
+            let _ = stream.send_data(Bytes::from(answer(&query)), true);
+        }
+    }
+
+    fn query(transport: &HttpsTransport, addr: SocketAddr, qname: &str) -> Option<Response> {
+        let request = Request::new(Rc::new(RawDomain::from_str(qname).unwrap()), 1);
+        let timeout = Some(Duration::from_secs(5));
+        let path = "/dns-query";
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_https(transport, addr, "dns.test", path, timeout, request)
+            .into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_https(transport, addr, "dns.test", path, timeout, request)
+    }
+
+    fn trusted(ca: &TestCa) -> TlsTransport {
+        TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_methods() {
+        let ca = test_ca();
+        let (addr, connections, requests) = serve(&ca, 1);
+        for method in [DohMethod::Post, DohMethod::Get] {
+            let transport = HttpsTransport::new()
+                .with_tls(&trusted(&ca))
+                .with_method(method);
+            for qname in ["a.example", "b.example"] {
+                let response = query(&transport, addr, qname).unwrap();
+                assert_eq!(response.header.id, 0);
+                assert_eq!(response.answers().len(), 1);
+                assert_eq!(response.answers()[0].ttl, 50);
+            }
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+        // 不受信任的CA
+        assert!(query(&HttpsTransport::new(), addr, "a.example").is_none());
+    }
+
+    #[test]
+    fn test_cache() {
+        let ca = test_ca();
+        let (addr, _, requests) = serve(&ca, 1);
+        let transport = HttpsTransport::new()
+            .with_tls(&trusted(&ca))
+            .with_method(DohMethod::Get);
+        for _ in 0..3 {
+            let response = query(&transport, addr, "a.example").unwrap();
+            assert_eq!(response.answers()[0].ttl, 50);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        query(&transport, addr, "b.example").unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // POST的响应不缓存
+        let transport = transport.with_method(DohMethod::Post);
+        for _ in 0..2 {
+            query(&transport, addr, "a.example").unwrap();
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let ca = test_ca();
+        let (addr, connections, requests) = serve(&ca, 2);
+        let transport = HttpsTransport::new()
+            .with_tls(&trusted(&ca))
+            .with_method(DohMethod::Get);
+        let pipelined = |qnames: [&str; 2]| {
+            let requests = qnames
+                .iter()
+                .map(|qname| Request::new(Rc::new(RawDomain::from_str(qname).unwrap()), 1))
+                .collect();
+            let timeout = Some(Duration::from_secs(5));
+            NetQuery::query_https_pipelined(
+                &transport,
+                addr,
+                "dns.test",
+                "/dns-query",
+                timeout,
+                requests,
+            )
+        };
+        // 服务器收齐两个请求才回复，依次发送时第一个查询就会超时
+        for result in pipelined(["a.example", "b.example"]) {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().answers()[0].ttl, 50);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // 命中缓存的查询不再发送
+        for (qname, result) in ["a.example", "c.example"]
+            .into_iter()
+            .zip(pipelined(["a.example", "c.example"]))
+        {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            let response = result.unwrap();
+            assert_eq!(
+                RawDomain::to_string(&response.question[0].qname).unwrap(),
+                qname
+            );
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_max_age() {
+        assert_eq!(max_age(Some("public, max-age=300")), Some(300));
+        assert_eq!(max_age(Some("max-age=300, no-store")), None);
+        assert_eq!(max_age(Some("private")), None);
+        assert_eq!(max_age(None), None);
+        assert_eq!(authority("dns.test", 443), "dns.test");
+        assert_eq!(authority("2001:db8::1", 8443), "[2001:db8::1]:8443");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_local_bind() {
+        let ca = test_ca();
+        let (addr, connections, _) = serve(&ca, 1);
+        let bind = LocalBind::new().with_addr("127.0.0.1".parse().unwrap());
+        let transport = HttpsTransport::new()
+            .with_tls(&trusted(&ca))
+            .with_local_bind(bind.clone());
+        assert!(query(&transport, addr, "a.example").is_some());
+        // 绑定到不存在的网卡时无法建立连接
+        let transport = transport.with_local_bind(bind.with_interface("no-such-if0"));
+        assert!(query(&transport, addr, "b.example").is_none());
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}
@@ -4,13 +4,31 @@ use crate::dns::error::ResultAndError;
 use crate::dns::error::debug_fmt;
 #[cfg(feature = "result_error")]
 use crate::dns::error::error_trait;
+#[cfg(feature = "dnscrypt")]
+use crate::dns::net::DnsCryptTransport;
+#[cfg(feature = "https")]
+use crate::dns::net::HttpsTransport;
+#[cfg(feature = "quic")]
+use crate::dns::net::QuicTransport;
+#[cfg(feature = "tls")]
+use crate::dns::net::TlsTransport;
+use crate::dns::net::tcp_pool::TcpPoolError;
+use crate::dns::net::udp_pool::UdpPoolError;
+use crate::dns::net::{TcpPool, UdpPool};
+#[cfg(feature = "https")]
+use crate::dns::types::base::DnsTypeNum;
 use crate::dns::types::parts::{Request, Response};
 #[cfg(feature = "result_error")]
+use crate::dns::utils::DecodeError;
+#[cfg(feature = "dnscrypt")]
+use crate::dns::utils::DnsCryptProvider;
+#[cfg(feature = "result_error")]
 use snafu::{ResultExt, Snafu};
 #[cfg(feature = "result_error")]
 use std::fmt::Debug;
 use std::io::{Read, Write};
-use std::net::{TcpStream, UdpSocket};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
 
 pub struct NetQuery {}
 
@@ -24,6 +42,7 @@ impl error_trait::B for NetQueryError {}
 type Result = Option<Response>;
 
 impl NetQuery {
+    #[cfg_attr(debug_assertions, allow(dead_code))]
     pub fn query_tcp(mut stream: TcpStream, request: Request, buf: &mut [u8; 1500]) -> Result {
         #[cfg(feature = "result_error")]
         {
@@ -47,16 +66,393 @@ impl NetQuery {
             stream.write_all(request.encode_to_tcp(buf)).ok()?;
             stream.read(buf).ok()?;
         }
-        let len = u16::from_be_bytes([buf[0], buf[1]]);
-        let response = Response::from_slice(&buf.as_slice()[2..(len + 2) as usize], &request);
+        let len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        // 长度前缀超出缓冲区时按截断处理
+        let end = (len + 2).min(buf.len());
+        let response = Response::from_slice(&buf.as_slice()[2..end], &request);
         #[cfg(feature = "result_error")]
         {
-            return response.into();
+            return match response.context(DecodeResponseSnafu {
+                target: debug_fmt(stream.peer_addr()),
+            }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            };
         }
         #[cfg(not(feature = "result_error"))]
+        response.ok()
+    }
+
+    // 通过连接池查询，请求中携带edns-tcp-keepalive以获知服务器允许的空闲时间
+    pub fn query_tcp_pool(
+        pool: &TcpPool,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        mut request: Request,
+    ) -> Result {
+        request.set_tcp_keepalive();
+        let message = pool.exchange(addr, timeout, &request);
+        Self::tcp_pool_response(addr, message, &request)
+    }
+
+    // 在同一连接上流水线发送多个查询，结果与requests一一对应。
+    // ID重复的查询会等前一个收到响应后再发送
+    pub fn query_tcp_pipelined(
+        pool: &TcpPool,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        mut requests: Vec<Request>,
+    ) -> Vec<Result> {
+        requests.iter_mut().for_each(Request::set_tcp_keepalive);
+        pool.exchange_all(addr, timeout, &requests)
+            .into_iter()
+            .zip(&requests)
+            .map(|(message, request)| Self::tcp_pool_response(addr, message, request))
+            .collect()
+    }
+
+    fn tcp_pool_response(
+        addr: SocketAddr,
+        message: std::result::Result<Vec<u8>, TcpPoolError>,
+        request: &Request,
+    ) -> Result {
+        #[cfg(feature = "result_error")]
+        {
+            let target = addr.to_string();
+            let message = match message {
+                Ok(message) => message,
+                Err(TcpPoolError::Connect(source)) => {
+                    return NetQueryError::ConnectTcpAddrError { target, source }.into();
+                }
+                Err(TcpPoolError::Write(source)) => {
+                    return NetQueryError::WriteTcpConnectError { target, source }.into();
+                }
+                Err(TcpPoolError::Recv(source)) => {
+                    return NetQueryError::RecvTcpPacketError { target, source }.into();
+                }
+            };
+            match Response::from_slice(&message, request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            }
+        }
+        #[cfg(not(feature = "result_error"))]
+        {
+            let _ = addr;
+            Response::from_slice(&message.ok()?, request).ok()
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn query_tls(
+        transport: &TlsTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        request: Request,
+    ) -> Result {
+        let message = transport.exchange(addr, server_name, timeout, &request);
+        Self::tls_response(addr, server_name, message, &request)
+    }
+
+    // 在同一TLS连接上流水线发送多个查询，结果与requests一一对应
+    #[cfg(feature = "tls")]
+    pub fn query_tls_pipelined(
+        transport: &TlsTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        requests: Vec<Request>,
+    ) -> Vec<Result> {
+        transport
+            .exchange_all(addr, server_name, timeout, &requests)
+            .into_iter()
+            .zip(&requests)
+            .map(|(message, request)| Self::tls_response(addr, server_name, message, request))
+            .collect()
+    }
+
+    #[cfg(feature = "tls")]
+    fn tls_response(
+        addr: SocketAddr,
+        server_name: &str,
+        message: std::io::Result<Vec<u8>>,
+        request: &Request,
+    ) -> Result {
+        #[cfg(feature = "result_error")]
+        {
+            let target = format!("{}#{}", addr, server_name);
+            let message = match message.context(TlsSnafu {
+                target: target.clone(),
+            }) {
+                Ok(message) => message,
+                Err(err) => return err.into(),
+            };
+            match Response::from_slice(&message, request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            }
+        }
+        #[cfg(not(feature = "result_error"))]
+        {
+            let _ = (addr, server_name);
+            Response::from_slice(&message.ok()?, request).ok()
+        }
+    }
+
+    #[cfg(feature = "dnscrypt")]
+    pub fn query_dnscrypt(
+        transport: &DnsCryptTransport,
+        addr: SocketAddr,
+        provider: &DnsCryptProvider,
+        timeout: Option<Duration>,
+        request: Request,
+    ) -> Result {
+        let message = transport.exchange(addr, provider, timeout, &request);
+        #[cfg(feature = "result_error")]
+        {
+            let target = format!("{}#{}", addr, provider.name);
+            let message = match message.context(DnsCryptSnafu {
+                target: target.clone(),
+            }) {
+                Ok(message) => message,
+                Err(err) => return err.into(),
+            };
+            match Response::from_slice(&message, &request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            }
+        }
+        #[cfg(not(feature = "result_error"))]
+        Response::from_slice(&message.ok()?, &request).ok()
+    }
+
+    // RFC 9250 4.2.1要求ID为0
+    #[cfg(feature = "quic")]
+    pub fn query_quic(
+        transport: &QuicTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        mut request: Request,
+    ) -> Result {
+        request.header.id = 0;
+        let message = transport.exchange(addr, server_name, timeout, &request);
+        Self::quic_response(addr, server_name, message, &request)
+    }
+
+    // 每个查询使用同一连接上的一条流，结果与requests一一对应
+    #[cfg(feature = "quic")]
+    pub fn query_quic_pipelined(
+        transport: &QuicTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        mut requests: Vec<Request>,
+    ) -> Vec<Result> {
+        requests
+            .iter_mut()
+            .for_each(|request| request.header.id = 0);
+        transport
+            .exchange_all(addr, server_name, timeout, &requests)
+            .into_iter()
+            .zip(&requests)
+            .map(|(message, request)| Self::quic_response(addr, server_name, message, request))
+            .collect()
+    }
+
+    #[cfg(feature = "quic")]
+    fn quic_response(
+        addr: SocketAddr,
+        server_name: &str,
+        message: std::io::Result<Vec<u8>>,
+        request: &Request,
+    ) -> Result {
+        #[cfg(feature = "result_error")]
+        {
+            let target = format!("{}#{}", addr, server_name);
+            let message = match message.context(QuicSnafu {
+                target: target.clone(),
+            }) {
+                Ok(message) => message,
+                Err(err) => return err.into(),
+            };
+            match Response::from_slice(&message, request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            }
+        }
+        #[cfg(not(feature = "result_error"))]
+        {
+            let _ = (addr, server_name);
+            Response::from_slice(&message.ok()?, request).ok()
+        }
+    }
+
+    // 按RFC 8484 4.1把ID置为0，缓存过的响应按Age扣减TTL
+    #[cfg(feature = "https")]
+    pub fn query_https(
+        transport: &HttpsTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        timeout: Option<Duration>,
+        mut request: Request,
+    ) -> Result {
+        request.header.id = 0;
+        let message = transport.exchange(addr, server_name, path, timeout, &request);
+        Self::https_response(addr, server_name, path, message, &request)
+    }
+
+    // 每个查询使用同一连接上的一条HTTP/2流，结果与requests一一对应
+    #[cfg(feature = "https")]
+    pub fn query_https_pipelined(
+        transport: &HttpsTransport,
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        timeout: Option<Duration>,
+        mut requests: Vec<Request>,
+    ) -> Vec<Result> {
+        requests
+            .iter_mut()
+            .for_each(|request| request.header.id = 0);
+        transport
+            .exchange_all(addr, server_name, path, timeout, &requests)
+            .into_iter()
+            .zip(&requests)
+            .map(|(message, request)| {
+                Self::https_response(addr, server_name, path, message, request)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "https")]
+    fn https_response(
+        addr: SocketAddr,
+        server_name: &str,
+        path: &str,
+        message: std::io::Result<(Vec<u8>, u32)>,
+        request: &Request,
+    ) -> Result {
+        #[cfg(feature = "result_error")]
+        let (message, age) = {
+            let target = format!("https://{}#{}{}", addr, server_name, path);
+            let (message, age) = match message.context(HttpsSnafu {
+                target: target.clone(),
+            }) {
+                Ok(message) => message,
+                Err(err) => return err.into(),
+            };
+            match Response::from_slice(&message, request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => (response, age),
+                Err(err) => return err.into(),
+            }
+        };
+        #[cfg(not(feature = "result_error"))]
+        let (message, age) = {
+            let _ = (addr, server_name, path);
+            let (message, age) = message.ok()?;
+            (Response::from_slice(&message, request).ok()?, age)
+        };
+        let mut response = message;
         response
+            .answer
+            .iter_mut()
+            .filter(|record| record.rtype != DnsTypeNum::OPT)
+            .for_each(|record| record.ttl = record.ttl.saturating_sub(age));
+        #[cfg(feature = "result_error")]
+        return Some(response).into();
+        #[cfg(not(feature = "result_error"))]
+        Some(response)
+    }
+
+    // 通过共享的UDP引擎查询，请求超过512字节或响应被截断(TC)时改用TCP
+    pub fn query_udp_pool(
+        udp: &UdpPool,
+        tcp: &TcpPool,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        mut request: Request,
+    ) -> Result {
+        let mut buf = [0_u8; 1500];
+        if request.encode_to_udp(&mut buf).len() > 512 {
+            return Self::query_tcp_pool(tcp, addr, timeout, request);
+        }
+        let message = udp.exchange(addr, timeout, &mut request);
+        let response = Self::udp_pool_response(addr, message, &request);
+        if Self::truncated(&response) {
+            return Self::query_tcp_pool(tcp, addr, timeout, request);
+        }
+        response
+    }
+
+    // 同时发送多个查询，结果与requests一一对应。被截断的响应在同一TCP连接上流水线重新查询
+    pub fn query_udp_pipelined(
+        udp: &UdpPool,
+        tcp: &TcpPool,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        mut requests: Vec<Request>,
+    ) -> Vec<Result> {
+        let mut results: Vec<Result> = udp
+            .exchange_all(timeout, &mut requests, |_| addr)
+            .into_iter()
+            .zip(&requests)
+            .map(|(message, request)| Self::udp_pool_response(addr, message, request))
+            .collect();
+        let (indices, truncated): (Vec<usize>, Vec<Request>) = requests
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| Self::truncated(&results[*i]))
+            .unzip();
+        if !truncated.is_empty() {
+            let retried = Self::query_tcp_pipelined(tcp, addr, timeout, truncated);
+            for (i, result) in indices.into_iter().zip(retried) {
+                results[i] = result;
+            }
+        }
+        results
+    }
+
+    fn truncated(result: &Result) -> bool {
+        #[cfg(feature = "result_error")]
+        let response = result.get_result();
+        #[cfg(not(feature = "result_error"))]
+        let response = result.as_ref();
+        response.is_some_and(|response| response.header.truncated == 1)
     }
 
+    fn udp_pool_response(
+        addr: SocketAddr,
+        message: std::result::Result<Vec<u8>, UdpPoolError>,
+        request: &Request,
+    ) -> Result {
+        #[cfg(feature = "result_error")]
+        {
+            let target = addr.to_string();
+            let message = match message {
+                Ok(message) => message,
+                Err(UdpPoolError::Bind(source) | UdpPoolError::Send(source)) => {
+                    return NetQueryError::UdpPacketSendError { target, source }.into();
+                }
+                Err(UdpPoolError::Recv(source)) => {
+                    return NetQueryError::RecvUdpPacketError { target, source }.into();
+                }
+            };
+            match Response::from_slice(&message, request).context(DecodeResponseSnafu { target }) {
+                Ok(response) => Some(response).into(),
+                Err(err) => err.into(),
+            }
+        }
+        #[cfg(not(feature = "result_error"))]
+        {
+            let _ = addr;
+            Response::from_slice(&message.ok()?, request).ok()
+        }
+    }
+
+    #[cfg_attr(debug_assertions, allow(dead_code))]
     pub fn query_udp(socket: UdpSocket, request: Request, buf: &mut [u8; 1500]) -> Result {
         let arr = request.encode_to_udp(buf);
         if arr.len() > 512 {
@@ -96,7 +492,12 @@ impl NetQuery {
                 Ok(number_of_bytes) => {
                     let response =
                         Response::from_slice(&buf.as_slice()[..number_of_bytes], &request);
-                    return response.into();
+                    return match response.context(DecodeResponseSnafu {
+                        target: debug_fmt(socket.peer_addr()),
+                    }) {
+                        Ok(response) => Some(response).into(),
+                        Err(err) => err.into(),
+                    };
                 }
                 Err(err) => err.into(),
             }
@@ -105,10 +506,45 @@ impl NetQuery {
         {
             socket.send(arr).ok()?;
             let number_of_bytes = socket.recv(buf).ok()?;
-            Response::from_slice(&buf.as_slice()[..number_of_bytes], &request)
+            Response::from_slice(&buf.as_slice()[..number_of_bytes], &request).ok()
+        }
+    }
+}
+// 一次写入所有带2字节长度前缀的请求，再读取完整的响应，返回的报文不含长度前缀。
+// 服务器可以乱序回复(RFC 7766 7)，响应按ID与请求对应，结果与requests一一对应
+#[cfg(feature = "tls")]
+pub(crate) fn exchange_framed<S: Read + Write>(
+    stream: &mut S,
+    requests: &[Request],
+) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut buf = [0_u8; 1500];
+    for request in requests {
+        stream.write_all(request.encode_to_tcp(&mut buf))?;
+    }
+    stream.flush()?;
+    let mut messages: Vec<Option<Vec<u8>>> = requests.iter().map(|_| None).collect();
+    let mut pending = requests.len();
+    while pending > 0 {
+        let mut len = [0_u8; 2];
+        stream.read_exact(&mut len)?;
+        let mut message = vec![0_u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut message)?;
+        let Some(&id) = message.first_chunk::<2>() else {
+            continue;
+        };
+        let id = u16::from_be_bytes(id);
+        let slot = requests
+            .iter()
+            .zip(&messages)
+            .position(|(request, message)| message.is_none() && request.header.id == id);
+        if let Some(i) = slot {
+            messages[i] = Some(message);
+            pending -= 1;
         }
     }
+    Ok(messages.into_iter().flatten().collect())
 }
+
 #[cfg(feature = "result_error")]
 #[derive(Snafu, Debug)]
 pub enum NetQueryError {
@@ -142,4 +578,33 @@ pub enum NetQueryError {
         target: String,
         source: std::io::Error,
     },
+    #[snafu(display("DecodeResponseError, target: {}, info: {}", target, source))]
+    DecodeResponseError { target: String, source: DecodeError },
+    // 连接、握手或收发失败，target为 地址#SNI
+    #[cfg(feature = "tls")]
+    #[snafu(display("TlsError, target: {}, info: {}", target, source))]
+    TlsError {
+        target: String,
+        source: std::io::Error,
+    },
+    // HTTP/2请求失败或响应不是200的application/dns-message
+    #[cfg(feature = "https")]
+    #[snafu(display("HttpsError, target: {}, info: {}", target, source))]
+    HttpsError {
+        target: String,
+        source: std::io::Error,
+    },
+    #[cfg(feature = "quic")]
+    #[snafu(display("QuicError, target: {}, info: {}", target, source))]
+    QuicError {
+        target: String,
+        source: std::io::Error,
+    },
+    // 证书获取、校验失败或响应无法解密，target为 地址#provider name
+    #[cfg(feature = "dnscrypt")]
+    #[snafu(display("DnsCryptError, target: {}, info: {}", target, source))]
+    DnsCryptError {
+        target: String,
+        source: std::io::Error,
+    },
 }
@@ -0,0 +1,436 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::{LocalBind, TlsTransport};
+use crate::dns::types::parts::Request;
+use quinn::crypto::rustls::QuicClientConfig;
+use quinn::{ClientConfig, Connection, Endpoint, EndpointConfig, RecvStream, TokioRuntime, VarInt};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+use tokio::task::JoinSet;
+
+// RFC 9250 4.3 DOQ_NO_ERROR
+const DOQ_NO_ERROR: u32 = 0;
+
+// DNS over QUIC(RFC 9250)，ALPN为doq，每个查询使用一条双向流，报文带2字节长度前缀。
+// 同一地址与SNI的连接在查询间复用，新建连接时若有可恢复的会话则用0-RTT发送普通查询
+pub struct QuicTransport {
+    config: ClientConfig,
+    bind: LocalBind,
+    endpoints: RefCell<HashMap<bool, Endpoint>>,
+    connections: RefCell<HashMap<(SocketAddr, String), Connection>>,
+    runtime: Runtime,
+}
+
+impl Default for QuicTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for QuicTransport {
+    fn drop(&mut self) {
+        for connection in self.connections.get_mut().values() {
+            connection.close(VarInt::from_u32(DOQ_NO_ERROR), b"");
+        }
+    }
+}
+
+impl QuicTransport {
+    // 使用webpki-roots内置的根证书
+    pub fn new() -> QuicTransport {
+        let runtime = Builder::new_current_thread()
+            .enable_io()
+            .enable_time()
+            .build()
+            .expect("failed to build DoQ runtime");
+        QuicTransport {
+            config: client_config(&TlsTransport::new()),
+            bind: LocalBind::new(),
+            endpoints: RefCell::new(HashMap::new()),
+            connections: RefCell::new(HashMap::new()),
+            runtime,
+        }
+    }
+
+    // 沿用TlsTransport的根证书与SPKI pin
+    pub fn with_tls(mut self, tls: &TlsTransport) -> QuicTransport {
+        self.config = client_config(tls);
+        for (_, connection) in self.connections.get_mut().drain() {
+            connection.close(VarInt::from_u32(DOQ_NO_ERROR), b"");
+        }
+        self
+    }
+
+    // 已有的端点与连接被丢弃，之后的查询使用新的本地地址
+    pub fn with_local_bind(mut self, bind: LocalBind) -> QuicTransport {
+        self.bind = bind;
+        for (_, connection) in self.connections.get_mut().drain() {
+            connection.close(VarInt::from_u32(DOQ_NO_ERROR), b"");
+        }
+        self.endpoints.get_mut().clear();
+        self
+    }
+
+    // 返回不带长度前缀的响应报文
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        request: &Request,
+    ) -> io::Result<Vec<u8>> {
+        self.exchange_on(addr, server_name, timeout, std::slice::from_ref(request))?
+            .remove(0)
+    }
+
+    // 每个查询使用同一连接上的一条流并发发送，结果与requests一一对应
+    pub(crate) fn exchange_all(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> Vec<io::Result<Vec<u8>>> {
+        match self.exchange_on(addr, server_name, timeout, requests) {
+            Ok(results) => results,
+            Err(err) => requests
+                .iter()
+                .map(|_| Err(io::Error::new(err.kind(), err.to_string())))
+                .collect(),
+        }
+    }
+
+    // 外层的错误表示连接失败或超时
+    fn exchange_on(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> io::Result<Vec<io::Result<Vec<u8>>>> {
+        let messages: Vec<Vec<u8>> = requests
+            .iter()
+            .map(|request| {
+                let mut buf = [0_u8; 1500];
+                request.encode_to_tcp(&mut buf).to_vec()
+            })
+            .collect();
+        // 只有标准查询可以重放，UPDATE、NOTIFY等不使用0-RTT(RFC 9250 4.5)
+        let early_data = requests.iter().all(|request| request.header.opcode == 0);
+        let key = (addr, server_name.to_string());
+        let reused = self
+            .connections
+            .borrow_mut()
+            .remove(&key)
+            .filter(|connection| connection.close_reason().is_none());
+        let is_reused = reused.is_some();
+        let exchange = async {
+            let (connection, zero_rtt) = match reused {
+                Some(connection) => (connection, false),
+                None => self.connect(addr, server_name, early_data).await?,
+            };
+            let results = query_all(&connection, &messages).await;
+            // 复用的连接可能已被服务器关闭，0-RTT数据也可能被拒绝，完整握手后重试一次
+            if (is_reused || zero_rtt) && results.iter().any(Result::is_err) {
+                let (connection, _) = self.connect(addr, server_name, false).await?;
+                let results = query_all(&connection, &messages).await;
+                return Ok::<_, io::Error>((connection, results));
+            }
+            Ok((connection, results))
+        };
+        let (connection, results) = self.runtime.block_on(async {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, exchange)
+                    .await
+                    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "DoQ query timed out"))?,
+                None => exchange.await,
+            }
+        })?;
+        self.connections.borrow_mut().insert(key, connection);
+        Ok(results)
+    }
+
+    // 返回的bool表示连接是否处于0-RTT阶段
+    async fn connect(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        early_data: bool,
+    ) -> io::Result<(Connection, bool)> {
+        let connecting = self
+            .endpoint(addr)?
+            .connect_with(self.config.clone(), addr, server_name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if !early_data {
+            return Ok((connecting.await?, false));
+        }
+        match connecting.into_0rtt() {
+            Ok((connection, _)) => Ok((connection, true)),
+            Err(connecting) => Ok((connecting.await?, false)),
+        }
+    }
+
+    // IPv4与IPv6各使用一个本地端点，需要在运行时内调用
+    fn endpoint(&self, addr: SocketAddr) -> io::Result<Endpoint> {
+        let mut endpoints = self.endpoints.borrow_mut();
+        if let Some(endpoint) = endpoints.get(&addr.is_ipv6()) {
+            return Ok(endpoint.clone());
+        }
+        let endpoint = Endpoint::new(
+            EndpointConfig::default(),
+            None,
+            self.bind.udp_socket(addr.is_ipv6())?,
+            Arc::new(TokioRuntime),
+        )?;
+        endpoints.insert(addr.is_ipv6(), endpoint.clone());
+        Ok(endpoint)
+    }
+}
+
+fn client_config(tls: &TlsTransport) -> ClientConfig {
+    let mut config = Arc::unwrap_or_clone(tls.client_config(vec![b"doq".to_vec()]));
+    config.enable_early_data = true;
+    let config = QuicClientConfig::try_from(config).expect("ring supports TLS 1.3");
+    ClientConfig::new(Arc::new(config))
+}
+
+// 所有查询先在当前任务中写出，0-RTT阶段的查询随握手一起发送，再在单独的任务中并发读取响应。
+// 结果与messages一一对应
+async fn query_all(connection: &Connection, messages: &[Vec<u8>]) -> Vec<io::Result<Vec<u8>>> {
+    let mut results: Vec<io::Result<Vec<u8>>> = messages
+        .iter()
+        .map(|_| Err(io::Error::other("DoQ query task failed")))
+        .collect();
+    let mut tasks = JoinSet::new();
+    for (i, message) in messages.iter().enumerate() {
+        match send(connection, message).await {
+            Ok(recv) => {
+                tasks.spawn(async move { (i, receive(recv).await) });
+            }
+            Err(err) => results[i] = Err(err),
+        }
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((i, result)) = joined {
+            results[i] = result;
+        }
+    }
+    results
+}
+
+// 发送后关闭发送方向
+async fn send(connection: &Connection, message: &[u8]) -> io::Result<RecvStream> {
+    let (mut send, recv) = connection.open_bi().await?;
+    send.write_all(message).await?;
+    send.finish().map_err(io::Error::other)?;
+    Ok(recv)
+}
+
+// 服务器回复完整的响应后同样关闭流
+async fn receive(mut recv: RecvStream) -> io::Result<Vec<u8>> {
+    let response = recv
+        .read_to_end(2 + u16::MAX as usize)
+        .await
+        .map_err(io::Error::other)?;
+    match response.split_first_chunk::<2>() {
+        Some((len, message)) if u16::from_be_bytes(*len) as usize == message.len() => {
+            Ok(message.to_vec())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "length prefix does not match the stream",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::net::NetQuery;
+    use crate::dns::net::tls::provider;
+    use crate::dns::net::tls::tests::{TestCa, answer, test_ca};
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::parts::Response;
+    use quinn::crypto::rustls::QuicServerConfig;
+    use quinn::{SendStream, ServerConfig};
+    use rustls::pki_types::PrivateKeyDer;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counters {
+        connections: AtomicUsize,
+        zero_rtt: AtomicUsize,
+        streams: AtomicUsize,
+    }
+
+    // 收到batch条流后才开始回复，返回监听地址，以及已接受的连接数与以0-RTT收到的查询数
+    fn serve(ca: &TestCa, batch: usize) -> (SocketAddr, Arc<Counters>) {
+        let mut config = rustls::ServerConfig::builder_with_provider(provider())
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![ca.cert.clone()],
+                PrivateKeyDer::Pkcs8(ca.key.clone().into()),
+            )
+            .unwrap();
+        config.alpn_protocols = vec![b"doq".to_vec()];
+        config.max_early_data_size = u32::MAX;
+        let config = QuicServerConfig::try_from(config).unwrap();
+        let config = ServerConfig::with_crypto(Arc::new(config));
+        let counters = Arc::new(Counters::default());
+        let counter = counters.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let endpoint = Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+                sender.send(endpoint.local_addr().unwrap()).unwrap();
+                while let Some(incoming) = endpoint.accept().await {
+                    counter.connections.fetch_add(1, Ordering::SeqCst);
+                    let counter = counter.clone();
+                    tokio::spawn(async move {
+                        let Ok(connecting) = incoming.accept() else {
+                            return;
+                        };
+                        let connection = match connecting.into_0rtt() {
+                            Ok((connection, _)) => connection,
+                            Err(connecting) => match connecting.await {
+                                Ok(connection) => connection,
+                                Err(_) => return,
+                            },
+                        };
+                        while let Ok((send, recv)) = connection.accept_bi().await {
+                            if recv.is_0rtt() {
+                                counter.zero_rtt.fetch_add(1, Ordering::SeqCst);
+                            }
+                            counter.streams.fetch_add(1, Ordering::SeqCst);
+                            tokio::spawn(handle(send, recv, counter.clone(), batch));
+                        }
+                    });
+                }
+            });
+        });
+        (receiver.recv().unwrap(), counters)
+    }
+
+    // ID不为0时不回复，直接重置流
+    async fn handle(
+        mut send: SendStream,
+        mut recv: RecvStream,
+        counters: Arc<Counters>,
+        batch: usize,
+    ) {
+        let Ok(query) = recv.read_to_end(u16::MAX as usize + 2).await else {
+            return;
+        };
+        while counters.streams.load(Ordering::SeqCst) < batch {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        if query.len() < 14 || query[2..4] != [0, 0] {
+            let _ = send.reset(VarInt::from_u32(1));
+            return;
+        }
+        let response = answer(&query[2..]);
+        let _ = send.write_all(&(response.len() as u16).to_be_bytes()).await;
+        let _ = send.write_all(&response).await;
+        let _ = send.finish();
+        let _ = send.stopped().await;
+    }
+
+    fn query(transport: &QuicTransport, addr: SocketAddr, name: &str) -> Option<Response> {
+        let request = Request::new(Rc::new(RawDomain::from_str("example.com").unwrap()), 1);
+        let timeout = Some(Duration::from_secs(5));
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_quic(transport, addr, name, timeout, request).into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_quic(transport, addr, name, timeout, request)
+    }
+
+    fn trusted(ca: &TestCa) -> TlsTransport {
+        TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_reuse() {
+        let ca = test_ca();
+        let (addr, counters) = serve(&ca, 1);
+        let transport = QuicTransport::new().with_tls(&trusted(&ca));
+        for _ in 0..3 {
+            let response = query(&transport, addr, "dns.test").unwrap();
+            assert_eq!(response.header.id, 0);
+            assert_eq!(response.answers().len(), 1);
+        }
+        assert_eq!(counters.connections.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.zero_rtt.load(Ordering::SeqCst), 0);
+
+        // 连接关闭后恢复会话，查询以0-RTT发送
+        for connection in transport.connections.borrow().values() {
+            connection.close(VarInt::from_u32(DOQ_NO_ERROR), b"");
+        }
+        assert!(query(&transport, addr, "dns.test").is_some());
+        assert_eq!(counters.connections.load(Ordering::SeqCst), 2);
+        assert_eq!(counters.zero_rtt.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let ca = test_ca();
+        let (addr, counters) = serve(&ca, 2);
+        let transport = QuicTransport::new().with_tls(&trusted(&ca));
+        let domain = Rc::new(RawDomain::from_str("example.com").unwrap());
+        let requests = vec![Request::new(domain.clone(), 1), Request::new(domain, 28)];
+        // 服务器收到两条流才回复，依次发送时第一个查询就会超时
+        let results = NetQuery::query_quic_pipelined(
+            &transport,
+            addr,
+            "dns.test",
+            Some(Duration::from_secs(5)),
+            requests,
+        );
+        for (qtype, result) in [1, 28].into_iter().zip(results) {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().question[0].qtype, qtype);
+        }
+        assert_eq!(counters.connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_verify() {
+        let ca = test_ca();
+        let (addr, _) = serve(&ca, 1);
+        assert!(query(&QuicTransport::new(), addr, "dns.test").is_none());
+        let transport = QuicTransport::new().with_tls(&trusted(&ca));
+        assert!(query(&transport, addr, "other.test").is_none());
+        assert!(query(&transport, addr, "dns.test").is_some());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_local_bind() {
+        let ca = test_ca();
+        let (addr, counters) = serve(&ca, 1);
+        let bind = LocalBind::new().with_addr("127.0.0.1".parse().unwrap());
+        let transport = QuicTransport::new()
+            .with_tls(&trusted(&ca))
+            .with_local_bind(bind.clone());
+        assert!(query(&transport, addr, "dns.test").is_some());
+        // 绑定到不存在的网卡时无法创建端点
+        let transport = transport.with_local_bind(bind.with_interface("no-such-if0"));
+        assert!(query(&transport, addr, "dns.test").is_none());
+        assert_eq!(counters.connections.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,552 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::LocalBind;
+use crate::dns::types::parts::{Edns, RecordDataType, Request, Response};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+// 每个服务器保留一条TCP连接，查询按ID与响应匹配(RFC 7766 6.2.1)，响应可以乱序到达。
+// 连接空闲超过idle_timeout或服务器通过edns-tcp-keepalive给出的超时后不再复用，
+// 服务器关闭连接时未收到响应的查询在新连接上重新发送
+pub struct TcpPool {
+    idle_timeout: Duration,
+    max_in_flight: usize,
+    bind: LocalBind,
+    connections: RefCell<HashMap<SocketAddr, PooledConnection>>,
+}
+
+struct PooledConnection {
+    stream: TcpStream,
+    idle_until: Instant,
+}
+
+pub(crate) enum TcpPoolError {
+    Connect(io::Error),
+    Write(io::Error),
+    Recv(io::Error),
+}
+
+impl TcpPoolError {
+    fn is_timeout(&self) -> bool {
+        let (TcpPoolError::Connect(err) | TcpPoolError::Write(err) | TcpPoolError::Recv(err)) =
+            self;
+        matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        )
+    }
+
+    // io::Error不能Clone，同一错误需要返回给多个查询
+    fn duplicate(&self) -> TcpPoolError {
+        let copy = |err: &io::Error| io::Error::new(err.kind(), err.to_string());
+        match self {
+            TcpPoolError::Connect(err) => TcpPoolError::Connect(copy(err)),
+            TcpPoolError::Write(err) => TcpPoolError::Write(copy(err)),
+            TcpPoolError::Recv(err) => TcpPoolError::Recv(copy(err)),
+        }
+    }
+}
+
+impl Default for TcpPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpPool {
+    pub fn new() -> TcpPool {
+        TcpPool {
+            idle_timeout: Duration::from_secs(10),
+            max_in_flight: 64,
+            bind: LocalBind::new(),
+            connections: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // 服务器给出的keepalive超时更短时以服务器为准
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> TcpPool {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    // 同一连接上同时等待响应的查询数
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> TcpPool {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    // 已有的连接会被关闭
+    pub fn with_local_bind(mut self, bind: LocalBind) -> TcpPool {
+        self.bind = bind;
+        self.connections.get_mut().clear();
+        self
+    }
+
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        request: &Request,
+    ) -> Result<Vec<u8>, TcpPoolError> {
+        let mut results = self.exchange_all(addr, timeout, std::slice::from_ref(request));
+        results.pop().expect("one result per request")
+    }
+
+    // 返回不带长度前缀的响应报文，与requests一一对应。timeout是整批查询的总时限
+    pub(crate) fn exchange_all(
+        &self,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> Vec<Result<Vec<u8>, TcpPoolError>> {
+        let mut results: Vec<Option<Result<Vec<u8>, TcpPoolError>>> =
+            requests.iter().map(|_| None).collect();
+        let mut batch = Batch {
+            requests,
+            pending: (0..requests.len()).collect(),
+            in_flight: HashMap::new(),
+            max_in_flight: self.max_in_flight,
+        };
+        let now = Instant::now();
+        let mut stream = self
+            .connections
+            .borrow_mut()
+            .remove(&addr)
+            .filter(|connection| now < connection.idle_until)
+            .map(|connection| connection.stream);
+        let deadline = timeout.map(|timeout| now + timeout);
+        // 复用的连接或已经回答过本批查询的连接出错时，认为是服务器关闭了连接。
+        // 每批最多重连一次，防止服务器每回答一个查询就断开时无限重连
+        let mut may_reconnect = stream.is_some();
+        let mut reconnected = false;
+        let mut idle_timeout = self.idle_timeout;
+        while !batch.is_done() {
+            let remaining = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => Some(remaining),
+                    // 连接上可能还有未收到的响应，不能再放回连接池
+                    _ => {
+                        stream = None;
+                        let err = io::Error::new(io::ErrorKind::TimedOut, "TCP exchange timed out");
+                        batch.fail(&mut results, TcpPoolError::Recv(err));
+                        break;
+                    }
+                },
+                None => None,
+            };
+            let current = match &mut stream {
+                Some(current) => current,
+                None => match connect(&self.bind, addr, remaining) {
+                    Ok(new) => {
+                        may_reconnect = false;
+                        stream.insert(new)
+                    }
+                    Err(err) => {
+                        batch.fail(&mut results, TcpPoolError::Connect(err));
+                        break;
+                    }
+                },
+            };
+            if let Err(err) = set_timeout(current, remaining) {
+                batch.fail(&mut results, TcpPoolError::Connect(err));
+                stream = None;
+                break;
+            }
+            match batch.step(current, &mut results) {
+                Ok((answered, keepalive)) => {
+                    may_reconnect |= answered;
+                    if let Some(keepalive) = keepalive {
+                        idle_timeout = idle_timeout.min(keepalive);
+                    }
+                }
+                Err(err) => {
+                    stream = None;
+                    batch.requeue();
+                    // 读取超时不是连接被关闭，重发只会让总耗时翻倍
+                    if !may_reconnect || reconnected || err.is_timeout() {
+                        batch.fail(&mut results, err);
+                        break;
+                    }
+                    reconnected = true;
+                }
+            }
+        }
+        // 超时为0表示服务器希望客户端关闭连接
+        if let Some(stream) = stream
+            && !idle_timeout.is_zero()
+        {
+            self.connections.borrow_mut().insert(
+                addr,
+                PooledConnection {
+                    stream,
+                    idle_until: Instant::now() + idle_timeout,
+                },
+            );
+        }
+        results
+            .into_iter()
+            .map(|result| result.expect("every request is answered or failed"))
+            .collect()
+    }
+}
+
+struct Batch<'a> {
+    requests: &'a [Request],
+    pending: VecDeque<usize>,
+    in_flight: HashMap<u16, usize>,
+    max_in_flight: usize,
+}
+
+impl Batch<'_> {
+    fn is_done(&self) -> bool {
+        self.pending.is_empty() && self.in_flight.is_empty()
+    }
+
+    // 写入窗口内的查询后读取一个响应，返回该响应是否回答了本批的查询及其中的keepalive超时
+    fn step(
+        &mut self,
+        stream: &mut TcpStream,
+        results: &mut [Option<Result<Vec<u8>, TcpPoolError>>],
+    ) -> Result<(bool, Option<Duration>), TcpPoolError> {
+        let mut out = Vec::new();
+        let mut buf = [0_u8; 1500];
+        while self.in_flight.len() < self.max_in_flight
+            && let Some(&index) = self.pending.front()
+        {
+            // ID相同的查询要等前一个的响应，否则无法区分
+            let id = self.requests[index].header.id;
+            if self.in_flight.contains_key(&id) {
+                break;
+            }
+            self.pending.pop_front();
+            self.in_flight.insert(id, index);
+            out.extend_from_slice(self.requests[index].encode_to_tcp(&mut buf));
+        }
+        if !out.is_empty() {
+            stream.write_all(&out).map_err(TcpPoolError::Write)?;
+        }
+
+        let mut len = [0_u8; 2];
+        stream.read_exact(&mut len).map_err(TcpPoolError::Recv)?;
+        let mut message = vec![0_u8; u16::from_be_bytes(len) as usize];
+        stream
+            .read_exact(&mut message)
+            .map_err(TcpPoolError::Recv)?;
+        let Some(id) = message.first_chunk::<2>().map(|id| u16::from_be_bytes(*id)) else {
+            return Err(TcpPoolError::Recv(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response shorter than 2 bytes",
+            )));
+        };
+        let keepalive = tcp_keepalive(&message);
+        // 未知ID的响应直接丢弃
+        let Some(index) = self.in_flight.remove(&id) else {
+            return Ok((false, keepalive));
+        };
+        results[index] = Some(Ok(message));
+        Ok((true, keepalive))
+    }
+
+    // 连接断开后，已发送但未收到响应的查询按原顺序重新排队
+    fn requeue(&mut self) {
+        let mut in_flight: Vec<usize> = self.in_flight.drain().map(|(_, index)| index).collect();
+        in_flight.sort_unstable();
+        for index in in_flight.into_iter().rev() {
+            self.pending.push_front(index);
+        }
+    }
+
+    fn fail(&mut self, results: &mut [Option<Result<Vec<u8>, TcpPoolError>>], err: TcpPoolError) {
+        self.requeue();
+        for index in self.pending.drain(..) {
+            results[index] = Some(Err(err.duplicate()));
+        }
+    }
+}
+
+fn connect(bind: &LocalBind, addr: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let stream = bind.tcp_connect(addr, timeout)?;
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+fn set_timeout(stream: &TcpStream, timeout: Option<Duration>) -> io::Result<()> {
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)
+}
+
+// OPT中edns-tcp-keepalive的TIMEOUT，单位为100毫秒(RFC 7828 3.1)
+fn tcp_keepalive(message: &[u8]) -> Option<Duration> {
+    let response = Response::from_slice_uncheck(message).ok()?;
+    response.additionals().iter().find_map(|record| {
+        let RecordDataType::OPT(opt) = &record.data else {
+            return None;
+        };
+        let (_, data) = opt
+            .options()
+            .iter()
+            .find(|(code, _)| *code == Edns::TCP_KEEPALIVE)?;
+        let timeout: [u8; 2] = data.as_slice().try_into().ok()?;
+        Some(Duration::from_millis(
+            u16::from_be_bytes(timeout) as u64 * 100,
+        ))
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dns::net::NetQuery;
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::base::record::{A, OPT};
+    use crate::dns::types::parts::{MessageBuilder, Record};
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Copy, Default)]
+    pub(crate) struct Behavior {
+        // 收齐batch个查询后倒序回复
+        batch: usize,
+        // 每条连接回复close_after个查询后关闭
+        close_after: Option<usize>,
+        // 响应中携带的edns-tcp-keepalive超时，单位为100毫秒
+        keepalive: Option<u16>,
+        // 每个响应发送前的延迟
+        delay: Option<Duration>,
+    }
+
+    // 返回监听地址与已接受的连接数
+    pub(crate) fn serve(behavior: Behavior) -> (SocketAddr, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { return };
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || handle(stream, behavior));
+            }
+        });
+        (addr, connections)
+    }
+
+    fn handle(mut stream: TcpStream, behavior: Behavior) {
+        let mut answered = 0;
+        loop {
+            let mut queries = Vec::new();
+            while queries.len() < behavior.batch.max(1) {
+                let mut len = [0_u8; 2];
+                if stream.read_exact(&mut len).is_err() {
+                    return;
+                }
+                let mut query = vec![0_u8; u16::from_be_bytes(len) as usize];
+                if stream.read_exact(&mut query).is_err() {
+                    return;
+                }
+                queries.push(query);
+            }
+            for query in queries.iter().rev() {
+                if behavior.close_after == Some(answered) {
+                    return;
+                }
+                if let Some(delay) = behavior.delay {
+                    std::thread::sleep(delay);
+                }
+                let response = answer(query, behavior.keepalive);
+                let _ = stream.write_all(&(response.len() as u16).to_be_bytes());
+                let _ = stream.write_all(&response);
+                answered += 1;
+            }
+        }
+    }
+
+    pub(crate) fn answer(query: &[u8], keepalive: Option<u16>) -> Vec<u8> {
+        let query = Response::from_slice_uncheck(query).unwrap();
+        let question = &query.question[0];
+        let name = || RawDomain::from_raw(question.qname.as_ref().as_ref().clone());
+        let mut builder = MessageBuilder::new()
+            .id(query.header.id)
+            .response(true)
+            .rec_desired(true)
+            .rec_avail(true)
+            .question(Rc::new(name()), question.qtype, question.qclass)
+            .answer(Record::from_data(
+                name(),
+                1,
+                60,
+                RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 1))),
+            ));
+        if let Some(timeout) = keepalive {
+            let opt = OPT::new(vec![(Edns::TCP_KEEPALIVE, timeout.to_be_bytes().to_vec())]);
+            builder = builder.additional(Record::from_data(
+                RawDomain::from_raw(Vec::new()),
+                1232,
+                0,
+                RecordDataType::OPT(opt),
+            ));
+        }
+        let mut buf = [0_u8; 512];
+        builder.encode_to_udp(&mut buf).to_vec()
+    }
+
+    fn request(id: u16) -> Request {
+        let mut request = Request::new(Rc::new(RawDomain::from_str("example.com").unwrap()), 1);
+        request.header.id = id;
+        request
+    }
+
+    fn query(pool: &TcpPool, addr: SocketAddr, id: u16) -> Option<Response> {
+        let timeout = Some(Duration::from_secs(5));
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_tcp_pool(pool, addr, timeout, request(id)).into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_tcp_pool(pool, addr, timeout, request(id))
+    }
+
+    #[test]
+    fn test_reuse() {
+        let (addr, connections) = serve(Behavior::default());
+        let pool = TcpPool::new();
+        for id in 1..=3 {
+            assert_eq!(query(&pool, addr, id).unwrap().header.id, id);
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let (addr, connections) = serve(Behavior {
+            batch: 3,
+            ..Default::default()
+        });
+        let pool = TcpPool::new();
+        // 服务器收齐三个查询后倒序回复
+        let requests = vec![request(1), request(2), request(3)];
+        let results =
+            NetQuery::query_tcp_pipelined(&pool, addr, Some(Duration::from_secs(5)), requests);
+        for (result, id) in results.into_iter().zip(1..) {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().header.id, id);
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // ID相同的查询依次发送
+        let (addr, _) = serve(Behavior::default());
+        let results =
+            NetQuery::query_tcp_pipelined(&pool, addr, None, vec![request(7), request(7)]);
+        for result in results {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().header.id, 7);
+        }
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let (addr, connections) = serve(Behavior {
+            close_after: Some(1),
+            ..Default::default()
+        });
+        let pool = TcpPool::new();
+        assert!(query(&pool, addr, 1).is_some());
+        assert!(query(&pool, addr, 2).is_some());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+        // 新建的连接上没有收到任何响应时不再重试
+        let (addr, _) = serve(Behavior {
+            close_after: Some(0),
+            ..Default::default()
+        });
+        assert!(query(&pool, addr, 1).is_none());
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let (addr, connections) = serve(Behavior {
+            keepalive: Some(0),
+            ..Default::default()
+        });
+        let pool = TcpPool::new();
+        assert!(query(&pool, addr, 1).is_some());
+        assert!(pool.connections.borrow().is_empty());
+        assert!(query(&pool, addr, 2).is_some());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+        // 服务器给出的超时比本地的短，以服务器为准
+        let (addr, connections) = serve(Behavior {
+            keepalive: Some(1),
+            ..Default::default()
+        });
+        assert!(query(&pool, addr, 1).is_some());
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(query(&pool, addr, 2).is_some());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let (addr, connections) = serve(Behavior::default());
+        let pool = TcpPool::new().with_idle_timeout(Duration::from_millis(100));
+        assert!(query(&pool, addr, 1).is_some());
+        assert!(query(&pool, addr, 2).is_some());
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(query(&pool, addr, 3).is_some());
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_reconnect_once_per_batch() {
+        // 每条连接只回答一个查询，一批查询最多重连一次
+        let (addr, connections) = serve(Behavior {
+            close_after: Some(1),
+            ..Default::default()
+        });
+        let pool = TcpPool::new();
+        let requests = vec![request(1), request(2), request(3)];
+        let results =
+            NetQuery::query_tcp_pipelined(&pool, addr, Some(Duration::from_secs(5)), requests);
+        let answered: Vec<bool> = results
+            .into_iter()
+            .map(|result| {
+                #[cfg(feature = "result_error")]
+                let result = result.into_result();
+                result.is_some()
+            })
+            .collect();
+        assert_eq!(answered, [true, true, false]);
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_batch_deadline() {
+        // 每个响应都在单次读取超时之内到达，但整批超过总时限
+        let (addr, _) = serve(Behavior {
+            delay: Some(Duration::from_millis(200)),
+            ..Default::default()
+        });
+        let pool = TcpPool::new();
+        let requests = vec![request(1), request(2), request(3)];
+        let start = Instant::now();
+        let results =
+            NetQuery::query_tcp_pipelined(&pool, addr, Some(Duration::from_millis(300)), requests);
+        assert!(start.elapsed() < Duration::from_millis(500));
+        let answered: Vec<bool> = results
+            .into_iter()
+            .map(|result| {
+                #[cfg(feature = "result_error")]
+                let result = result.into_result();
+                result.is_some()
+            })
+            .collect();
+        assert_eq!(answered, [true, false, false]);
+        assert!(pool.connections.borrow().is_empty());
+    }
+}
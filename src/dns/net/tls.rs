@@ -0,0 +1,477 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::LocalBind;
+use crate::dns::net::query::exchange_framed;
+use crate::dns::types::parts::Request;
+use ring::digest::{SHA256, digest};
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, ring::default_provider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    StreamOwned,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+// DNS over TLS(RFC 7858)，报文沿用TCP的2字节长度前缀。
+// 证书按WebPKI校验，设置了SPKI pin(RFC 7858 4.2)时服务器证书的公钥还必须与其中之一匹配，
+// 同一地址与SNI的连接在查询后保留，供下一次查询复用
+pub struct TlsTransport {
+    verifier: Arc<WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+    config: Arc<ClientConfig>,
+    bind: LocalBind,
+    connections: RefCell<HashMap<(SocketAddr, String), TlsStream>>,
+}
+
+impl Default for TlsTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TlsTransport {
+    // 使用webpki-roots内置的根证书
+    pub fn new() -> TlsTransport {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let verifier = build_verifier(roots).expect("webpki-roots is not empty");
+        TlsTransport {
+            config: build_config(verifier.clone(), Vec::new(), Vec::new()),
+            verifier,
+            pins: Vec::new(),
+            bind: LocalBind::new(),
+            connections: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // 替换信任的根证书，之前设置的SPKI pin被清除
+    pub fn with_root_certificates(
+        mut self,
+        certs: Vec<CertificateDer<'static>>,
+    ) -> Result<TlsTransport, rustls::Error> {
+        let mut roots = RootCertStore::empty();
+        for cert in certs {
+            roots.add(cert)?;
+        }
+        self.verifier = build_verifier(roots)?;
+        self.pins = Vec::new();
+        self.config = build_config(self.verifier.clone(), Vec::new(), Vec::new());
+        self.connections.get_mut().clear();
+        Ok(self)
+    }
+
+    // 服务器证书SubjectPublicKeyInfo的SHA-256
+    pub fn with_spki_pins(mut self, pins: Vec<[u8; 32]>) -> TlsTransport {
+        self.config = build_config(self.verifier.clone(), pins.clone(), Vec::new());
+        self.pins = pins;
+        self.connections.get_mut().clear();
+        self
+    }
+
+    pub fn with_local_bind(mut self, bind: LocalBind) -> TlsTransport {
+        self.bind = bind;
+        self.connections.get_mut().clear();
+        self
+    }
+
+    // 相同证书校验设置下带ALPN的配置，供DoH等基于TLS的传输使用
+    pub(crate) fn client_config(&self, alpn: Vec<Vec<u8>>) -> Arc<ClientConfig> {
+        build_config(self.verifier.clone(), self.pins.clone(), alpn)
+    }
+
+    fn connect(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+    ) -> io::Result<TlsStream> {
+        let name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let connection = ClientConnection::new(self.config.clone(), name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let socket = self.bind.tcp_connect(addr, timeout)?;
+        socket.set_read_timeout(timeout)?;
+        socket.set_write_timeout(timeout)?;
+        socket.set_nodelay(true)?;
+        let mut stream = StreamOwned::new(connection, socket);
+        // 先完成握手，证书错误在这里返回
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+        Ok(stream)
+    }
+
+    // 返回不带长度前缀的响应报文
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        request: &Request,
+    ) -> io::Result<Vec<u8>> {
+        let key = (addr, server_name.to_string());
+        let (stream, mut messages) =
+            self.exchange_on(addr, server_name, timeout, std::slice::from_ref(request))?;
+        self.connections.borrow_mut().insert(key, stream);
+        Ok(messages.remove(0))
+    }
+
+    // 在同一连接上流水线发送多个查询，结果与requests一一对应
+    pub(crate) fn exchange_all(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> Vec<io::Result<Vec<u8>>> {
+        let key = (addr, server_name.to_string());
+        match self.exchange_on(addr, server_name, timeout, requests) {
+            Ok((stream, messages)) => {
+                self.connections.borrow_mut().insert(key, stream);
+                messages.into_iter().map(Ok).collect()
+            }
+            Err(err) => requests
+                .iter()
+                .map(|_| Err(io::Error::new(err.kind(), err.to_string())))
+                .collect(),
+        }
+    }
+
+    fn exchange_on(
+        &self,
+        addr: SocketAddr,
+        server_name: &str,
+        timeout: Option<Duration>,
+        requests: &[Request],
+    ) -> io::Result<(TlsStream, Vec<Vec<u8>>)> {
+        let key = (addr, server_name.to_string());
+        let reused = self.connections.borrow_mut().remove(&key);
+        let is_reused = reused.is_some();
+        let mut stream = match reused {
+            Some(stream) => stream,
+            None => self.connect(addr, server_name, timeout)?,
+        };
+        let messages = match exchange_framed(&mut stream, requests) {
+            Ok(messages) => messages,
+            // 复用的连接可能已被服务器关闭，重新建立一次
+            Err(_) if is_reused => {
+                stream = self.connect(addr, server_name, timeout)?;
+                exchange_framed(&mut stream, requests)?
+            }
+            Err(err) => return Err(err),
+        };
+        Ok((stream, messages))
+    }
+}
+
+pub(crate) fn provider() -> Arc<CryptoProvider> {
+    Arc::new(default_provider())
+}
+
+fn build_verifier(roots: RootCertStore) -> Result<Arc<WebPkiServerVerifier>, rustls::Error> {
+    WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider())
+        .build()
+        .map_err(|e| rustls::Error::General(e.to_string()))
+}
+
+fn build_config(
+    verifier: Arc<WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+    alpn: Vec<Vec<u8>>,
+) -> Arc<ClientConfig> {
+    let mut config = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PinnedVerifier { verifier, pins }))
+        .with_no_client_auth();
+    config.alpn_protocols = alpn;
+    Arc::new(config)
+}
+
+// 证书中SubjectPublicKeyInfo的SHA-256，即with_spki_pins使用的pin
+pub fn spki_sha256(cert: &CertificateDer<'_>) -> Option<[u8; 32]> {
+    let cert = webpki::EndEntityCert::try_from(cert).ok()?;
+    let spki = cert.subject_public_key_info();
+    digest(&SHA256, spki.as_ref()).as_ref().try_into().ok()
+}
+
+#[derive(Debug)]
+struct PinnedVerifier {
+    verifier: Arc<WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        if self.pins.is_empty() {
+            return Ok(verified);
+        }
+        match spki_sha256(end_entity) {
+            Some(hash) if self.pins.contains(&hash) => Ok(verified),
+            _ => Err(rustls::Error::General("SPKI pin mismatch".to_string())),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.verifier.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.verifier.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::dns::net::NetQuery;
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::base::record::A;
+    use crate::dns::types::parts::{MessageBuilder, Record, RecordDataType, Response};
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ServerConfig, ServerConnection};
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) struct TestCa {
+        pub(crate) ca: CertificateDer<'static>,
+        pub(crate) cert: CertificateDer<'static>,
+        pub(crate) key: Vec<u8>,
+    }
+
+    // 测试时生成的自签名CA，以及由它签发的dns.test服务器证书
+    pub(crate) fn test_ca() -> TestCa {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["dns.test".to_string()])
+            .unwrap()
+            .signed_by(&key, &*ca)
+            .unwrap();
+        TestCa {
+            ca: ca.der().clone(),
+            cert: cert.der().clone(),
+            key: key.serialize_der(),
+        }
+    }
+
+    // 对每个查询回复一条192.0.2.1的A记录
+    pub(crate) fn answer(query: &[u8]) -> Vec<u8> {
+        let query = Response::from_slice_uncheck(query).unwrap();
+        let question = &query.question[0];
+        let name = || RawDomain::from_raw(question.qname.as_ref().as_ref().clone());
+        let mut buf = [0_u8; 512];
+        MessageBuilder::new()
+            .id(query.header.id)
+            .response(true)
+            .rec_desired(true)
+            .rec_avail(true)
+            .question(Rc::new(name()), question.qtype, question.qclass)
+            .answer(Record::from_data(
+                name(),
+                1,
+                60,
+                RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 1))),
+            ))
+            .encode_to_udp(&mut buf)
+            .to_vec()
+    }
+
+    // 每条连接最多回答queries_per_connection个查询后关闭，收齐batch个查询后倒序回复，
+    // 返回监听地址与已接受的连接数
+    fn serve(
+        ca: &TestCa,
+        queries_per_connection: usize,
+        batch: usize,
+    ) -> (SocketAddr, Arc<AtomicUsize>) {
+        let config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![ca.cert.clone()],
+                PrivateKeyDer::Pkcs8(ca.key.clone().into()),
+            )
+            .unwrap();
+        let config = Arc::new(config);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        std::thread::spawn(move || {
+            for socket in listener.incoming() {
+                let Ok(socket) = socket else { return };
+                counter.fetch_add(1, Ordering::SeqCst);
+                let connection = ServerConnection::new(config.clone()).unwrap();
+                std::thread::spawn(move || {
+                    let mut stream = StreamOwned::new(connection, socket);
+                    let mut answered = 0;
+                    while answered < queries_per_connection {
+                        let mut queries = Vec::new();
+                        while queries.len() < batch {
+                            let mut len = [0_u8; 2];
+                            if stream.read_exact(&mut len).is_err() {
+                                return;
+                            }
+                            let mut query = vec![0_u8; u16::from_be_bytes(len) as usize];
+                            if stream.read_exact(&mut query).is_err() {
+                                return;
+                            }
+                            queries.push(query);
+                        }
+                        for query in queries.iter().rev() {
+                            let response = answer(query);
+                            let _ = stream.write_all(&(response.len() as u16).to_be_bytes());
+                            let _ = stream.write_all(&response);
+                            answered += 1;
+                        }
+                        let _ = stream.flush();
+                    }
+                    stream.conn.send_close_notify();
+                    let _ = stream.flush();
+                });
+            }
+        });
+        (addr, accepted)
+    }
+
+    fn request() -> Request {
+        Request::new(Rc::new(RawDomain::from_str("example.com").unwrap()), 1)
+    }
+
+    fn query(transport: &TlsTransport, addr: SocketAddr, name: &str) -> Option<Response> {
+        let timeout = Some(Duration::from_secs(5));
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_tls(transport, addr, name, timeout, request()).into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_tls(transport, addr, name, timeout, request())
+    }
+
+    #[test]
+    fn test_reuse() {
+        let ca = test_ca();
+        let (addr, accepted) = serve(&ca, usize::MAX, 1);
+        let transport = TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap();
+        for _ in 0..3 {
+            let response = query(&transport, addr, "dns.test").unwrap();
+            assert_eq!(response.answers().len(), 1);
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        // 服务器关闭连接后重新建立
+        let (addr, accepted) = serve(&ca, 1, 1);
+        for _ in 0..2 {
+            assert!(query(&transport, addr, "dns.test").is_some());
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let ca = test_ca();
+        let (addr, accepted) = serve(&ca, usize::MAX, 2);
+        let transport = TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap();
+        let requests: Vec<Request> = (1..=2)
+            .map(|id| {
+                let mut request = request();
+                request.header.id = id;
+                request
+            })
+            .collect();
+        // 服务器收齐两个查询才倒序回复，依次发送时第一个查询就会超时
+        let results = NetQuery::query_tls_pipelined(
+            &transport,
+            addr,
+            "dns.test",
+            Some(Duration::from_secs(5)),
+            requests,
+        );
+        for (id, result) in (1..=2).zip(results) {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().header.id, id);
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_verify() {
+        let ca = test_ca();
+        let (addr, _) = serve(&ca, usize::MAX, 1);
+        // 不受信任的CA与不匹配的名称
+        assert!(query(&TlsTransport::new(), addr, "dns.test").is_none());
+        let transport = TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap();
+        assert!(query(&transport, addr, "other.test").is_none());
+
+        let pin = spki_sha256(&ca.cert).unwrap();
+        let transport = transport.with_spki_pins(vec![[0; 32], pin]);
+        assert!(query(&transport, addr, "dns.test").is_some());
+        let transport = transport.with_spki_pins(vec![spki_sha256(&ca.ca).unwrap()]);
+        assert!(query(&transport, addr, "dns.test").is_none());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_local_bind() {
+        let ca = test_ca();
+        let (addr, _) = serve(&ca, usize::MAX, 1);
+        let bind = LocalBind::new().with_addr("127.0.0.1".parse().unwrap());
+        let transport = TlsTransport::new()
+            .with_root_certificates(vec![ca.ca.clone()])
+            .unwrap()
+            .with_local_bind(bind.clone());
+        assert!(query(&transport, addr, "dns.test").is_some());
+        // 绑定到不存在的网卡时无法建立连接
+        let transport = transport.with_local_bind(bind.with_interface("no-such-if0"));
+        assert!(query(&transport, addr, "dns.test").is_none());
+    }
+}
@@ -0,0 +1,541 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::LocalBind;
+use crate::dns::types::parts::Request;
+use mio::net::UdpSocket;
+use mio::{Events, Interest, Poll, Token};
+use rand::{Rng, rng};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+// 非阻塞的UDP查询引擎，多个查询复用一组源端口随机的socket。
+// 每个查询随机选择socket，ID在socket内不重复，响应需要同时匹配ID、服务器地址与问题，
+// 不匹配的报文丢弃并计数。socket使用max_uses次后重新绑定，端口随之更换(RFC 5452 9.2)
+pub struct UdpPool {
+    sockets_per_family: usize,
+    max_uses: usize,
+    bind: LocalBind,
+    state: RefCell<Option<State>>,
+    stats: Cell<UdpStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UdpStats {
+    pub sent: u64,
+    pub received: u64,
+    // ID、来源地址或问题不匹配而丢弃的报文，包括超时后才到达的响应
+    pub mismatched: u64,
+    pub timed_out: u64,
+}
+
+pub(crate) enum UdpPoolError {
+    Bind(io::Error),
+    Send(io::Error),
+    Recv(io::Error),
+}
+
+impl UdpPoolError {
+    // io::Error不能Clone，同一错误需要返回给多个查询
+    fn duplicate(&self) -> UdpPoolError {
+        let copy = |err: &io::Error| io::Error::new(err.kind(), err.to_string());
+        match self {
+            UdpPoolError::Bind(err) => UdpPoolError::Bind(copy(err)),
+            UdpPoolError::Send(err) => UdpPoolError::Send(copy(err)),
+            UdpPoolError::Recv(err) => UdpPoolError::Recv(copy(err)),
+        }
+    }
+}
+
+struct State {
+    poll: Poll,
+    sockets: Vec<PooledSocket>,
+    next_token: usize,
+}
+
+struct PooledSocket {
+    socket: UdpSocket,
+    token: Token,
+    ipv6: bool,
+    uses: usize,
+}
+
+// 已编码、等待响应的查询
+struct Pending {
+    index: usize,
+    addr: SocketAddr,
+    token: Token,
+    message: Vec<u8>,
+    question_end: usize,
+}
+
+impl Default for UdpPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UdpPool {
+    pub fn new() -> UdpPool {
+        UdpPool {
+            sockets_per_family: 16,
+            max_uses: 128,
+            bind: LocalBind::new(),
+            state: RefCell::new(None),
+            stats: Cell::new(UdpStats::default()),
+        }
+    }
+
+    // IPv4与IPv6各自最多保留的socket数
+    pub fn with_sockets(mut self, sockets_per_family: usize) -> UdpPool {
+        self.sockets_per_family = sockets_per_family.max(1);
+        self
+    }
+
+    // 每个socket发送多少个查询后更换端口
+    pub fn with_max_uses(mut self, max_uses: usize) -> UdpPool {
+        self.max_uses = max_uses.max(1);
+        self
+    }
+
+    // 已绑定的socket会被关闭，之后按新的地址与网卡重新绑定
+    pub fn with_local_bind(mut self, bind: LocalBind) -> UdpPool {
+        self.bind = bind;
+        self.state = RefCell::new(None);
+        self
+    }
+
+    pub fn stats(&self) -> UdpStats {
+        self.stats.get()
+    }
+
+    // 返回完整的响应报文，request的ID可能被替换以避免与其他查询冲突
+    pub(crate) fn exchange(
+        &self,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+        request: &mut Request,
+    ) -> Result<Vec<u8>, UdpPoolError> {
+        let mut results = self.exchange_all(timeout, std::slice::from_mut(request), |_| addr);
+        results.pop().expect("one result per request")
+    }
+
+    // 同时发送所有查询，结果与requests一一对应，addr给出每个查询的服务器
+    pub(crate) fn exchange_all<F: Fn(usize) -> SocketAddr>(
+        &self,
+        timeout: Option<Duration>,
+        requests: &mut [Request],
+        addr: F,
+    ) -> Vec<Result<Vec<u8>, UdpPoolError>> {
+        let mut results: Vec<Option<Result<Vec<u8>, UdpPoolError>>> =
+            requests.iter().map(|_| None).collect();
+        let mut state = self.state.borrow_mut();
+        let state = match &mut *state {
+            Some(state) => state,
+            None => match Poll::new() {
+                Ok(poll) => state.insert(State {
+                    poll,
+                    sockets: Vec::new(),
+                    next_token: 0,
+                }),
+                Err(err) => {
+                    let err = UdpPoolError::Bind(err);
+                    return requests.iter().map(|_| Err(err.duplicate())).collect();
+                }
+            },
+        };
+        self.retire(state);
+
+        let mut stats = self.stats.get();
+        let mut pending = Vec::with_capacity(requests.len());
+        let mut outstanding: HashMap<(Token, u16), usize> = HashMap::new();
+        for (index, request) in requests.iter_mut().enumerate() {
+            let addr = addr(index);
+            let token = match self.socket_for(state, addr.is_ipv6()) {
+                Ok(token) => token,
+                Err(err) => {
+                    results[index] = Some(Err(UdpPoolError::Bind(err)));
+                    continue;
+                }
+            };
+            while outstanding.contains_key(&(token, request.header.id)) {
+                request.header.id = rng().random();
+            }
+            let mut buf = [0_u8; 1500];
+            let message = request.encode_to_udp(&mut buf);
+            let Some(question_end) = question_end(message).filter(|_| message.len() <= 512) else {
+                results[index] = Some(Err(UdpPoolError::Send(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "request too large for UDP",
+                ))));
+                continue;
+            };
+            outstanding.insert((token, request.header.id), pending.len());
+            pending.push(Pending {
+                index,
+                addr,
+                token,
+                message: message.to_vec(),
+                question_end,
+            });
+        }
+
+        // 发送缓冲区满时留到socket可写后再发送
+        let mut unsent: Vec<usize> = Vec::new();
+        for (position, query) in pending.iter().enumerate() {
+            match send(state, query) {
+                Ok(true) => stats.sent += 1,
+                Ok(false) => unsent.push(position),
+                Err(err) => {
+                    outstanding.retain(|_, p| *p != position);
+                    results[query.index] = Some(Err(UdpPoolError::Send(err)));
+                }
+            }
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut events = Events::with_capacity(64);
+        let mut buf = [0_u8; 4096];
+        while !outstanding.is_empty() {
+            let wait = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if wait.is_some_and(|wait| wait.is_zero()) {
+                break;
+            }
+            if let Err(err) = state.poll.poll(&mut events, wait) {
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                let err = UdpPoolError::Recv(err);
+                for (_, position) in outstanding.drain() {
+                    results[pending[position].index] = Some(Err(err.duplicate()));
+                }
+                break;
+            }
+            for event in events.iter() {
+                let token = event.token();
+                if event.is_writable() {
+                    unsent.retain(|&position| {
+                        let query = &pending[position];
+                        if query.token != token {
+                            return true;
+                        }
+                        match send(state, query) {
+                            Ok(true) => stats.sent += 1,
+                            Ok(false) => return true,
+                            Err(err) => {
+                                outstanding.retain(|_, p| *p != position);
+                                results[query.index] = Some(Err(UdpPoolError::Send(err)));
+                            }
+                        }
+                        false
+                    });
+                }
+                if !event.is_readable() {
+                    continue;
+                }
+                let Some(socket) = state.sockets.iter().find(|socket| socket.token == token) else {
+                    continue;
+                };
+                // mio为边沿触发，需要读到WouldBlock为止
+                while let Ok((len, from)) = socket.socket.recv_from(&mut buf) {
+                    let message = &buf[..len];
+                    let matched = message
+                        .first_chunk::<2>()
+                        .map(|id| (token, u16::from_be_bytes(*id)))
+                        .and_then(|key| outstanding.get(&key).map(|&position| (key, position)))
+                        .filter(|(_, position)| is_response(&pending[*position], from, message));
+                    match matched {
+                        Some((key, position)) => {
+                            outstanding.remove(&key);
+                            results[pending[position].index] = Some(Ok(message.to_vec()));
+                            stats.received += 1;
+                        }
+                        None => stats.mismatched += 1,
+                    }
+                }
+            }
+        }
+
+        for (_, position) in outstanding {
+            let query = &pending[position];
+            stats.timed_out += 1;
+            results[query.index] = Some(Err(UdpPoolError::Recv(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no response from {}", query.addr),
+            ))));
+        }
+        self.stats.set(stats);
+        results
+            .into_iter()
+            .map(|result| result.expect("every request is answered or failed"))
+            .collect()
+    }
+
+    // 用满次数的socket在两次查询之间关闭，此时没有等待中的查询
+    fn retire(&self, state: &mut State) {
+        let max_uses = self.max_uses;
+        let registry = state.poll.registry();
+        state.sockets.retain_mut(|socket| {
+            if socket.uses < max_uses {
+                return true;
+            }
+            let _ = registry.deregister(&mut socket.socket);
+            false
+        });
+    }
+
+    // 不足sockets_per_family个时按地址族新绑定一个，端口由系统随机分配，否则随机选取已有的socket
+    fn socket_for(&self, state: &mut State, ipv6: bool) -> io::Result<Token> {
+        let candidates: Vec<usize> = (0..state.sockets.len())
+            .filter(|&i| state.sockets[i].ipv6 == ipv6 && state.sockets[i].uses < self.max_uses)
+            .collect();
+        let bound = state.sockets.iter().filter(|s| s.ipv6 == ipv6).count();
+        let index = if candidates.is_empty() || bound < self.sockets_per_family {
+            let socket = self.bind.udp_socket(ipv6)?;
+            socket.set_nonblocking(true)?;
+            let mut socket = UdpSocket::from_std(socket);
+            let token = Token(state.next_token);
+            state.next_token += 1;
+            state.poll.registry().register(
+                &mut socket,
+                token,
+                Interest::READABLE | Interest::WRITABLE,
+            )?;
+            state.sockets.push(PooledSocket {
+                socket,
+                token,
+                ipv6,
+                uses: 0,
+            });
+            state.sockets.len() - 1
+        } else {
+            candidates[rng().random_range(0..candidates.len())]
+        };
+        let socket = &mut state.sockets[index];
+        socket.uses += 1;
+        Ok(socket.token)
+    }
+}
+
+// 返回false表示发送缓冲区已满
+fn send(state: &State, query: &Pending) -> io::Result<bool> {
+    let socket = state
+        .sockets
+        .iter()
+        .find(|socket| socket.token == query.token)
+        .expect("socket outlives the batch");
+    match socket.socket.send_to(&query.message, query.addr) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+// 来源地址、QR位、问题数与问题部分都需要与请求一致，域名不区分大小写
+fn is_response(query: &Pending, from: SocketAddr, message: &[u8]) -> bool {
+    let question = 4..6;
+    let section = 12..query.question_end;
+    from == query.addr
+        && message.get(2).is_some_and(|flags| flags & 0x80 != 0)
+        && message.get(question.clone()) == query.message.get(question)
+        && message.get(section.clone()).is_some_and(|section_bytes| {
+            section_bytes.eq_ignore_ascii_case(&query.message[section])
+        })
+}
+
+// 请求由本库编码，问题部分的域名不含压缩指针
+fn question_end(message: &[u8]) -> Option<usize> {
+    let mut pos = 12;
+    loop {
+        let len = *message.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            break;
+        }
+    }
+    Some(pos + 4).filter(|&end| end <= message.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::net::tcp_pool::tests::{Behavior, answer, serve as serve_tcp};
+    use crate::dns::net::{NetQuery, TcpPool};
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::parts::Response;
+    use std::rc::Rc;
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    // 返回监听地址与收到的查询的来源端口，respond决定如何回复
+    fn serve<F>(respond: F) -> (SocketAddr, Arc<Mutex<Vec<u16>>>)
+    where
+        F: Fn(&std::net::UdpSocket, &[u8], SocketAddr) + Send + 'static,
+    {
+        serve_on("127.0.0.1:0", respond)
+    }
+
+    fn serve_on<F>(bind: &str, respond: F) -> (SocketAddr, Arc<Mutex<Vec<u16>>>)
+    where
+        F: Fn(&std::net::UdpSocket, &[u8], SocketAddr) + Send + 'static,
+    {
+        let socket = std::net::UdpSocket::bind(bind).unwrap();
+        let addr = socket.local_addr().unwrap();
+        let ports = Arc::new(Mutex::new(Vec::new()));
+        let record = ports.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0_u8; 1500];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                record.lock().unwrap().push(peer.port());
+                respond(&socket, &buf[..len], peer);
+            }
+        });
+        (addr, ports)
+    }
+
+    fn reply(socket: &std::net::UdpSocket, query: &[u8], peer: SocketAddr) {
+        let _ = socket.send_to(&answer(query, None), peer);
+    }
+
+    fn request(name: &str, id: u16) -> Request {
+        let mut request = Request::new(Rc::new(RawDomain::from_str(name).unwrap()), 1);
+        request.header.id = id;
+        request
+    }
+
+    fn query(pool: &UdpPool, addr: SocketAddr, request: Request) -> Option<Response> {
+        let timeout = Some(Duration::from_millis(500));
+        let tcp = TcpPool::new();
+        #[cfg(feature = "result_error")]
+        return NetQuery::query_udp_pool(pool, &tcp, addr, timeout, request).into_result();
+        #[cfg(not(feature = "result_error"))]
+        NetQuery::query_udp_pool(pool, &tcp, addr, timeout, request)
+    }
+
+    #[test]
+    fn test_multiplex() {
+        let (addr, _) = serve(reply);
+        let pool = UdpPool::new().with_sockets(4);
+        // 相同的ID在同一socket上会被替换
+        let mut requests: Vec<Request> = (0..100)
+            .map(|i| request(&format!("host{}.example.com", i), (i % 3) as u16))
+            .collect();
+        let results = pool.exchange_all(Some(Duration::from_secs(5)), &mut requests, |_| addr);
+        for (result, request) in results.into_iter().zip(&requests) {
+            let Ok(message) = result else {
+                panic!("query not answered");
+            };
+            assert!(Response::from_slice(&message, request).is_ok());
+        }
+        let stats = pool.stats();
+        assert_eq!(
+            (stats.sent, stats.received, stats.mismatched),
+            (100, 100, 0)
+        );
+        assert!(pool.state.borrow().as_ref().unwrap().sockets.len() <= 4);
+    }
+
+    #[test]
+    fn test_dual_stack() {
+        let (v4, _) = serve(reply);
+        let (v6, _) = serve_on("[::1]:0", reply);
+        let pool = UdpPool::new();
+        let mut requests: Vec<Request> = (0..10).map(|id| request("example.com", id)).collect();
+        let results = pool.exchange_all(Some(Duration::from_secs(5)), &mut requests, |i| {
+            if i % 2 == 0 { v4 } else { v6 }
+        });
+        assert!(results.iter().all(Result::is_ok));
+        let state = pool.state.borrow();
+        let sockets = &state.as_ref().unwrap().sockets;
+        assert!(sockets.iter().any(|socket| socket.ipv6));
+        assert!(sockets.iter().any(|socket| !socket.ipv6));
+    }
+
+    #[test]
+    fn test_spoofed() {
+        let spoofer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (addr, _) = serve(move |socket, query, peer| {
+            let mut wrong_id = answer(query, None);
+            wrong_id[0] ^= 0xff;
+            let _ = socket.send_to(&wrong_id, peer);
+            let mut other = query.to_vec();
+            other[13] = b'x';
+            let _ = socket.send_to(&answer(&other, None), peer);
+            // 伪造的来源地址
+            let _ = spoofer.send_to(&answer(query, None), peer);
+            // 请求原样返回，QR位为0
+            let _ = socket.send_to(query, peer);
+            reply(socket, query, peer);
+        });
+        let pool = UdpPool::new();
+        let response = query(&pool, addr, request("example.com", 1)).unwrap();
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(pool.stats().mismatched, 4);
+        assert_eq!(pool.stats().received, 1);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let (addr, _) = serve(|socket, query, peer| {
+            let mut response = answer(query, None);
+            response[13..24].make_ascii_uppercase();
+            let _ = socket.send_to(&response, peer);
+        });
+        assert!(query(&UdpPool::new(), addr, request("example.com", 1)).is_some());
+    }
+
+    #[test]
+    fn test_port_rotation() {
+        let (addr, ports) = serve(reply);
+        let pool = UdpPool::new().with_sockets(1).with_max_uses(2);
+        for id in 0..3 {
+            assert!(query(&pool, addr, request("example.com", id)).is_some());
+        }
+        let ports = ports.lock().unwrap();
+        assert_eq!(ports[0], ports[1]);
+        assert_ne!(ports[1], ports[2]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        // 同一端口上UDP只回复TC置位的响应，TCP回复完整响应
+        let (addr, connections) = serve_tcp(Behavior::default());
+        let (_, queries) = serve_on(&addr.to_string(), |socket, query, peer| {
+            let mut response = answer(query, None);
+            response[2] |= 0b0000_0010;
+            let _ = socket.send_to(&response, peer);
+        });
+        let pool = UdpPool::new();
+        let response = query(&pool, addr, request("example.com", 1)).unwrap();
+        assert_eq!(response.header.truncated, 0);
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let tcp = TcpPool::new();
+        let requests = (0..3).map(|id| request("example.com", id)).collect();
+        let results = NetQuery::query_udp_pipelined(
+            &pool,
+            &tcp,
+            addr,
+            Some(Duration::from_secs(5)),
+            requests,
+        );
+        for result in results {
+            #[cfg(feature = "result_error")]
+            let result = result.into_result();
+            assert_eq!(result.unwrap().header.truncated, 0);
+        }
+        // 被截断的查询在同一连接上流水线重试
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(queries.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (addr, _) = serve(|_, _, _| {});
+        let pool = UdpPool::new();
+        assert!(query(&pool, addr, request("example.com", 1)).is_none());
+        assert_eq!(pool.stats().timed_out, 1);
+    }
+}
@@ -1,24 +1,91 @@
 #![cfg_attr(debug_assertions, allow(unused_variables, dead_code))]
 
+use crate::dns::error::ResultAndError;
 #[cfg(feature = "result_error")]
-use crate::dns::error::{NetError, error_trait,TraceErrorFormat};
-use crate::dns::error::{ResultAndError};
+use crate::dns::error::{NetError, TraceErrorFormat, error_trait};
+#[cfg(feature = "dnscrypt")]
+use crate::dns::net::DnsCryptTransport;
+#[cfg(feature = "https")]
+use crate::dns::net::HttpsTransport;
 use crate::dns::net::NetQuery;
 #[cfg(feature = "result_error")]
 use crate::dns::net::NetQueryError;
-use crate::dns::types::base::{DnsTypeNum, RawDomain};
+#[cfg(feature = "quic")]
+use crate::dns::net::QuicTransport;
+#[cfg(feature = "tls")]
+use crate::dns::net::TlsTransport;
+use crate::dns::net::{LocalBind, TcpPool, UdpPool};
+use crate::dns::types::base::{DnsRcodeNum, DnsTypeNum, RawDomain};
 use crate::dns::types::parts::{RecordDataType, Request, Response};
-use crate::dns::utils::ServerType;
+use crate::dns::utils::{ServerAddr, ServerHost, ServerParseError, ServerType};
 #[cfg(feature = "logger")]
 use log::debug;
 use paste::paste;
 use smallvec::SmallVec;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::iter::FilterMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 
 use std::slice::Iter;
+use std::time::{Duration, Instant};
+
+mod dnssec;
+mod forward;
+mod iterative;
+mod lookup;
+
+#[allow(unused_imports)]
+pub use dnssec::{RRsetValidation, TrustAnchor, Validation, ValidationStatus, Validator};
+#[allow(unused_imports)]
+pub use forward::{ForwardRuleError, ForwardRules};
+#[allow(unused_imports)]
+pub use iterative::{IterativeConfig, IterativeResolver, MinimiseQType};
+#[allow(unused_imports)]
+pub use lookup::{LookupIpStrategy, SocketAddrs};
+
+// 上游服务器同时有IPv4与IPv6地址时优先尝试的地址族，同一地址族内保持配置顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressPreference {
+    #[default]
+    None,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressPreference {
+    // 排序用的键，偏好的地址族在前
+    #[inline]
+    pub(crate) fn rank(self, ip: &IpAddr) -> u8 {
+        match self {
+            AddressPreference::Ipv4 => ip.is_ipv6() as u8,
+            AddressPreference::Ipv6 => ip.is_ipv4() as u8,
+            AddressPreference::None => 0,
+        }
+    }
+}
+
+// 系统解析器没有给出TTL，域名上游的地址按固定时间缓存
+const BOOTSTRAP_TTL: Duration = Duration::from_secs(300);
 
 pub struct Resolver {
     server: SmallVec<[ServerType; 5]>,
+    forward: ForwardRules,
+    preference: AddressPreference,
+    lookup_strategy: LookupIpStrategy,
+    bind: LocalBind,
+    // 域名上游解析得到的地址及其过期时间
+    bootstrap: RefCell<HashMap<String, (IpAddr, Instant)>>,
+    tcp_pool: TcpPool,
+    udp_pool: UdpPool,
+    #[cfg(feature = "tls")]
+    tls: TlsTransport,
+    #[cfg(feature = "https")]
+    https: HttpsTransport,
+    #[cfg(feature = "quic")]
+    quic: QuicTransport,
+    #[cfg(feature = "dnscrypt")]
+    dnscrypt: DnsCryptTransport,
 }
 
 pub struct ResolveConfig {
@@ -70,6 +137,16 @@ impl std::fmt::Debug for ResolverQueryError {
     }
 }
 
+// 校验路径需要设置DO位，并且接受NXDOMAIN以便校验否定应答
+fn new_request(domain: &std::rc::Rc<RawDomain>, qtype: u16, dnssec_ok: bool) -> Request {
+    let mut request = Request::new(domain.clone(), qtype);
+    if dnssec_ok {
+        request.set_dnssec_ok();
+        request.accept_rcode(DnsRcodeNum::NXDOMAIN);
+    }
+    request
+}
+
 // 没什么营养的东西
 #[cfg(feature = "result_error")]
 fn convert_err(value: NetQueryError, path: &str) -> NetError {
@@ -110,93 +187,408 @@ fn convert_err(value: NetQueryError, path: &str) -> NetError {
                 trace: path.to_string(),
             })
         }
+        NetQueryError::DecodeResponseError { target, source } => {
+            NetError::DecodeResponseError(TraceErrorFormat {
+                info: format!("target: {}, info: {}", target, source),
+                trace: path.to_string(),
+            })
+        }
+        #[cfg(feature = "tls")]
+        NetQueryError::TlsError { target, source } => NetError::TlsError(TraceErrorFormat {
+            info: format!("target: {}, info: {}", target, source),
+            trace: path.to_string(),
+        }),
+        #[cfg(feature = "https")]
+        NetQueryError::HttpsError { target, source } => NetError::HttpsError(TraceErrorFormat {
+            info: format!("target: {}, info: {}", target, source),
+            trace: path.to_string(),
+        }),
+        #[cfg(feature = "quic")]
+        NetQueryError::QuicError { target, source } => NetError::QuicError(TraceErrorFormat {
+            info: format!("target: {}, info: {}", target, source),
+            trace: path.to_string(),
+        }),
+        #[cfg(feature = "dnscrypt")]
+        NetQueryError::DnsCryptError { target, source } => {
+            NetError::DnsCryptError(TraceErrorFormat {
+                info: format!("target: {}, info: {}", target, source),
+                trace: path.to_string(),
+            })
+        }
     }
 }
 #[cfg(feature = "result_error")]
 impl error_trait::B for ResolverQueryError {}
 
 impl Resolver {
-    pub fn new(mut server: Vec<String>) -> Result<Resolver, std::net::AddrParseError> {
-        let vec = server
-            .iter_mut()
-            .try_fold(SmallVec::new(), |mut vec, str| {
-                vec.push(ServerType::from_string(str)?);
-                Ok(vec)
-            })?;
-        Ok(Resolver { server: vec })
+    pub fn new(server: Vec<String>) -> Result<Resolver, ServerParseError> {
+        let vec = server.iter().try_fold(SmallVec::new(), |mut vec, str| {
+            vec.push(ServerType::from_string(str)?);
+            Ok(vec)
+        })?;
+        Ok(Resolver {
+            server: vec,
+            forward: ForwardRules::new(),
+            preference: AddressPreference::None,
+            lookup_strategy: LookupIpStrategy::Ipv4AndIpv6,
+            bind: LocalBind::new(),
+            bootstrap: RefCell::new(HashMap::new()),
+            tcp_pool: TcpPool::new(),
+            udp_pool: UdpPool::new(),
+            #[cfg(feature = "tls")]
+            tls: TlsTransport::new(),
+            #[cfg(feature = "https")]
+            https: HttpsTransport::new(),
+            #[cfg(feature = "quic")]
+            quic: QuicTransport::new(),
+            #[cfg(feature = "dnscrypt")]
+            dnscrypt: DnsCryptTransport::new(),
+        })
+    }
+
+    // 替换TCP连接池的空闲超时等设置，本地地址以with_local_bind为准
+    pub fn with_tcp_pool(mut self, tcp_pool: TcpPool) -> Resolver {
+        self.tcp_pool = tcp_pool.with_local_bind(self.bind.clone());
+        self
+    }
+
+    // 替换UDP查询使用的socket数与端口更换频率，本地地址以with_local_bind为准
+    pub fn with_udp_pool(mut self, udp_pool: UdpPool) -> Resolver {
+        self.udp_pool = udp_pool.with_local_bind(self.bind.clone());
+        self
+    }
+
+    // 所有传输方式查询时使用的本地地址与网卡
+    pub fn with_local_bind(mut self, bind: LocalBind) -> Resolver {
+        self.udp_pool = std::mem::take(&mut self.udp_pool).with_local_bind(bind.clone());
+        self.tcp_pool = std::mem::take(&mut self.tcp_pool).with_local_bind(bind.clone());
+        #[cfg(feature = "tls")]
+        {
+            self.tls = std::mem::take(&mut self.tls).with_local_bind(bind.clone());
+        }
+        #[cfg(feature = "https")]
+        {
+            self.https = std::mem::take(&mut self.https).with_local_bind(bind.clone());
+        }
+        #[cfg(feature = "quic")]
+        {
+            self.quic = std::mem::take(&mut self.quic).with_local_bind(bind.clone());
+        }
+        #[cfg(feature = "dnscrypt")]
+        {
+            self.dnscrypt = std::mem::take(&mut self.dnscrypt).with_local_bind(bind.clone());
+        }
+        self.bind = bind;
+        self
+    }
+
+    pub fn with_preference(mut self, preference: AddressPreference) -> Resolver {
+        self.preference = preference;
+        self
+    }
+
+    // 按域名后缀选择上游，没有命中规则的域名使用new时给出的服务器
+    pub fn with_forward_rules(mut self, forward: ForwardRules) -> Resolver {
+        self.forward = forward;
+        self
+    }
+
+    // 先按转发规则选出上游，再按地址族偏好排序，需要先解析域名的服务器排在最后
+    fn ordered_servers(&self, domain: &RawDomain) -> SmallVec<[&ServerType; 5]> {
+        let servers = self.forward.route(domain).unwrap_or(&self.server);
+        let mut servers: SmallVec<[&ServerType; 5]> = servers.iter().collect();
+        let rank = |server: &&ServerType| match server.addr().socket_addr() {
+            Some(addr) => self.preference.rank(&addr.ip()),
+            None => 2,
+        };
+        servers.sort_by_key(rank);
+        servers
+    }
+
+    // 替换DoT使用的根证书、SPKI pin等设置，DoH与DoQ也沿用这些设置，本地地址以with_local_bind为准
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsTransport) -> Resolver {
+        #[cfg(feature = "https")]
+        {
+            self.https = self.https.with_tls(&tls);
+        }
+        #[cfg(feature = "quic")]
+        {
+            self.quic = self.quic.with_tls(&tls);
+        }
+        self.tls = tls.with_local_bind(self.bind.clone());
+        self
+    }
+
+    // 替换DoH的请求方法等设置，证书设置以with_tls为准，本地地址以with_local_bind为准
+    #[cfg(feature = "https")]
+    pub fn with_https(mut self, https: HttpsTransport) -> Resolver {
+        self.https = https.with_tls(&self.tls).with_local_bind(self.bind.clone());
+        self
+    }
+
+    // 替换DNSCrypt的客户端密钥与传输方式，本地地址以with_local_bind为准
+    #[cfg(feature = "dnscrypt")]
+    pub fn with_dnscrypt(mut self, dnscrypt: DnsCryptTransport) -> Resolver {
+        self.dnscrypt = dnscrypt.with_local_bind(self.bind.clone());
+        self
     }
 
     fn query(&self, domain: String, qtype: u16) -> ResolverQueryResult {
+        self.query_with(domain, qtype, false)
+    }
+
+    // 域名上游的地址由系统解析器解析，按地址族偏好选择后缓存BOOTSTRAP_TTL。
+    // 系统解析器不能指向本解析器自身，否则需要用bootstrap参数给出地址
+    fn server_socket_addr(&self, addr: &ServerAddr) -> Option<SocketAddr> {
+        if let Some(socket_addr) = addr.socket_addr() {
+            return Some(socket_addr);
+        }
+        let ServerHost::Name(name) = &addr.host else {
+            return None;
+        };
+        let now = Instant::now();
+        if let Some((ip, expires)) = self.bootstrap.borrow().get(name)
+            && now < *expires
+        {
+            return Some(SocketAddr::new(*ip, addr.port));
+        }
+        let ip = (name.as_str(), addr.port)
+            .to_socket_addrs()
+            .ok()?
+            .map(|socket_addr| socket_addr.ip())
+            .min_by_key(|ip| self.preference.rank(ip))?;
+        #[cfg(feature = "logger")]
+        debug!("上游 {} 解析为 {}", name, ip);
+        self.bootstrap
+            .borrow_mut()
+            .insert(name.clone(), (ip, now + BOOTSTRAP_TTL));
+        Some(SocketAddr::new(ip, addr.port))
+    }
+
+    // 带DO位查询并在本地完成DNSSEC校验
+    pub fn query_validated(
+        &self,
+        domain: String,
+        qtype: u16,
+        validator: &Validator,
+    ) -> (ResolverQueryResult, Validation) {
+        let result = self.query_with(domain, qtype, true);
+        let validation = match result.get_result() {
+            Some(response) => validator.validate(response, |name, qtype| {
+                self.query_with(name.to_string()?, qtype, true)
+                    .into_result()
+            }),
+            None => Validation {
+                status: ValidationStatus::Indeterminate,
+                answers: Vec::new(),
+            },
+        };
+        (result, validation)
+    }
+
+    fn query_with(&self, domain: String, qtype: u16, dnssec_ok: bool) -> ResolverQueryResult {
         #[cfg(feature = "result_error")]
         let mut error_vec = Vec::new();
         if let Some(domain) = RawDomain::from_str(domain.as_str()) {
             let domain = std::rc::Rc::new(domain);
-            let mut buf = [0_u8; 1500];
-            for server in &self.server {
+            for server in self.ordered_servers(&domain) {
+                // 未启用对应feature的类型不支持，没有bootstrap参数的域名先通过系统解析器取得地址
+                let socket_addr = match server {
+                    ServerType::Udp(addr) | ServerType::Tcp(addr) => self.server_socket_addr(addr),
+                    #[cfg(feature = "tls")]
+                    ServerType::Tls(addr) => self.server_socket_addr(addr),
+                    #[cfg(feature = "https")]
+                    ServerType::Https(addr) => self.server_socket_addr(addr),
+                    #[cfg(feature = "quic")]
+                    ServerType::Quic(addr) => self.server_socket_addr(addr),
+                    #[cfg(feature = "dnscrypt")]
+                    ServerType::DnsCrypt(addr, _) => self.server_socket_addr(addr),
+                    // 启用全部feature时所有类型都已覆盖
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                };
+                let Some(addr) = socket_addr else {
+                    #[cfg(feature = "logger")]
+                    debug!("不支持的上游服务器 {}", server);
+                    #[cfg(feature = "result_error")]
+                    error_vec.push(NetError::UnsupportedServer(TraceErrorFormat {
+                        info: server.to_string(),
+                        trace: "Resolver::query".to_string(),
+                    }));
+                    continue;
+                };
+                let timeout = server.addr().timeout;
                 return match server {
-                    ServerType::Tcp(addr) => {
-                        //后面可以考虑复用连接
-                        if let Ok(stream) = std::net::TcpStream::connect(addr) {
-                            let request = Request::new(domain.clone(), qtype);
-                            #[cfg(feature = "result_error")]
-                            match NetQuery::query_tcp(stream, request, &mut buf).into_index() {
-                                Ok(response) => response.into(),
-                                Err(e) => {
-                                    error_vec.push(convert_err(e,"Resolver::query => ServerType::Tcp => NetQuery::query_tcp ->"));
-                                    continue;
-                                }
+                    #[cfg(feature = "tls")]
+                    ServerType::Tls(server_addr) => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        // 没有域名时用IP地址校验证书
+                        let name = server_addr
+                            .server_name()
+                            .map_or_else(|| addr.ip().to_string(), str::to_string);
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_tls(&self.tls, addr, &name, timeout, request)
+                            .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::Tls => NetQuery::query_tls ->",
+                                ));
+                                continue;
                             }
-                            #[cfg(not(feature = "result_error"))]
-                            ResolverQueryResult::from(NetQuery::query_tcp(
-                                stream, request, &mut buf,
-                            ))
-                        } else {
-                            #[cfg(feature = "logger")]
-                            debug!("连接到对应的tcp server失败");
-                            #[cfg(feature = "result_error")]
-                            error_vec.push(NetError::ConnectTcpAddrError(TraceErrorFormat {
-                                info: addr.to_string(),
-                                trace: "Resolver::query => ServerType::Tcp".to_string(),
-                            }));
-                            continue; //连接到server失败, 则尝试备用server
                         }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_tls(
+                            &self.tls, addr, &name, timeout, request,
+                        ))
                     }
-                    ServerType::Udp(addr) => {
-                        if let Ok(socket) = std::net::UdpSocket::bind("0.0.0.0:0") {
-                            if let Ok(addr) = socket.connect(addr) {
-                                let request = Request::new(domain.clone(), qtype);
-                                #[cfg(feature = "result_error")]
-                                match NetQuery::query_udp(socket, request, &mut buf).into_index() {
-                                    Ok(response) => response.into(),
-                                    Err(e) => {
-                                        error_vec.push(convert_err(e,"Resolver::query => ServerType::Udp => NetQuery::query_udp ->"));
-                                        continue;
-                                    }
-                                }
-                                #[cfg(not(feature = "result_error"))]
-                                ResolverQueryResult::from(NetQuery::query_udp(
-                                    socket, request, &mut buf,
-                                ))
-                            } else {
-                                #[cfg(feature = "logger")]
-                                debug!("连接到对应的udp server失败");
-                                #[cfg(feature = "result_error")]
-                                error_vec.push(NetError::ConnectUdpAddrError(TraceErrorFormat {
-                                    info: addr.to_string(),
-                                    trace: "Resolver::query => ServerType::Udp".to_string(),
-                                }));
+                    #[cfg(feature = "dnscrypt")]
+                    ServerType::DnsCrypt(_, provider) => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_dnscrypt(
+                            &self.dnscrypt,
+                            addr,
+                            provider,
+                            timeout,
+                            request,
+                        )
+                        .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::DnsCrypt => NetQuery::query_dnscrypt ->",
+                                ));
+                                continue;
+                            }
+                        }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_dnscrypt(
+                            &self.dnscrypt,
+                            addr,
+                            provider,
+                            timeout,
+                            request,
+                        ))
+                    }
+                    #[cfg(feature = "quic")]
+                    ServerType::Quic(server_addr) => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        let name = server_addr
+                            .server_name()
+                            .map_or_else(|| addr.ip().to_string(), str::to_string);
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_quic(&self.quic, addr, &name, timeout, request)
+                            .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::Quic => NetQuery::query_quic ->",
+                                ));
+                                continue;
+                            }
+                        }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_quic(
+                            &self.quic, addr, &name, timeout, request,
+                        ))
+                    }
+                    #[cfg(feature = "https")]
+                    ServerType::Https(server_addr) => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        let name = server_addr
+                            .server_name()
+                            .map_or_else(|| addr.ip().to_string(), str::to_string);
+                        let path = server_addr.path.as_str();
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_https(
+                            &self.https,
+                            addr,
+                            &name,
+                            path,
+                            timeout,
+                            request,
+                        )
+                        .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::Https => NetQuery::query_https ->",
+                                ));
+                                continue;
+                            }
+                        }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_https(
+                            &self.https,
+                            addr,
+                            &name,
+                            path,
+                            timeout,
+                            request,
+                        ))
+                    }
+                    ServerType::Tcp(_) => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_tcp_pool(&self.tcp_pool, addr, timeout, request)
+                            .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::Tcp => NetQuery::query_tcp_pool ->",
+                                ));
+                                continue;
+                            }
+                        }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_tcp_pool(
+                            &self.tcp_pool,
+                            addr,
+                            timeout,
+                            request,
+                        ))
+                    }
+                    _ => {
+                        let request = new_request(&domain, qtype, dnssec_ok);
+                        #[cfg(feature = "result_error")]
+                        match NetQuery::query_udp_pool(
+                            &self.udp_pool,
+                            &self.tcp_pool,
+                            addr,
+                            timeout,
+                            request,
+                        )
+                        .into_index()
+                        {
+                            Ok(response) => response.into(),
+                            Err(e) => {
+                                error_vec.push(convert_err(
+                                    e,
+                                    "Resolver::query => ServerType::Udp => NetQuery::query_udp_pool ->",
+                                ));
                                 continue;
                             }
-                        } else {
-                            #[cfg(feature = "logger")]
-                            debug!("监听udp端口失败");
-                            #[cfg(feature = "result_error")]
-                            error_vec.push(NetError::BindUdpAddrError(TraceErrorFormat {
-                                info: "".to_string(),
-                                trace: "Resolver::query => ServerType::Udp".to_string(),
-                            }));
-                            continue; //监听udp失败，尝试备用
                         }
+                        #[cfg(not(feature = "result_error"))]
+                        ResolverQueryResult::from(NetQuery::query_udp_pool(
+                            &self.udp_pool,
+                            &self.tcp_pool,
+                            addr,
+                            timeout,
+                            request,
+                        ))
                     }
                 };
             }
@@ -247,7 +639,13 @@ macro_rules! query_type_map {
     (CNAME) => { std::string::String };
     (SOA) => { $crate::dns::types::base::record::SOA };
     (TXT) => { Vec<String> };
-    (AAAA) => { std::net::Ipv6Addr }
+    (AAAA) => { std::net::Ipv6Addr };
+    (DS) => { $crate::dns::types::base::record::DS };
+    (RRSIG) => { $crate::dns::types::base::record::RRSIG };
+    (NSEC) => { $crate::dns::types::base::record::NSEC };
+    (DNSKEY) => { $crate::dns::types::base::record::DNSKEY };
+    (NSEC3) => { $crate::dns::types::base::record::NSEC3 };
+    (NSEC3PARAM) => { $crate::dns::types::base::record::NSEC3PARAM }
 }
 
 // todo
@@ -256,7 +654,7 @@ macro_rules! query_result_map {
     (single,$query_type:ty) => {Option<$query_type>};
     (all,$query_type:ty) => {Vec<$query_type>};
     (iter,$query_type:ty) => {
-        Option<FilterMap<Iter<crate::dns::types::parts::Record>,
+        Option<FilterMap<Iter<'_, crate::dns::types::parts::Record>,
             fn(&crate::dns::types::parts::Record) -> Option<$query_type>>>
     };
     (into_iter,$query_type:ty) => {
@@ -339,6 +737,12 @@ define_get_record!(cname, CNAME);
 define_get_record!(soa, SOA);
 define_get_record!(txt, TXT);
 define_get_record!(aaaa, AAAA);
+define_get_record!(ds, DS);
+define_get_record!(rrsig, RRSIG);
+define_get_record!(nsec, NSEC);
+define_get_record!(dnskey, DNSKEY);
+define_get_record!(nsec3, NSEC3);
+define_get_record!(nsec3param, NSEC3PARAM);
 // todo
 
 #[macro_export]
@@ -499,7 +903,7 @@ pub type QueryResult<T> = ResultAndError<T, QueryError>;
 
 #[cfg(feature = "result_error")]
 pub enum QueryError {
-    ServerParseError(std::net::AddrParseError),
+    ServerParseError(ServerParseError),
     TargetParseError(TraceErrorFormat),
     ResolverQueryError { err: Vec<NetError>, trace: String },
 }
@@ -507,8 +911,8 @@ pub enum QueryError {
 #[cfg(feature = "result_error")]
 impl error_trait::B for QueryError {}
 #[cfg(feature = "result_error")]
-impl From<std::net::AddrParseError> for QueryError {
-    fn from(err: std::net::AddrParseError) -> Self {
+impl From<ServerParseError> for QueryError {
+    fn from(err: ServerParseError) -> Self {
         QueryError::ServerParseError(err)
     }
 }
@@ -559,7 +963,130 @@ mod tests {
     use crate::dns::error::init_logger;
     #[cfg(feature = "logger")]
     use crate::dns::error::set_println_enabled;
-    use crate::dns::resolver::Resolver;
+    use crate::dns::net::tcp_pool::tests::answer;
+    use crate::dns::net::{LocalBind, UdpPool};
+    use crate::dns::resolver::{AddressPreference, ForwardRules, Resolver};
+    use std::net::{SocketAddr, UdpSocket};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 返回本地UDP服务器的地址与收到的查询数
+    fn serve(bind: &str) -> (SocketAddr, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind(bind).unwrap();
+        let addr = socket.local_addr().unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0_u8; 1500];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = socket.send_to(&answer(&buf[..len], None), peer);
+            }
+        });
+        (addr, queries)
+    }
+
+    #[test]
+    fn test_dual_stack() {
+        let (v4, v4_queries) = serve("127.0.0.1:0");
+        let (v6, v6_queries) = serve("[::1]:0");
+        let servers = vec![v4.to_string(), v6.to_string()];
+
+        let resolver = Resolver::new(servers.clone()).unwrap();
+        assert!(
+            resolver
+                .query_a("example.com".to_string())
+                .get_a_record()
+                .is_some()
+        );
+        assert_eq!(v4_queries.load(Ordering::SeqCst), 1);
+
+        let resolver = Resolver::new(servers.clone())
+            .unwrap()
+            .with_preference(AddressPreference::Ipv6);
+        assert!(
+            resolver
+                .query_a("example.com".to_string())
+                .get_a_record()
+                .is_some()
+        );
+        assert_eq!(v6_queries.load(Ordering::SeqCst), 1);
+
+        // 只有IPv6上游时同样可用，本地地址按地址族选择
+        let resolver = Resolver::new(vec![v6.to_string()])
+            .unwrap()
+            .with_local_bind(
+                LocalBind::new()
+                    .with_addr("127.0.0.1".parse().unwrap())
+                    .with_addr("::1".parse().unwrap()),
+            )
+            .with_udp_pool(UdpPool::new().with_sockets(1));
+        assert!(
+            resolver
+                .query_a("example.com".to_string())
+                .get_a_record()
+                .is_some()
+        );
+        assert_eq!(v6_queries.load(Ordering::SeqCst), 2);
+        assert_eq!(v4_queries.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_hostname_upstream() {
+        let (addr, queries) = serve("127.0.0.1:0");
+        let resolver = Resolver::new(vec![format!("udp://localhost:{}", addr.port())])
+            .unwrap()
+            .with_preference(AddressPreference::Ipv4);
+        for _ in 0..2 {
+            assert!(
+                resolver
+                    .query_a("example.com".to_string())
+                    .get_a_record()
+                    .is_some()
+            );
+        }
+        assert_eq!(
+            resolver.lookup_ip("example.com".to_string()),
+            vec!["192.0.2.1".parse::<std::net::IpAddr>().unwrap()]
+        );
+        assert_eq!(queries.load(Ordering::SeqCst), 4);
+        let bootstrap = resolver.bootstrap.borrow();
+        assert_eq!(bootstrap.len(), 1);
+        assert_eq!(bootstrap["localhost"].0, addr.ip());
+    }
+
+    #[test]
+    fn test_forward_rules() {
+        let (default, default_queries) = serve("127.0.0.1:0");
+        let (corp, corp_queries) = serve("127.0.0.1:0");
+        let rules = ForwardRules::parse(&format!(
+            "server=/corp.internal/{}#{}\nlocal=/blocked.lan/\n",
+            corp.ip(),
+            corp.port()
+        ))
+        .unwrap();
+        let resolver = Resolver::new(vec![default.to_string()])
+            .unwrap()
+            .with_forward_rules(rules);
+
+        let query = |name: &str| resolver.query_a(name.to_string()).get_a_record().is_some();
+        assert!(query("git.Corp.Internal"));
+        assert_eq!(corp_queries.load(Ordering::SeqCst), 1);
+        assert_eq!(default_queries.load(Ordering::SeqCst), 0);
+        assert!(query("example.com"));
+        assert_eq!(default_queries.load(Ordering::SeqCst), 1);
+        // 没有上游的后缀不会发出查询
+        assert!(!query("nas.blocked.lan"));
+        assert_eq!(corp_queries.load(Ordering::SeqCst), 1);
+        assert_eq!(default_queries.load(Ordering::SeqCst), 1);
+        // lookup_ip同样按规则选择上游
+        assert_eq!(
+            resolver.lookup_ip("www.corp.internal".to_string()),
+            vec!["192.0.2.1".parse::<std::net::IpAddr>().unwrap()]
+        );
+        assert_eq!(corp_queries.load(Ordering::SeqCst), 3);
+        assert_eq!(default_queries.load(Ordering::SeqCst), 1);
+    }
 
     #[test]
     fn test_query_a() {
@@ -688,4 +1215,4 @@ mod tests {
         };
         println!("{:?}", result.unwrap().next());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,1390 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::record::{
+    DNSKEY, DS, NSEC, NSEC3, RRSIG, TypeBitmap, base32hex_encode,
+};
+use crate::dns::types::base::{
+    DnsRcodeNum, DnsTypeNum, RawDomain, base_name, canonical_cmp, common_labels, is_subdomain_of,
+    is_wildcard, label_count,
+};
+use crate::dns::types::parts::{Record, RecordDataType, Response};
+use crate::dns::utils::SliceOperator;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "logger")]
+use log::debug;
+use ring::digest;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[cfg(test)]
+mod fixtures;
+
+// 超过该迭代次数的NSEC3按不安全处理(RFC 9276)
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+// 由好到坏排列，合并多个RRset的结果时取最大值
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ValidationStatus {
+    Secure,
+    Insecure,
+    Indeterminate,
+    Bogus,
+}
+
+// answer部分中一个RRset的校验结果
+#[derive(Debug)]
+pub struct RRsetValidation {
+    pub name: RawDomain,
+    pub rtype: u16,
+    pub status: ValidationStatus,
+}
+
+// status为整体结果，取各RRset与否定证明中最差的状态；
+// answers按answer部分中RRset的出现顺序排列，否定应答时为空
+#[derive(Debug)]
+pub struct Validation {
+    pub status: ValidationStatus,
+    pub answers: Vec<RRsetValidation>,
+}
+
+impl Validation {
+    #[inline]
+    fn whole(status: ValidationStatus) -> Validation {
+        Validation {
+            status,
+            answers: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrustAnchor {
+    owner: Vec<u8>, // 小写的label序列
+    ds: Vec<DS>,
+    keys: Vec<DNSKEY>,
+}
+
+impl TrustAnchor {
+    pub fn new<T: AsRef<str>>(owner: T) -> Option<TrustAnchor> {
+        Some(TrustAnchor {
+            owner: RawDomain::from_str(owner)?.as_ref().to_ascii_lowercase(),
+            ds: Vec::new(),
+            keys: Vec::new(),
+        })
+    }
+
+    // IANA发布的根区KSK(20326与38696)
+    pub fn root() -> TrustAnchor {
+        TrustAnchor::new(".")
+            .unwrap()
+            .with_ds(DS::new(
+                20326,
+                8,
+                2,
+                hex_decode("E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D"),
+            ))
+            .with_ds(DS::new(
+                38696,
+                8,
+                2,
+                hex_decode("683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16"),
+            ))
+    }
+
+    #[inline]
+    pub fn with_ds(mut self, ds: DS) -> TrustAnchor {
+        self.ds.push(ds);
+        self
+    }
+
+    #[inline]
+    pub fn with_dnskey(mut self, key: DNSKEY) -> TrustAnchor {
+        self.keys.push(key);
+        self
+    }
+}
+
+pub struct Validator {
+    anchor: TrustAnchor,
+    // 固定的校验时间(unix秒)，None时使用当前时间
+    time: Option<u32>,
+}
+
+impl Validator {
+    pub fn new(anchor: TrustAnchor) -> Validator {
+        Validator { anchor, time: None }
+    }
+
+    #[inline]
+    pub fn with_time(mut self, time: DateTime<Utc>) -> Validator {
+        self.time = Some(time.timestamp() as u32);
+        self
+    }
+
+    // fetch用于获取校验链上需要的DNSKEY/DS记录，调用方应在请求中设置DO位
+    pub fn validate<F>(&self, response: &Response, fetch: F) -> Validation
+    where
+        F: FnMut(&RawDomain, u16) -> Option<Response>,
+    {
+        let Some(question) = response.question.first() else {
+            return Validation::whole(ValidationStatus::Indeterminate);
+        };
+        let qname = question.qname.as_ref().as_ref().as_slice();
+        let mut session = Session {
+            anchor: &self.anchor,
+            now: self.time.unwrap_or_else(|| Utc::now().timestamp() as u32),
+            fetch,
+            cuts: HashMap::new(),
+        };
+        let answers = collect_rrsets(response.answers());
+        let authority = collect_rrsets(response.authorities());
+        if answers.is_empty() {
+            return Validation::whole(session.denial(
+                qname,
+                question.qtype,
+                response.header.rcode == DnsRcodeNum::NXDOMAIN,
+                &authority,
+            ));
+        }
+        let answers: Vec<RRsetValidation> = answers
+            .iter()
+            .map(|set| RRsetValidation {
+                name: RawDomain::from_raw(set.name.to_vec()),
+                rtype: set.rtype,
+                status: session.positive(set, &authority),
+            })
+            .collect();
+        Validation {
+            status: answers.iter().map(|a| a.status).max().unwrap(),
+            answers,
+        }
+    }
+}
+
+// 同名同类型的一组记录及覆盖它们的签名
+struct RRset<'r> {
+    name: &'r [u8],
+    rtype: u16,
+    class: u16,
+    records: Vec<&'r Record>,
+    sigs: Vec<&'r RRSIG>,
+}
+
+fn collect_rrsets(records: &[Record]) -> Vec<RRset<'_>> {
+    let mut sets: Vec<RRset> = Vec::new();
+    let mut sigs = Vec::new();
+    for rec in records {
+        let name = rec.name.as_ref().as_slice();
+        match &rec.data {
+            RecordDataType::OPT(_) => {}
+            RecordDataType::RRSIG(sig) => sigs.push((name, sig)),
+            _ => {
+                match sets
+                    .iter_mut()
+                    .find(|s| s.rtype == rec.rtype && s.name.eq_ignore_ascii_case(name))
+                {
+                    Some(set) => set.records.push(rec),
+                    None => sets.push(RRset {
+                        name,
+                        rtype: rec.rtype,
+                        class: rec.class,
+                        records: vec![rec],
+                        sigs: Vec::new(),
+                    }),
+                }
+            }
+        }
+    }
+    for (name, sig) in sigs {
+        if let Some(set) = sets
+            .iter_mut()
+            .find(|s| s.rtype == sig.type_covered() && s.name.eq_ignore_ascii_case(name))
+        {
+            set.sigs.push(sig);
+        }
+    }
+    sets
+}
+
+// 信任链上某一级名称的状态
+#[derive(Clone)]
+enum Cut {
+    Secure(Rc<Vec<DNSKEY>>),
+    // 该名称不是区域切分点，仍属于父区域
+    Inside,
+    Insecure,
+    Bogus,
+    Indeterminate,
+}
+
+impl Cut {
+    #[inline]
+    fn status(&self) -> ValidationStatus {
+        match self {
+            Cut::Secure(_) => ValidationStatus::Secure,
+            Cut::Insecure => ValidationStatus::Insecure,
+            Cut::Indeterminate => ValidationStatus::Indeterminate,
+            Cut::Inside | Cut::Bogus => ValidationStatus::Bogus,
+        }
+    }
+}
+
+struct Session<'v, F> {
+    anchor: &'v TrustAnchor,
+    now: u32,
+    fetch: F,
+    cuts: HashMap<Vec<u8>, Cut>,
+}
+
+impl<F> Session<'_, F>
+where
+    F: FnMut(&RawDomain, u16) -> Option<Response>,
+{
+    fn positive(&mut self, set: &RRset, authority: &[RRset]) -> ValidationStatus {
+        let sig = match self.verified_sig(set) {
+            Ok(sig) => sig,
+            Err(status) => return status,
+        };
+        // 通配符展开的答案需要证明原名称不存在，证明与答案由同一区域签名
+        match wildcard_labels(set, sig) {
+            Some((labels, signer)) => self
+                .authenticated_proof(authority, &signer, |proof| proof.wildcard(set.name, labels)),
+            None => ValidationStatus::Secure,
+        }
+    }
+
+    fn denial(
+        &mut self,
+        qname: &[u8],
+        qtype: u16,
+        nxdomain: bool,
+        authority: &[RRset],
+    ) -> ValidationStatus {
+        if authority.iter().all(|s| s.sigs.is_empty()) {
+            return match self.walk(qname).1 {
+                Cut::Secure(_) => ValidationStatus::Bogus,
+                cut => cut.status(),
+            };
+        }
+        // 证明必须由最近祖先所在的区域签名，父区域的记录不能用于子区域之内的名称
+        let labels = Proof::from_rrsets(authority).enclosing_labels(qname, qtype, nxdomain);
+        let zone = match self.walk(base_name(qname, labels)) {
+            (zone, Cut::Secure(_)) => zone,
+            (_, cut) => return cut.status(),
+        };
+        self.authenticated_proof(authority, &zone, |proof| {
+            if nxdomain {
+                proof.nxdomain(qname)
+            } else {
+                proof.nodata(qname, qtype)
+            }
+        })
+    }
+
+    // authority中的记录全部校验通过后再检查否定证明，只使用zone签名的NSEC/NSEC3
+    fn authenticated_proof<P>(
+        &mut self,
+        authority: &[RRset],
+        zone: &[u8],
+        check: P,
+    ) -> ValidationStatus
+    where
+        P: FnOnce(&Proof) -> ValidationStatus,
+    {
+        let mut status = ValidationStatus::Secure;
+        for set in authority {
+            status = status.max(self.rrset_status(set));
+        }
+        if status != ValidationStatus::Secure {
+            return status;
+        }
+        let Cut::Secure(keys) = self.zone_trust(zone) else {
+            return ValidationStatus::Bogus;
+        };
+        let sets = authority.iter().filter(|set| {
+            matches!(set.rtype, DnsTypeNum::NSEC | DnsTypeNum::NSEC3)
+                && self.verify_rrset(set, zone, &keys)
+        });
+        check(&Proof::from_rrsets(sets))
+    }
+
+    fn rrset_status(&mut self, set: &RRset) -> ValidationStatus {
+        match self.verified_sig(set) {
+            Ok(_) => ValidationStatus::Secure,
+            Err(status) => status,
+        }
+    }
+
+    // 依次尝试每个RRSIG，任一签名通过即为安全并返回该签名；
+    // 全部失败时返回其中最好的状态，只有签名者可信但都校验失败时才是Bogus
+    fn verified_sig<'r>(&mut self, set: &RRset<'r>) -> Result<&'r RRSIG, ValidationStatus> {
+        if set.sigs.is_empty() {
+            // 没有签名，只有在不安全的委派下才可以接受
+            return Err(match self.walk(set.name).1 {
+                Cut::Secure(_) => ValidationStatus::Bogus,
+                cut => cut.status(),
+            });
+        }
+        let mut status = ValidationStatus::Bogus;
+        for &sig in &set.sigs {
+            let signer = sig.signer_name();
+            let signer = signer.as_ref().as_ref().as_slice();
+            if !is_subdomain_of(set.name, signer) {
+                continue;
+            }
+            match self.zone_trust(signer) {
+                Cut::Secure(keys) => {
+                    if self.verify_sig(set, sig, signer, &keys) {
+                        return Ok(sig);
+                    }
+                }
+                cut => status = status.min(cut.status()),
+            }
+        }
+        #[cfg(feature = "logger")]
+        debug!("RRset签名校验失败, type {}", set.rtype);
+        Err(status)
+    }
+
+    // 获取签名者区域的可信密钥，签名者必须是信任链上真实存在的区域
+    fn zone_trust(&mut self, zone: &[u8]) -> Cut {
+        let (found, cut) = self.walk(zone);
+        match cut {
+            Cut::Secure(_) if !found.eq_ignore_ascii_case(zone) => Cut::Bogus,
+            cut => cut,
+        }
+    }
+
+    // 从信任锚开始逐级向下建立信任链，返回包含name的最深一级区域及其状态
+    fn walk(&mut self, name: &[u8]) -> (Vec<u8>, Cut) {
+        let anchor = self.anchor.owner.clone();
+        if !is_subdomain_of(name, &anchor) {
+            return (anchor, Cut::Indeterminate);
+        }
+        let mut cut = self.anchor_keys();
+        let mut zone = anchor.clone();
+        for n in label_count(&anchor) + 1..=label_count(name) {
+            let keys = match &cut {
+                Cut::Secure(keys) => keys.clone(),
+                _ => break,
+            };
+            let child = base_name(name, n).to_ascii_lowercase();
+            let next = match self.cuts.get(&child) {
+                Some(next) => next.clone(),
+                None => {
+                    let next = self.delegation(&zone, &keys, &child);
+                    self.cuts.insert(child.clone(), next.clone());
+                    next
+                }
+            };
+            if !matches!(next, Cut::Inside) {
+                zone = child;
+                cut = next;
+            }
+        }
+        (zone, cut)
+    }
+
+    fn anchor_keys(&mut self) -> Cut {
+        let anchor = self.anchor;
+        if let Some(cut) = self.cuts.get(&anchor.owner) {
+            return cut.clone();
+        }
+        let ds: Vec<&DS> = anchor.ds.iter().collect();
+        let cut = self.zone_keys(&anchor.owner, &ds, &anchor.keys);
+        self.cuts.insert(anchor.owner.clone(), cut.clone());
+        cut
+    }
+
+    // 向父区域查询child的DS，确认child是否为安全的区域切分点
+    fn delegation(&mut self, zone: &[u8], keys: &[DNSKEY], child: &[u8]) -> Cut {
+        let Some(response) = (self.fetch)(&RawDomain::from_raw(child.to_vec()), DnsTypeNum::DS)
+        else {
+            return Cut::Indeterminate;
+        };
+        let answers = collect_rrsets(response.answers());
+        if let Some(set) = answers
+            .iter()
+            .find(|s| s.rtype == DnsTypeNum::DS && s.name.eq_ignore_ascii_case(child))
+        {
+            if !self.verify_rrset(set, zone, keys) {
+                return Cut::Bogus;
+            }
+            let ds: Vec<&DS> = set
+                .records
+                .iter()
+                .filter_map(|r| match &r.data {
+                    RecordDataType::DS(ds) => Some(ds),
+                    _ => None,
+                })
+                .collect();
+            return self.zone_keys(child, &ds, &[]);
+        }
+        // 没有DS记录，需要父区域签名的NSEC/NSEC3证明
+        let authority = collect_rrsets(response.authorities());
+        for set in authority
+            .iter()
+            .filter(|s| s.rtype == DnsTypeNum::NSEC || s.rtype == DnsTypeNum::NSEC3)
+        {
+            if !self.verify_rrset(set, zone, keys) {
+                return Cut::Bogus;
+            }
+        }
+        Proof::from_rrsets(&authority).delegation(child)
+    }
+
+    // 用DS或锚定的DNSKEY确认zone的DNSKEY集合
+    fn zone_keys(&mut self, zone: &[u8], ds: &[&DS], anchors: &[DNSKEY]) -> Cut {
+        let ds: Vec<&DS> = ds
+            .iter()
+            .copied()
+            .filter(|d| supported_algorithm(d.algorithm()) && supported_digest(d.digest_type()))
+            .collect();
+        if ds.is_empty() && !anchors.iter().any(|k| supported_algorithm(k.algorithm())) {
+            // 只有不支持的算法时按不安全处理(RFC 4035 5.2)
+            return Cut::Insecure;
+        }
+        let Some(response) = (self.fetch)(&RawDomain::from_raw(zone.to_vec()), DnsTypeNum::DNSKEY)
+        else {
+            return Cut::Indeterminate;
+        };
+        let answers = collect_rrsets(response.answers());
+        let Some(set) = answers
+            .iter()
+            .find(|s| s.rtype == DnsTypeNum::DNSKEY && s.name.eq_ignore_ascii_case(zone))
+        else {
+            return Cut::Bogus;
+        };
+        let keys: Vec<DNSKEY> = set
+            .records
+            .iter()
+            .filter_map(|r| match &r.data {
+                RecordDataType::DNSKEY(key) => Some(key.clone()),
+                _ => None,
+            })
+            .collect();
+        for key in &keys {
+            let trusted = anchors.iter().any(|a| same_key(a, key))
+                || ds.iter().any(|d| ds_matches(zone, key, d));
+            if trusted && self.verify_rrset(set, zone, std::slice::from_ref(key)) {
+                return Cut::Secure(Rc::new(keys));
+            }
+        }
+        #[cfg(feature = "logger")]
+        debug!("DNSKEY集合无法由DS或信任锚确认");
+        Cut::Bogus
+    }
+
+    fn verify_rrset(&self, set: &RRset, signer: &[u8], keys: &[DNSKEY]) -> bool {
+        set.sigs
+            .iter()
+            .any(|sig| self.verify_sig(set, sig, signer, keys))
+    }
+
+    fn verify_sig(&self, set: &RRset, sig: &RRSIG, signer: &[u8], keys: &[DNSKEY]) -> bool {
+        let sig_signer = sig.signer_name();
+        if !sig_signer.as_ref().as_ref().eq_ignore_ascii_case(signer)
+            || sig.labels() as usize > wildcard_free_labels(set.name)
+            || !in_window(sig, self.now)
+        {
+            return false;
+        }
+        keys.iter()
+            .filter(|k| {
+                k.is_zone_key()
+                    && k.protocol() == 3
+                    && k.algorithm() == sig.algorithm()
+                    && k.key_tag() == sig.key_tag()
+            })
+            .any(|k| {
+                signed_data(sig, set)
+                    .is_some_and(|data| verify_signature(k, &data, sig.signature()))
+            })
+    }
+}
+
+// 校验否定应答用到的NSEC/NSEC3记录
+struct Proof<'r> {
+    nsec: Vec<(&'r [u8], &'r NSEC)>,
+    nsec3: Vec<Nsec3Entry<'r>>,
+}
+
+struct Nsec3Entry<'r> {
+    // owner第一个label，即base32hex编码的哈希
+    hash: String,
+    zone: &'r [u8],
+    record: &'r NSEC3,
+}
+
+impl Nsec3Entry<'_> {
+    fn hash_of(&self, name: &[u8]) -> Option<String> {
+        if self.record.hash_algorithm() != 1 || !is_subdomain_of(name, self.zone) {
+            return None;
+        }
+        Some(base32hex_encode(&nsec3_hash(
+            name,
+            self.record.salt(),
+            self.record.iterations(),
+        )))
+    }
+
+    fn matches(&self, name: &[u8]) -> bool {
+        self.hash_of(name).is_some_and(|h| h == self.hash)
+    }
+
+    fn covers(&self, name: &[u8]) -> bool {
+        let Some(h) = self.hash_of(name) else {
+            return false;
+        };
+        let next = base32hex_encode(self.record.next_hashed_owner());
+        if self.hash < next {
+            self.hash < h && h < next
+        } else {
+            // 哈希链的最后一条
+            h > self.hash || h < next
+        }
+    }
+}
+
+impl<'r> Proof<'r> {
+    fn from_rrsets<'s, I>(sets: I) -> Proof<'r>
+    where
+        I: IntoIterator<Item = &'s RRset<'r>>,
+        'r: 's,
+    {
+        let mut proof = Proof {
+            nsec: Vec::new(),
+            nsec3: Vec::new(),
+        };
+        for set in sets {
+            for rec in &set.records {
+                match &rec.data {
+                    RecordDataType::NSEC(nsec) => proof.nsec.push((set.name, nsec)),
+                    RecordDataType::NSEC3(nsec3) if !set.name.is_empty() => {
+                        let label = &set.name[1..1 + set.name[0] as usize];
+                        proof.nsec3.push(Nsec3Entry {
+                            hash: String::from_utf8_lossy(label).to_ascii_uppercase(),
+                            zone: &set.name[1 + set.name[0] as usize..],
+                            record: nsec3,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        proof
+    }
+
+    #[inline]
+    fn nsec3_too_expensive(&self) -> bool {
+        self.nsec3
+            .iter()
+            .any(|e| e.record.iterations() > MAX_NSEC3_ITERATIONS)
+    }
+
+    fn nsec_matching(&self, name: &[u8]) -> Option<&'r NSEC> {
+        self.nsec
+            .iter()
+            .find(|(owner, _)| owner.eq_ignore_ascii_case(name))
+            .map(|(_, nsec)| *nsec)
+    }
+
+    fn nsec_covering(&self, name: &[u8]) -> Option<(&'r [u8], &'r NSEC)> {
+        self.nsec.iter().copied().find(|(owner, nsec)| {
+            if is_delegation(nsec.types()) && is_subdomain_of(name, owner) {
+                return false;
+            }
+            let next = nsec.next_domain();
+            let next = next.as_ref().as_ref().as_slice();
+            if canonical_cmp(owner, next) == Ordering::Less {
+                canonical_cmp(owner, name) == Ordering::Less
+                    && canonical_cmp(name, next) == Ordering::Less
+            } else {
+                canonical_cmp(owner, name) == Ordering::Less && is_subdomain_of(name, next)
+            }
+        })
+    }
+
+    // NSEC3最近祖先证明(RFC 5155 8.3)，返回最近祖先的label数与覆盖next closer的记录
+    fn closest_encloser(&self, name: &[u8]) -> Option<(usize, &Nsec3Entry<'r>)> {
+        let total = label_count(name);
+        for n in (0..total).rev() {
+            if self
+                .nsec3
+                .iter()
+                .any(|e| e.matches(base_name(name, n)) && !is_delegation(e.record.types()))
+            {
+                let next_closer = base_name(name, n + 1);
+                return self
+                    .nsec3
+                    .iter()
+                    .find(|e| e.covers(next_closer))
+                    .map(|e| (n, e));
+            }
+        }
+        None
+    }
+
+    // 否定应答中已证明存在的最深名称的label数，证明应由该名称所在的区域签名。
+    // DS由父区域证明，其他类型只有qname是区域顶点时才由qname自身所在的区域证明
+    fn enclosing_labels(&self, qname: &[u8], qtype: u16, nxdomain: bool) -> usize {
+        let total = label_count(qname);
+        let parent = total.saturating_sub(1);
+        if nxdomain {
+            if let Some((owner, nsec)) = self.nsec_covering(qname) {
+                return closest_nsec_encloser(qname, owner, nsec);
+            }
+            return self.closest_encloser(qname).map_or(parent, |(n, _)| n);
+        }
+        let apex = |types: &TypeBitmap| types.contains(DnsTypeNum::SOA);
+        let is_apex = self
+            .nsec_matching(qname)
+            .is_some_and(|nsec| apex(nsec.types()))
+            || self
+                .nsec3
+                .iter()
+                .any(|e| e.matches(qname) && apex(e.record.types()));
+        if qtype != DnsTypeNum::DS && is_apex {
+            total
+        } else {
+            parent
+        }
+    }
+
+    fn nodata(&self, qname: &[u8], qtype: u16) -> ValidationStatus {
+        let no_type =
+            |types: &TypeBitmap| !types.contains(qtype) && !types.contains(DnsTypeNum::CNAME);
+        // 委派点上的记录只能证明没有DS
+        let usable = |types: &TypeBitmap| qtype == DnsTypeNum::DS || !is_delegation(types);
+        if !self.nsec.is_empty() {
+            if let Some(nsec) = self.nsec_matching(qname) {
+                return secure_if(usable(nsec.types()) && no_type(nsec.types()));
+            }
+            if let Some((owner, nsec)) = self.nsec_covering(qname) {
+                // 空的非终端节点
+                let next = nsec.next_domain();
+                if is_subdomain_of(next.as_ref().as_ref(), qname) {
+                    return ValidationStatus::Secure;
+                }
+                let wildcard =
+                    wildcard_of(base_name(qname, closest_nsec_encloser(qname, owner, nsec)));
+                if let Some(nsec) = self.nsec_matching(&wildcard) {
+                    return secure_if(no_type(nsec.types()));
+                }
+            }
+            return ValidationStatus::Bogus;
+        }
+        if self.nsec3_too_expensive() {
+            return ValidationStatus::Insecure;
+        }
+        if let Some(entry) = self.nsec3.iter().find(|e| e.matches(qname)) {
+            let types = entry.record.types();
+            return secure_if(usable(types) && no_type(types));
+        }
+        if let Some((n, cover)) = self.closest_encloser(qname) {
+            if qtype == DnsTypeNum::DS && cover.record.is_opt_out() {
+                return ValidationStatus::Insecure;
+            }
+            let wildcard = wildcard_of(base_name(qname, n));
+            if let Some(entry) = self.nsec3.iter().find(|e| e.matches(&wildcard)) {
+                return secure_if(no_type(entry.record.types()));
+            }
+        }
+        ValidationStatus::Bogus
+    }
+
+    fn nxdomain(&self, qname: &[u8]) -> ValidationStatus {
+        if !self.nsec.is_empty() {
+            let Some((owner, nsec)) = self.nsec_covering(qname) else {
+                return ValidationStatus::Bogus;
+            };
+            let wildcard = wildcard_of(base_name(qname, closest_nsec_encloser(qname, owner, nsec)));
+            return secure_if(self.nsec_covering(&wildcard).is_some());
+        }
+        if self.nsec3_too_expensive() {
+            return ValidationStatus::Insecure;
+        }
+        let Some((n, cover)) = self.closest_encloser(qname) else {
+            return ValidationStatus::Bogus;
+        };
+        let wildcard = wildcard_of(base_name(qname, n));
+        if !self.nsec3.iter().any(|e| e.covers(&wildcard)) {
+            return ValidationStatus::Bogus;
+        }
+        if cover.record.is_opt_out() {
+            ValidationStatus::Insecure
+        } else {
+            ValidationStatus::Secure
+        }
+    }
+
+    // 通配符展开的答案，labels为RRSIG中的label数
+    fn wildcard(&self, owner: &[u8], labels: usize) -> ValidationStatus {
+        if !self.nsec.is_empty() {
+            return secure_if(self.nsec_covering(owner).is_some());
+        }
+        if self.nsec3_too_expensive() {
+            return ValidationStatus::Insecure;
+        }
+        let next_closer = base_name(owner, labels + 1);
+        match self.nsec3.iter().find(|e| e.covers(next_closer)) {
+            Some(e) if e.record.is_opt_out() => ValidationStatus::Insecure,
+            Some(_) => ValidationStatus::Secure,
+            None => ValidationStatus::Bogus,
+        }
+    }
+
+    // 父区域对child不存在DS的证明
+    fn delegation(&self, child: &[u8]) -> Cut {
+        let check = |types: &TypeBitmap| {
+            if types.contains(DnsTypeNum::DS) || types.contains(DnsTypeNum::SOA) {
+                Cut::Bogus
+            } else if types.contains(DnsTypeNum::NS) {
+                Cut::Insecure
+            } else {
+                Cut::Inside
+            }
+        };
+        if !self.nsec.is_empty() {
+            if let Some(nsec) = self.nsec_matching(child) {
+                return check(nsec.types());
+            }
+            if self.nsec_covering(child).is_some() {
+                return Cut::Inside;
+            }
+            return Cut::Bogus;
+        }
+        if self.nsec3_too_expensive() {
+            return Cut::Insecure;
+        }
+        if let Some(entry) = self.nsec3.iter().find(|e| e.matches(child)) {
+            return check(entry.record.types());
+        }
+        match self.closest_encloser(child) {
+            Some((_, cover)) if cover.record.is_opt_out() => Cut::Insecure,
+            Some(_) => Cut::Inside,
+            None => Cut::Bogus,
+        }
+    }
+}
+
+// 父区域在委派点上的NSEC/NSEC3(有NS没有SOA)只能证明该点没有DS，
+// 不能用于该点及其之下名称的否定应答(RFC 4035 5.4, RFC 6840 4.1)
+#[inline]
+fn is_delegation(types: &TypeBitmap) -> bool {
+    types.contains(DnsTypeNum::NS) && !types.contains(DnsTypeNum::SOA)
+}
+
+#[inline]
+fn secure_if(proven: bool) -> ValidationStatus {
+    if proven {
+        ValidationStatus::Secure
+    } else {
+        ValidationStatus::Bogus
+    }
+}
+
+// 由覆盖qname的NSEC推出最近祖先的label数
+fn closest_nsec_encloser(qname: &[u8], owner: &[u8], nsec: &NSEC) -> usize {
+    let next = nsec.next_domain();
+    common_labels(qname, owner).max(common_labels(qname, next.as_ref().as_ref()))
+}
+
+fn wildcard_of(name: &[u8]) -> Vec<u8> {
+    let mut wildcard = Vec::with_capacity(name.len() + 2);
+    wildcard.extend_from_slice(&[1, b'*']);
+    wildcard.extend_from_slice(name);
+    wildcard
+}
+
+// RRSIG中的labels不计算最左侧的通配符
+#[inline]
+fn wildcard_free_labels(name: &[u8]) -> usize {
+    label_count(name) - is_wildcard(name) as usize
+}
+
+// 若RRset由通配符展开，返回校验通过的RRSIG中的label数与签名者
+fn wildcard_labels(set: &RRset, sig: &RRSIG) -> Option<(usize, Vec<u8>)> {
+    let labels = sig.labels() as usize;
+    if labels < wildcard_free_labels(set.name) {
+        Some((
+            labels,
+            sig.signer_name().as_ref().as_ref().to_ascii_lowercase(),
+        ))
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn supported_algorithm(algorithm: u8) -> bool {
+    matches!(algorithm, 8 | 13 | 14 | 15)
+}
+
+#[inline]
+fn supported_digest(digest_type: u8) -> bool {
+    matches!(digest_type, 1 | 2 | 4)
+}
+
+#[inline]
+fn in_window(sig: &RRSIG, now: u32) -> bool {
+    // 序列号算术(RFC 1982)
+    now.wrapping_sub(sig.sig_inception()) as i32 >= 0
+        && sig.sig_expiration().wrapping_sub(now) as i32 >= 0
+}
+
+fn same_key(a: &DNSKEY, b: &DNSKEY) -> bool {
+    a.flags() == b.flags() && a.algorithm() == b.algorithm() && a.public_key() == b.public_key()
+}
+
+fn ds_matches(owner: &[u8], key: &DNSKEY, ds: &DS) -> bool {
+    if ds.key_tag() != key.key_tag() || ds.algorithm() != key.algorithm() {
+        return false;
+    }
+    let algorithm = match ds.digest_type() {
+        1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        2 => &digest::SHA256,
+        4 => &digest::SHA384,
+        _ => return false,
+    };
+    let mut data = RawDomain::from_raw(owner.to_vec()).to_canonical_wire();
+    data.extend(SliceOperator::encode_to_vec(|operator| {
+        key.encode_canonical(operator)
+    }));
+    digest::digest(algorithm, &data).as_ref() == ds.digest()
+}
+
+fn nsec3_hash(name: &[u8], salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut data = name.to_ascii_lowercase();
+    data.push(0);
+    data.extend_from_slice(salt);
+    let mut hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &data);
+    for _ in 0..iterations {
+        let mut data = hash.as_ref().to_vec();
+        data.extend_from_slice(salt);
+        hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, &data);
+    }
+    hash.as_ref().to_vec()
+}
+
+// 组装被签名的数据(RFC 4034 3.1.8.1)
+fn signed_data(sig: &RRSIG, set: &RRset) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(512);
+    data.extend_from_slice(&sig.type_covered().to_be_bytes());
+    data.push(sig.algorithm());
+    data.push(sig.labels());
+    data.extend_from_slice(&sig.original_ttl().to_be_bytes());
+    data.extend_from_slice(&sig.sig_expiration().to_be_bytes());
+    data.extend_from_slice(&sig.sig_inception().to_be_bytes());
+    data.extend_from_slice(&sig.key_tag().to_be_bytes());
+    data.extend(sig.signer_name().to_canonical_wire());
+
+    let labels = sig.labels() as usize;
+    let mut owner = if labels < wildcard_free_labels(set.name) {
+        wildcard_of(base_name(set.name, labels))
+    } else {
+        set.name.to_vec()
+    };
+    owner.make_ascii_lowercase();
+    owner.push(0);
+
+    let mut rdatas: Vec<Vec<u8>> = set
+        .records
+        .iter()
+        .map(|r| r.data.canonical_rdata())
+        .collect();
+    rdatas.sort();
+    rdatas.dedup();
+    for rdata in rdatas {
+        data.extend_from_slice(&owner);
+        data.extend_from_slice(&set.rtype.to_be_bytes());
+        data.extend_from_slice(&set.class.to_be_bytes());
+        data.extend_from_slice(&sig.original_ttl().to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend(rdata);
+    }
+    Some(data)
+}
+
+fn verify_signature(key: &DNSKEY, data: &[u8], sig: &[u8]) -> bool {
+    let public_key = key.public_key();
+    match key.algorithm() {
+        8 => {
+            // RFC 3110：指数长度(1或3字节)+指数+模数
+            let Some((&first, rest)) = public_key.split_first() else {
+                return false;
+            };
+            let (len, rest) = if first == 0 {
+                match rest {
+                    [a, b, rest @ ..] => (u16::from_be_bytes([*a, *b]) as usize, rest),
+                    _ => return false,
+                }
+            } else {
+                (first as usize, rest)
+            };
+            if rest.len() <= len {
+                return false;
+            }
+            RsaPublicKeyComponents {
+                n: &rest[len..],
+                e: &rest[..len],
+            }
+            .verify(
+                &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+                data,
+                sig,
+            )
+            .is_ok()
+        }
+        13 | 14 => {
+            let algorithm = if key.algorithm() == 13 {
+                &signature::ECDSA_P256_SHA256_FIXED
+            } else {
+                &signature::ECDSA_P384_SHA384_FIXED
+            };
+            let mut point = Vec::with_capacity(public_key.len() + 1);
+            point.push(0x04);
+            point.extend_from_slice(public_key);
+            UnparsedPublicKey::new(algorithm, point)
+                .verify(data, sig)
+                .is_ok()
+        }
+        15 => UnparsedPublicKey::new(&signature::ED25519, public_key)
+            .verify(data, sig)
+            .is_ok(),
+        _ => false,
+    }
+}
+
+fn hex_decode(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{RESPONSES, ROOT_DS_SHA256, ROOT_KEY_TAG};
+    use super::*;
+    use crate::dns::types::base::record::{A, AAAA};
+    use crate::dns::types::parts::MessageBuilder;
+    use chrono::TimeZone;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn fixture(name: &str, qtype: u16) -> Option<Vec<u8>> {
+        RESPONSES
+            .iter()
+            .find(|(n, t, _)| *n == name && *t == qtype)
+            .map(|(_, _, hex)| hex_decode(hex))
+    }
+
+    fn fetch(name: &RawDomain, qtype: u16) -> Option<Response> {
+        let mut name = name.to_string()?;
+        if name != "." {
+            name.push('.');
+        }
+        Response::from_slice_uncheck(&fixture(&name, qtype)?).ok()
+    }
+
+    fn validator() -> Validator {
+        let anchor =
+            TrustAnchor::root().with_ds(DS::new(ROOT_KEY_TAG, 8, 2, hex_decode(ROOT_DS_SHA256)));
+        Validator::new(anchor).with_time(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap())
+    }
+
+    fn validate(name: &str, qtype: u16) -> ValidationStatus {
+        let response = Response::from_slice_uncheck(&fixture(name, qtype).unwrap()).unwrap();
+        validator().validate(&response, fetch).status
+    }
+
+    #[test]
+    fn test_nsec3_hash() {
+        // RFC 5155 附录A
+        let name = RawDomain::from_str("example").unwrap();
+        let hash = nsec3_hash(name.as_ref(), &hex_decode("aabbccdd"), 12);
+        assert_eq!(base32hex_encode(&hash), "0P9MHAVEQVM6T7VBL5LOP2U3T2RP3TOM");
+    }
+
+    #[test]
+    fn test_secure() {
+        // RSA/SHA-256 -> ECDSA P-256
+        assert_eq!(
+            validate("www.example.", DnsTypeNum::A),
+            ValidationStatus::Secure
+        );
+        // ECDSA P-384
+        assert_eq!(
+            validate("host.p384.example.", DnsTypeNum::A),
+            ValidationStatus::Secure
+        );
+        // Ed25519
+        assert_eq!(
+            validate("www.ed.example.", DnsTypeNum::AAAA),
+            ValidationStatus::Secure
+        );
+        assert_eq!(
+            validate("a.wild.example.", DnsTypeNum::A),
+            ValidationStatus::Secure
+        );
+    }
+
+    #[test]
+    fn test_denial() {
+        assert_eq!(
+            validate("nothere.example.", DnsTypeNum::A),
+            ValidationStatus::Secure
+        );
+        assert_eq!(
+            validate("nohost.p384.example.", DnsTypeNum::A),
+            ValidationStatus::Secure
+        );
+        assert_eq!(
+            validate("www.ed.example.", DnsTypeNum::TXT),
+            ValidationStatus::Secure
+        );
+    }
+
+    #[test]
+    fn test_insecure() {
+        assert_eq!(
+            validate("www.insecure.example.", DnsTypeNum::A),
+            ValidationStatus::Insecure
+        );
+    }
+
+    #[test]
+    fn test_bogus() {
+        // 篡改A记录
+        let mut slice = fixture("www.example.", DnsTypeNum::A).unwrap();
+        let pos = slice.windows(4).position(|w| w == [192, 0, 2, 1]).unwrap();
+        slice[pos + 3] = 99;
+        let response = Response::from_slice_uncheck(&slice).unwrap();
+        assert_eq!(
+            validator().validate(&response, fetch).status,
+            ValidationStatus::Bogus
+        );
+
+        // 签名已过期
+        let response =
+            Response::from_slice_uncheck(&fixture("www.example.", DnsTypeNum::A).unwrap()).unwrap();
+        let expired = validator().with_time(Utc.with_ymd_and_hms(2040, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            expired.validate(&response, fetch).status,
+            ValidationStatus::Bogus
+        );
+
+        // 信任锚与根区密钥不一致
+        let wrong = Validator::new(TrustAnchor::new(".").unwrap().with_ds(DS::new(
+            ROOT_KEY_TAG,
+            8,
+            2,
+            vec![0; 32],
+        )))
+        .with_time(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(
+            wrong.validate(&response, fetch).status,
+            ValidationStatus::Bogus
+        );
+
+        // NODATA应答去掉NSEC后无法证明
+        let response =
+            Response::from_slice_uncheck(&fixture("www.ed.example.", DnsTypeNum::TXT).unwrap())
+                .unwrap();
+        let mut stripped = Response {
+            header: response.header,
+            question: response.question,
+            answer: response
+                .answer
+                .into_iter()
+                .filter(|r| match &r.data {
+                    RecordDataType::RRSIG(sig) => sig.type_covered() == DnsTypeNum::SOA,
+                    _ => r.rtype == DnsTypeNum::SOA,
+                })
+                .collect(),
+        };
+        stripped.header.authority_rrs = 2;
+        assert_eq!(
+            validator().validate(&stripped, fetch).status,
+            ValidationStatus::Bogus
+        );
+    }
+
+    #[test]
+    fn test_indeterminate() {
+        let response =
+            Response::from_slice_uncheck(&fixture("www.example.", DnsTypeNum::A).unwrap()).unwrap();
+        assert_eq!(
+            validator().validate(&response, |_, _| None).status,
+            ValidationStatus::Indeterminate
+        );
+    }
+
+    // fixtures中没有的场景用固定种子的Ed25519密钥在测试时签名，有效期与fixtures相同
+    struct TestZone {
+        name: RawDomain,
+        key: Ed25519KeyPair,
+        dnskey: DNSKEY,
+    }
+
+    impl TestZone {
+        fn new(name: &str, seed: u8) -> TestZone {
+            let key = Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap();
+            let dnskey = DNSKEY::new(257, 3, 15, key.public_key().as_ref().to_vec());
+            TestZone {
+                name: RawDomain::from_str(name).unwrap(),
+                key,
+                dnskey,
+            }
+        }
+
+        fn ds(&self) -> DS {
+            let mut data = self.name.to_canonical_wire();
+            data.extend(SliceOperator::encode_to_vec(|operator| {
+                self.dnskey.encode_canonical(operator)
+            }));
+            let digest = digest::digest(&digest::SHA256, &data);
+            DS::new(self.dnskey.key_tag(), 15, 2, digest.as_ref().to_vec())
+        }
+
+        // 同名同类型的一组记录及其RRSIG
+        fn sign(&self, owner: &str, data: Vec<RecordDataType>) -> Vec<Record> {
+            let owner = RawDomain::from_str(owner).unwrap();
+            let mut records: Vec<Record> = data
+                .into_iter()
+                .map(|data| Record::from_data(owner.to_canonical(), 1, 3600, data))
+                .collect();
+            let rtype = records[0].rtype;
+            let rrsig = |signature| {
+                RRSIG::new(
+                    rtype,
+                    15,
+                    wildcard_free_labels(owner.as_ref()) as u8,
+                    3600,
+                    2082758400,
+                    1767225600,
+                    self.dnskey.key_tag(),
+                    Rc::new(self.name.to_canonical()),
+                    signature,
+                )
+            };
+            let set = RRset {
+                name: owner.as_ref(),
+                rtype,
+                class: 1,
+                records: records.iter().collect(),
+                sigs: Vec::new(),
+            };
+            let data = signed_data(&rrsig(Vec::new()), &set).unwrap();
+            let signature = self.key.sign(&data).as_ref().to_vec();
+            records.push(Record::from_data(
+                owner.to_canonical(),
+                1,
+                3600,
+                RecordDataType::RRSIG(rrsig(signature)),
+            ));
+            records
+        }
+    }
+
+    fn message(
+        qname: &str,
+        qtype: u16,
+        rcode: u16,
+        answer: Vec<Record>,
+        authority: Vec<Record>,
+    ) -> Response {
+        let mut builder = MessageBuilder::new().response(true).rcode(rcode).question(
+            Rc::new(RawDomain::from_str(qname).unwrap()),
+            qtype,
+            1,
+        );
+        for record in answer {
+            builder = builder.answer(record);
+        }
+        for record in authority {
+            builder = builder.authority(record);
+        }
+        let mut buf = [0_u8; 4096];
+        Response::from_slice_uncheck(builder.encode_to_udp(&mut buf)).unwrap()
+    }
+
+    fn nsec(next: &str, types: &[u16]) -> RecordDataType {
+        RecordDataType::NSEC(NSEC::new(
+            Rc::new(RawDomain::from_str(next).unwrap()),
+            TypeBitmap::from_types(types.iter().copied()),
+        ))
+    }
+
+    // parent. -> child.parent. 均已签名，以parent.的密钥为信任锚
+    fn validate_tree(parent: &TestZone, child: &TestZone, response: &Response) -> Validation {
+        let fetch = |name: &RawDomain, qtype: u16| -> Option<Response> {
+            let name = name.to_string()?;
+            let (zone, data) = match (name.as_str(), qtype) {
+                ("parent", DnsTypeNum::DNSKEY) => {
+                    (parent, RecordDataType::DNSKEY(parent.dnskey.clone()))
+                }
+                ("child.parent", DnsTypeNum::DS) => (parent, RecordDataType::DS(child.ds())),
+                ("child.parent", DnsTypeNum::DNSKEY) => {
+                    (child, RecordDataType::DNSKEY(child.dnskey.clone()))
+                }
+                _ => return None,
+            };
+            Some(message(
+                &name,
+                qtype,
+                0,
+                zone.sign(&name, vec![data]),
+                Vec::new(),
+            ))
+        };
+        let validator = Validator::new(
+            TrustAnchor::new("parent.")
+                .unwrap()
+                .with_dnskey(parent.dnskey.clone()),
+        )
+        .with_time(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap());
+        validator.validate(response, fetch)
+    }
+
+    #[test]
+    fn test_replayed_delegation() {
+        let parent = TestZone::new("parent.", 1);
+        let child = TestZone::new("child.parent.", 2);
+        let validate = |response: &Response| validate_tree(&parent, &child, response).status;
+
+        // 子区域自己签名的NSEC可以证明a.child.parent.不存在
+        let apex = nsec(
+            "www.child.parent.",
+            &[
+                DnsTypeNum::SOA,
+                DnsTypeNum::NS,
+                DnsTypeNum::DNSKEY,
+                DnsTypeNum::NSEC,
+                DnsTypeNum::RRSIG,
+            ],
+        );
+        let response = message(
+            "a.child.parent.",
+            DnsTypeNum::A,
+            3,
+            Vec::new(),
+            child.sign("child.parent.", vec![apex]),
+        );
+        assert_eq!(validate(&response), ValidationStatus::Secure);
+
+        // 父区域在委派点上的NSEC覆盖了子区域内的名称，但不能用于证明
+        let delegation = || {
+            parent.sign(
+                "child.parent.",
+                vec![nsec(
+                    "z.parent.",
+                    &[
+                        DnsTypeNum::NS,
+                        DnsTypeNum::DS,
+                        DnsTypeNum::NSEC,
+                        DnsTypeNum::RRSIG,
+                    ],
+                )],
+            )
+        };
+        let response = message(
+            "a.child.parent.",
+            DnsTypeNum::A,
+            3,
+            Vec::new(),
+            delegation(),
+        );
+        assert_eq!(validate(&response), ValidationStatus::Bogus);
+        let response = message("child.parent.", DnsTypeNum::A, 0, Vec::new(), delegation());
+        assert_eq!(validate(&response), ValidationStatus::Bogus);
+        let response = message(
+            "www.child.parent.",
+            DnsTypeNum::TXT,
+            0,
+            Vec::new(),
+            delegation(),
+        );
+        assert_eq!(validate(&response), ValidationStatus::Bogus);
+    }
+
+    #[test]
+    fn test_per_answer_status() {
+        let parent = TestZone::new("parent.", 1);
+        let child = TestZone::new("child.parent.", 2);
+        let mut answer = parent.sign(
+            "www.parent.",
+            vec![RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 1)))],
+        );
+        answer.extend(child.sign(
+            "www.child.parent.",
+            vec![RecordDataType::AAAA(AAAA::new(Ipv6Addr::new(
+                0x2001, 0xdb8, 0, 0, 0, 0, 0, 1,
+            )))],
+        ));
+        // 安全区域内没有签名的记录
+        answer.push(Record::from_data(
+            RawDomain::from_str("child.parent.").unwrap(),
+            1,
+            3600,
+            RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 2))),
+        ));
+        let response = message("www.parent.", DnsTypeNum::A, 0, answer, Vec::new());
+        let validation = validate_tree(&parent, &child, &response);
+        assert_eq!(validation.status, ValidationStatus::Bogus);
+        let answers: Vec<(String, u16, ValidationStatus)> = validation
+            .answers
+            .iter()
+            .map(|a| (a.name.to_string().unwrap(), a.rtype, a.status))
+            .collect();
+        assert_eq!(
+            answers,
+            [
+                (
+                    "www.parent".to_string(),
+                    DnsTypeNum::A,
+                    ValidationStatus::Secure
+                ),
+                (
+                    "www.child.parent".to_string(),
+                    DnsTypeNum::AAAA,
+                    ValidationStatus::Secure
+                ),
+                (
+                    "child.parent".to_string(),
+                    DnsTypeNum::A,
+                    ValidationStatus::Bogus
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_rrsigs() {
+        let parent = TestZone::new("parent.", 1);
+        let child = TestZone::new("child.parent.", 2);
+        let a = |last| RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, last)));
+        // 父区域为另一组数据生成的签名对这组记录无效，放在子区域的有效签名之前
+        let answer = |with_valid: bool| {
+            let stale = parent.sign("www.child.parent.", vec![a(9)]).pop().unwrap();
+            let mut records = child.sign("www.child.parent.", vec![a(1)]);
+            let valid = records.pop().unwrap();
+            records.push(stale);
+            if with_valid {
+                records.push(valid);
+            }
+            message("www.child.parent.", DnsTypeNum::A, 0, records, Vec::new())
+        };
+        assert_eq!(
+            validate_tree(&parent, &child, &answer(true)).status,
+            ValidationStatus::Secure
+        );
+        assert_eq!(
+            validate_tree(&parent, &child, &answer(false)).status,
+            ValidationStatus::Bogus
+        );
+    }
+}
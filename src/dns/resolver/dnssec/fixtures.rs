@@ -0,0 +1,208 @@
+use crate::dns::types::base::DnsTypeNum;
+
+// 测试用的签名区域，由离线脚本生成后固定下来
+// 签名有效期 2026-01-01 ~ 2036-01-01
+// . (RSA/SHA-256) -> example. (ECDSA P-256, NSEC, KSK+ZSK)
+//   -> p384.example. (ECDSA P-384, NSEC3, DS使用SHA-384)
+//   -> ed.example. (Ed25519, NSEC)
+//   -> insecure.example. (没有DS)
+
+pub(super) const ROOT_KEY_TAG: u16 = 12007;
+pub(super) const ROOT_DS_SHA256: &str =
+    "8E09BCA841884F431B223A50A2DC6934A2FD3F71871595C32E363898AF9A503D";
+
+pub(super) const RESPONSES: &[(&str, u16, &str)] = &[
+    (
+        ".",
+        DnsTypeNum::DNSKEY,
+        "1234818000010002000000000000300001000030000100000e1001080101030803010001c9623cb1a5faee32\
+         89f2faa8981e59f5fdd1d27286421c802446df61d8a95817562400938df033ed7c493fce2172e302ff3cf031\
+         e1ab441476339bacde888372195619f1e145c8fd798068f2b86d9f3d6bbf13408fec48affd9a980dd715dc9d\
+         3a8d0c4519849e3d9a1de50b7e75accb29311605a5e420fee56cb90840062097e1e461e763c32009b63d2f89\
+         2fa7bc1c4cf1ec5fcb8f567f690b271a4b85f2628b27d3f540ecc7f087541d730c22dd17feb375f60ff8b2d8\
+         0ab86445728d2c8abbf2c4e2d47961203f46f5aae73867434deefc784de7938123aa7a801b097af122a57fd9\
+         ce75b6199c1ce0e7613be2d2f7ab6a9fad023fc4d8102cbdf81cda7300002e000100000e1001130030080000\
+         000e107c245f006955b9002ee700a02694295c9a87d4418a41a0664440ca413dc1ff7fb10586d6b4ca4d556a\
+         118790f9ad2e3c90bf0c191ccd1eebc48c45bb2a0e601fd3eb20e499e27d27bf4ab2c975ed9bc83cfab9b724\
+         2bccb439999005437a73401023ca181967dbc1ea83afed13e418d77842d18a37993b3698b730f0aed2a4155a\
+         a56bad8aa36dcfcf30fe179554c15e123801c88df6532013899da3f0628fc6c12d0c3615b827ea0b0ba94b66\
+         80d5c9aaff59bb2250145758794f3a0617487d76291c14f6c16efb00e85afa23d70237586f361c612007d43d\
+         67228073509668641bf55a407b671159642b8ec9c413ca1349e3ab8346ded65dd2efa5c35940036c8d478c09\
+         c6df16718f03",
+    ),
+    (
+        "example.",
+        DnsTypeNum::DS,
+        "123481800001000200000000076578616d706c6500002b0001076578616d706c6500002b000100000e100024\
+         49f20d027c7def31b7fd1c7c84cbad2a43c49ab1c5c2a312341990887b74621a2f5dd323076578616d706c65\
+         00002e000100000e100113002b080100000e107c245f006955b9002ee7008e9022b9f48092ee29c65f2e33cc\
+         81c41e22b1c4d9a89bd7c57e517594aeb715793c9c7fd1233093ae0d19d68407d076a76c8cdde28fc0afd118\
+         6908ed429dbc79860d24c7a899dfbde3a32d2536bda3a3dd4f5a87af969a9168880cfc9f86453158fa7c89ff\
+         a1c0f4d0d93d17cadee8be993f1638b012e4261ee16b59ceb415b6b8a8cdd7dadc9ac3a72278c3c0af1f4b82\
+         27a3fcfba0f916bc32e23810272ce8687f6b0563140855a143325f5d934fb278db5c9422ad0874327eaa9c12\
+         1613ba48f96b54c88f3e2e7bfb8612a321ab47af4c4cab48ae78ca5b49a09c8a8022a9c6b970912b6c338fbd\
+         46d9304d82a6ad56d7169439d825a96085e905a4ae56",
+    ),
+    (
+        "example.",
+        DnsTypeNum::DNSKEY,
+        "123481800001000300000000076578616d706c650000300001076578616d706c65000030000100000e100044\
+         0101030d090511f0f8b2982d2de6fb7f01a62ef74559e5eedf99b17feeca0831eb3a1d1520cef506fac7d8de\
+         5a5556b1cba003dc43f3ca826b631ba2448475b3bc840e26076578616d706c65000030000100000e10004401\
+         00030d31592d2e6fc874884df10624803743f88e4e6574f416d24f386b4453aeb790b792291b37b0801cb249\
+         0e73eec75611444d819be79789221a1958aa7851a94ee4076578616d706c6500002e000100000e10005b0030\
+         0d0100000e107c245f006955b90049f2076578616d706c65007b7e345c55908765e204ac6c9bd2ba7525f04e\
+         294d9943fba084850914093319fba32a773dafe4814ef9626121705ccc54003cfa7b3f3f04975610df39f1e1\
+         f2",
+    ),
+    (
+        "www.example.",
+        DnsTypeNum::A,
+        "12348180000100020000000003777777076578616d706c65000001000103777777076578616d706c65000001\
+         000100000e100004c000020103777777076578616d706c6500002e000100000e10005b00010d0200000e107c\
+         245f006955b900ed17076578616d706c6500c8d8204d1f286992b408c028570060753be795fd32b2659bf714\
+         5650151caa61fcf500a24a2ea285e52ca72942705244212ee4470af6db7bfc3e2ff1a5300a5e",
+    ),
+    (
+        "p384.example.",
+        DnsTypeNum::DS,
+        "1234818000010002000000000470333834076578616d706c6500002b00010470333834076578616d706c6500\
+         002b000100000e100034e5c70e046402d9666a0804454fac3a2e5512803a88e887cfcf8d8c04203c4dfbcc76\
+         8030e022ad75f5229f16063523b9ce864a520470333834076578616d706c6500002e000100000e10005b002b\
+         0d0200000e107c245f006955b900ed17076578616d706c65001c876ef93eef7fbe1715c08d067e5bc4afd82d\
+         55f7792e8b175b0a2553c628e5d000e047199f25744cba5090ad242296562c1ddf7f55b1beee6b9d3037bb0c\
+         1a",
+    ),
+    (
+        "p384.example.",
+        DnsTypeNum::DNSKEY,
+        "1234818000010002000000000470333834076578616d706c6500003000010470333834076578616d706c6500\
+         0030000100000e1000640101030ea3b56167c9b809dc88e0864dfd31f601e0f7adf819d156da4628fa875714\
+         65ad1830ef398b2b3995f3fc2d4dd4a2ce5625d4cedc70a69e48f62411988000ad2750fea5e49e872af99c9b\
+         a730fef6e00db29a5900ff87c538da8f39c6b71dc19e0470333834076578616d706c6500002e000100000e10\
+         008000300e0200000e107c245f006955b900e5c70470333834076578616d706c6500466185f1d0229e07f9d9\
+         7aa3360b8f842348380e1ae9e3c3c1725378e07867ea6f09909cade8436f23079eccf32bd577651291f2c368\
+         4c9df491524fc2211191f96ddb1b7fa795f61944c1b00d143473fc16765646a6c72e11ed69fd8dce1124",
+    ),
+    (
+        "host.p384.example.",
+        DnsTypeNum::A,
+        "12348180000100020000000004686f73740470333834076578616d706c65000001000104686f737404703338\
+         34076578616d706c65000001000100000e100004c000020304686f73740470333834076578616d706c650000\
+         2e000100000e10008000010e0300000e107c245f006955b900e5c70470333834076578616d706c6500e7625d\
+         c2f74de0775b2764dc3818764380e980d7a19e9fb4052c39769a5be164a3b14d74f674a83dc940f156e5de2e\
+         d69ee1e8565034e8a266262fd67ec82d71436ba382d3918883ddfd971d3e48060ed82a0b7d0cecb8aa5bc9fe\
+         5031b50d98",
+    ),
+    (
+        "nohost.p384.example.",
+        DnsTypeNum::A,
+        "123481830001000000060000066e6f686f73740470333834076578616d706c65000001000104703338340765\
+         78616d706c65000006000100000e10003e026e730470333834076578616d706c65000a686f73746d61737465\
+         720470333834076578616d706c65000000000100001c2000000e10001275000000012c047033383407657861\
+         6d706c6500002e000100000e10008000060e0200000e107c245f006955b900e5c70470333834076578616d70\
+         6c650058b1bc678f1be3be7b764d5dd8c2ec45ee68c169d02b7dda02eac966acbea20938a981044cbeea7339\
+         178fbfdae5750ac0542235f1545b2838fa153e64c99be387fc9c1dbae68514f18132c5cea44d5387af083a0e\
+         24ce66b9603666bacd0be42039756f346566767135356c6c6572626b386d397666683831677471716c636267\
+         0470333834076578616d706c65000032000100000e1000270100000104aabbccdd148c33ea70b1d8fc89cc48\
+         5a6b7d427073d2cc108f0007220000000002902039756f346566767135356c6c6572626b386d397666683831\
+         677471716c6362670470333834076578616d706c6500002e000100000e10008000320e0300000e107c245f00\
+         6955b900e5c70470333834076578616d706c6500f71da7816696505d9efa62a8ee8ecb999444fd61309c70aa\
+         a9ff801db003fcc5d6e5d668efda1d90dc3b9691e1477fd5f9ac985cd6bbcd5aa627dc01068fc0a1692e41d0\
+         6a6ab6e3d4251157d1ba57425ab6f7967b20e3ba1237b0766286174b20686770756b733568723375386a6a32\
+         3862396c6e71676a67656639636f3434660470333834076578616d706c65000032000100000e100026010000\
+         0104aabbccdd144fb0473ffa296b576d744593f7c5018775aab170000640000000000220686770756b733568\
+         723375386a6a323862396c6e71676a67656639636f3434660470333834076578616d706c6500002e00010000\
+         0e10008000320e0300000e107c245f006955b900e5c70470333834076578616d706c65009db930fb3ac78347\
+         5b832c26ace4f96b9536ce37dcae06275553219b02ea58ac139347a20c7cbacd1259b113842c27232501f54b\
+         f216bd6dc253f7c76239bff6e528a4de3798171634aac9f12003c0e7ecde17bfaca181a89b7191862ff6efc2",
+    ),
+    (
+        "ed.example.",
+        DnsTypeNum::DS,
+        "123481800001000200000000026564076578616d706c6500002b0001026564076578616d706c6500002b0001\
+         00000e1000245d0c0f02ab198fd1cc1243ce2f8948c757fb15c69dd63c123c4a6090539db124d5d043590265\
+         64076578616d706c6500002e000100000e10005b002b0d0200000e107c245f006955b900ed17076578616d70\
+         6c65008bcf0a59121ce3294e15808dda86fcdae37e4bc593ea856f5b84b02d18213880661b8ba43fda391c42\
+         bf46b692cc3e44eaa4cb24e660294ece5bbd352670e8b7",
+    ),
+    (
+        "ed.example.",
+        DnsTypeNum::DNSKEY,
+        "123481800001000200000000026564076578616d706c650000300001026564076578616d706c650000300001\
+         00000e1000240101030f92904bcae3b99c4a1fdfa38d5fa44fd00305b6481a4702ec0e933906df0e8a920265\
+         64076578616d706c6500002e000100000e10005e00300f0200000e107c245f006955b9005d0c026564076578\
+         616d706c650077262dff78d2cd7f6374eb6e33aaae14f304e6411263828d6bee141e32d8198819a33ec5bcf6\
+         6ee8545d5696488a44f546ceec460f0309b9fbab82a9cf7fed05",
+    ),
+    (
+        "www.ed.example.",
+        DnsTypeNum::AAAA,
+        "12348180000100020000000003777777026564076578616d706c6500001c000103777777026564076578616d\
+         706c6500001c000100000e10001020010db800000000000000000000000103777777026564076578616d706c\
+         6500002e000100000e10005e001c0f0300000e107c245f006955b9005d0c026564076578616d706c650042b1\
+         28923ed39afe211cdbf20c89d396319452757a86988e50e2d063a2dc2fec74e724bd69dce87f1b696e0afe99\
+         c2cc83e2649af11947ba6504c1ddfc72a408",
+    ),
+    (
+        "www.ed.example.",
+        DnsTypeNum::TXT,
+        "12348180000100000004000003777777026564076578616d706c650000100001026564076578616d706c6500\
+         0006000100000e10003a026e73026564076578616d706c65000a686f73746d6173746572026564076578616d\
+         706c65000000000100001c2000000e10001275000000012c026564076578616d706c6500002e000100000e10\
+         005e00060f0200000e107c245f006955b9005d0c026564076578616d706c65006e600138ea72667e4854793c\
+         89b09c917e3b611411cd4c8d9a369329df45b3cae2b726a726d667c12cd96b2b0efe87da9bb8e990bad7ef35\
+         a07a4a03aa04aa0103777777026564076578616d706c6500002f000100000e100014026564076578616d706c\
+         6500000600000008000303777777026564076578616d706c6500002e000100000e10005e002f0f0300000e10\
+         7c245f006955b9005d0c026564076578616d706c6500af4ee476409e2909d4da1a299ecba7f395703d5cd746\
+         c857b7ea545ec242e5bc94cd5da3dd0201759b1f3dca1345819c0a49f5f193426bc0017e8963a570230b",
+    ),
+    (
+        "insecure.example.",
+        DnsTypeNum::DS,
+        "12348180000100000004000008696e736563757265076578616d706c6500002b0001076578616d706c650000\
+         06000100000e100034026e73076578616d706c65000a686f73746d6173746572076578616d706c6500000000\
+         0100001c2000000e10001275000000012c076578616d706c6500002e000100000e10005b00060d0100000e10\
+         7c245f006955b900ed17076578616d706c6500e07746ca3a2e9525563be8c583e82e3770dd4b31939bbc6027\
+         c2e11b315cbc768513e46d4e3ab541aaf6881898c53fd423a59003d16a4ed708a8863fd69cbf1d08696e7365\
+         63757265076578616d706c6500002f000100000e1000160470333834076578616d706c650000062000000000\
+         0308696e736563757265076578616d706c6500002e000100000e10005b002f0d0200000e107c245f006955b9\
+         00ed17076578616d706c65006d3bb32aea853db13522f13950b07aa25ceebe27afc0230cab53b1d1ce9ba601\
+         742c40fbc2947b77e04289e562a340d478fb5be254d003b65b950628ac91c79d",
+    ),
+    (
+        "www.insecure.example.",
+        DnsTypeNum::A,
+        "1234818000010001000000000377777708696e736563757265076578616d706c650000010001037777770869\
+         6e736563757265076578616d706c65000001000100000e100004c0000204",
+    ),
+    (
+        "a.wild.example.",
+        DnsTypeNum::A,
+        "12348180000100020002000001610477696c64076578616d706c65000001000101610477696c64076578616d\
+         706c65000001000100000e100004c000020201610477696c64076578616d706c6500002e000100000e10005b\
+         00010d0200000e107c245f006955b900ed17076578616d706c6500c5af4cb10393cfa47ca34897d148f03946\
+         d49de2a51052915decd1d9997c6a6deaf3653677d54301f89e8aa2a57056b5f69ff42196785f3e610bb90a4b\
+         31b70d012a0477696c64076578616d706c6500002f000100000e10001503777777076578616d706c65000006\
+         400000000003012a0477696c64076578616d706c6500002e000100000e10005b002f0d0200000e107c245f00\
+         6955b900ed17076578616d706c6500f2f20c87bba371a714ef0a8e6e8373a8bb7441f65f04b37acf1b19ddf9\
+         36205893d2a3b232eb82a4fb65a3acac428f60a51eccdf56aedeed8fbc70b7d48ac5b5",
+    ),
+    (
+        "nothere.example.",
+        DnsTypeNum::A,
+        "123481830001000000060000076e6f7468657265076578616d706c650000010001076578616d706c65000006\
+         000100000e100034026e73076578616d706c65000a686f73746d6173746572076578616d706c650000000001\
+         00001c2000000e10001275000000012c076578616d706c6500002e000100000e10005b00060d0100000e107c\
+         245f006955b900ed17076578616d706c6500e07746ca3a2e9525563be8c583e82e3770dd4b31939bbc6027c2\
+         e11b315cbc768513e46d4e3ab541aaf6881898c53fd423a59003d16a4ed708a8863fd69cbf1d08696e736563\
+         757265076578616d706c6500002f000100000e1000160470333834076578616d706c65000006200000000003\
+         08696e736563757265076578616d706c6500002e000100000e10005b002f0d0200000e107c245f006955b900\
+         ed17076578616d706c6500f7089a74647aa6489d9ad6a507e8364295e3616f233c11538c489f78e8ff723145\
+         2601549eb436306dfe869f3a424000fa49cd565d96daa2cf638c0c7d23b003076578616d706c6500002f0001\
+         00000e100015026564076578616d706c6500000722000000000380076578616d706c6500002e000100000e10\
+         005b002f0d0100000e107c245f006955b900ed17076578616d706c65000865851bc9e8770951c315d120175c\
+         188828c607665cb0e6e843c1d535037ee5a2020c7a7163fb3fbc03b05b25262930a772117f44ed9aadb3bca1\
+         500570353c",
+    ),
+];
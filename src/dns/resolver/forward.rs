@@ -0,0 +1,280 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::RawDomain;
+use crate::dns::utils::{ServerParseError, ServerType};
+use smallvec::SmallVec;
+use snafu::{ResultExt, Snafu};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+#[derive(Snafu, Debug)]
+pub enum ForwardRuleError {
+    #[snafu(display("InvalidLine, line: {}, content: {}", line, content))]
+    InvalidLine { line: usize, content: String },
+    #[snafu(display("InvalidDomain, line: {}, domain: {}", line, domain))]
+    InvalidDomain { line: usize, domain: String },
+    #[snafu(display("InvalidServer, line: {}, info: {}", line, source))]
+    InvalidServer {
+        line: usize,
+        source: ServerParseError,
+    },
+    #[snafu(display("ReadFile, path: {}, info: {}", path, source))]
+    ReadFile {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+// 按域名后缀选择上游(split DNS)，最长后缀优先，后缀本身也被匹配。
+// 值为None时使用Resolver配置的上游，为空列表时不向任何上游转发
+#[derive(Debug, Default)]
+pub struct ForwardRules {
+    rules: HashMap<RawDomain, Option<SmallVec<[ServerType; 5]>>>,
+}
+
+impl ForwardRules {
+    pub fn new() -> ForwardRules {
+        Self::default()
+    }
+
+    // 每行一条规则，#开头的行为注释，支持两种格式:
+    //   corp.internal 10.0.0.53 tcp://10.0.0.54   (后缀可写成*.corp.internal，.或*表示所有域名)
+    //   server=/corp.internal/lan/10.0.0.53#5353  (dnsmasq格式，#单独出现表示使用默认上游)
+    pub fn parse(config: &str) -> Result<ForwardRules, ForwardRuleError> {
+        let mut rules = ForwardRules::new();
+        for (index, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.parse_line(index + 1, line)?;
+        }
+        Ok(rules)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ForwardRules, ForwardRuleError> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path).context(ReadFileSnafu {
+            path: path.display().to_string(),
+        })?;
+        Self::parse(&config)
+    }
+
+    // servers为["#"]时使用默认上游，为空时该后缀不转发
+    pub fn add_rule(&mut self, suffix: &str, servers: &[&str]) -> Result<(), ForwardRuleError> {
+        self.add(0, &[suffix], servers)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // 从domain开始逐级去掉左侧label查找，第一个命中的即为最长后缀。
+    // 没有规则命中或命中默认上游时返回None
+    pub fn route(&self, domain: &RawDomain) -> Option<&[ServerType]> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        if let Some(servers) = self.rules.get(domain) {
+            return servers.as_deref();
+        }
+        let mut name = domain.parent();
+        while let Some(current) = name {
+            if let Some(servers) = self.rules.get(&current) {
+                return servers.as_deref();
+            }
+            name = current.parent();
+        }
+        None
+    }
+
+    fn parse_line(&mut self, line: usize, content: &str) -> Result<(), ForwardRuleError> {
+        let invalid = || ForwardRuleError::InvalidLine {
+            line,
+            content: content.to_string(),
+        };
+        // 服务器参数中也可能出现=，只有第一个字段中的=表示dnsmasq格式
+        if let Some((key, value)) = content.split_once('=')
+            && !key.contains(char::is_whitespace)
+        {
+            let (key, value) = (key.trim(), value.trim());
+            if key != "server" && key != "local" {
+                return Err(invalid());
+            }
+            // server=ip 设置所有域名的上游
+            let Some(value) = value.strip_prefix('/') else {
+                if key == "local" || value.is_empty() {
+                    return Err(invalid());
+                }
+                return self.add(line, &["."], &[&dnsmasq_server(value)]);
+            };
+            let (domains, server) = value.rsplit_once('/').ok_or_else(invalid)?;
+            let domains: SmallVec<[&str; 5]> =
+                domains.split('/').filter(|d| !d.is_empty()).collect();
+            if domains.is_empty() || (key == "local" && !server.is_empty()) {
+                return Err(invalid());
+            }
+            return match server {
+                "" => self.add(line, &domains, &[]),
+                "#" => self.add(line, &domains, &["#"]),
+                server => self.add(line, &domains, &[&dnsmasq_server(server)]),
+            };
+        }
+        let mut fields = content.split_whitespace();
+        let suffix = fields.next().ok_or_else(invalid)?;
+        let servers: SmallVec<[&str; 5]> = fields.collect();
+        if servers.is_empty() {
+            return Err(invalid());
+        }
+        self.add(line, &[suffix], &servers)
+    }
+
+    // 同一后缀出现多次时合并上游，与默认上游混用时以最后一条为准
+    fn add(
+        &mut self,
+        line: usize,
+        suffixes: &[&str],
+        servers: &[&str],
+    ) -> Result<(), ForwardRuleError> {
+        let group = if servers == ["#"] {
+            None
+        } else {
+            let group = servers
+                .iter()
+                .try_fold(SmallVec::new(), |mut group, server| {
+                    group.push(
+                        ServerType::from_string(server).context(InvalidServerSnafu { line })?,
+                    );
+                    Ok(group)
+                })?;
+            Some(group)
+        };
+        for suffix in suffixes {
+            let domain = suffix_domain(suffix).ok_or_else(|| ForwardRuleError::InvalidDomain {
+                line,
+                domain: suffix.to_string(),
+            })?;
+            match (self.rules.get_mut(&domain), &group) {
+                (Some(Some(existing)), Some(group)) => existing.extend(group.iter().cloned()),
+                _ => {
+                    self.rules.insert(domain, group.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// *.example.com与example.com等价，.与*表示根域名
+fn suffix_domain(suffix: &str) -> Option<RawDomain> {
+    let suffix = suffix.strip_prefix("*.").unwrap_or(suffix);
+    let suffix = if suffix == "*" { "." } else { suffix };
+    RawDomain::from_str(suffix).map(|domain| domain.to_canonical())
+}
+
+// dnsmasq用#分隔端口，转换为ServerType的格式
+fn dnsmasq_server(value: &str) -> String {
+    let (host, port) = value.split_once('#').unwrap_or((value, "53"));
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V6(ip)) => format!("[{}]:{}", ip, port),
+        _ => format!("{}:{}", host, port),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(rules: &ForwardRules, name: &str) -> Option<Vec<String>> {
+        let domain = RawDomain::from_str(name).unwrap();
+        rules
+            .route(&domain)
+            .map(|servers| servers.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn test_longest_suffix() {
+        let rules = ForwardRules::parse(
+            "# split DNS\n\
+             *.internal 10.0.0.1\n\
+             *.corp.internal 10.0.0.2 10.0.0.3\n\
+             cn 223.5.5.5\n\
+             . https://1.1.1.1/dns-query\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 4);
+        let corp = Some(vec![
+            "udp://10.0.0.2:53".to_string(),
+            "udp://10.0.0.3:53".to_string(),
+        ]);
+        assert_eq!(route(&rules, "a.corp.internal"), corp);
+        assert_eq!(route(&rules, "A.B.Corp.Internal."), corp);
+        assert_eq!(route(&rules, "corp.internal"), corp);
+        assert_eq!(
+            route(&rules, "x.internal"),
+            Some(vec!["udp://10.0.0.1:53".to_string()])
+        );
+        assert_eq!(
+            route(&rules, "www.baidu.cn"),
+            Some(vec!["udp://223.5.5.5:53".to_string()])
+        );
+        assert_eq!(
+            route(&rules, "example.com"),
+            Some(vec!["https://1.1.1.1:443/dns-query".to_string()])
+        );
+        assert_eq!(route(&ForwardRules::new(), "example.com"), None);
+    }
+
+    #[test]
+    fn test_dnsmasq() {
+        let rules = ForwardRules::parse(
+            "server=/corp.internal/lan/10.0.0.53#5353\n\
+             server=/corp.internal/::1\n\
+             server=/public.corp.internal/#\n\
+             local=/blocked.lan/\n\
+             server=8.8.8.8\n",
+        )
+        .unwrap();
+        let corp = Some(vec![
+            "udp://10.0.0.53:5353".to_string(),
+            "udp://[::1]:53".to_string(),
+        ]);
+        assert_eq!(route(&rules, "git.corp.internal"), corp);
+        assert_eq!(
+            route(&rules, "nas.lan"),
+            Some(vec!["udp://10.0.0.53:5353".to_string()])
+        );
+        assert_eq!(route(&rules, "www.public.corp.internal"), None);
+        assert_eq!(route(&rules, "x.blocked.lan"), Some(vec![]));
+        assert_eq!(
+            route(&rules, "example.com"),
+            Some(vec!["udp://8.8.8.8:53".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let cases = [
+            ("corp.internal", "InvalidLine, line: 2"),
+            ("address=/a/1.2.3.4", "InvalidLine, line: 2"),
+            ("local=/a/1.2.3.4", "InvalidLine, line: 2"),
+            ("server=//1.2.3.4", "InvalidLine, line: 2"),
+            ("a..b 1.1.1.1", "InvalidDomain, line: 2"),
+            ("example.com ftp://1.1.1.1", "InvalidServer, line: 2"),
+            ("server=/a/1.2.3.4#port", "InvalidServer, line: 2"),
+        ];
+        for (line, expected) in cases {
+            let err = ForwardRules::parse(&format!("# rules\n{}", line)).unwrap_err();
+            assert!(err.to_string().starts_with(expected), "{}: {}", line, err);
+        }
+        let err = ForwardRules::from_file("/nonexistent/forward.conf").unwrap_err();
+        assert!(matches!(err, ForwardRuleError::ReadFile { .. }));
+    }
+}
@@ -0,0 +1,743 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+#[cfg(feature = "result_error")]
+use crate::dns::error::{NetError, TraceErrorFormat};
+use crate::dns::net::{NetQuery, TcpPool, UdpPool};
+#[cfg(feature = "result_error")]
+use crate::dns::resolver::ResolverQueryError;
+#[cfg(feature = "result_error")]
+use crate::dns::resolver::convert_err;
+use crate::dns::resolver::{AddressPreference, ResolverQueryResult};
+use crate::dns::types::base::{DnsRcodeNum, DnsTypeNum, RawDomain};
+use crate::dns::types::parts::{RecordDataType, Request, Response};
+#[cfg(feature = "logger")]
+use log::{debug, trace};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::rc::Rc;
+use std::time::Duration;
+
+// 根服务器的IPv4地址 a ~ m
+const ROOT_HINTS: [Ipv4Addr; 13] = [
+    Ipv4Addr::new(198, 41, 0, 4),
+    Ipv4Addr::new(170, 247, 170, 2),
+    Ipv4Addr::new(192, 33, 4, 12),
+    Ipv4Addr::new(199, 7, 91, 13),
+    Ipv4Addr::new(192, 203, 230, 10),
+    Ipv4Addr::new(192, 5, 5, 241),
+    Ipv4Addr::new(192, 112, 36, 4),
+    Ipv4Addr::new(198, 97, 190, 53),
+    Ipv4Addr::new(192, 36, 148, 17),
+    Ipv4Addr::new(192, 58, 128, 30),
+    Ipv4Addr::new(193, 0, 14, 129),
+    Ipv4Addr::new(199, 7, 83, 42),
+    Ipv4Addr::new(202, 12, 27, 33),
+];
+
+// 根服务器的IPv6地址 a ~ m
+const ROOT_HINTS_V6: [Ipv6Addr; 13] = [
+    Ipv6Addr::new(0x2001, 0x503, 0xba3e, 0, 0, 0, 0x2, 0x30),
+    Ipv6Addr::new(0x2801, 0x1b8, 0x10, 0, 0, 0, 0, 0xb),
+    Ipv6Addr::new(0x2001, 0x500, 0x2, 0, 0, 0, 0, 0xc),
+    Ipv6Addr::new(0x2001, 0x500, 0x2d, 0, 0, 0, 0, 0xd),
+    Ipv6Addr::new(0x2001, 0x500, 0xa8, 0, 0, 0, 0, 0xe),
+    Ipv6Addr::new(0x2001, 0x500, 0x2f, 0, 0, 0, 0, 0xf),
+    Ipv6Addr::new(0x2001, 0x500, 0x12, 0, 0, 0, 0, 0xd0d),
+    Ipv6Addr::new(0x2001, 0x500, 0x1, 0, 0, 0, 0, 0x53),
+    Ipv6Addr::new(0x2001, 0x7fe, 0, 0, 0, 0, 0, 0x53),
+    Ipv6Addr::new(0x2001, 0x503, 0xc27, 0, 0, 0, 0x2, 0x30),
+    Ipv6Addr::new(0x2001, 0x7fd, 0, 0, 0, 0, 0, 0x1),
+    Ipv6Addr::new(0x2001, 0x500, 0x9f, 0, 0, 0, 0, 0x42),
+    Ipv6Addr::new(0x2001, 0xdc3, 0, 0, 0, 0, 0, 0x35),
+];
+
+// 解析NS名称时允许的最大嵌套深度，防止互相依赖的委派无限递归
+const MAX_DEPTH: usize = 4;
+
+// 所有服务器都没有响应时用相同名称重试的次数
+const MAX_RETRIES: usize = 1;
+
+// 向一组服务器查询的结果
+enum Reply {
+    // NOERROR或NXDOMAIN
+    Answer(Response),
+    // 有响应的服务器都返回了FORMERR、SERVFAIL、NOTIMP或REFUSED
+    Rejected,
+    // 没有服务器响应
+    NoResponse,
+}
+
+#[cfg(feature = "result_error")]
+type ErrorVec = Vec<NetError>;
+#[cfg(not(feature = "result_error"))]
+type ErrorVec = ();
+
+// RFC 9156 中最小化查询使用的类型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MinimiseQType {
+    A,
+    NS,
+}
+
+impl MinimiseQType {
+    #[inline]
+    fn as_u16(self) -> u16 {
+        match self {
+            MinimiseQType::A => DnsTypeNum::A,
+            MinimiseQType::NS => DnsTypeNum::NS,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IterativeConfig {
+    // 是否开启QNAME最小化(RFC 9156)
+    pub qname_minimisation: bool,
+    pub minimise_qtype: MinimiseQType,
+    pub root_hints: Vec<IpAddr>,
+    // 根服务器与权威服务器同时有IPv4与IPv6地址时优先尝试的地址族
+    pub preference: AddressPreference,
+    // 所有权威服务器使用的端口，测试时可以指向本地服务器
+    pub port: u16,
+    pub timeout: Duration,
+    // 单次解析最多发出的查询步数
+    pub max_steps: usize,
+}
+
+impl Default for IterativeConfig {
+    fn default() -> Self {
+        IterativeConfig {
+            qname_minimisation: true,
+            minimise_qtype: MinimiseQType::A,
+            root_hints: ROOT_HINTS
+                .iter()
+                .map(|ip| IpAddr::V4(*ip))
+                .chain(ROOT_HINTS_V6.iter().map(|ip| IpAddr::V6(*ip)))
+                .collect(),
+            preference: AddressPreference::None,
+            port: 53,
+            timeout: Duration::from_secs(3),
+            max_steps: 32,
+        }
+    }
+}
+
+pub struct IterativeResolver {
+    config: IterativeConfig,
+    udp: UdpPool,
+    tcp: TcpPool,
+}
+
+impl IterativeResolver {
+    pub fn new(config: IterativeConfig) -> IterativeResolver {
+        IterativeResolver {
+            config,
+            udp: UdpPool::new(),
+            tcp: TcpPool::new(),
+        }
+    }
+
+    // 替换向权威服务器查询时使用的UDP socket池
+    pub fn with_udp_pool(mut self, udp: UdpPool) -> IterativeResolver {
+        self.udp = udp;
+        self
+    }
+
+    pub fn query(&self, domain: String, qtype: u16) -> ResolverQueryResult {
+        #[cfg(feature = "result_error")]
+        let mut errors: ErrorVec = Vec::new();
+        #[cfg(not(feature = "result_error"))]
+        let mut errors: ErrorVec = ();
+        if let Some(qname) = RawDomain::from_str(domain.as_str()) {
+            let response = self.resolve(Rc::new(qname), qtype, 0, &mut errors);
+            #[cfg(feature = "result_error")]
+            if response.is_none() {
+                return ResolverQueryError::NetError {
+                    err: errors,
+                    trace: "IterativeResolver::query".to_string(),
+                }
+                .into();
+            }
+            ResolverQueryResult::from(response)
+        } else {
+            #[cfg(feature = "result_error")]
+            return ResolverQueryError::TargetParseError(TraceErrorFormat {
+                info: format!("domain: {}", domain),
+                trace: "IterativeResolver::query".to_string(),
+            })
+            .into();
+            #[cfg(not(feature = "result_error"))]
+            ResolverQueryResult::from(None)
+        }
+    }
+
+    fn resolve(
+        &self,
+        qname: Rc<RawDomain>,
+        qtype: u16,
+        depth: usize,
+        errors: &mut ErrorVec,
+    ) -> Option<Response> {
+        let total = qname.num_labels();
+        let mut servers = self.ordered(self.config.root_hints.clone());
+        let mut zone_labels = 0; // 当前区域切分点的label数
+        let mut known_labels = 0; // 已确认存在(但不是切分点)的祖先名称的label数
+        // 最小化查询被拒绝的区域切分点，只在该区域内改用完整名称
+        let mut fallback_cut = None;
+        let mut retries = 0;
+
+        for _ in 0..self.config.max_steps {
+            let minimise = self.config.qname_minimisation && fallback_cut != Some(zone_labels);
+            let labels = if minimise {
+                (zone_labels.max(known_labels) + 1).min(total)
+            } else {
+                total
+            };
+            let minimised = labels < total;
+            let (name, query_type) = if minimised {
+                (
+                    Rc::new(qname.base_name(labels)),
+                    self.config.minimise_qtype.as_u16(),
+                )
+            } else {
+                (qname.clone(), qtype)
+            };
+            #[cfg(feature = "logger")]
+            trace!(
+                "迭代查询 {:?} type {}, 区域深度 {}",
+                name, query_type, zone_labels
+            );
+
+            let mut response = match self.send(&servers, name.clone(), query_type, errors) {
+                Reply::Answer(response) => response,
+                Reply::Rejected if minimised => {
+                    // 部分服务器对最小化查询返回错误(RFC 9156 4)，此时在当前区域内退回完整名称
+                    #[cfg(feature = "logger")]
+                    debug!("最小化查询 {:?} 被拒绝，在当前区域内退回完整名称", name);
+                    fallback_cut = Some(zone_labels);
+                    continue;
+                }
+                Reply::NoResponse if retries < MAX_RETRIES => {
+                    retries += 1;
+                    continue;
+                }
+                Reply::Rejected | Reply::NoResponse => return None,
+            };
+            retries = 0;
+
+            // 祖先名称不存在时其下的名称也不存在(RFC 8020)，不再继续向下查询
+            if response.header.rcode == DnsRcodeNum::NXDOMAIN {
+                // 应答的问题是最小化后的祖先名称，改回原始的名称与类型
+                if minimised && let Some(question) = response.question.first_mut() {
+                    question.qname = qname.clone();
+                    question.qtype = qtype;
+                }
+                return Some(response);
+            }
+
+            if let Some((cut, ns_names)) = find_delegation(&response, &name) {
+                let cut_labels = cut.num_labels();
+                if cut_labels <= zone_labels || !qname.is_subdomain_of(cut) {
+                    #[cfg(feature = "logger")]
+                    debug!("收到向上或越界的委派，放弃解析");
+                    return None;
+                }
+                let next = self.delegation_servers(&response, cut, &ns_names, depth, errors);
+                if next.is_empty() {
+                    return None;
+                }
+                servers = next;
+                zone_labels = cut_labels;
+                known_labels = cut_labels;
+                continue;
+            }
+
+            if minimised {
+                // 祖先名称存在但不是区域切分点，继续向下增加一个label
+                known_labels = labels;
+                continue;
+            }
+            return Some(response);
+        }
+        None
+    }
+
+    fn send(
+        &self,
+        servers: &[IpAddr],
+        domain: Rc<RawDomain>,
+        qtype: u16,
+        errors: &mut ErrorVec,
+    ) -> Reply {
+        let mut rejected = false;
+        for ip in servers {
+            let addr = SocketAddr::new(*ip, self.config.port);
+            let timeout = Some(self.config.timeout);
+            let mut request = Request::new(domain.clone(), qtype);
+            request.header.rec_desired = 0;
+            for rcode in [
+                DnsRcodeNum::FORMERR,
+                DnsRcodeNum::SERVFAIL,
+                DnsRcodeNum::NXDOMAIN,
+                DnsRcodeNum::NOTIMP,
+                DnsRcodeNum::REFUSED,
+            ] {
+                request.accept_rcode(rcode);
+            }
+            #[cfg(feature = "result_error")]
+            let response =
+                match NetQuery::query_udp_pool(&self.udp, &self.tcp, addr, timeout, request)
+                    .into_index()
+                {
+                    Ok(Some(response)) => response,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        errors.push(convert_err(
+                            e,
+                            "IterativeResolver::send => NetQuery::query_udp_pool ->",
+                        ));
+                        continue;
+                    }
+                };
+            #[cfg(not(feature = "result_error"))]
+            let Some(response) =
+                NetQuery::query_udp_pool(&self.udp, &self.tcp, addr, timeout, request)
+            else {
+                continue;
+            };
+            match response.header.rcode {
+                DnsRcodeNum::NOERROR | DnsRcodeNum::NXDOMAIN => return Reply::Answer(response),
+                _rcode => {
+                    #[cfg(feature = "logger")]
+                    debug!("{} 返回rcode {}", addr, _rcode);
+                    #[cfg(feature = "result_error")]
+                    errors.push(NetError::DecodeResponseError(TraceErrorFormat {
+                        info: format!("target: {}, rcode: {}", addr, _rcode),
+                        trace: "IterativeResolver::send".to_string(),
+                    }));
+                    rejected = true;
+                }
+            }
+        }
+        if rejected {
+            Reply::Rejected
+        } else {
+            Reply::NoResponse
+        }
+    }
+
+    // 优先使用additional中的A/AAAA glue，没有glue时从根开始解析NS名称的A与AAAA记录。
+    // 只信任被委派区域之内的NS名称的glue，区域之外的名称总是单独解析，防止缓存投毒
+    fn delegation_servers(
+        &self,
+        response: &Response,
+        cut: &RawDomain,
+        ns_names: &[Rc<RawDomain>],
+        depth: usize,
+        errors: &mut ErrorVec,
+    ) -> Vec<IpAddr> {
+        let glue: Vec<IpAddr> = response
+            .additionals()
+            .iter()
+            .filter(|rec| {
+                rec.name.is_subdomain_of(cut) && ns_names.iter().any(|ns| **ns == rec.name)
+            })
+            .filter_map(|rec| record_ip(&rec.data))
+            .collect();
+        if !glue.is_empty() || depth >= MAX_DEPTH {
+            return self.ordered(glue);
+        }
+        // 先查询偏好的地址族
+        let qtypes = match self.config.preference {
+            AddressPreference::Ipv6 => [DnsTypeNum::AAAA, DnsTypeNum::A],
+            _ => [DnsTypeNum::A, DnsTypeNum::AAAA],
+        };
+        // 区域之内没有glue的NS名称需要经过这次委派才能解析，跳过
+        for ns in ns_names.iter().filter(|ns| !ns.is_subdomain_of(cut)) {
+            let mut addrs = Vec::new();
+            for qtype in qtypes {
+                if let Some(res) = self.resolve(ns.clone(), qtype, depth + 1, errors) {
+                    addrs.extend(res.answers().iter().filter_map(|rec| record_ip(&rec.data)));
+                }
+            }
+            if !addrs.is_empty() {
+                return self.ordered(addrs);
+            }
+        }
+        Vec::new()
+    }
+
+    // 按地址族偏好稳定排序
+    fn ordered(&self, mut servers: Vec<IpAddr>) -> Vec<IpAddr> {
+        servers.sort_by_key(|ip| self.config.preference.rank(ip));
+        servers
+    }
+}
+
+fn record_ip(data: &RecordDataType) -> Option<IpAddr> {
+    match data {
+        RecordDataType::A(a) => Some(IpAddr::V4(a.get_index())),
+        RecordDataType::AAAA(aaaa) => Some(IpAddr::V6(aaaa.get_index())),
+        _ => None,
+    }
+}
+
+// 从响应中找出委派: 无answer时authority中的NS，或针对查询名称本身的NS answer
+fn find_delegation<'a>(
+    response: &'a Response,
+    qname: &RawDomain,
+) -> Option<(&'a RawDomain, Vec<Rc<RawDomain>>)> {
+    let answer_ns = response
+        .answers()
+        .iter()
+        .any(|rec| matches!(rec.data, RecordDataType::NS(_)) && rec.name == *qname);
+    let section = if answer_ns {
+        response.answers()
+    } else if response.answers().is_empty() {
+        response.authorities()
+    } else {
+        return None;
+    };
+    let mut cut: Option<&RawDomain> = None;
+    let mut ns_names = Vec::new();
+    for rec in section {
+        if let RecordDataType::NS(ns) = &rec.data {
+            match &cut {
+                Some(cut) if **cut != rec.name => continue,
+                Some(_) => {}
+                None => cut = Some(&rec.name),
+            }
+            ns_names.push(ns.get_index());
+        }
+    }
+    Some((cut?, ns_names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::types::base::record::{A, AAAA, NS};
+    use crate::dns::types::parts::{MessageBuilder, Record};
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn encode_name(name: &str) -> Vec<u8> {
+        let mut v = RawDomain::from_str(name).unwrap().as_ref().clone();
+        v.push(0);
+        v
+    }
+
+    fn push_record(buf: &mut Vec<u8>, name: &str, rtype: u16, rdata: &[u8]) {
+        buf.extend_from_slice(&encode_name(name));
+        buf.extend_from_slice(&rtype.to_be_bytes());
+        buf.extend_from_slice(&1_u16.to_be_bytes());
+        buf.extend_from_slice(&300_u32.to_be_bytes());
+        buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(rdata);
+    }
+
+    fn referral(query: &[u8], question_end: usize, zone: &str, ns: &str, glue: IpAddr) -> Vec<u8> {
+        let mut buf = query[..question_end].to_vec();
+        buf[2] = 0x80;
+        buf[3] = 0x00;
+        buf[6..12].copy_from_slice(&[0, 0, 0, 1, 0, 1]);
+        push_record(&mut buf, zone, DnsTypeNum::NS, &encode_name(ns));
+        match glue {
+            IpAddr::V4(ip) => push_record(&mut buf, ns, DnsTypeNum::A, &ip.octets()),
+            IpAddr::V6(ip) => push_record(&mut buf, ns, DnsTypeNum::AAAA, &ip.octets()),
+        }
+        buf
+    }
+
+    fn answer(query: &[u8], question_end: usize, name: &str, ip: [u8; 4]) -> Vec<u8> {
+        let mut buf = query[..question_end].to_vec();
+        buf[2] = 0x84;
+        buf[3] = 0x00;
+        buf[6..12].copy_from_slice(&[0, 1, 0, 0, 0, 0]);
+        push_record(&mut buf, name, DnsTypeNum::A, &ip);
+        buf
+    }
+
+    fn rcode_only(query: &[u8], question_end: usize, rcode: u8) -> Vec<u8> {
+        let mut buf = query[..question_end].to_vec();
+        buf[2] = 0x80;
+        buf[3] = rcode;
+        buf
+    }
+
+    const LOCAL: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    const LOCAL_V6: IpAddr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    const POISON: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 99));
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mode {
+        Normal,
+        // 拒绝com的最小化查询，收到第一个完整名称时像根服务器一样委派到com
+        RefuseCom,
+        // 丢弃第一个com查询
+        DropCom,
+        // example.com委派给区域之外的ns.example.net，并附带伪造的glue
+        OutOfBailiwick,
+        // 只监听[::1]，委派只带AAAA glue
+        Ipv6Only,
+    }
+
+    // 在本地模拟根、com和example.com三级权威服务器，记录收到的每个问题。
+    // test之下的名称都不存在
+    fn spawn_server(mode: Mode) -> (u16, Arc<Mutex<Vec<(String, u16)>>>) {
+        let bind = if mode == Mode::Ipv6Only {
+            "[::1]:0"
+        } else {
+            "127.0.0.1:0"
+        };
+        let glue = if mode == Mode::Ipv6Only {
+            LOCAL_V6
+        } else {
+            LOCAL
+        };
+        let socket = UdpSocket::bind(bind).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        thread::spawn(move || {
+            let mut buf = [0_u8; 512];
+            let mut first_com = true;
+            let mut first_full = true;
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                let query = &buf[..len];
+                let mut pos = 12;
+                while query[pos] != 0 {
+                    pos += query[pos] as usize + 1;
+                }
+                let name = RawDomain::from_raw(query[12..pos].to_vec())
+                    .to_string()
+                    .unwrap();
+                let qtype = u16::from_be_bytes([query[pos + 1], query[pos + 2]]);
+                let question_end = pos + 5;
+                log.lock().unwrap().push((name.clone(), qtype));
+
+                let reply = match (mode, name.as_str()) {
+                    (Mode::DropCom, "com") if first_com => {
+                        first_com = false;
+                        continue;
+                    }
+                    (Mode::RefuseCom, "com") => {
+                        rcode_only(query, question_end, DnsRcodeNum::REFUSED)
+                    }
+                    (Mode::RefuseCom, "www.example.com") if first_full => {
+                        first_full = false;
+                        referral(query, question_end, "com", "a.gtld.com", LOCAL)
+                    }
+                    (_, "com") => referral(query, question_end, "com", "a.gtld.com", glue),
+                    (Mode::OutOfBailiwick, "example.com") => {
+                        referral(query, question_end, "example.com", "ns.example.net", POISON)
+                    }
+                    (_, "example.com") => {
+                        referral(query, question_end, "example.com", "ns.example.com", glue)
+                    }
+                    (_, "ns.example.net") => answer(query, question_end, &name, [127, 0, 0, 1]),
+                    (_, "test") => rcode_only(query, question_end, DnsRcodeNum::NXDOMAIN),
+                    _ => answer(query, question_end, &name, [192, 0, 2, 1]),
+                };
+                socket.send_to(&reply, peer).unwrap();
+            }
+        });
+        (port, seen)
+    }
+
+    fn local_config(port: u16) -> IterativeConfig {
+        IterativeConfig {
+            root_hints: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            port,
+            timeout: Duration::from_secs(2),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_qname_minimisation() {
+        let (port, seen) = spawn_server(Mode::Normal);
+        let resolver = IterativeResolver::new(local_config(port));
+        let result = resolver.query("www.example.com".to_string(), DnsTypeNum::A);
+        assert_eq!(result.get_a_record(), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("com".to_string(), DnsTypeNum::A),
+                ("example.com".to_string(), DnsTypeNum::A),
+                ("www.example.com".to_string(), DnsTypeNum::A),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimise_with_ns() {
+        let (port, seen) = spawn_server(Mode::Normal);
+        let config = IterativeConfig {
+            minimise_qtype: MinimiseQType::NS,
+            ..local_config(port)
+        };
+        let result =
+            IterativeResolver::new(config).query("www.example.com".to_string(), DnsTypeNum::A);
+        assert!(result.get_a_record().is_some());
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0], ("com".to_string(), DnsTypeNum::NS));
+        assert_eq!(seen[1], ("example.com".to_string(), DnsTypeNum::NS));
+    }
+
+    #[test]
+    fn test_fallback_to_full_name() {
+        let (port, seen) = spawn_server(Mode::RefuseCom);
+        let resolver = IterativeResolver::new(local_config(port));
+        let result = resolver.query("www.example.com".to_string(), DnsTypeNum::A);
+        assert!(result.get_a_record().is_some());
+        // 只在根区域内使用完整名称，进入com之后恢复最小化
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("com".to_string(), DnsTypeNum::A),
+                ("www.example.com".to_string(), DnsTypeNum::A),
+                ("example.com".to_string(), DnsTypeNum::A),
+                ("www.example.com".to_string(), DnsTypeNum::A),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_out_of_bailiwick_glue() {
+        let (port, seen) = spawn_server(Mode::OutOfBailiwick);
+        let config = IterativeConfig {
+            timeout: Duration::from_millis(300),
+            ..local_config(port)
+        };
+        let result =
+            IterativeResolver::new(config).query("www.example.com".to_string(), DnsTypeNum::A);
+        assert_eq!(result.get_a_record(), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        let seen = seen.lock().unwrap();
+        assert!(seen.contains(&("ns.example.net".to_string(), DnsTypeNum::A)));
+        assert_eq!(
+            seen.last(),
+            Some(&("www.example.com".to_string(), DnsTypeNum::A))
+        );
+    }
+
+    #[test]
+    fn test_timeout_keeps_minimised() {
+        let (port, seen) = spawn_server(Mode::DropCom);
+        let config = IterativeConfig {
+            timeout: Duration::from_millis(300),
+            ..local_config(port)
+        };
+        let result =
+            IterativeResolver::new(config).query("www.example.com".to_string(), DnsTypeNum::A);
+        assert!(result.get_a_record().is_some());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("com".to_string(), DnsTypeNum::A),
+                ("com".to_string(), DnsTypeNum::A),
+                ("example.com".to_string(), DnsTypeNum::A),
+                ("www.example.com".to_string(), DnsTypeNum::A),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stop_on_nxdomain() {
+        let (port, seen) = spawn_server(Mode::Normal);
+        let resolver = IterativeResolver::new(local_config(port));
+        let result = resolver.query("www.nx.test".to_string(), DnsTypeNum::AAAA);
+        let response = result.get_result().unwrap();
+        assert_eq!(response.header.rcode, DnsRcodeNum::NXDOMAIN);
+        let question = &response.question[0];
+        assert_eq!(
+            RawDomain::to_string(&question.qname).unwrap(),
+            "www.nx.test"
+        );
+        assert_eq!(question.qtype, DnsTypeNum::AAAA);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("test".to_string(), DnsTypeNum::A)]
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        let (port, seen) = spawn_server(Mode::Normal);
+        let config = IterativeConfig {
+            qname_minimisation: false,
+            ..local_config(port)
+        };
+        let result =
+            IterativeResolver::new(config).query("www.example.com".to_string(), DnsTypeNum::A);
+        assert!(result.get_a_record().is_some());
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ipv6_glue() {
+        let (port, seen) = spawn_server(Mode::Ipv6Only);
+        let config = IterativeConfig {
+            root_hints: vec![LOCAL_V6],
+            ..local_config(port)
+        };
+        let result =
+            IterativeResolver::new(config).query("www.example.com".to_string(), DnsTypeNum::A);
+        assert_eq!(result.get_a_record(), Some(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(seen.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_glue_preference() {
+        let ns = Rc::new(RawDomain::from_str("ns.example.com").unwrap());
+        let mut buf = [0_u8; 512];
+        let message = MessageBuilder::new()
+            .response(true)
+            .authority(Record::from_data(
+                RawDomain::from_str("example.com").unwrap(),
+                1,
+                300,
+                RecordDataType::NS(NS::new(ns.clone())),
+            ))
+            .additional(Record::from_data(
+                RawDomain::from_str("ns.example.com").unwrap(),
+                1,
+                300,
+                RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 53))),
+            ))
+            .additional(Record::from_data(
+                RawDomain::from_str("ns.example.com").unwrap(),
+                1,
+                300,
+                RecordDataType::AAAA(AAAA::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53))),
+            ))
+            .encode_to_udp(&mut buf);
+        let response = Response::from_slice_uncheck(message).unwrap();
+        let cut = RawDomain::from_str("example.com").unwrap();
+        let v4: IpAddr = "192.0.2.53".parse().unwrap();
+        let v6: IpAddr = "2001:db8::53".parse().unwrap();
+        for (preference, expected) in [
+            (AddressPreference::None, [v4, v6]),
+            (AddressPreference::Ipv4, [v4, v6]),
+            (AddressPreference::Ipv6, [v6, v4]),
+        ] {
+            let resolver = IterativeResolver::new(IterativeConfig {
+                preference,
+                ..Default::default()
+            });
+            #[cfg(feature = "result_error")]
+            let mut errors: ErrorVec = Vec::new();
+            #[cfg(not(feature = "result_error"))]
+            let mut errors: ErrorVec = ();
+            let servers =
+                resolver.delegation_servers(&response, &cut, &[ns.clone()], 0, &mut errors);
+            assert_eq!(servers, expected);
+        }
+        let hints = IterativeConfig::default().root_hints;
+        assert_eq!(hints.len(), 26);
+        assert!(hints[13..].iter().all(IpAddr::is_ipv6));
+    }
+}
@@ -0,0 +1,506 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::net::NetQuery;
+use crate::dns::resolver::Resolver;
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::types::parts::{RecordDataType, Request, Response};
+use crate::dns::utils::ServerType;
+use std::cmp::Ordering;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::rc::Rc;
+
+// lookup_ip查询的记录类型与返回地址的排列方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LookupIpStrategy {
+    // 同时查询A与AAAA，按RFC 6724排序后两个地址族交替排列(RFC 8305 4)
+    #[default]
+    Ipv4AndIpv6,
+    Ipv4Only,
+    Ipv6Only,
+    // 所有IPv6地址排在IPv4地址之前，地址族内按RFC 6724排序
+    PreferIpv6,
+}
+
+// RFC 6724 2.1的默认策略表，按前缀长度从长到短排列，第一个匹配的即为最长匹配
+const POLICY_TABLE: [(u128, u8, u8, u8); 9] = [
+    (0x0000_0000_0000_0000_0000_0000_0000_0001, 128, 50, 0),
+    (0x0000_0000_0000_0000_0000_ffff_0000_0000, 96, 35, 4),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 96, 1, 3),
+    (0x2001_0000_0000_0000_0000_0000_0000_0000, 32, 5, 5),
+    (0x2002_0000_0000_0000_0000_0000_0000_0000, 16, 30, 2),
+    (0x3ffe_0000_0000_0000_0000_0000_0000_0000, 16, 1, 12),
+    (0xfec0_0000_0000_0000_0000_0000_0000_0000, 10, 1, 11),
+    (0xfc00_0000_0000_0000_0000_0000_0000_0000, 7, 3, 13),
+    (0x0000_0000_0000_0000_0000_0000_0000_0000, 0, 40, 1),
+];
+
+// 可以直接传给TcpStream::connect等接受ToSocketAddrs的函数，解析时使用Resolver::lookup_ip
+pub struct SocketAddrs<'a> {
+    resolver: &'a Resolver,
+    host: String,
+    port: u16,
+}
+
+impl ToSocketAddrs for SocketAddrs<'_> {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        let addrs: Vec<SocketAddr> = self
+            .resolver
+            .lookup_ip(self.host.clone())
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, self.port))
+            .collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("failed to lookup address information: {}", self.host),
+            ));
+        }
+        Ok(addrs.into_iter())
+    }
+}
+
+impl Resolver {
+    pub fn with_lookup_strategy(mut self, strategy: LookupIpStrategy) -> Resolver {
+        self.lookup_strategy = strategy;
+        self
+    }
+
+    // 返回的地址已按连接时应尝试的顺序排列，name是IP地址时直接返回
+    pub fn lookup_ip(&self, name: String) -> Vec<IpAddr> {
+        if let Ok(ip) = name.trim_start_matches('[').trim_end_matches(']').parse() {
+            return vec![ip];
+        }
+        let qtypes: &[u16] = match self.lookup_strategy {
+            LookupIpStrategy::Ipv4Only => &[DnsTypeNum::A],
+            LookupIpStrategy::Ipv6Only => &[DnsTypeNum::AAAA],
+            _ => &[DnsTypeNum::A, DnsTypeNum::AAAA],
+        };
+        let mut addrs: Vec<IpAddr> = Vec::new();
+        for response in self.query_concurrent(&name, qtypes).iter().flatten() {
+            for record in response.answers() {
+                let ip = match &record.data {
+                    RecordDataType::A(a) => a.get_general_output().map(IpAddr::V4),
+                    RecordDataType::AAAA(aaaa) => aaaa.get_general_output().map(IpAddr::V6),
+                    _ => None,
+                };
+                if let Some(ip) = ip
+                    && !addrs.contains(&ip)
+                {
+                    addrs.push(ip);
+                }
+            }
+        }
+        sort_destinations(&mut addrs, |ip| self.source_addr(ip));
+        match self.lookup_strategy {
+            LookupIpStrategy::Ipv4AndIpv6 => interleave(addrs),
+            LookupIpStrategy::PreferIpv6 => {
+                addrs.sort_by_key(|ip| ip.is_ipv4());
+                addrs
+            }
+            _ => addrs,
+        }
+    }
+
+    // 与std::net的ToSocketAddrs对应，host可以是域名或IP地址
+    pub fn socket_addrs(&self, host: &str, port: u16) -> SocketAddrs<'_> {
+        SocketAddrs {
+            resolver: self,
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    // 解析"host:port"或"[v6]:port"形式的目标，与&str的to_socket_addrs对应
+    pub fn lookup_socket_addrs(&self, target: &str) -> io::Result<std::vec::IntoIter<SocketAddr>> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address");
+        let (host, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse().map_err(|_| invalid())?;
+        self.socket_addrs(host, port).to_socket_addrs()
+    }
+
+    // 在同一服务器上同时发出所有查询：UDP一次发出，TCP与DoT在复用的连接上流水线发送，
+    // DoH与DoQ每个查询各占一条流。DNSCrypt依次查询。某个类型没有得到响应时在下一个服务器上重试
+    fn query_concurrent(&self, name: &str, qtypes: &[u16]) -> Vec<Option<Response>> {
+        let mut results: Vec<Option<Response>> = qtypes.iter().map(|_| None).collect();
+        let Some(domain) = RawDomain::from_str(name) else {
+            return results;
+        };
+        let domain = Rc::new(domain);
+        for server in self.ordered_servers(&domain) {
+            let pending: Vec<usize> = (0..qtypes.len())
+                .filter(|&i| results[i].is_none())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            let Some(addr) = self.server_socket_addr(server.addr()) else {
+                continue;
+            };
+            let timeout = server.addr().timeout;
+            let requests: Vec<Request> = pending
+                .iter()
+                .map(|&i| Request::new(domain.clone(), qtypes[i]))
+                .collect();
+            let responses = match server {
+                ServerType::Udp(_) => NetQuery::query_udp_pipelined(
+                    &self.udp_pool,
+                    &self.tcp_pool,
+                    addr,
+                    timeout,
+                    requests,
+                ),
+                ServerType::Tcp(_) => {
+                    NetQuery::query_tcp_pipelined(&self.tcp_pool, addr, timeout, requests)
+                }
+                #[cfg(feature = "tls")]
+                ServerType::Tls(server_addr) => {
+                    // 没有域名时用IP地址校验证书
+                    let name = server_addr
+                        .server_name()
+                        .map_or_else(|| addr.ip().to_string(), str::to_string);
+                    NetQuery::query_tls_pipelined(&self.tls, addr, &name, timeout, requests)
+                }
+                #[cfg(feature = "https")]
+                ServerType::Https(server_addr) => {
+                    let name = server_addr
+                        .server_name()
+                        .map_or_else(|| addr.ip().to_string(), str::to_string);
+                    let path = server_addr.path.as_str();
+                    NetQuery::query_https_pipelined(
+                        &self.https,
+                        addr,
+                        &name,
+                        path,
+                        timeout,
+                        requests,
+                    )
+                }
+                #[cfg(feature = "quic")]
+                ServerType::Quic(server_addr) => {
+                    let name = server_addr
+                        .server_name()
+                        .map_or_else(|| addr.ip().to_string(), str::to_string);
+                    NetQuery::query_quic_pipelined(&self.quic, addr, &name, timeout, requests)
+                }
+                _ => {
+                    // query_with自身会依次尝试所有服务器
+                    for i in pending {
+                        results[i] = self.query(name.to_string(), qtypes[i]).into_result();
+                    }
+                    break;
+                }
+            };
+            for (i, response) in pending.into_iter().zip(responses) {
+                #[cfg(feature = "result_error")]
+                let response = response.into_result();
+                results[i] = response;
+            }
+        }
+        results
+    }
+
+    // 连接UDP socket不会发出报文，只让系统按路由选出源地址
+    fn source_addr(&self, destination: IpAddr) -> Option<IpAddr> {
+        let socket = self.bind.udp_socket(destination.is_ipv6()).ok()?;
+        socket.connect(SocketAddr::new(destination, 53)).ok()?;
+        Some(socket.local_addr().ok()?.ip())
+    }
+}
+
+// RFC 6724 6，省略了需要额外系统信息的规则3、4、7。sort_by是稳定排序，满足规则10
+fn sort_destinations<F: Fn(IpAddr) -> Option<IpAddr>>(addrs: &mut [IpAddr], source: F) {
+    let mut keyed: Vec<(IpAddr, Option<IpAddr>)> =
+        addrs.iter().map(|&ip| (ip, source(ip))).collect();
+    keyed.sort_by(|a, b| compare_destinations(*a, *b));
+    for (addr, (ip, _)) in addrs.iter_mut().zip(keyed) {
+        *addr = ip;
+    }
+}
+
+fn compare_destinations(a: (IpAddr, Option<IpAddr>), b: (IpAddr, Option<IpAddr>)) -> Ordering {
+    let ((da, sa), (db, sb)) = (a, b);
+    // 规则1: 没有可用源地址的排在后面
+    let rule1 = sa.is_none().cmp(&sb.is_none());
+    // 规则2: 优先源地址与目标作用域相同的
+    let same_scope = |d: IpAddr, s: Option<IpAddr>| s.is_some_and(|s| scope(d) == scope(s));
+    let rule2 = same_scope(db, sb).cmp(&same_scope(da, sa));
+    // 规则5: 优先源地址与目标标签相同的
+    let same_label = |d: IpAddr, s: Option<IpAddr>| s.is_some_and(|s| policy(d).1 == policy(s).1);
+    let rule5 = same_label(db, sb).cmp(&same_label(da, sa));
+    // 规则6: 优先级高的在前
+    let rule6 = policy(db).0.cmp(&policy(da).0);
+    // 规则8: 作用域小的在前
+    let rule8 = scope(da).cmp(&scope(db));
+    // 规则9: IPv6目标与源地址公共前缀长的在前
+    let rule9 = match (da, sa, db, sb) {
+        (IpAddr::V6(da), Some(IpAddr::V6(sa)), IpAddr::V6(db), Some(IpAddr::V6(sb))) => {
+            common_prefix_len(db, sb).cmp(&common_prefix_len(da, sa))
+        }
+        _ => Ordering::Equal,
+    };
+    rule1
+        .then(rule2)
+        .then(rule5)
+        .then(rule6)
+        .then(rule8)
+        .then(rule9)
+}
+
+// IPv4地址按IPv4映射地址查策略表(RFC 6724 2.1)
+fn policy(ip: IpAddr) -> (u8, u8) {
+    let addr = u128::from(to_ipv6(ip));
+    POLICY_TABLE
+        .iter()
+        .find(|(prefix, len, _, _)| *len == 0 || (addr ^ prefix) >> (128 - *len as u32) == 0)
+        .map_or((40, 1), |&(_, _, precedence, label)| (precedence, label))
+}
+
+// RFC 6724 3.1与3.2，2为链路本地，5为站点本地，14为全局
+fn scope(ip: IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(ip) if ip.is_loopback() || ip.is_link_local() => 2,
+        IpAddr::V4(_) => 14,
+        IpAddr::V6(ip) if ip.is_multicast() => ip.octets()[1] & 0x0f,
+        IpAddr::V6(ip) if ip.is_loopback() || ip.is_unicast_link_local() => 2,
+        IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfec0 => 5,
+        IpAddr::V6(_) => 14,
+    }
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn common_prefix_len(a: Ipv6Addr, b: Ipv6Addr) -> u32 {
+    (u128::from(a) ^ u128::from(b)).leading_zeros()
+}
+
+// RFC 8305 4，从排在最前的地址族开始，两个地址族交替
+fn interleave(addrs: Vec<IpAddr>) -> Vec<IpAddr> {
+    let Some(first) = addrs.first().copied() else {
+        return addrs;
+    };
+    let (mut preferred, mut other): (Vec<IpAddr>, Vec<IpAddr>) = addrs
+        .into_iter()
+        .partition(|ip| ip.is_ipv6() == first.is_ipv6());
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    preferred.reverse();
+    other.reverse();
+    loop {
+        match (preferred.pop(), other.pop()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::types::base::record::{A, AAAA};
+    use crate::dns::types::parts::{MessageBuilder, Record};
+    use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, UdpSocket};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    // RFC 6724 10.2中的例子
+    #[test]
+    fn test_sort_destinations() {
+        let sort = |addrs: &[&str], sources: &[(&str, &str)]| {
+            let mut addrs: Vec<IpAddr> = addrs.iter().map(|s| ip(s)).collect();
+            sort_destinations(&mut addrs, |d| {
+                sources
+                    .iter()
+                    .find(|(dst, _)| ip(dst) == d)
+                    .map(|(_, src)| ip(src))
+            });
+            addrs
+        };
+        // 优先匹配作用域
+        assert_eq!(
+            sort(
+                &["2001:db8:1::1", "fe80::1"],
+                &[("2001:db8:1::1", "fe80::2"), ("fe80::1", "fe80::2")]
+            ),
+            vec![ip("fe80::1"), ip("2001:db8:1::1")]
+        );
+        // 优先级: 原生IPv6优先于IPv4
+        assert_eq!(
+            sort(
+                &["198.51.100.121", "2001:db8:1::1"],
+                &[
+                    ("2001:db8:1::1", "2001:db8:1::2"),
+                    ("198.51.100.121", "198.51.100.117")
+                ]
+            ),
+            vec![ip("2001:db8:1::1"), ip("198.51.100.121")]
+        );
+        // 没有IPv6源地址时IPv6目标不可用
+        assert_eq!(
+            sort(
+                &["2001:db8:1::1", "198.51.100.121"],
+                &[("198.51.100.121", "198.51.100.117")]
+            ),
+            vec![ip("198.51.100.121"), ip("2001:db8:1::1")]
+        );
+        // 最长前缀匹配
+        assert_eq!(
+            sort(
+                &["2001:db8:1::1", "2001:db8:3ffe::1"],
+                &[
+                    ("2001:db8:1::1", "2001:db8:3f44::2"),
+                    ("2001:db8:3ffe::1", "2001:db8:3f44::2")
+                ]
+            ),
+            vec![ip("2001:db8:3ffe::1"), ip("2001:db8:1::1")]
+        );
+        // 标签匹配: ULA源地址优先ULA目标
+        assert_eq!(
+            sort(
+                &["2001:db8:1::1", "fd00::1"],
+                &[("2001:db8:1::1", "fd00::2"), ("fd00::1", "fd00::2")]
+            ),
+            vec![ip("fd00::1"), ip("2001:db8:1::1")]
+        );
+    }
+
+    #[test]
+    fn test_interleave() {
+        let addrs = vec![ip("2001:db8::1"), ip("2001:db8::2"), ip("192.0.2.1")];
+        assert_eq!(
+            interleave(addrs),
+            vec![ip("2001:db8::1"), ip("192.0.2.1"), ip("2001:db8::2")]
+        );
+        assert!(interleave(Vec::new()).is_empty());
+    }
+
+    // 收到batch个查询后才一并回复，batch为2时可以确认A与AAAA是并发发出的
+    fn serve(batch: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+        let size = batch;
+        std::thread::spawn(move || {
+            let mut buf = [0_u8; 1500];
+            let mut batch = Vec::new();
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                counter.fetch_add(1, AtomicOrdering::SeqCst);
+                batch.push((buf[..len].to_vec(), peer));
+                if batch.len() < size {
+                    continue;
+                }
+                for (query, peer) in batch.drain(..) {
+                    let _ = socket.send_to(&answer(&query), peer);
+                }
+            }
+        });
+        (addr, queries)
+    }
+
+    fn answer(query: &[u8]) -> Vec<u8> {
+        let query = Response::from_slice_uncheck(query).unwrap();
+        let question = &query.question[0];
+        let name = || RawDomain::from_raw(question.qname.as_ref().as_ref().clone());
+        let data = if question.qtype == DnsTypeNum::AAAA {
+            vec![RecordDataType::AAAA(AAAA::new(Ipv6Addr::LOCALHOST))]
+        } else {
+            vec![
+                RecordDataType::A(A::new(Ipv4Addr::LOCALHOST)),
+                RecordDataType::A(A::new(Ipv4Addr::new(127, 0, 0, 2))),
+            ]
+        };
+        let mut buf = [0_u8; 512];
+        let mut builder = MessageBuilder::new()
+            .id(query.header.id)
+            .response(true)
+            .rec_desired(true)
+            .rec_avail(true)
+            .question(Rc::new(name()), question.qtype, question.qclass);
+        for data in data {
+            builder = builder.answer(Record::from_data(name(), 1, 60, data));
+        }
+        builder.encode_to_udp(&mut buf).to_vec()
+    }
+
+    fn resolver(addr: SocketAddr) -> Resolver {
+        Resolver::new(vec![format!("udp://{}?timeout=500ms", addr)]).unwrap()
+    }
+
+    #[test]
+    fn test_lookup_ip() {
+        let (v4, v6) = (vec![ip("127.0.0.1"), ip("127.0.0.2")], vec![ip("::1")]);
+        // 没有IPv6环回地址时::1不可用，按RFC 6724排在IPv4之后
+        let interleaved = if resolver(SocketAddr::from(([127, 0, 0, 1], 53)))
+            .source_addr(v6[0])
+            .is_some()
+        {
+            vec![v6[0], v4[0], v4[1]]
+        } else {
+            vec![v4[0], v6[0], v4[1]]
+        };
+
+        let (addr, queries) = serve(2);
+        let addrs = resolver(addr).lookup_ip("example.com".to_string());
+        assert_eq!(queries.load(AtomicOrdering::SeqCst), 2);
+        assert_eq!(addrs, interleaved);
+
+        let (addr, queries) = serve(1);
+        let lookup = |strategy| {
+            resolver(addr)
+                .with_lookup_strategy(strategy)
+                .lookup_ip("example.com".to_string())
+        };
+        assert_eq!(lookup(LookupIpStrategy::Ipv4Only), v4);
+        assert_eq!(queries.load(AtomicOrdering::SeqCst), 1);
+        assert_eq!(lookup(LookupIpStrategy::Ipv6Only), v6);
+        assert_eq!(queries.load(AtomicOrdering::SeqCst), 2);
+        assert_eq!(lookup(LookupIpStrategy::Ipv4AndIpv6), interleaved);
+        assert_eq!(
+            lookup(LookupIpStrategy::PreferIpv6),
+            vec![v6[0], v4[0], v4[1]]
+        );
+        assert_eq!(queries.load(AtomicOrdering::SeqCst), 6);
+
+        assert_eq!(
+            resolver(addr).lookup_ip("192.0.2.1".to_string()),
+            vec![ip("192.0.2.1")]
+        );
+    }
+
+    #[test]
+    fn test_socket_addrs() {
+        let (addr, _) = serve(2);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let resolver = resolver(addr).with_lookup_strategy(LookupIpStrategy::PreferIpv6);
+        let addrs: Vec<SocketAddr> = resolver
+            .lookup_socket_addrs(&format!("example.com:{}", port))
+            .unwrap()
+            .collect();
+        assert_eq!(addrs.len(), 3);
+        assert!(addrs.iter().all(|addr| addr.port() == port));
+        let literal: Vec<SocketAddr> = resolver
+            .socket_addrs("127.0.0.1", port)
+            .to_socket_addrs()
+            .unwrap()
+            .collect();
+        assert_eq!(literal, vec![SocketAddr::new(ip("127.0.0.1"), port)]);
+        // ::1上没有监听，connect会继续尝试127.0.0.1
+        assert!(
+            std::net::TcpStream::connect(resolver.socket_addrs("localhost.test", port)).is_ok()
+        );
+        assert!(resolver.lookup_socket_addrs("example.com").is_err());
+    }
+}
@@ -5,10 +5,17 @@ mod fmt;
 pub mod record;
 mod string;
 
-#[cfg(feature = "fmt")]
+pub use dns_type::DnsOpcode;
+pub use dns_type::DnsRcodeNum;
 pub use dns_type::DnsType;
 pub use dns_type::DnsTypeNum;
-pub use domain::RawDomain;
+#[allow(unused_imports)]
+pub use domain::{
+    IdnaConfig, IdnaError, Labels, MAX_LABEL_LEN, MAX_NAME_LEN, NameRef, NameRefLabels, RawDomain,
+};
+pub(crate) use domain::{
+    base_name, canonical_cmp, common_labels, is_subdomain_of, is_wildcard, label_count,
+};
 #[cfg(feature = "fmt")]
-pub use fmt::{DnsClass, DnsTTL};
+pub use fmt::{DnsClass, DnsRcode, DnsSecAlgorithm, DnsSecDigest, DnsTTL};
 pub use string::RawString;
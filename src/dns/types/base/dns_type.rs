@@ -14,6 +14,7 @@ macro_rules! make_dns_type {
         //         AAAA,
         //      }
 
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
         pub enum DnsType {
             $(
                 $field,
@@ -56,7 +57,6 @@ macro_rules! make_dns_type {
         //  }
 
         impl DnsType {
-            #[cfg(feature = "fmt")]
             pub fn from_u16(dns_type: u16) -> Option<DnsType> {
                 match dns_type {
                     $(
@@ -65,6 +65,25 @@ macro_rules! make_dns_type {
                     _ => None,
                 }
             }
+
+            #[inline]
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(
+                        DnsType::$field => stringify!($field),
+                    )*
+                }
+            }
+
+            // 按助记符查找，不区分大小写
+            pub fn from_name(name: &str) -> Option<DnsType> {
+                $(
+                    if name.eq_ignore_ascii_case(stringify!($field)) {
+                        return Some(DnsType::$field);
+                    }
+                )*
+                None
+            }
         }
 
         // #[cfg(feature = "fmt")]
@@ -94,18 +113,16 @@ macro_rules! make_dns_type {
         #[cfg(feature = "fmt")]
         impl Display for DnsType {
             fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-                match self {
-                    $(
-                        DnsType::$field => write!(f, stringify!($field))?,
-                    )*
-                }
-                Ok(())
+                f.write_str(self.as_str())
             }
         }
     }
 }
 
-make_dns_type!(A, NS, CNAME, SOA, TXT, AAAA);
+make_dns_type!(
+    A, NS, CNAME, SOA, PTR, MX, TXT, AAAA, SRV, OPT, DS, RRSIG, NSEC, DNSKEY, NSEC3, NSEC3PARAM,
+    CDS, CDNSKEY, SVCB, HTTPS, CAA
+);
 
 // todo
 pub struct DnsTypeNum;
@@ -115,6 +132,44 @@ impl DnsTypeNum {
     pub const NS: u16 = 2;
     pub const CNAME: u16 = 5;
     pub const SOA: u16 = 6;
+    pub const PTR: u16 = 12;
+    pub const MX: u16 = 15;
     pub const TXT: u16 = 16;
     pub const AAAA: u16 = 28;
+    pub const SRV: u16 = 33;
+    pub const OPT: u16 = 41;
+    pub const DS: u16 = 43;
+    pub const RRSIG: u16 = 46;
+    pub const NSEC: u16 = 47;
+    pub const DNSKEY: u16 = 48;
+    pub const NSEC3: u16 = 50;
+    pub const NSEC3PARAM: u16 = 51;
+    pub const CDS: u16 = 59;
+    pub const CDNSKEY: u16 = 60;
+    pub const SVCB: u16 = 64;
+    pub const HTTPS: u16 = 65;
+    pub const CAA: u16 = 257;
+}
+
+// 头部的4bit OPCODE(RFC 1035 4.1.1、RFC 1996、RFC 2136)
+pub struct DnsOpcode;
+
+impl DnsOpcode {
+    pub const QUERY: u8 = 0;
+    pub const IQUERY: u8 = 1;
+    pub const STATUS: u8 = 2;
+    pub const NOTIFY: u8 = 4;
+    pub const UPDATE: u8 = 5;
+}
+
+// header中的4bit rcode
+pub struct DnsRcodeNum;
+
+impl DnsRcodeNum {
+    pub const NOERROR: u8 = 0;
+    pub const FORMERR: u8 = 1;
+    pub const SERVFAIL: u8 = 2;
+    pub const NXDOMAIN: u8 = 3;
+    pub const NOTIMP: u8 = 4;
+    pub const REFUSED: u8 = 5;
 }
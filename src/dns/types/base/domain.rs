@@ -1,183 +1,278 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceReader};
 #[cfg(feature = "logger")]
 use log::{debug, trace};
+pub use name_ref::{NameRef, NameRefLabels};
+use smallvec::SmallVec;
+use std::cmp::Ordering;
 #[cfg(feature = "fmt")]
 use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+pub use uts46::{IdnaConfig, IdnaError};
 
-#[derive(PartialEq, Debug)]
+mod name_ref;
+mod uts46;
+
+// 相等、哈希与排序均忽略ASCII大小写，排序遵循RFC 4034 6.1
+#[derive(Debug)]
 pub struct RawDomain {
     domain: Vec<u8>, //不包含最后的0x0
 }
 const SUFFIX: &[u8] = "xn--".as_bytes();
+// 单个label与整个域名(含结尾0x0)的长度上限(RFC 1035 2.3.4)
+pub const MAX_LABEL_LEN: usize = 63;
+pub const MAX_NAME_LEN: usize = 255;
+
+// 按从左到右的顺序遍历label，不包含结尾的空label
+#[derive(Clone, Debug)]
+pub struct Labels<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&len, rest) = self.remaining.split_first()?;
+        let (label, rest) = rest.split_at((len as usize).min(rest.len()));
+        self.remaining = rest;
+        Some(label)
+    }
+}
+
 impl RawDomain {
     pub fn as_ref(&self) -> &Vec<u8> {
         &self.domain
     }
 
-    pub fn from_str<T: AsRef<str>>(s: T) -> Option<RawDomain> {
-        //不带0x0
-        let s = s.as_ref();
-        let vec = s
-            .split('.')
-            .try_fold(Vec::with_capacity(20), |mut v: Vec<u8>, str| {
-                if str.len() == 0 {
-                    return Some(v);
-                }
-                if str.is_ascii() {
-                    v.push(str.len() as u8);
-                    v.extend_from_slice(str.as_bytes());
-                } else {
-                    match punycode::encode(str) {
-                        Ok(s) => {
-                            let mut len = SUFFIX.len() as u8;
-                            let bytes = s.as_bytes();
-                            len += bytes.len() as u8;
-                            v.push(len);
-                            v.extend_from_slice(SUFFIX);
-                            v.extend_from_slice(bytes);
-                        }
-                        Err(_) => return None,
-                    }
-                }
-                Some(v)
-            })?;
-        Some(RawDomain { domain: vec })
+    // 直接使用已编码好的label序列(不带0x0)，调用者需保证格式正确
+    #[inline]
+    pub(crate) fn from_raw(domain: Vec<u8>) -> RawDomain {
+        RawDomain { domain }
     }
 
-    // 主解析逻辑
-    #[inline(always)]
-    fn parse_labels<F>(reader: &mut SliceReader, handle_label: F) -> Option<(Vec<u8>, u8)>
-    where
-        F: Fn(usize) -> bool, // 返回false表示需要终止解析
-    {
-        let mut domain = Vec::with_capacity(30);
-        let mut max_pos = reader.pos();
-        let mut visited_num = 0x1_u32; //用移位判断循环次数
-
-        loop {
-            let start_pos = reader.pos();
-            visited_num = visited_num << 1;
-            // 防止指针循环
-            if visited_num == 0 {
-                #[cfg(feature = "logger")]
-                trace!("检测到循环指针");
-                return None;
-            }
+    // 转为小写后的域名
+    #[inline]
+    pub fn to_canonical(&self) -> RawDomain {
+        RawDomain {
+            domain: self.domain.to_ascii_lowercase(),
+        }
+    }
 
-            if !handle_label(reader.pos()) {
-                break; //记得后面要检查domain大小
-            }
+    // RFC 4034 6.2 规范形式：小写、不压缩、带结尾的0x0
+    pub fn to_canonical_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::with_capacity(self.domain.len() + 1);
+        wire.extend(self.domain.iter().map(u8::to_ascii_lowercase));
+        wire.push(0x0);
+        wire
+    }
 
-            let label_len = reader.peek_u8();
+    #[inline]
+    pub fn labels(&self) -> Labels<'_> {
+        labels(&self.domain)
+    }
 
-            // 处理指针
-            if (label_len & 0b1100_0000_u8) == 0b1100_0000_u8 {
-                let pointer_pos = reader.read_u16() as usize & 0x_3FFF;
-                if pointer_pos > reader.len() {
-                    #[cfg(feature = "logger")]
-                    trace!("parse_labels中处理指针时出界");
-                    return None;
-                }
-                max_pos = max_pos.max(reader.pos());
-                reader.set_pos(pointer_pos);
-                continue;
-            }
-            reader.skip(1);
+    #[inline]
+    pub fn num_labels(&self) -> usize {
+        label_count(&self.domain)
+    }
 
-            // 处理结束标记
-            if label_len == 0 {
-                max_pos = max_pos.max(reader.pos());
-                break;
-            }
+    #[inline]
+    pub fn is_root(&self) -> bool {
+        self.domain.is_empty()
+    }
 
-            // 处理普通标签
-            let end_pos = reader.pos() + label_len as usize;
-            if end_pos > reader.len() {
-                #[cfg(feature = "logger")]
-                trace!("parse_labels中处理普通标签时出界");
-                return None;
-            }
+    // 去掉最左侧的label，根域名没有父域名
+    pub fn parent(&self) -> Option<RawDomain> {
+        let len = *self.domain.first()? as usize;
+        Some(RawDomain {
+            domain: self.domain[len + 1..].to_vec(),
+        })
+    }
+
+    // 取最后n个label组成的域名，n超过label数时返回自身
+    #[inline]
+    pub fn base_name(&self, n: usize) -> RawDomain {
+        RawDomain {
+            domain: base_name(&self.domain, n).to_vec(),
+        }
+    }
+
+    // 与parent相等时也返回true，比较时忽略大小写
+    #[inline]
+    pub fn is_subdomain_of(&self, parent: &RawDomain) -> bool {
+        is_subdomain_of(&self.domain, &parent.domain)
+    }
 
-            domain.extend_from_slice(&reader.as_ref()[start_pos..end_pos]);
-            max_pos = max_pos.max(end_pos);
-            reader.set_pos(end_pos);
+    // 在左侧加上一个label，label为空或超出长度限制时返回None
+    pub fn prepend_label<T: AsRef<[u8]>>(&self, label: T) -> Option<RawDomain> {
+        let label = label.as_ref();
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return None;
         }
+        let mut domain = Vec::with_capacity(self.domain.len() + label.len() + 1);
+        domain.push(label.len() as u8);
+        domain.extend_from_slice(label);
+        domain.extend_from_slice(&self.domain);
+        Self::checked(domain)
+    }
 
-        Some((domain, max_pos as u8))
+    // 把suffix拼接到右侧，如 www + example.com
+    pub fn append(&self, suffix: &RawDomain) -> Option<RawDomain> {
+        let mut domain = Vec::with_capacity(self.domain.len() + suffix.domain.len());
+        domain.extend_from_slice(&self.domain);
+        domain.extend_from_slice(&suffix.domain);
+        Self::checked(domain)
     }
 
-    // 优化后的两个公开函数
-    pub fn from_reader(reader: &mut SliceReader) -> Option<RawDomain> {
-        let len = reader.len();
+    #[inline]
+    pub fn is_wildcard(&self) -> bool {
+        is_wildcard(&self.domain)
+    }
 
-        let (domain, max_pos) = Self::parse_labels(reader, |current_pos| current_pos < len)?;
+    // 通配符 *.example.com 匹配example.com之下的任意名称(不含其自身)，
+    // 是否被更近的已存在名称遮蔽(RFC 4592)需要由调用者结合区域数据判断
+    pub fn wildcard_matches(&self, name: &RawDomain) -> bool {
+        if !self.is_wildcard() {
+            return false;
+        }
+        let base = &self.domain[2..];
+        label_count(&name.domain) > label_count(base) && is_subdomain_of(&name.domain, base)
+    }
 
-        reader.set_pos(max_pos as usize);
+    #[inline]
+    fn checked(domain: Vec<u8>) -> Option<RawDomain> {
+        if domain.len() + 1 > MAX_NAME_LEN {
+            return None;
+        }
         Some(RawDomain { domain })
     }
 
-    pub fn from_reader_with_size(reader: &mut SliceReader, size: usize) -> Option<RawDomain> {
+    #[inline]
+    pub fn from_str<T: AsRef<str>>(s: T) -> Option<RawDomain> {
+        Self::from_idna(s).ok()
+    }
+
+    // 纯ASCII输入保持原样(保留大小写与下划线等)，否则按UTS #46 非过渡模式转换为A-label。
+    // ASCII输入中的A-label需要通过ToUnicode的有效性检查，与to_string的处理保持一致
+    pub fn from_idna<T: AsRef<str>>(s: T) -> Result<RawDomain, IdnaError> {
+        let s = s.as_ref();
+        if s.is_ascii() {
+            let domain = Self::from_ascii(s)?;
+            if domain.has_a_label() {
+                let ascii = domain.to_ascii_string().ok_or(IdnaError::Invalid)?;
+                uts46::to_unicode(&ascii, &IdnaConfig::default()).1?;
+            }
+            return Ok(domain);
+        }
+        let config = IdnaConfig {
+            verify_dns_length: false, // 长度在编码时检查，且允许结尾的根域名点
+            ..Default::default()
+        };
+        Self::from_ascii(&uts46::to_ascii(s, &config)?)
+    }
+
+    fn from_ascii(s: &str) -> Result<RawDomain, IdnaError> {
+        //不带0x0
+        let s = s.strip_suffix('.').unwrap_or(s);
+        let mut domain = Vec::with_capacity(s.len() + 1);
+        if !s.is_empty() {
+            for label in s.split('.') {
+                if label.is_empty() {
+                    return Err(IdnaError::EmptyLabel);
+                }
+                if label.len() > MAX_LABEL_LEN {
+                    return Err(IdnaError::LabelTooLong);
+                }
+                domain.push(label.len() as u8);
+                domain.extend_from_slice(label.as_bytes());
+            }
+        }
+        Self::checked(domain).ok_or(IdnaError::NameTooLong)
+    }
+
+    // 主解析逻辑，limit为第一次指针跳转前允许读取到的位置(不含)，
+    // 返回域名以及名称在原位置上的结束位置
+    fn parse_labels(
+        reader: &mut SliceReader,
+        limit: usize,
+    ) -> Result<(Vec<u8>, usize), DecodeError> {
+        let mut domain = Vec::with_capacity(30);
+        let end = walk_labels(reader, limit, |label| {
+            domain.push(label.len() as u8);
+            domain.extend_from_slice(label);
+        })?;
+        Ok((domain, end))
+    }
+
+    // 读取后reader位于名称之后
+    pub fn from_reader(reader: &mut SliceReader) -> Result<RawDomain, DecodeError> {
+        let (domain, name_end) = Self::parse_labels(reader, reader.len())?;
+        reader.set_pos(name_end);
+        Ok(RawDomain { domain })
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        size: usize,
+    ) -> Result<RawDomain, DecodeError> {
         let start_pos = reader.pos();
         let end_pos = start_pos + size;
 
         if end_pos > reader.len() {
             #[cfg(feature = "logger")]
             debug!("读取RDATA时出界");
-            return None;
+            return Err(DecodeError::Truncated {
+                offset: start_pos,
+                need: size,
+            });
         }
 
-        let (domain, _) = Self::parse_labels(reader, |current_pos| current_pos < end_pos)?;
+        let (domain, _) = Self::parse_labels(reader, end_pos)?;
 
         reader.set_pos(end_pos);
-        Some(RawDomain { domain })
+        Ok(RawDomain { domain })
     }
 
+    // 含有A-label时尽量显示为Unicode，转换失败则保留ASCII形式
     pub fn to_string(&self) -> Option<String> {
-        let mut string = String::with_capacity(40);
-        let mut remaining = self.domain.as_slice();
-
-        while !remaining.is_empty() {
-            let part_length = remaining[0] as usize;
-            remaining = &remaining[1..];
-
-            let part_bytes = &remaining[..part_length];
-
-            // 处理内容
-            if part_bytes.starts_with(b"xn--") {
-                // Punycode 编码的部分，解码
-                let input = std::str::from_utf8(&part_bytes[4..]).unwrap(); // 去掉 'xn--' 前缀
-                match punycode::decode(input) {
-                    Ok(decoded_part) => {
-                        string.push_str(&decoded_part);
-                    }
-                    Err(_) => {
-                        #[cfg(feature = "logger")]
-                        {
-                            debug!("punycode解码失败, 解码输入的为 {}", input);
-                        }
-                        return None;
-                    }
-                }
-            } else {
-                for byte in part_bytes {
-                    if byte.is_ascii() {
-                        string.push(*byte as char);
-                    } else {
-                        #[cfg(feature = "logger")]
-                        {
-                            debug!("domain内有非ASCII字符:{},{}", *byte as char, *byte);
-                        }
-                        return None;
-                    }
-                }
+        let ascii = self.to_ascii_string()?;
+        if self.has_a_label() {
+            if let (unicode, Ok(())) = uts46::to_unicode(&ascii, &IdnaConfig::default()) {
+                return Some(unicode);
             }
+            #[cfg(feature = "logger")]
+            debug!("A-label无法转换为Unicode: {}", ascii);
+        }
+        Some(ascii)
+    }
 
-            if part_length != remaining.len() {
+    fn has_a_label(&self) -> bool {
+        self.labels()
+            .any(|label| label.len() >= 4 && label[..4].eq_ignore_ascii_case(SUFFIX))
+    }
+
+    // UTS #46 ToUnicode
+    pub fn to_unicode(&self) -> Result<String, IdnaError> {
+        let ascii = self.to_ascii_string().ok_or(IdnaError::Invalid)?;
+        let (unicode, result) = uts46::to_unicode(&ascii, &IdnaConfig::default());
+        result.map(|_| unicode)
+    }
+
+    fn to_ascii_string(&self) -> Option<String> {
+        let mut string = String::with_capacity(self.domain.len());
+        for label in self.labels() {
+            if !label.is_ascii() {
+                #[cfg(feature = "logger")]
+                debug!("domain内有非ASCII字符: {:?}", label);
+                return None;
+            }
+            if !string.is_empty() {
                 string.push('.');
             }
-            remaining = &remaining[part_length..];
+            // 已确认为ASCII
+            string.push_str(std::str::from_utf8(label).ok()?);
         }
         if string.is_empty() {
             string.push('.');
@@ -200,6 +295,178 @@ impl RawDomain {
         )
     }
 }
+// label长度都小于0x40，不会受大小写转换影响，可以直接比较整个序列
+impl PartialEq for RawDomain {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.domain.eq_ignore_ascii_case(&other.domain)
+    }
+}
+
+impl Eq for RawDomain {}
+
+impl Hash for RawDomain {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.domain.len());
+        for byte in &self.domain {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+    }
+}
+
+impl PartialOrd for RawDomain {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RawDomain {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        canonical_cmp(&self.domain, &other.domain)
+    }
+}
+
+// 跟随压缩指针依次访问名称中的每个label，不分配内存，
+// limit为第一次指针跳转前允许读取到的位置(不含)，返回名称在原位置上的结束位置
+pub(crate) fn walk_labels<'a>(
+    reader: &mut SliceReader<'a>,
+    limit: usize,
+    mut on_label: impl FnMut(&'a [u8]),
+) -> Result<usize, DecodeError> {
+    // 不含结尾0x0的长度
+    let mut name_len = 0;
+    let mut limit = limit.min(reader.len());
+    let mut end = None;
+    // 指针只能指向已访问过的最小位置之前，保证跳转次数有限，不会循环
+    let mut lowest = reader.pos();
+
+    loop {
+        let label_pos = reader.pos();
+        if label_pos >= limit {
+            return Err(DecodeError::Truncated {
+                offset: label_pos,
+                need: 1,
+            });
+        }
+        let label_len = reader.peek_u8()?;
+
+        match label_len & 0b1100_0000 {
+            // 处理指针
+            0b1100_0000 => {
+                if label_pos + 2 > limit {
+                    return Err(DecodeError::Truncated {
+                        offset: label_pos,
+                        need: 2,
+                    });
+                }
+                let target = (reader.read_u16()? & 0x3FFF) as usize;
+                if target >= lowest {
+                    #[cfg(feature = "logger")]
+                    trace!("walk_labels中的指针{}没有指向之前的位置", target);
+                    return Err(DecodeError::BadPointer {
+                        offset: label_pos,
+                        target,
+                    });
+                }
+                end.get_or_insert(reader.pos());
+                lowest = target;
+                limit = reader.len();
+                reader.set_pos(target);
+            }
+            0b0000_0000 => {
+                reader.skip(1)?;
+                // 处理结束标记
+                if label_len == 0 {
+                    end.get_or_insert(reader.pos());
+                    break;
+                }
+                // 处理普通标签
+                let len = label_len as usize;
+                if label_pos + 1 + len > limit {
+                    return Err(DecodeError::Truncated {
+                        offset: label_pos + 1,
+                        need: len,
+                    });
+                }
+                if name_len + len + 2 > MAX_NAME_LEN {
+                    #[cfg(feature = "logger")]
+                    trace!("walk_labels中域名超出255字节");
+                    return Err(DecodeError::NameTooLong { offset: label_pos });
+                }
+                name_len += len + 1;
+                on_label(reader.read_slice(len)?);
+            }
+            // 0b01与0b10开头的是已废弃的扩展label类型
+            _ => {
+                return Err(DecodeError::LabelTooLong {
+                    offset: label_pos,
+                    len: label_len,
+                });
+            }
+        }
+    }
+
+    Ok(end.unwrap_or(reader.pos()))
+}
+
+// 以下函数直接处理不带0x0的label序列，供解析过程中只持有切片的调用者使用
+
+#[inline]
+pub(crate) fn labels(name: &[u8]) -> Labels<'_> {
+    Labels { remaining: name }
+}
+
+#[inline]
+pub(crate) fn label_count(name: &[u8]) -> usize {
+    labels(name).count()
+}
+
+// 取最后n个label
+pub(crate) fn base_name(name: &[u8], n: usize) -> &[u8] {
+    let skip = label_count(name).saturating_sub(n);
+    let pos = labels(name).take(skip).map(|label| label.len() + 1).sum();
+    &name[pos..]
+}
+
+pub(crate) fn is_subdomain_of(name: &[u8], parent: &[u8]) -> bool {
+    let n = label_count(parent);
+    label_count(name) >= n && base_name(name, n).eq_ignore_ascii_case(parent)
+}
+
+// 从右侧开始相同的label数
+pub(crate) fn common_labels(a: &[u8], b: &[u8]) -> usize {
+    let la: SmallVec<[&[u8]; 8]> = labels(a).collect();
+    let lb: SmallVec<[&[u8]; 8]> = labels(b).collect();
+    la.iter()
+        .rev()
+        .zip(lb.iter().rev())
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count()
+}
+
+#[inline]
+pub(crate) fn is_wildcard(name: &[u8]) -> bool {
+    name.starts_with(&[1, b'*'])
+}
+
+// RFC 4034 6.1 规范排序：从最右侧label开始逐个按小写字节比较，较短的在前
+pub(crate) fn canonical_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let la: SmallVec<[&[u8]; 8]> = labels(a).collect();
+    let lb: SmallVec<[&[u8]; 8]> = labels(b).collect();
+    for (x, y) in la.iter().rev().zip(lb.iter().rev()) {
+        let ord = x
+            .iter()
+            .map(u8::to_ascii_lowercase)
+            .cmp(y.iter().map(u8::to_ascii_lowercase));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    la.len().cmp(&lb.len())
+}
+
 #[cfg(feature = "fmt")]
 impl Display for RawDomain {
     #[inline]
@@ -213,6 +480,8 @@ mod tests {
     use super::*;
     #[cfg(feature = "logger")]
     use crate::dns::error::init_logger;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
 
     #[test]
     fn test_from_reader() {
@@ -250,9 +519,385 @@ mod tests {
         assert_eq!(reader.pos(), 43 + 15);
     }
 
+    #[test]
+    fn test_from_reader_long_message() {
+        // 域名结束位置超过255时不能被截断
+        let mut slice = vec![0_u8; 300];
+        slice.extend_from_slice(&[
+            3, 119, 119, 119, 5, 98, 97, 105, 100, 117, 3, 99, 111, 109, 0,
+        ]);
+        slice.extend_from_slice(&[0xc1, 0x2c]);
+        let reader = &mut SliceReader::from_slice(&slice);
+        reader.set_pos(300);
+        RawDomain::from_reader(reader).unwrap();
+        assert_eq!(reader.pos(), 315);
+        let domain = RawDomain::from_reader(reader).unwrap();
+        assert_eq!(domain.to_string().unwrap(), "www.baidu.com".to_string());
+        assert_eq!(reader.pos(), 317);
+    }
+
+    #[test]
+    fn test_from_reader_errors() {
+        let decode = |slice: &[u8], pos: usize| {
+            let reader = &mut SliceReader::from_slice(slice);
+            reader.set_pos(pos);
+            RawDomain::from_reader(reader)
+        };
+        // 缺少结尾的0x0
+        assert_eq!(
+            decode(&[3, b'w', b'w', b'w'], 0).err(),
+            Some(DecodeError::Truncated { offset: 4, need: 1 })
+        );
+        assert_eq!(
+            decode(&[3, b'w', b'w'], 0).err(),
+            Some(DecodeError::Truncated { offset: 1, need: 3 })
+        );
+        assert_eq!(
+            decode(&[0xc0], 0).err(),
+            Some(DecodeError::Truncated { offset: 0, need: 2 })
+        );
+        // 指向自身、指向之后的位置、相互指向
+        assert_eq!(
+            decode(&[0xc0, 0x00], 0).err(),
+            Some(DecodeError::BadPointer {
+                offset: 0,
+                target: 0
+            })
+        );
+        assert_eq!(
+            decode(&[0xc0, 0x05, 0, 0, 0, 0], 0).err(),
+            Some(DecodeError::BadPointer {
+                offset: 0,
+                target: 5
+            })
+        );
+        assert_eq!(
+            decode(&[1, b'a', 0xc0, 0x04, 0xc0, 0x00], 4).err(),
+            Some(DecodeError::BadPointer {
+                offset: 2,
+                target: 4
+            })
+        );
+        assert_eq!(
+            decode(&[0x40, 0], 0).err(),
+            Some(DecodeError::LabelTooLong {
+                offset: 0,
+                len: 0x40
+            })
+        );
+        // 超过31个label的合法名称
+        let mut slice = [1, b'a'].repeat(100);
+        slice.push(0);
+        assert_eq!(decode(&slice, 0).unwrap().num_labels(), 100);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        use std::collections::HashSet;
+        let upper = RawDomain::from_str("WWW.Example.com").unwrap();
+        let lower = RawDomain::from_str("www.example.com").unwrap();
+        assert_eq!(upper, lower);
+        assert_ne!(upper, RawDomain::from_str("www.example.net").unwrap());
+        let set: HashSet<&RawDomain> = [&upper, &lower].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        assert_eq!(upper.to_canonical().as_ref(), lower.as_ref());
+        assert_eq!(
+            upper.to_canonical_wire(),
+            [
+                3, 119, 119, 119, 7, 101, 120, 97, 109, 112, 108, 101, 3, 99, 111, 109, 0
+            ]
+        );
+        assert_eq!(RawDomain::from_str(".").unwrap().to_canonical_wire(), [0]);
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        // RFC 4034 6.1 中的排序示例
+        let names: Vec<RawDomain> = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            "zABC.a.EXAMPLE",
+            "z.example",
+            "\\001.z.example",
+            "*.z.example",
+            "\\200.z.example",
+        ]
+        .iter()
+        .map(|n| match n.strip_prefix("\\") {
+            // 转义的单字节label
+            Some(rest) => {
+                let (byte, rest) = rest.split_at(3);
+                let mut raw = vec![1, byte.parse::<u8>().unwrap()];
+                raw.extend_from_slice(RawDomain::from_str(&rest[1..]).unwrap().as_ref());
+                RawDomain::from_raw(raw)
+            }
+            None => RawDomain::from_str(n).unwrap(),
+        })
+        .collect();
+        for pair in names.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        let mut shuffled: Vec<&RawDomain> = names.iter().rev().collect();
+        shuffled.sort();
+        assert!(shuffled.into_iter().eq(names.iter()));
+        assert_eq!(
+            names[3].cmp(&RawDomain::from_str("z.A.example").unwrap()),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let name = RawDomain::from_str("www.Example.com").unwrap();
+        assert!(
+            name.labels()
+                .eq([&b"www"[..], &b"Example"[..], &b"com"[..]].into_iter())
+        );
+        assert_eq!(name.num_labels(), 3);
+        let root = RawDomain::from_str(".").unwrap();
+        assert!(root.is_root());
+        assert_eq!(root.num_labels(), 0);
+        assert!(root.parent().is_none());
+
+        let parent = name.parent().unwrap();
+        assert_eq!(parent, RawDomain::from_str("example.com").unwrap());
+        assert_eq!(name.base_name(1), RawDomain::from_str("com").unwrap());
+        assert_eq!(name.base_name(0), root);
+        assert_eq!(name.base_name(5), name);
+        assert!(name.is_subdomain_of(&RawDomain::from_str("EXAMPLE.COM").unwrap()));
+        assert!(name.is_subdomain_of(&name));
+        assert!(name.is_subdomain_of(&root));
+        assert!(!name.is_subdomain_of(&RawDomain::from_str("ample.com").unwrap()));
+        assert!(!parent.is_subdomain_of(&name));
+        assert_eq!(
+            common_labels(name.as_ref(), b"\x04mail\x07example\x03COM"),
+            2
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let com = RawDomain::from_str("com").unwrap();
+        let name = com.prepend_label("example").unwrap();
+        assert_eq!(name, RawDomain::from_str("example.com").unwrap());
+        let www = RawDomain::from_str("www").unwrap();
+        assert_eq!(
+            www.append(&name).unwrap(),
+            RawDomain::from_str("www.example.com").unwrap()
+        );
+        assert!(com.prepend_label("").is_none());
+        assert!(com.prepend_label([b'a'; 63]).is_some());
+        assert!(com.prepend_label([b'a'; 64]).is_none());
+
+        // 域名的wire格式最长255字节
+        let mut long = RawDomain::from_str(".").unwrap();
+        for _ in 0..3 {
+            long = long.prepend_label([b'a'; 63]).unwrap();
+        }
+        let long = long.prepend_label([b'a'; 61]).unwrap();
+        assert_eq!(long.as_ref().len() + 1, MAX_NAME_LEN);
+        assert!(long.prepend_label("a").is_none());
+        assert!(long.append(&com).is_none());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(RawDomain::from_str(format!("{}.com", "a".repeat(63))).is_some());
+        assert!(RawDomain::from_str(format!("{}.com", "a".repeat(64))).is_none());
+        let name = vec!["a".repeat(63); 4].join(".");
+        assert!(RawDomain::from_str(&name).is_none());
+        assert!(RawDomain::from_str(&name[2..]).is_some());
+
+        // 解析时拒绝超过255字节的域名
+        let mut slice = Vec::new();
+        for _ in 0..4 {
+            slice.push(63);
+            slice.extend_from_slice(&[b'a'; 63]);
+        }
+        slice.push(0);
+        let reader = &mut SliceReader::from_slice(&slice);
+        assert_eq!(
+            RawDomain::from_reader(reader).err(),
+            Some(DecodeError::NameTooLong { offset: 192 })
+        );
+        let reader = &mut SliceReader::from_slice(&slice[64..]);
+        assert!(RawDomain::from_reader(reader).is_ok());
+    }
+
+    #[test]
+    fn test_wildcard() {
+        let wildcard = RawDomain::from_str("*.example.com").unwrap();
+        assert!(wildcard.is_wildcard());
+        assert!(wildcard.wildcard_matches(&RawDomain::from_str("a.example.com").unwrap()));
+        assert!(wildcard.wildcard_matches(&RawDomain::from_str("a.B.Example.com").unwrap()));
+        assert!(!wildcard.wildcard_matches(&RawDomain::from_str("example.com").unwrap()));
+        assert!(!wildcard.wildcard_matches(&RawDomain::from_str("a.example.net").unwrap()));
+        let plain = RawDomain::from_str("www.example.com").unwrap();
+        assert!(!plain.is_wildcard());
+        assert!(!plain.wildcard_matches(&plain));
+    }
+
     #[test]
     fn test_from_str() {
         let domain = RawDomain::from_str("www.baidu.com").unwrap();
         assert_eq!(domain.to_string().unwrap(), "www.baidu.com".to_string());
+        assert_eq!(RawDomain::from_str("www.baidu.com.").unwrap(), domain);
+        assert!(RawDomain::from_str("").unwrap().is_root());
+        assert_eq!(
+            RawDomain::from_idna("a..b").err(),
+            Some(IdnaError::EmptyLabel)
+        );
+        assert_eq!(
+            RawDomain::from_idna(".com").err(),
+            Some(IdnaError::EmptyLabel)
+        );
+        // 服务类名称中的下划线需要保留
+        let srv = RawDomain::from_str("_443._tcp.Example.com").unwrap();
+        assert_eq!(srv.to_string().unwrap(), "_443._tcp.Example.com");
+    }
+
+    #[test]
+    fn test_idna() {
+        // 大小写映射、NFC规范化与全角句点
+        let expected = RawDomain::from_str("xn--bcher-kva.example").unwrap();
+        for input in ["Bücher.example", "BU\u{308}CHER.example", "bücher。example"] {
+            let domain = RawDomain::from_idna(input).unwrap();
+            assert_eq!(domain.as_ref(), expected.as_ref());
+        }
+        assert_eq!(expected.to_string().unwrap(), "bücher.example");
+        assert_eq!(expected.to_unicode().unwrap(), "bücher.example");
+        // 非过渡模式下ß保持不变
+        let domain = RawDomain::from_idna("faß.de").unwrap();
+        assert_eq!(domain.to_string().unwrap(), "faß.de");
+        assert_eq!(domain, RawDomain::from_str("xn--fa-hia.de").unwrap());
+
+        // 非法的A-label不会panic，显示时保留原样
+        let invalid = RawDomain::from_raw(b"\x08xn--a-b-\x03com".to_vec());
+        assert_eq!(invalid.to_string().unwrap(), "xn--a-b-.com");
+        assert_eq!(invalid.to_unicode(), Err(IdnaError::Invalid));
+        assert_eq!(RawDomain::from_raw(vec![1, 0xff]).to_string(), None);
+        // Bidi规则
+        assert_eq!(
+            RawDomain::from_idna("0\u{5d0}.example").err(),
+            Some(IdnaError::Invalid)
+        );
+
+        // ASCII输入中的A-label同样需要有效
+        for input in ["xn--a.com", "xn--a-b-.com", "www.XN--A.example."] {
+            assert_eq!(RawDomain::from_idna(input).err(), Some(IdnaError::Invalid));
+        }
+        let domain = RawDomain::from_idna("XN--BCHER-KVA.example").unwrap();
+        assert_eq!(domain.to_string().unwrap(), "bücher.example");
+    }
+
+    #[test]
+    fn test_idna_v2_a_labels() {
+        // IdnaTestV2中纯ASCII且含A-label的输入，只检查默认配置下也适用的错误：
+        // 不启用STD3(U1)与连字符检查(V2、V3、V4)，长度由from_ascii单独检查(A4)
+        let ignored = ["U1", "V2", "V3", "V4", "A4_1", "A4_2", "X4_2"];
+        let mut checked = 0;
+        for line in include_str!("domain/IdnaTestV2.txt").lines() {
+            let line = line.split('#').next().unwrap();
+            let columns: Vec<&str> = line.split(';').map(str::trim).collect();
+            let source = columns[0];
+            if columns.len() < 5
+                || source.contains('\\')
+                || !source.is_ascii()
+                || !source.to_ascii_lowercase().contains("xn--")
+            {
+                continue;
+            }
+            // 与uts46的一致性测试一样跳过Unicode 17才分配的CJK扩展J
+            if columns[1]
+                .chars()
+                .any(|c| ('\u{323B0}'..='\u{3347F}').contains(&c))
+            {
+                continue;
+            }
+            let status = columns[2].trim_start_matches('[').trim_end_matches(']');
+            let invalid = status
+                .split(", ")
+                .any(|code| !code.is_empty() && !ignored.contains(&code));
+            let result = RawDomain::from_idna(source);
+            if matches!(result, Err(error) if error != IdnaError::Invalid) {
+                continue;
+            }
+            assert_eq!(result.is_err(), invalid, "{}", line);
+            checked += 1;
+        }
+        assert!(checked > 100);
+    }
+
+    // 小写label组成的名称，返回(不带0x0的label序列, 各label)
+    fn arb_name() -> impl Strategy<Value = Vec<Vec<u8>>> {
+        vec(
+            vec(prop_oneof![b'a'..=b'z', b'0'..=b'9', Just(b'-')], 1..=12),
+            0..8,
+        )
+    }
+
+    fn wire(labels: &[Vec<u8>]) -> Vec<u8> {
+        labels
+            .iter()
+            .flat_map(|label| std::iter::once(label.len() as u8).chain(label.iter().copied()))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decode_total(packet in vec(any::<u8>(), 0..300), start in 0_usize..300) {
+            let mut reader = SliceReader::from_slice(&packet);
+            reader.set_pos(start);
+            if let Ok(name) = RawDomain::from_reader(&mut reader) {
+                prop_assert!(name.as_ref().len() < MAX_NAME_LEN);
+                prop_assert!(name.labels().all(|label| label.len() <= MAX_LABEL_LEN));
+                prop_assert!(reader.pos() <= packet.len());
+            }
+        }
+
+        #[test]
+        fn prop_round_trip(prefix in vec(any::<u8>(), 0..20), labels in arb_name()) {
+            let name = wire(&labels);
+            let mut packet = prefix.clone();
+            packet.extend_from_slice(&name);
+            packet.push(0);
+            let mut reader = SliceReader::from_slice(&packet);
+            reader.set_pos(prefix.len());
+            let domain = RawDomain::from_reader(&mut reader).unwrap();
+            prop_assert_eq!(domain.as_ref(), &name);
+            prop_assert_eq!(reader.pos(), packet.len());
+        }
+
+        // 从最后一个label开始写入，之后每个label都跟一个指向上一段的指针
+        #[test]
+        fn prop_pointer_chain(labels in arb_name()) {
+            let mut packet = vec![0_u8];
+            let mut last = 0;
+            for label in labels.iter().rev() {
+                let pos = packet.len();
+                packet.push(label.len() as u8);
+                packet.extend_from_slice(label);
+                packet.extend_from_slice(&(0xC000 | last as u16).to_be_bytes());
+                last = pos;
+            }
+            let mut reader = SliceReader::from_slice(&packet);
+            reader.set_pos(last);
+            let domain = RawDomain::from_reader(&mut reader).unwrap();
+            prop_assert_eq!(domain.as_ref(), &wire(&labels));
+        }
+
+        // 全部由指针组成(都指向指针的起始位置)的报文只能返回错误，不能死循环
+        #[test]
+        fn prop_pointer_loop(targets in vec((0_u16..32).prop_map(|t| t * 2), 1..32), start in 0_usize..32) {
+            let packet: Vec<u8> = targets
+                .iter()
+                .flat_map(|target| (0xC000 | target).to_be_bytes())
+                .collect();
+            let mut reader = SliceReader::from_slice(&packet);
+            reader.set_pos(start * 2 % packet.len());
+            prop_assert!(RawDomain::from_reader(&mut reader).is_err());
+        }
     }
 }
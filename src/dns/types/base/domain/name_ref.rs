@@ -0,0 +1,182 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use super::{RawDomain, walk_labels};
+use crate::dns::utils::{DecodeError, SliceReader};
+#[cfg(feature = "fmt")]
+use std::fmt::Display;
+
+// 报文内的域名，只记录名称在报文中的位置，需要时才跟随压缩指针读取label，不分配内存
+#[derive(Clone, Copy, Debug)]
+pub struct NameRef<'a> {
+    packet: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NameRef<'a> {
+    // 校验名称(规则与RawDomain::from_reader相同)，读取后reader位于名称之后
+    pub fn from_reader(reader: &mut SliceReader<'a>) -> Result<NameRef<'a>, DecodeError> {
+        let pos = reader.pos();
+        let end = walk_labels(reader, reader.len(), |_| {})?;
+        reader.set_pos(end);
+        Ok(NameRef {
+            packet: reader.as_ref(),
+            pos,
+        })
+    }
+
+    #[inline]
+    pub fn labels(&self) -> NameRefLabels<'a> {
+        NameRefLabels {
+            packet: self.packet,
+            pos: Some(self.pos),
+        }
+    }
+
+    #[inline]
+    pub fn num_labels(&self) -> usize {
+        self.labels().count()
+    }
+
+    #[inline]
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    // 复制为RawDomain
+    pub fn to_raw_domain(&self) -> RawDomain {
+        let mut domain = Vec::with_capacity(30);
+        for label in self.labels() {
+            domain.push(label.len() as u8);
+            domain.extend_from_slice(label);
+        }
+        RawDomain::from_raw(domain)
+    }
+
+    #[inline]
+    pub fn to_string(&self) -> Option<String> {
+        self.to_raw_domain().to_string()
+    }
+}
+
+// 与RawDomain一样忽略ASCII大小写
+impl PartialEq<RawDomain> for NameRef<'_> {
+    fn eq(&self, other: &RawDomain) -> bool {
+        let mut other_labels = other.labels();
+        for label in self.labels() {
+            match other_labels.next() {
+                Some(other) if label.eq_ignore_ascii_case(other) => {}
+                _ => return false,
+            }
+        }
+        other_labels.next().is_none()
+    }
+}
+
+impl PartialEq<NameRef<'_>> for RawDomain {
+    #[inline]
+    fn eq(&self, other: &NameRef<'_>) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq for NameRef<'_> {
+    fn eq(&self, other: &NameRef<'_>) -> bool {
+        let mut other_labels = other.labels();
+        for label in self.labels() {
+            match other_labels.next() {
+                Some(other) if label.eq_ignore_ascii_case(other) => {}
+                _ => return false,
+            }
+        }
+        other_labels.next().is_none()
+    }
+}
+
+impl Eq for NameRef<'_> {}
+
+#[cfg(feature = "fmt")]
+impl Display for NameRef<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_raw_domain(), f)
+    }
+}
+
+// 按从左到右的顺序遍历label，跟随压缩指针
+#[derive(Clone, Debug)]
+pub struct NameRefLabels<'a> {
+    packet: &'a [u8],
+    pos: Option<usize>,
+}
+
+impl<'a> Iterator for NameRefLabels<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pos = self.pos?;
+        loop {
+            let len = *self.packet.get(pos)? as usize;
+            if len & 0xC0 == 0xC0 {
+                let target = u16::from_be_bytes([len as u8, *self.packet.get(pos + 1)?]);
+                let target = (target & 0x3FFF) as usize;
+                // 构造时已校验过，这里只防止死循环
+                if target >= pos {
+                    self.pos = None;
+                    return None;
+                }
+                pos = target;
+                continue;
+            }
+            if len == 0 {
+                self.pos = None;
+                return None;
+            }
+            let label = self.packet.get(pos + 1..pos + 1 + len)?;
+            self.pos = Some(pos + 1 + len);
+            return Some(label);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_ref() {
+        // 0: example.com  13: www -> 0  19: 指向19自身
+        let packet = [
+            7, b'E', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 3, b'w', b'w',
+            b'w', 0xc0, 0x00, 0xc0, 0x13,
+        ];
+        let mut reader = SliceReader::from_slice(&packet);
+        reader.set_pos(13);
+        let name = NameRef::from_reader(&mut reader).unwrap();
+        assert_eq!(reader.pos(), 19);
+        assert_eq!(
+            name.labels().collect::<Vec<_>>(),
+            [&b"www"[..], b"Example", b"com"]
+        );
+        assert_eq!(name.num_labels(), 3);
+        assert!(!name.is_root());
+        let expected = RawDomain::from_str("www.example.COM").unwrap();
+        assert!(name == expected);
+        assert!(name != RawDomain::from_str("example.com").unwrap());
+        assert!(name != RawDomain::from_str("a.www.example.com").unwrap());
+        assert_eq!(name.to_raw_domain().as_ref(), b"\x03www\x07Example\x03com");
+
+        reader.set_pos(0);
+        let parent = NameRef::from_reader(&mut reader).unwrap();
+        assert!(parent != name);
+        assert_eq!(parent.to_string().unwrap(), "Example.com");
+
+        reader.set_pos(19);
+        assert_eq!(
+            NameRef::from_reader(&mut reader).unwrap_err(),
+            DecodeError::BadPointer {
+                offset: 19,
+                target: 19
+            }
+        );
+    }
+}
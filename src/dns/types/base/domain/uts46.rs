@@ -0,0 +1,221 @@
+use idna::uts46::{AsciiDenyList, DnsLength, Hyphens, Uts46};
+use snafu::Snafu;
+
+// UTS #46 处理失败的原因，映射/规范化/Bidi/CONTEXTJ/punycode等检查的错误统一为Invalid
+#[derive(Snafu, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdnaError {
+    #[snafu(display("IdnaInvalid, UTS #46 processing or validity check failed"))]
+    Invalid,
+    #[snafu(display("IdnaEmptyLabel, domain contains an empty label"))]
+    EmptyLabel,
+    #[snafu(display("IdnaLabelTooLong, label exceeds 63 bytes"))]
+    LabelTooLong,
+    #[snafu(display("IdnaNameTooLong, domain exceeds 253 characters"))]
+    NameTooLong,
+}
+
+// UTS #46 的可选项，始终使用非过渡处理(Nontransitional_Processing)
+#[derive(Clone, Copy, Debug)]
+pub struct IdnaConfig {
+    pub use_std3_ascii_rules: bool, // 开启后拒绝下划线等非LDH字符
+    pub check_hyphens: bool,
+    pub verify_dns_length: bool,
+}
+
+impl Default for IdnaConfig {
+    // DNS中常见 _dmarc、_443._tcp 这类名称，默认不启用STD3规则与连字符检查
+    fn default() -> Self {
+        Self {
+            use_std3_ascii_rules: false,
+            check_hyphens: false,
+            verify_dns_length: true,
+        }
+    }
+}
+
+impl IdnaConfig {
+    // IdnaTestV2 一致性测试使用的全部检查
+    pub fn strict() -> Self {
+        Self {
+            use_std3_ascii_rules: true,
+            check_hyphens: true,
+            verify_dns_length: true,
+        }
+    }
+
+    #[inline]
+    fn deny_list(&self) -> AsciiDenyList {
+        if self.use_std3_ascii_rules {
+            AsciiDenyList::STD3
+        } else {
+            AsciiDenyList::EMPTY
+        }
+    }
+
+    #[inline]
+    fn hyphens(&self) -> Hyphens {
+        if self.check_hyphens {
+            Hyphens::Check
+        } else {
+            Hyphens::Allow
+        }
+    }
+}
+
+// UTS #46 ToASCII
+pub fn to_ascii(domain: &str, config: &IdnaConfig) -> Result<String, IdnaError> {
+    let ascii = Uts46::new()
+        .to_ascii(
+            domain.as_bytes(),
+            config.deny_list(),
+            config.hyphens(),
+            DnsLength::Ignore,
+        )
+        .map_err(|_| IdnaError::Invalid)?;
+    if config.verify_dns_length {
+        verify_dns_length(&ascii)?;
+    }
+    Ok(ascii.into_owned())
+}
+
+// UTS #46 ToUnicode，出错时仍返回结果，出错的位置以U+FFFD标记，仅可用于展示
+pub fn to_unicode(domain: &str, config: &IdnaConfig) -> (String, Result<(), IdnaError>) {
+    let (unicode, result) =
+        Uts46::new().to_unicode(domain.as_bytes(), config.deny_list(), config.hyphens());
+    (unicode.into_owned(), result.map_err(|_| IdnaError::Invalid))
+}
+
+// VerifyDnsLength(UTS #46 4.2 步骤4)，结尾的根域名点也按空label处理
+fn verify_dns_length(ascii: &str) -> Result<(), IdnaError> {
+    if ascii.len() > 253 {
+        return Err(IdnaError::NameTooLong);
+    }
+    for label in ascii.split('.') {
+        if label.is_empty() {
+            return Err(IdnaError::EmptyLabel);
+        }
+        if label.len() > 63 {
+            return Err(IdnaError::LabelTooLong);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 解析 \uXXXX 转义
+    fn unescape(input: &str) -> String {
+        let mut output = String::new();
+        let mut chars = input.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                output.push(c);
+                continue;
+            }
+            match chars.next().unwrap() {
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).unwrap();
+                    // 单独的代理项无法放入String，保留原样
+                    match char::from_u32(code) {
+                        Some(c) => output.push(c),
+                        None => output.push_str(&format!("\\u{hex}")),
+                    }
+                }
+                c => output.push(c),
+            }
+        }
+        output
+    }
+
+    fn status(column: &str) -> Vec<&str> {
+        column
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(", ")
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_idna_v2_vectors() {
+        // https://www.unicode.org/Public/idna/16.0.0/IdnaTestV2.txt
+        let config = IdnaConfig::strict();
+        let mut failures = Vec::new();
+        let mut total = 0;
+        for (i, line) in include_str!("IdnaTestV2.txt").lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            if line.trim().is_empty() {
+                continue;
+            }
+            total += 1;
+            let columns: Vec<&str> = line.split(';').map(str::trim).collect();
+            let source = unescape(columns[0]);
+            let expected_unicode = match columns[1] {
+                "" => source.clone(),
+                "\"\"" => String::new(),
+                s => unescape(s),
+            };
+            let unicode_status = status(columns[2]);
+            let expected_ascii = match columns[3] {
+                "" => expected_unicode.clone(),
+                "\"\"" => String::new(),
+                s => unescape(s),
+            };
+            let ascii_status = match columns[4] {
+                "" => unicode_status.clone(),
+                s => status(s),
+            };
+            // CJK扩展J在Unicode 17中才分配，所用的数据比测试文件新，不再视为V7
+            if expected_unicode
+                .chars()
+                .any(|c| ('\u{323B0}'..='\u{3347F}').contains(&c))
+            {
+                continue;
+            }
+
+            // X4_2 (toUnicode中的空label)在实现中只体现在toASCII，这里与参考实现一样忽略
+            let (unicode, result) = to_unicode(&source, &config);
+            let unicode_ok = if unicode_status.is_empty() {
+                result.is_ok() && unicode == expected_unicode
+            } else {
+                result.is_err() || unicode_status.contains(&"X4_2")
+            };
+            let ascii = to_ascii(&source, &config);
+            let ascii_ok = if ascii_status.is_empty() {
+                ascii.as_deref() == Ok(expected_ascii.as_str())
+            } else {
+                ascii.is_err()
+            };
+            if !unicode_ok || !ascii_ok {
+                failures.push(i + 1);
+            }
+        }
+        assert!(total > 6000);
+        assert!(failures.is_empty(), "failed lines: {:?}", failures);
+    }
+
+    #[test]
+    fn test_errors() {
+        let config = IdnaConfig::default();
+        assert_eq!(to_ascii("a..b", &config), Err(IdnaError::EmptyLabel));
+        assert_eq!(
+            to_ascii(&"a".repeat(64), &config),
+            Err(IdnaError::LabelTooLong)
+        );
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert_eq!(to_ascii(&long, &config), Err(IdnaError::NameTooLong));
+        assert_eq!(to_ascii("xn--a.com", &config), Err(IdnaError::Invalid));
+        // 默认配置允许下划线，严格配置不允许
+        assert_eq!(
+            to_ascii("_dmarc.例子.com", &config).unwrap(),
+            "_dmarc.xn--fsqu00a.com"
+        );
+        assert_eq!(
+            to_ascii("_dmarc.例子.com", &IdnaConfig::strict()),
+            Err(IdnaError::Invalid)
+        );
+    }
+}
@@ -12,6 +12,63 @@ impl DnsClass {
         }
     }
 }
+// 含EDNS扩展后的12bit rcode(RFC 6895 2.3)
+pub struct DnsRcode;
+impl DnsRcode {
+    pub fn get_str(rcode: u16) -> &'static str {
+        match rcode {
+            0 => "NOERROR",
+            1 => "FORMERR",
+            2 => "SERVFAIL",
+            3 => "NXDOMAIN",
+            4 => "NOTIMP",
+            5 => "REFUSED",
+            6 => "YXDOMAIN",
+            7 => "YXRRSET",
+            8 => "NXRRSET",
+            9 => "NOTAUTH",
+            10 => "NOTZONE",
+            16 => "BADVERS",
+            23 => "BADCOOKIE",
+            _ => "RESERVED",
+        }
+    }
+}
+
+pub struct DnsSecAlgorithm;
+impl DnsSecAlgorithm {
+    pub fn get_str(algorithm: u8) -> &'static str {
+        match algorithm {
+            1 => "RSA/MD5",
+            3 => "DSA/SHA-1",
+            5 => "RSA/SHA-1",
+            6 => "DSA-NSEC3-SHA1",
+            7 => "RSASHA1-NSEC3-SHA1",
+            8 => "RSA/SHA-256",
+            10 => "RSA/SHA-512",
+            12 => "GOST R 34.10-2001",
+            13 => "ECDSA Curve P-256 with SHA-256",
+            14 => "ECDSA Curve P-384 with SHA-384",
+            15 => "Ed25519",
+            16 => "Ed448",
+            _ => "Unknown",
+        }
+    }
+}
+
+pub struct DnsSecDigest;
+impl DnsSecDigest {
+    pub fn get_str(digest_type: u8) -> &'static str {
+        match digest_type {
+            1 => "SHA-1",
+            2 => "SHA-256",
+            3 => "GOST R 34.11-94",
+            4 => "SHA-384",
+            _ => "Unknown",
+        }
+    }
+}
+
 pub struct DnsTTL;
 impl DnsTTL {
     const SECOND: u32 = 1;
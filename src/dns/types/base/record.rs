@@ -1,9 +1,17 @@
+mod dnssec;
 mod domain;
 mod ip_addr;
+mod nsec;
+mod opt;
 mod soa;
 mod txt;
 
+pub(crate) use dnssec::hex_upper;
+pub use dnssec::{DNSKEY, DS, RRSIG};
 pub use domain::{CNAME, NS};
 pub use ip_addr::{A, AAAA};
+pub use nsec::{NSEC, NSEC3, NSEC3PARAM, TypeBitmap};
+pub(crate) use nsec::{base32hex_decode, base32hex_encode, type_name};
+pub use opt::OPT;
 pub use soa::SOA;
 pub use txt::TXT;
@@ -0,0 +1,590 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+#[cfg(feature = "fmt")]
+use crate::dns::types::base::record::nsec::type_name;
+#[cfg(feature = "fmt")]
+use crate::dns::types::base::{DnsSecAlgorithm, DnsSecDigest, DnsTTL};
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader, rdata_end, rdata_within};
+#[cfg(feature = "fmt")]
+use base64::Engine;
+#[cfg(feature = "fmt")]
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "fmt")]
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub struct DNSKEY {
+    flags: u16,
+    // 固定为3
+    protocol: u8,
+    algorithm: u8,
+    public_key: Vec<u8>,
+}
+
+impl DNSKEY {
+    pub const FIX_SIZE: usize = 4;
+    pub const ZONE_KEY: u16 = 0x0100;
+    pub const SECURE_ENTRY_POINT: u16 = 0x0001;
+
+    pub fn new(flags: u16, protocol: u8, algorithm: u8, public_key: Vec<u8>) -> DNSKEY {
+        DNSKEY {
+            flags,
+            protocol,
+            algorithm,
+            public_key,
+        }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<DNSKEY> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        rdata_end(reader, raw_len, Self::FIX_SIZE, DnsTypeNum::DNSKEY)?;
+        Ok(Self {
+            flags: reader.read_u16()?,
+            protocol: reader.read_u8()?,
+            algorithm: reader.read_u8()?,
+            public_key: reader.read_slice(raw_len - Self::FIX_SIZE)?.to_vec(),
+        })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_u16(self.flags);
+        operator.write_u8(self.protocol);
+        operator.write_u8(self.algorithm);
+        operator.write_slice(&self.public_key);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    // RFC 4034 Appendix B
+    pub fn key_tag(&self) -> u16 {
+        if self.algorithm == 1 {
+            let len = self.public_key.len();
+            if len < 3 {
+                return 0;
+            }
+            return u16::from_be_bytes([self.public_key[len - 3], self.public_key[len - 2]]);
+        }
+        let mut ac = self.flags as u32 + ((self.protocol as u32) << 8) + self.algorithm as u32;
+        for (i, byte) in self.public_key.iter().enumerate() {
+            ac += if i & 1 == 0 {
+                (*byte as u32) << 8
+            } else {
+                *byte as u32
+            };
+        }
+        ac += (ac >> 16) & 0xFFFF;
+        (ac & 0xFFFF) as u16
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}DNSKEY:")?;
+        let mut flag_desc = Vec::new();
+        if self.is_zone_key() {
+            flag_desc.push("Zone Key");
+        }
+        if self.is_secure_entry_point() {
+            flag_desc.push("Secure Entry Point");
+        }
+        writeln!(
+            f,
+            "{_indent}\tFlags: {:#06X} ({})",
+            self.flags,
+            flag_desc.join(", ")
+        )?;
+        writeln!(f, "{_indent}\tProtocol: {}", self.protocol)?;
+        writeln!(
+            f,
+            "{_indent}\tAlgorithm: {} ({})",
+            DnsSecAlgorithm::get_str(self.algorithm),
+            self.algorithm
+        )?;
+        writeln!(f, "{_indent}\tKey id: {}", self.key_tag())?;
+        writeln!(
+            f,
+            "{_indent}\tPublic Key: {}",
+            STANDARD.encode(&self.public_key)
+        )
+    }
+}
+
+#[allow(unused)]
+impl DNSKEY {
+    #[inline]
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn protocol(&self) -> u8 {
+        self.protocol
+    }
+
+    #[inline]
+    pub fn algorithm(&self) -> u8 {
+        self.algorithm
+    }
+
+    #[inline]
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    #[inline]
+    pub fn is_zone_key(&self) -> bool {
+        self.flags & Self::ZONE_KEY != 0
+    }
+
+    #[inline]
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags & Self::SECURE_ENTRY_POINT != 0
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for DNSKEY {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DS {
+    key_tag: u16,
+    algorithm: u8,
+    digest_type: u8,
+    digest: Vec<u8>,
+}
+
+impl DS {
+    pub const FIX_SIZE: usize = 4;
+
+    pub fn new(key_tag: u16, algorithm: u8, digest_type: u8, digest: Vec<u8>) -> DS {
+        DS {
+            key_tag,
+            algorithm,
+            digest_type,
+            digest,
+        }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<DS> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        rdata_end(reader, raw_len, Self::FIX_SIZE, DnsTypeNum::DS)?;
+        Ok(Self {
+            key_tag: reader.read_u16()?,
+            algorithm: reader.read_u8()?,
+            digest_type: reader.read_u8()?,
+            digest: reader.read_slice(raw_len - Self::FIX_SIZE)?.to_vec(),
+        })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_u16(self.key_tag);
+        operator.write_u8(self.algorithm);
+        operator.write_u8(self.digest_type);
+        operator.write_slice(&self.digest);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}DS:")?;
+        writeln!(f, "{_indent}\tKey id: {}", self.key_tag)?;
+        writeln!(
+            f,
+            "{_indent}\tAlgorithm: {} ({})",
+            DnsSecAlgorithm::get_str(self.algorithm),
+            self.algorithm
+        )?;
+        writeln!(
+            f,
+            "{_indent}\tDigest Type: {} ({})",
+            DnsSecDigest::get_str(self.digest_type),
+            self.digest_type
+        )?;
+        writeln!(f, "{_indent}\tDigest: {}", hex_upper(&self.digest))
+    }
+}
+
+#[allow(unused)]
+impl DS {
+    #[inline]
+    pub fn key_tag(&self) -> u16 {
+        self.key_tag
+    }
+
+    #[inline]
+    pub fn algorithm(&self) -> u8 {
+        self.algorithm
+    }
+
+    #[inline]
+    pub fn digest_type(&self) -> u8 {
+        self.digest_type
+    }
+
+    #[inline]
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for DS {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RRSIG {
+    type_covered: u16,
+    algorithm: u8,
+    labels: u8,
+    original_ttl: u32,
+    // 以下两个时间为自1970-01-01 00:00:00 UTC起的秒数
+    sig_expiration: u32,
+    sig_inception: u32,
+    key_tag: u16,
+    signer_name: Rc<RawDomain>,
+    signature: Vec<u8>,
+}
+
+impl RRSIG {
+    pub const FIX_SIZE: usize = 18;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        type_covered: u16,
+        algorithm: u8,
+        labels: u8,
+        original_ttl: u32,
+        sig_expiration: u32,
+        sig_inception: u32,
+        key_tag: u16,
+        signer_name: Rc<RawDomain>,
+        signature: Vec<u8>,
+    ) -> RRSIG {
+        RRSIG {
+            type_covered,
+            algorithm,
+            labels,
+            original_ttl,
+            sig_expiration,
+            sig_inception,
+            key_tag,
+            signer_name,
+            signature,
+        }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<RRSIG> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        let end = rdata_end(reader, raw_len, Self::FIX_SIZE + 1, DnsTypeNum::RRSIG)?;
+        let type_covered = reader.read_u16()?;
+        let algorithm = reader.read_u8()?;
+        let labels = reader.read_u8()?;
+        let original_ttl = reader.read_u32()?;
+        let sig_expiration = reader.read_u32()?;
+        let sig_inception = reader.read_u32()?;
+        let key_tag = reader.read_u16()?;
+        // 签名者名称必须完整位于RDATA内
+        let signer_name = Rc::new(RawDomain::from_reader(reader)?);
+        rdata_within(reader, start, raw_len, DnsTypeNum::RRSIG)?;
+        let signature = reader.read_slice(end - reader.pos())?.to_vec();
+        Ok(Self {
+            type_covered,
+            algorithm,
+            labels,
+            original_ttl,
+            sig_expiration,
+            sig_inception,
+            key_tag,
+            signer_name,
+            signature,
+        })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_u16(self.type_covered);
+        operator.write_u8(self.algorithm);
+        operator.write_u8(self.labels);
+        operator.write_u32(self.original_ttl);
+        operator.write_u32(self.sig_expiration);
+        operator.write_u32(self.sig_inception);
+        operator.write_u16(self.key_tag);
+        operator.write_slice(self.signer_name.as_ref().as_ref());
+        operator.write_u8(0x0);
+        operator.write_slice(&self.signature);
+    }
+
+    // 规范形式中签名者名称需要转为小写
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        operator.write_u16(self.type_covered);
+        operator.write_u8(self.algorithm);
+        operator.write_u8(self.labels);
+        operator.write_u32(self.original_ttl);
+        operator.write_u32(self.sig_expiration);
+        operator.write_u32(self.sig_inception);
+        operator.write_u16(self.key_tag);
+        operator.write_slice(&self.signer_name.to_canonical_wire());
+        operator.write_slice(&self.signature);
+    }
+
+    #[inline]
+    pub fn expiration_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.sig_expiration as i64, 0)
+    }
+
+    #[inline]
+    pub fn inception_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.sig_inception as i64, 0)
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}RRSIG:")?;
+        writeln!(
+            f,
+            "{_indent}\tType Covered: {} ({})",
+            type_name(self.type_covered),
+            self.type_covered
+        )?;
+        writeln!(
+            f,
+            "{_indent}\tAlgorithm: {} ({})",
+            DnsSecAlgorithm::get_str(self.algorithm),
+            self.algorithm
+        )?;
+        writeln!(f, "{_indent}\tLabels: {}", self.labels)?;
+        writeln!(
+            f,
+            "{_indent}\tOriginal TTL: {} ({})",
+            self.original_ttl,
+            DnsTTL::get_str(self.original_ttl)
+        )?;
+        writeln!(
+            f,
+            "{_indent}\tSignature Expiration: {} ({})",
+            fmt_time(self.expiration_time()),
+            self.sig_expiration
+        )?;
+        writeln!(
+            f,
+            "{_indent}\tSignature Inception: {} ({})",
+            fmt_time(self.inception_time()),
+            self.sig_inception
+        )?;
+        writeln!(f, "{_indent}\tKey Tag: {}", self.key_tag)?;
+        writeln!(f, "{_indent}\tSigner's name: {}", self.signer_name)?;
+        writeln!(
+            f,
+            "{_indent}\tSignature: {}",
+            STANDARD.encode(&self.signature)
+        )
+    }
+}
+
+#[allow(unused)]
+impl RRSIG {
+    #[inline]
+    pub fn type_covered(&self) -> u16 {
+        self.type_covered
+    }
+
+    #[inline]
+    pub fn algorithm(&self) -> u8 {
+        self.algorithm
+    }
+
+    #[inline]
+    pub fn labels(&self) -> u8 {
+        self.labels
+    }
+
+    #[inline]
+    pub fn original_ttl(&self) -> u32 {
+        self.original_ttl
+    }
+
+    #[inline]
+    pub fn sig_expiration(&self) -> u32 {
+        self.sig_expiration
+    }
+
+    #[inline]
+    pub fn sig_inception(&self) -> u32 {
+        self.sig_inception
+    }
+
+    #[inline]
+    pub fn key_tag(&self) -> u16 {
+        self.key_tag
+    }
+
+    #[inline]
+    pub fn signer_name(&self) -> Rc<RawDomain> {
+        self.signer_name.clone()
+    }
+
+    #[inline]
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for RRSIG {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[cfg(feature = "fmt")]
+fn fmt_time(time: Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => "???".to_string(),
+    }
+}
+
+pub(crate) fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    // RFC 4034 5.4 中 dskey.example.com 的 DNSKEY, key id = 60485
+    const DSKEY: &str = "AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxeYCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+EPbtG9DMBmADjFDc2w/rljwvFw==";
+
+    #[test]
+    fn test_dnskey() {
+        let key = DNSKEY::new(256, 3, 5, STANDARD.decode(DSKEY).unwrap());
+        assert_eq!(key.key_tag(), 60485);
+        assert!(key.is_zone_key());
+        assert!(!key.is_secure_entry_point());
+
+        let buf = &mut [0_u8; 512];
+        let mut operator = SliceOperator::from_slice(buf);
+        key.encode_rdata(&mut operator);
+        let len = operator.pos();
+        let mut reader = SliceReader::from_slice(&buf[..len]);
+        let decoded = DNSKEY::from_reader_with_size(&mut reader, len).unwrap();
+        assert_eq!(reader.pos(), len);
+        assert_eq!(decoded.flags(), 256);
+        assert_eq!(decoded.algorithm(), 5);
+        assert_eq!(decoded.public_key(), key.public_key());
+        assert!(
+            DNSKEY::from_reader_with_size(&mut SliceReader::from_slice(&[1, 0, 3]), 3).is_err()
+        );
+    }
+
+    #[test]
+    fn test_ds() {
+        let slice = [
+            0xec, 0x45, 0x05, 0x01, 0x2b, 0xb1, 0x83, 0xaf, 0x5f, 0x22, 0x58, 0x81, 0x79, 0xa5,
+            0x3b, 0x0a, 0x98, 0x63, 0x1f, 0xad, 0x1a, 0x29, 0x21, 0x18,
+        ];
+        let ds =
+            DS::from_reader_with_size(&mut SliceReader::from_slice(&slice), slice.len()).unwrap();
+        assert_eq!(ds.key_tag(), 60485);
+        assert_eq!(ds.algorithm(), 5);
+        assert_eq!(ds.digest_type(), 1);
+        assert_eq!(ds.digest().len(), 20);
+        #[cfg(feature = "fmt")]
+        assert!(
+            ds.to_string()
+                .contains("Digest: 2BB183AF5F22588179A53B0A98631FAD1A292118")
+        );
+    }
+
+    #[test]
+    fn test_rrsig() {
+        // RFC 4034 3.3 中 host.example.com 的 RRSIG
+        let rrsig = RRSIG::new(
+            1,
+            5,
+            3,
+            86400,
+            1048354263,
+            1045762263,
+            2642,
+            Rc::new(RawDomain::from_str("example.com").unwrap()),
+            vec![0xa0, 0x90, 0x75, 0x5b],
+        );
+        assert_eq!(
+            rrsig
+                .expiration_time()
+                .unwrap()
+                .format("%Y%m%d%H%M%S")
+                .to_string(),
+            "20030322173103"
+        );
+        assert_eq!(
+            rrsig
+                .inception_time()
+                .unwrap()
+                .format("%Y%m%d%H%M%S")
+                .to_string(),
+            "20030220173103"
+        );
+
+        let buf = &mut [0_u8; 512];
+        let mut operator = SliceOperator::from_slice(buf);
+        rrsig.encode_rdata(&mut operator);
+        let len = operator.pos();
+        assert_eq!(len, RRSIG::FIX_SIZE + 13 + 4);
+        let mut reader = SliceReader::from_slice(&buf[..len]);
+        let decoded = RRSIG::from_reader_with_size(&mut reader, len).unwrap();
+        assert_eq!(reader.pos(), len);
+        assert_eq!(decoded.key_tag(), 2642);
+        assert_eq!(
+            decoded.signer_name().as_ref().to_string().unwrap(),
+            "example.com"
+        );
+        assert_eq!(decoded.signature(), &[0xa0, 0x90, 0x75, 0x5b]);
+        #[cfg(feature = "fmt")]
+        assert!(
+            decoded
+                .to_string()
+                .contains("Signature Expiration: 2003-03-22 17:31:03 UTC (1048354263)")
+        );
+    }
+}
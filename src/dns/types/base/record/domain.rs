@@ -1,11 +1,16 @@
-use crate::dns::types::base::RawDomain;
-use crate::dns::utils::SliceReader;
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader, rdata_consumed, rdata_end};
 use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub struct NS(Rc<RawDomain>);
 
 impl NS {
+    #[inline]
+    pub fn new(domain: Rc<RawDomain>) -> NS {
+        NS(domain)
+    }
+
     #[inline]
     pub fn get_index(&self) -> Rc<RawDomain> {
         self.0.clone()
@@ -23,8 +28,26 @@ impl NS {
     }
 
     #[inline]
-    pub fn from_reader_with_size(reader: &mut SliceReader, _raw_len: usize) -> Option<Self> {
-        Some(Self(Rc::new(RawDomain::from_reader(reader)?)))
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        rdata_end(reader, raw_len, 1, DnsTypeNum::NS)?;
+        let domain = RawDomain::from_reader(reader)?;
+        rdata_consumed(reader, start, raw_len, DnsTypeNum::NS)?;
+        Ok(Self(Rc::new(domain)))
+    }
+
+    #[inline]
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.0.as_ref().as_ref());
+        operator.write_u8(0x0);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        operator.write_slice(&self.0.to_canonical_wire());
     }
 }
 
@@ -32,6 +55,11 @@ impl NS {
 pub struct CNAME(Rc<RawDomain>);
 
 impl CNAME {
+    #[inline]
+    pub fn new(domain: Rc<RawDomain>) -> CNAME {
+        CNAME(domain)
+    }
+
     #[inline]
     pub fn get_index(&self) -> Rc<RawDomain> {
         self.0.clone()
@@ -49,7 +77,25 @@ impl CNAME {
     }
 
     #[inline]
-    pub fn from_reader_with_size(reader: &mut SliceReader, _raw_len: usize) -> Option<Self> {
-        Some(Self(Rc::new(RawDomain::from_reader(reader)?)))
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        rdata_end(reader, raw_len, 1, DnsTypeNum::CNAME)?;
+        let domain = RawDomain::from_reader(reader)?;
+        rdata_consumed(reader, start, raw_len, DnsTypeNum::CNAME)?;
+        Ok(Self(Rc::new(domain)))
+    }
+
+    #[inline]
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.0.as_ref().as_ref());
+        operator.write_u8(0x0);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        operator.write_slice(&self.0.to_canonical_wire());
     }
 }
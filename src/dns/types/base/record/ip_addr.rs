@@ -1,9 +1,15 @@
-use crate::dns::utils::SliceReader;
+use crate::dns::types::base::DnsTypeNum;
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader};
 
 #[derive(Clone, Debug)]
 pub struct A(std::net::Ipv4Addr);
 
 impl A {
+    #[inline]
+    pub fn new(addr: std::net::Ipv4Addr) -> A {
+        A(addr)
+    }
+
     #[inline]
     pub fn get_index(&self) -> std::net::Ipv4Addr {
         self.0
@@ -21,10 +27,30 @@ impl A {
     }
 
     #[inline]
-    pub fn from_reader_with_size(reader: &mut SliceReader, size: usize) -> Option<Self> {
-        Some(Self(std::net::Ipv4Addr::from(
-            <[u8; 4]>::try_from(reader.read_slice(size)).ok()?,
-        )))
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        size: usize,
+    ) -> Result<Self, DecodeError> {
+        let offset = reader.pos();
+        let octets = <[u8; 4]>::try_from(reader.read_slice(size)?).map_err(|_| {
+            DecodeError::RdataLengthMismatch {
+                offset,
+                rtype: DnsTypeNum::A,
+                rdlength: size,
+                consumed: 4,
+            }
+        })?;
+        Ok(Self(std::net::Ipv4Addr::from(octets)))
+    }
+
+    #[inline]
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(&self.0.octets());
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
     }
 }
 
@@ -32,6 +58,11 @@ impl A {
 pub struct AAAA(std::net::Ipv6Addr);
 
 impl AAAA {
+    #[inline]
+    pub fn new(addr: std::net::Ipv6Addr) -> AAAA {
+        AAAA(addr)
+    }
+
     #[inline]
     pub fn get_index(&self) -> std::net::Ipv6Addr {
         self.0
@@ -49,9 +80,29 @@ impl AAAA {
     }
 
     #[inline]
-    pub fn from_reader_with_size(reader: &mut SliceReader, size: usize) -> Option<Self> {
-        Some(Self(std::net::Ipv6Addr::from(
-            <[u8; 16]>::try_from(reader.read_slice(size)).ok()?,
-        )))
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        size: usize,
+    ) -> Result<Self, DecodeError> {
+        let offset = reader.pos();
+        let octets = <[u8; 16]>::try_from(reader.read_slice(size)?).map_err(|_| {
+            DecodeError::RdataLengthMismatch {
+                offset,
+                rtype: DnsTypeNum::AAAA,
+                rdlength: size,
+                consumed: 16,
+            }
+        })?;
+        Ok(Self(std::net::Ipv6Addr::from(octets)))
+    }
+
+    #[inline]
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(&self.0.octets());
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
     }
 }
@@ -0,0 +1,614 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+#[cfg(feature = "fmt")]
+use crate::dns::types::base::record::dnssec::hex_upper;
+use crate::dns::types::base::{DnsType, DnsTypeNum, RawDomain};
+use crate::dns::utils::{
+    DecodeError, SliceOperator, SliceReader, rdata_consumed, rdata_end, rdata_within,
+};
+#[cfg(feature = "fmt")]
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+// NSEC/NSEC3中的Type Bit Maps，按类型号升序保存
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct TypeBitmap(Vec<u16>);
+
+impl TypeBitmap {
+    pub fn from_types<T: IntoIterator<Item = u16>>(types: T) -> TypeBitmap {
+        let mut vec: Vec<u16> = types.into_iter().collect();
+        vec.sort_unstable();
+        vec.dedup();
+        TypeBitmap(vec)
+    }
+
+    // rtype为所属记录的类型(NSEC或NSEC3)，仅用于错误信息
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        size: usize,
+        rtype: u16,
+    ) -> Result<TypeBitmap, DecodeError> {
+        let end = rdata_end(reader, size, 0, rtype)?;
+        let mut types = Vec::new();
+        let mut last_window: Option<u8> = None;
+        while reader.pos() < end {
+            let offset = reader.pos();
+            let malformed = |reason| DecodeError::MalformedRdata {
+                offset,
+                rtype,
+                reason,
+            };
+            if offset + 2 > end {
+                return Err(malformed("truncated bitmap window"));
+            }
+            let window = reader.read_u8()?;
+            let len = reader.read_u8()? as usize;
+            // 窗口必须递增，长度为1~32
+            if last_window.is_some_and(|last| last >= window) {
+                return Err(malformed("bitmap windows out of order"));
+            }
+            if len == 0 || len > 32 || reader.pos() + len > end {
+                return Err(malformed("bad bitmap length"));
+            }
+            last_window = Some(window);
+            for (i, byte) in reader.read_slice(len)?.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        types.push((window as u16) << 8 | (i * 8 + bit) as u16);
+                    }
+                }
+            }
+        }
+        Ok(TypeBitmap(types))
+    }
+
+    pub fn encode(&self, operator: &mut SliceOperator) {
+        let mut iter = self.0.iter().peekable();
+        while let Some(first) = iter.peek() {
+            let window = (**first >> 8) as u8;
+            let mut bitmap = [0_u8; 32];
+            let mut len = 0;
+            while let Some(t) = iter.next_if(|t| (**t >> 8) as u8 == window) {
+                let low = (*t & 0xFF) as usize;
+                bitmap[low / 8] |= 0x80 >> (low % 8);
+                len = low / 8 + 1;
+            }
+            operator.write_u8(window);
+            operator.write_u8(len as u8);
+            operator.write_slice(&bitmap[..len]);
+        }
+    }
+
+    #[inline]
+    pub fn types(&self) -> &[u16] {
+        &self.0
+    }
+
+    // 无法识别的类型会被忽略，需要完整信息时使用types()
+    pub fn dns_types(&self) -> Vec<DnsType> {
+        self.0
+            .iter()
+            .filter_map(|t| DnsType::from_u16(*t))
+            .collect()
+    }
+
+    #[inline]
+    pub fn contains(&self, dns_type: u16) -> bool {
+        self.0.binary_search(&dns_type).is_ok()
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for TypeBitmap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.0.iter().map(|t| type_name(*t)).collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+// 未知类型按RFC 3597输出为TYPEnnn
+pub(crate) fn type_name(dns_type: u16) -> String {
+    match DnsType::from_u16(dns_type) {
+        Some(t) => t.as_str().to_string(),
+        None => format!("TYPE{}", dns_type),
+    }
+}
+
+// RFC 4648 base32hex，不带填充，用于NSEC3的哈希
+pub(crate) fn base32hex_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer = 0_u16;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[((buffer >> bits) & 0x1F) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize] as char);
+    }
+    out
+}
+
+// base32hex_encode的逆运算，不区分大小写，不接受填充
+pub(crate) fn base32hex_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0_u16;
+    let mut bits = 0;
+    for c in text {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'A'..=b'V' => c - b'A' + 10,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // 剩余的bit只能是补齐用的0
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[derive(Clone, Debug)]
+pub struct NSEC {
+    next_domain: Rc<RawDomain>,
+    types: TypeBitmap,
+}
+
+impl NSEC {
+    pub fn new(next_domain: Rc<RawDomain>, types: TypeBitmap) -> NSEC {
+        NSEC { next_domain, types }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<NSEC> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        let end = rdata_end(reader, raw_len, 1, DnsTypeNum::NSEC)?;
+        // 下一个名称必须完整位于RDATA内
+        let next_domain = Rc::new(RawDomain::from_reader(reader)?);
+        rdata_within(reader, start, raw_len, DnsTypeNum::NSEC)?;
+        let types =
+            TypeBitmap::from_reader_with_size(reader, end - reader.pos(), DnsTypeNum::NSEC)?;
+        Ok(Self { next_domain, types })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.next_domain.as_ref().as_ref());
+        operator.write_u8(0x0);
+        self.types.encode(operator);
+    }
+
+    // NSEC中的下一个域名保持原样，不转小写(RFC 6840 5.1)
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}NSEC:")?;
+        writeln!(f, "{_indent}\tNext Domain Name: {}", self.next_domain)?;
+        writeln!(f, "{_indent}\tRR type in bit map: {}", self.types)
+    }
+}
+
+#[allow(unused)]
+impl NSEC {
+    #[inline]
+    pub fn next_domain(&self) -> Rc<RawDomain> {
+        self.next_domain.clone()
+    }
+
+    #[inline]
+    pub fn types(&self) -> &TypeBitmap {
+        &self.types
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for NSEC {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NSEC3 {
+    hash_algorithm: u8,
+    // 最低位为Opt-Out
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+    next_hashed_owner: Vec<u8>,
+    types: TypeBitmap,
+}
+
+impl NSEC3 {
+    pub const OPT_OUT: u8 = 0x01;
+
+    pub fn new(
+        hash_algorithm: u8,
+        flags: u8,
+        iterations: u16,
+        salt: Vec<u8>,
+        next_hashed_owner: Vec<u8>,
+        types: TypeBitmap,
+    ) -> NSEC3 {
+        NSEC3 {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt,
+            next_hashed_owner,
+            types,
+        }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<NSEC3> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        let end = rdata_end(reader, raw_len, 6, DnsTypeNum::NSEC3)?;
+        let hash_algorithm = reader.read_u8()?;
+        let flags = reader.read_u8()?;
+        let iterations = reader.read_u16()?;
+        let salt_len = reader.read_u8()? as usize;
+        let salt = reader.read_slice(salt_len)?.to_vec();
+        let hash_len = reader.read_u8()? as usize;
+        let next_hashed_owner = reader.read_slice(hash_len)?.to_vec();
+        rdata_within(reader, start, raw_len, DnsTypeNum::NSEC3)?;
+        let types =
+            TypeBitmap::from_reader_with_size(reader, end - reader.pos(), DnsTypeNum::NSEC3)?;
+        Ok(Self {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt,
+            next_hashed_owner,
+            types,
+        })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_u8(self.hash_algorithm);
+        operator.write_u8(self.flags);
+        operator.write_u16(self.iterations);
+        operator.write_u8(self.salt.len() as u8);
+        operator.write_slice(&self.salt);
+        operator.write_u8(self.next_hashed_owner.len() as u8);
+        operator.write_slice(&self.next_hashed_owner);
+        self.types.encode(operator);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}NSEC3:")?;
+        writeln!(
+            f,
+            "{_indent}\tHash Algorithm: {}",
+            fmt_hash_algorithm(self.hash_algorithm)
+        )?;
+        writeln!(
+            f,
+            "{_indent}\tFlags: {:#04X}{}",
+            self.flags,
+            if self.is_opt_out() { " (Opt-Out)" } else { "" }
+        )?;
+        writeln!(f, "{_indent}\tIterations: {}", self.iterations)?;
+        writeln!(f, "{_indent}\tSalt: {}", fmt_salt(&self.salt))?;
+        writeln!(
+            f,
+            "{_indent}\tNext Hashed Owner: {}",
+            base32hex_encode(&self.next_hashed_owner)
+        )?;
+        writeln!(f, "{_indent}\tRR type in bit map: {}", self.types)
+    }
+}
+
+#[allow(unused)]
+impl NSEC3 {
+    #[inline]
+    pub fn hash_algorithm(&self) -> u8 {
+        self.hash_algorithm
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn is_opt_out(&self) -> bool {
+        self.flags & Self::OPT_OUT != 0
+    }
+
+    #[inline]
+    pub fn iterations(&self) -> u16 {
+        self.iterations
+    }
+
+    #[inline]
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    #[inline]
+    pub fn next_hashed_owner(&self) -> &[u8] {
+        &self.next_hashed_owner
+    }
+
+    #[inline]
+    pub fn types(&self) -> &TypeBitmap {
+        &self.types
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for NSEC3 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NSEC3PARAM {
+    hash_algorithm: u8,
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+}
+
+impl NSEC3PARAM {
+    pub fn new(hash_algorithm: u8, flags: u8, iterations: u16, salt: Vec<u8>) -> NSEC3PARAM {
+        NSEC3PARAM {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt,
+        }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<NSEC3PARAM> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        rdata_end(reader, raw_len, 5, DnsTypeNum::NSEC3PARAM)?;
+        let hash_algorithm = reader.read_u8()?;
+        let flags = reader.read_u8()?;
+        let iterations = reader.read_u16()?;
+        let salt_len = reader.read_u8()? as usize;
+        let salt = reader.read_slice(salt_len)?.to_vec();
+        rdata_consumed(reader, start, raw_len, DnsTypeNum::NSEC3PARAM)?;
+        Ok(Self {
+            hash_algorithm,
+            flags,
+            iterations,
+            salt,
+        })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_u8(self.hash_algorithm);
+        operator.write_u8(self.flags);
+        operator.write_u16(self.iterations);
+        operator.write_u8(self.salt.len() as u8);
+        operator.write_slice(&self.salt);
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}NSEC3PARAM:")?;
+        writeln!(
+            f,
+            "{_indent}\tHash Algorithm: {}",
+            fmt_hash_algorithm(self.hash_algorithm)
+        )?;
+        writeln!(f, "{_indent}\tFlags: {:#04X}", self.flags)?;
+        writeln!(f, "{_indent}\tIterations: {}", self.iterations)?;
+        writeln!(f, "{_indent}\tSalt: {}", fmt_salt(&self.salt))
+    }
+}
+
+#[allow(unused)]
+impl NSEC3PARAM {
+    #[inline]
+    pub fn hash_algorithm(&self) -> u8 {
+        self.hash_algorithm
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn iterations(&self) -> u16 {
+        self.iterations
+    }
+
+    #[inline]
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for NSEC3PARAM {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[cfg(feature = "fmt")]
+fn fmt_hash_algorithm(algorithm: u8) -> String {
+    match algorithm {
+        1 => "SHA-1 (1)".to_string(),
+        _ => format!("Unknown ({})", algorithm),
+    }
+}
+
+#[cfg(feature = "fmt")]
+fn fmt_salt(salt: &[u8]) -> String {
+    if salt.is_empty() {
+        "-".to_string()
+    } else {
+        hex_upper(salt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::types::base::DnsTypeNum;
+
+    #[test]
+    fn test_type_bitmap() {
+        // RFC 4034 4.3: A MX RRSIG NSEC TYPE1234
+        let slice = [
+            0x00, 0x06, 0x40, 0x01, 0x00, 0x00, 0x00, 0x03, 0x04, 0x1b, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
+        ];
+        let mut reader = SliceReader::from_slice(&slice);
+        let bitmap =
+            TypeBitmap::from_reader_with_size(&mut reader, slice.len(), DnsTypeNum::NSEC).unwrap();
+        assert_eq!(bitmap.types(), &[1, 15, 46, 47, 1234]);
+        assert_eq!(
+            bitmap.dns_types(),
+            vec![DnsType::A, DnsType::MX, DnsType::RRSIG, DnsType::NSEC]
+        );
+        assert!(bitmap.contains(DnsTypeNum::MX));
+        assert!(!bitmap.contains(DnsTypeNum::AAAA));
+        #[cfg(feature = "fmt")]
+        assert_eq!(bitmap.to_string(), "A MX RRSIG NSEC TYPE1234");
+
+        let buf = &mut [0_u8; 64];
+        let mut operator = SliceOperator::from_slice(buf);
+        TypeBitmap::from_types([1234, 47, 1, 46, 15]).encode(&mut operator);
+        let len = operator.pos();
+        assert_eq!(&buf[..len], &slice);
+
+        // 窗口重复或长度为0都视为格式错误
+        let bad = [0x00, 0x01, 0x40, 0x00, 0x01, 0x01];
+        let mut reader = SliceReader::from_slice(&bad);
+        assert!(
+            TypeBitmap::from_reader_with_size(&mut reader, bad.len(), DnsTypeNum::NSEC).is_err()
+        );
+        let bad = [0x00, 0x00];
+        let mut reader = SliceReader::from_slice(&bad);
+        assert!(
+            TypeBitmap::from_reader_with_size(&mut reader, bad.len(), DnsTypeNum::NSEC).is_err()
+        );
+    }
+
+    #[test]
+    fn test_nsec() {
+        let nsec = NSEC::new(
+            Rc::new(RawDomain::from_str("host.example.com").unwrap()),
+            TypeBitmap::from_types([1, 15, 46, 47, 1234]),
+        );
+        let buf = &mut [0_u8; 128];
+        let mut operator = SliceOperator::from_slice(buf);
+        nsec.encode_rdata(&mut operator);
+        let len = operator.pos();
+        let mut reader = SliceReader::from_slice(&buf[..len]);
+        let decoded = NSEC::from_reader_with_size(&mut reader, len).unwrap();
+        assert_eq!(reader.pos(), len);
+        assert_eq!(
+            decoded.next_domain().as_ref().to_string().unwrap(),
+            "host.example.com"
+        );
+        assert_eq!(decoded.types(), nsec.types());
+    }
+
+    #[test]
+    fn test_nsec3() {
+        // RFC 5155 Appendix A: 0p9mhaveqvm6t7vbl5lop2u3t2rp3tom.example. NSEC3 1 1 12 aabbccdd
+        //     2t7b4g4vsa5smi47k61mv5bv1a22bojr MX DNSKEY NS SOA NSEC3PARAM RRSIG
+        let next = [
+            0x17, 0x4e, 0xb2, 0x40, 0x9f, 0xe2, 0x8b, 0xcb, 0x48, 0x87, 0xa1, 0x83, 0x6f, 0x95,
+            0x7f, 0x0a, 0x84, 0x25, 0xe2, 0x7b,
+        ];
+        assert_eq!(base32hex_encode(&next), "2T7B4G4VSA5SMI47K61MV5BV1A22BOJR");
+        assert_eq!(
+            base32hex_decode(b"2t7b4g4vsa5smi47k61mv5bv1a22bojr").unwrap(),
+            next
+        );
+        assert_eq!(base32hex_decode(b"00").unwrap(), [0]);
+        // 非法字符、多余的5bit以及非0的补齐bit
+        assert_eq!(base32hex_decode(b"0W"), None);
+        assert_eq!(base32hex_decode(b"000"), None);
+        assert_eq!(base32hex_decode(b"01"), None);
+        let nsec3 = NSEC3::new(
+            1,
+            1,
+            12,
+            vec![0xaa, 0xbb, 0xcc, 0xdd],
+            next.to_vec(),
+            TypeBitmap::from_types([15, 48, 2, 6, 51, 46]),
+        );
+        let buf = &mut [0_u8; 128];
+        let mut operator = SliceOperator::from_slice(buf);
+        nsec3.encode_rdata(&mut operator);
+        let len = operator.pos();
+        let mut reader = SliceReader::from_slice(&buf[..len]);
+        let decoded = NSEC3::from_reader_with_size(&mut reader, len).unwrap();
+        assert_eq!(reader.pos(), len);
+        assert!(decoded.is_opt_out());
+        assert_eq!(decoded.iterations(), 12);
+        assert_eq!(decoded.salt(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(decoded.next_hashed_owner(), &next);
+        assert_eq!(decoded.types().types(), &[2, 6, 15, 46, 48, 51]);
+
+        let param = NSEC3PARAM::new(1, 0, 12, vec![0xaa, 0xbb, 0xcc, 0xdd]);
+        let buf = &mut [0_u8; 16];
+        let mut operator = SliceOperator::from_slice(buf);
+        param.encode_rdata(&mut operator);
+        let len = operator.pos();
+        assert_eq!(&buf[..len], &[1, 0, 0, 12, 4, 0xaa, 0xbb, 0xcc, 0xdd]);
+        let mut reader = SliceReader::from_slice(&buf[..len]);
+        let decoded = NSEC3PARAM::from_reader_with_size(&mut reader, len).unwrap();
+        assert_eq!(decoded.iterations(), 12);
+        #[cfg(feature = "fmt")]
+        assert!(decoded.to_string().contains("Salt: AABBCCDD"));
+    }
+}
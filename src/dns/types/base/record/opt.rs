@@ -0,0 +1,99 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::DnsTypeNum;
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader, rdata_end, rdata_within};
+#[cfg(feature = "fmt")]
+use std::fmt::{Display, Formatter};
+
+// EDNS(0)的伪记录(RFC 6891)，udp负载大小/扩展rcode/DO位存放在Record的class与ttl中，
+// 这里只保存rdata里的option列表
+#[derive(Clone, Debug, Default)]
+pub struct OPT {
+    options: Vec<(u16, Vec<u8>)>,
+}
+
+impl OPT {
+    pub const DNSSEC_OK: u32 = 0x8000;
+
+    pub fn new(options: Vec<(u16, Vec<u8>)>) -> OPT {
+        OPT { options }
+    }
+
+    #[inline]
+    pub fn get_general_output(&self) -> Option<OPT> {
+        Some(self.clone())
+    }
+
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        let end = rdata_end(reader, raw_len, 0, DnsTypeNum::OPT)?;
+        let mut options = Vec::new();
+        while reader.pos() < end {
+            let code = reader.read_u16()?;
+            let len = reader.read_u16()? as usize;
+            options.push((code, reader.read_slice(len)?.to_vec()));
+            rdata_within(reader, start, raw_len, DnsTypeNum::OPT)?;
+        }
+        Ok(Self { options })
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        for (code, data) in &self.options {
+            operator.write_u16(*code);
+            operator.write_u16(data.len() as u16);
+            operator.write_slice(data);
+        }
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
+    }
+
+    #[inline]
+    pub fn options(&self) -> &[(u16, Vec<u8>)] {
+        &self.options
+    }
+
+    #[cfg(feature = "fmt")]
+    pub fn fmt_with_suffix(&self, f: &mut Formatter, _indent: &str) -> std::fmt::Result {
+        writeln!(f, "{_indent}OPT:")?;
+        for (code, data) in &self.options {
+            writeln!(f, "{_indent}\tOption: {} (length {})", code, data.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "fmt")]
+impl Display for OPT {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_suffix(f, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opt() {
+        let slice = [0x00, 0x0a, 0x00, 0x02, 0xab, 0xcd, 0x00, 0x0c, 0x00, 0x00];
+        let mut reader = SliceReader::from_slice(&slice);
+        let opt = OPT::from_reader_with_size(&mut reader, slice.len()).unwrap();
+        assert_eq!(opt.options(), &[(10, vec![0xab, 0xcd]), (12, vec![])]);
+        assert_eq!(reader.pos(), slice.len());
+
+        let mut buf = [0_u8; 16];
+        let mut operator = SliceOperator::from_slice(&mut buf);
+        opt.encode_rdata(&mut operator);
+        assert_eq!(operator.pos(), slice.len());
+        assert_eq!(&buf[..slice.len()], &slice);
+
+        let mut reader = SliceReader::from_slice(&slice[..5]);
+        assert!(OPT::from_reader_with_size(&mut reader, 5).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(feature = "fmt")]
 use crate::dns::types::base::DnsTTL;
-use crate::dns::types::base::RawDomain;
-use crate::dns::utils::SliceReader;
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader, rdata_consumed, rdata_end};
 #[cfg(feature = "fmt")]
 use std::fmt::{Display, Formatter};
 use std::rc::Rc;
@@ -26,23 +26,76 @@ pub struct SOA {
 }
 
 impl SOA {
+    // 两个至少为根域名的名称与5个u32
+    const LEAST_SIZE: usize = 22;
+
+    pub fn new(
+        primary_name: Rc<RawDomain>,
+        rname: Rc<RawDomain>,
+        serial_number: u32,
+        refresh_interval: u32,
+        retry_interval: u32,
+        expire_limit: u32,
+        minimum_ttl: u32,
+    ) -> SOA {
+        SOA {
+            primary_name,
+            rname,
+            serial_number,
+            refresh_interval,
+            retry_interval,
+            expire_limit,
+            minimum_ttl,
+        }
+    }
+
     #[inline]
     pub fn get_general_output(&self) -> Option<SOA> {
         Some(self.clone())
     }
 
-    pub fn from_reader_with_size(reader: &mut SliceReader, _raw_len: usize) -> Option<Self> {
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<Self, DecodeError> {
+        let start = reader.pos();
+        rdata_end(reader, raw_len, Self::LEAST_SIZE, DnsTypeNum::SOA)?;
         let primary_name = Rc::new(RawDomain::from_reader(reader)?);
         let rname = Rc::new(RawDomain::from_reader(reader)?);
-        Some(Self {
+        let soa = Self {
             primary_name,
             rname,
-            serial_number: reader.read_u32(),
-            refresh_interval: reader.read_u32(),
-            retry_interval: reader.read_u32(),
-            expire_limit: reader.read_u32(),
-            minimum_ttl: reader.read_u32(),
-        })
+            serial_number: reader.read_u32()?,
+            refresh_interval: reader.read_u32()?,
+            retry_interval: reader.read_u32()?,
+            expire_limit: reader.read_u32()?,
+            minimum_ttl: reader.read_u32()?,
+        };
+        rdata_consumed(reader, start, raw_len, DnsTypeNum::SOA)?;
+        Ok(soa)
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.primary_name.as_ref().as_ref());
+        operator.write_u8(0x0);
+        operator.write_slice(self.rname.as_ref().as_ref());
+        operator.write_u8(0x0);
+        self.encode_numbers(operator);
+    }
+
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        operator.write_slice(&self.primary_name.to_canonical_wire());
+        operator.write_slice(&self.rname.to_canonical_wire());
+        self.encode_numbers(operator);
+    }
+
+    #[inline]
+    fn encode_numbers(&self, operator: &mut SliceOperator) {
+        operator.write_u32(self.serial_number);
+        operator.write_u32(self.refresh_interval);
+        operator.write_u32(self.retry_interval);
+        operator.write_u32(self.expire_limit);
+        operator.write_u32(self.minimum_ttl);
     }
 
     #[cfg(feature = "fmt")]
@@ -1,5 +1,5 @@
-use crate::dns::types::base::RawString;
-use crate::dns::utils::SliceReader;
+use crate::dns::types::base::{DnsTypeNum, RawString};
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader, rdata_end};
 use smallvec::SmallVec;
 use std::fmt::Display;
 use std::rc::Rc;
@@ -8,6 +8,11 @@ use std::rc::Rc;
 pub struct TXT(Rc<SmallVec<[RawString; 1]>>);
 
 impl TXT {
+    #[inline]
+    pub fn new(strings: Vec<RawString>) -> TXT {
+        TXT(Rc::new(strings.into_iter().collect()))
+    }
+
     #[inline]
     pub fn get_index(&self) -> Rc<SmallVec<[RawString; 1]>> {
         self.0.clone()
@@ -30,20 +35,46 @@ impl TXT {
     }
 
     #[inline]
-    pub fn from_reader_with_size(reader: &mut SliceReader, _raw_len: usize) -> Option<TXT> {
+    pub fn from_reader_with_size(
+        reader: &mut SliceReader,
+        raw_len: usize,
+    ) -> Result<TXT, DecodeError> {
         let pos = reader.pos();
+        let end = rdata_end(reader, raw_len, 0, DnsTypeNum::TXT)?;
         let mut vec = SmallVec::new();
-        while reader.pos() < _raw_len + pos {
-            if let Some(v) = RawString::from_reader_with_maximum(reader, _raw_len + pos) {
-                vec.push(v);
-            } else {
-                break;
+        while reader.pos() < end {
+            // character-string不能跨出RDATA
+            let string_end = reader.pos() + 1 + reader.peek_u8()? as usize;
+            if string_end > end {
+                return Err(DecodeError::RdataLengthMismatch {
+                    offset: pos,
+                    rtype: DnsTypeNum::TXT,
+                    rdlength: raw_len,
+                    consumed: string_end - pos,
+                });
             }
+            vec.push(RawString::from_reader_with_maximum(reader, end)?);
         }
         if vec.is_empty() {
-            return None;
+            return Err(DecodeError::MalformedRdata {
+                offset: pos,
+                rtype: DnsTypeNum::TXT,
+                reason: "TXT without character-string",
+            });
+        }
+        Ok(TXT(vec.into()))
+    }
+
+    pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+        for v in self.0.iter() {
+            operator.write_u8(v.as_bytes().len() as u8);
+            operator.write_slice(v.as_bytes());
         }
-        Some(TXT(vec.into()))
+    }
+
+    #[inline]
+    pub fn encode_canonical(&self, operator: &mut SliceOperator) {
+        self.encode_rdata(operator)
     }
 }
 
@@ -70,7 +101,14 @@ mod tests {
         ];
         let mut reader = SliceReader::from_slice(&slice);
         reader.set_pos(46);
-        let txt = TXT::from_reader_with_size(&mut reader, 55);
+        let txt = TXT::from_reader_with_size(&mut reader, 55).unwrap();
+        assert_eq!(txt.get_index().len(), 1);
         assert_eq!(reader.as_ref()[reader.pos()], 192);
+
+        // character-string超出RDATA
+        reader.set_pos(45);
+        assert!(TXT::from_reader_with_size(&mut reader, 55).is_err());
+        reader.set_pos(46);
+        assert!(TXT::from_reader_with_size(&mut reader, 0).is_err());
     }
 }
@@ -1,4 +1,4 @@
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceReader};
 use std::fmt::Display;
 
 #[derive(Clone, Debug)]
@@ -7,17 +7,33 @@ pub struct RawString {
 }
 
 impl RawString {
-    pub fn from_reader_with_maximum(reader: &mut SliceReader, maximum: usize) -> Option<RawString> {
-        let len = reader.peek_u8();
-        if reader.pos() + len as usize > maximum {
-            return None;
+    // character-string最长255字节(RFC 1035 3.3)
+    pub fn new(str: Vec<u8>) -> Option<RawString> {
+        (str.len() <= u8::MAX as usize).then_some(RawString { str })
+    }
+
+    pub fn from_reader_with_maximum(
+        reader: &mut SliceReader,
+        maximum: usize,
+    ) -> Result<RawString, DecodeError> {
+        let len = reader.peek_u8()? as usize;
+        if reader.pos() + 1 + len > maximum {
+            return Err(DecodeError::Truncated {
+                offset: reader.pos() + 1,
+                need: len,
+            });
         }
-        reader.skip(1);
-        Some(RawString {
-            str: Vec::from(reader.read_slice(len as usize)),
+        reader.skip(1)?;
+        Ok(RawString {
+            str: Vec::from(reader.read_slice(len)?),
         })
     }
 
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.str.as_slice()
+    }
+
     pub fn to_string(&self) -> String {
         String::from_utf8_lossy(self.str.as_slice()).to_string()
     }
@@ -32,7 +48,7 @@ impl<'a> Display for RawString {
 #[cfg(test)]
 mod tests {
     use crate::dns::types::base::RawString;
-    use crate::dns::utils::SliceReader;
+    use crate::dns::utils::{DecodeError, SliceReader};
 
     #[test]
     fn test_raw_string() {
@@ -62,5 +78,15 @@ mod tests {
                 97
             ]
         );
+
+        // character-string超出允许的范围
+        reader.set_pos(46);
+        assert_eq!(
+            RawString::from_reader_with_maximum(&mut reader, 46 + 54).unwrap_err(),
+            DecodeError::Truncated {
+                offset: 47,
+                need: 54
+            }
+        );
     }
 }
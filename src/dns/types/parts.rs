@@ -1,12 +1,28 @@
 mod header;
+mod message_builder;
 mod question;
 mod record;
+mod record_ref;
 mod request;
 mod response;
+#[cfg(feature = "fmt")]
+mod response_fmt;
+mod response_ref;
+#[cfg(feature = "serde")]
+mod rfc8427;
 
+#[allow(unused_imports)]
+pub use message_builder::MessageBuilder;
 pub use record::Record;
 pub use record::RecordDataType;
 #[cfg(feature = "fmt")]
 pub use record::RecordFmtType;
-pub use request::Request;
+#[allow(unused_imports)]
+pub use record_ref::{CharacterStrings, RecordRef};
+pub use request::{Edns, Request};
 pub use response::Response;
+#[cfg(feature = "fmt")]
+#[allow(unused_imports)]
+pub use response_fmt::{ResponseDisplay, ResponseFmtType};
+#[allow(unused_imports)]
+pub use response_ref::{QuestionRef, Records, ResponseRef};
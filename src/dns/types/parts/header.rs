@@ -1,6 +1,6 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceReader};
 use rand::{Rng, rng};
 use std::fmt::Display;
 
@@ -125,7 +125,7 @@ impl RequestHeader {
 
     #[inline]
     fn get_flags_second_u8(&self) -> u8 {
-        self.z << 6 | self.check_disable << 4 | self.opcode
+        self.z << 6 | self.check_disable << 4
     }
 
     #[inline]
@@ -168,23 +168,25 @@ fn format_flag(value: u8, position: u8, length: u8) -> String {
     s
 }
 
-impl From<&mut SliceReader<'_>> for RequestHeader {
-    fn from(reader: &mut SliceReader) -> Self {
-        let id = reader.read_u16();
-        let first_u8 = reader.read_u8();
-        let second_u8 = reader.read_u8();
+impl TryFrom<&mut SliceReader<'_>> for RequestHeader {
+    type Error = DecodeError;
+
+    fn try_from(reader: &mut SliceReader) -> Result<Self, DecodeError> {
+        let id = reader.read_u16()?;
+        let first_u8 = reader.read_u8()?;
+        let second_u8 = reader.read_u8()?;
         let response = first_u8 >> 7;
         let opcode = (first_u8 << 1) >> 4;
         let truncated = (first_u8 & 0b0000_0010) >> 1;
         let rec_desired = first_u8 & 0b0000_0001;
         let z = (second_u8 << 1) >> 7;
         let check_disable = (second_u8 << 3) >> 7;
-        let questions = reader.read_u16();
-        let answer_rrs = reader.read_u16();
-        let authority_rrs = reader.read_u16();
-        let additional_rrs = reader.read_u16();
+        let questions = reader.read_u16()?;
+        let answer_rrs = reader.read_u16()?;
+        let authority_rrs = reader.read_u16()?;
+        let additional_rrs = reader.read_u16()?;
 
-        Self {
+        Ok(Self {
             id,
             response,
             opcode,
@@ -196,7 +198,7 @@ impl From<&mut SliceReader<'_>> for RequestHeader {
             answer_rrs,
             authority_rrs,
             additional_rrs,
-        }
+        })
     }
 }
 
@@ -243,11 +245,13 @@ pub struct ResponseHeader {
     pub additional_rrs: u16,
 }
 
-impl From<&mut SliceReader<'_>> for ResponseHeader {
-    fn from(reader: &mut SliceReader) -> Self {
-        let id = reader.read_u16();
-        let first_u8 = reader.read_u8();
-        let second_u8 = reader.read_u8();
+impl TryFrom<&mut SliceReader<'_>> for ResponseHeader {
+    type Error = DecodeError;
+
+    fn try_from(reader: &mut SliceReader) -> Result<Self, DecodeError> {
+        let id = reader.read_u16()?;
+        let first_u8 = reader.read_u8()?;
+        let second_u8 = reader.read_u8()?;
 
         let response = first_u8 >> 7;
         let opcode = (first_u8 << 1) >> 4;
@@ -259,12 +263,12 @@ impl From<&mut SliceReader<'_>> for ResponseHeader {
         let authenticated = (second_u8 << 2) >> 7;
         let check_disable = (second_u8 << 3) >> 7;
         let rcode = (second_u8 << 4) >> 4;
-        let questions = reader.read_u16();
-        let answer_rrs = reader.read_u16();
-        let authority_rrs = reader.read_u16();
-        let additional_rrs = reader.read_u16();
+        let questions = reader.read_u16()?;
+        let answer_rrs = reader.read_u16()?;
+        let authority_rrs = reader.read_u16()?;
+        let additional_rrs = reader.read_u16()?;
 
-        Self {
+        Ok(Self {
             id,
             response,
             opcode,
@@ -280,7 +284,7 @@ impl From<&mut SliceReader<'_>> for ResponseHeader {
             answer_rrs,
             authority_rrs,
             additional_rrs,
-        }
+        })
     }
 }
 
@@ -300,11 +304,11 @@ impl ResponseHeader {
             | self.z << 6
             | self.authenticated << 5
             | self.check_disable << 4
-            | self.opcode
+            | self.rcode
     }
 
     #[inline]
-    fn get_flags(&self) -> u16 {
+    pub(crate) fn get_flags(&self) -> u16 {
         (self.get_flags_first_u8() as u16) << 8 | (self.get_flags_second_u8() as u16)
     }
 }
@@ -488,7 +492,7 @@ mod tests {
                 0x6f, 0x6d, 0x00, 0x00, 0x01, 0x00, 0x01,
             ][..],
         );
-        let header = RequestHeader::from(&mut reader);
+        let header = RequestHeader::try_from(&mut reader).unwrap();
         assert_eq!(header.id, 0x75b3);
         assert_eq!(header.response, 0x00);
         assert_eq!(header.opcode, 0x00);
@@ -523,7 +527,7 @@ mod tests {
                 0x6b, 0x2a, 0x10,
             ][..],
         );
-        let header = ResponseHeader::from(&mut reader);
+        let header = ResponseHeader::try_from(&mut reader).unwrap();
         assert_eq!(header.id, 0x0f04);
         assert_eq!(header.response, 0x01);
         assert_eq!(header.opcode, 0x00);
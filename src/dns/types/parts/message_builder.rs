@@ -0,0 +1,394 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::{DnsOpcode, RawDomain};
+use crate::dns::types::parts::header::ResponseHeader;
+use crate::dns::types::parts::question::Question;
+use crate::dns::types::parts::record::Record;
+use crate::dns::types::parts::request::Edns;
+use crate::dns::utils::SliceOperator;
+use rand::{Rng, rng};
+use std::rc::Rc;
+
+// 构造任意DNS消息：可设置全部头部标志位、任意class的多个问题以及三个记录部分，
+// 用于测试、服务端应答以及发送NOTIFY/UPDATE等非查询消息。
+// 域名均不压缩，编码时由调用者保证缓冲区足够大
+#[derive(Debug)]
+pub struct MessageBuilder {
+    header: ResponseHeader,
+    // 完整的12bit rcode，高8bit需通过OPT伪记录携带(RFC 6891 6.1.3)
+    rcode: u16,
+    questions: Vec<Question>,
+    answers: Vec<Record>,
+    authorities: Vec<Record>,
+    additionals: Vec<Record>,
+    edns: Option<Edns>,
+}
+
+impl Default for MessageBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageBuilder {
+    // 随机id，opcode为QUERY，其余标志位均为0
+    pub fn new() -> MessageBuilder {
+        MessageBuilder {
+            header: ResponseHeader {
+                id: rng().random(),
+                response: 0,
+                opcode: DnsOpcode::QUERY,
+                authoritative: 0,
+                truncated: 0,
+                rec_desired: 0,
+                rec_avail: 0,
+                z: 0,
+                authenticated: 0,
+                check_disable: 0,
+                rcode: 0,
+                questions: 0,
+                answer_rrs: 0,
+                authority_rrs: 0,
+                additional_rrs: 0,
+            },
+            rcode: 0,
+            questions: Vec::new(),
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+            edns: None,
+        }
+    }
+
+    #[inline]
+    pub fn id(mut self, id: u16) -> MessageBuilder {
+        self.header.id = id;
+        self
+    }
+
+    #[inline]
+    pub fn response(mut self, response: bool) -> MessageBuilder {
+        self.header.response = response as u8;
+        self
+    }
+
+    // 只保留低4bit，常用值见DnsOpcode
+    #[inline]
+    pub fn opcode(mut self, opcode: u8) -> MessageBuilder {
+        self.header.opcode = opcode & 0xf;
+        self
+    }
+
+    #[inline]
+    pub fn authoritative(mut self, authoritative: bool) -> MessageBuilder {
+        self.header.authoritative = authoritative as u8;
+        self
+    }
+
+    #[inline]
+    pub fn truncated(mut self, truncated: bool) -> MessageBuilder {
+        self.header.truncated = truncated as u8;
+        self
+    }
+
+    #[inline]
+    pub fn rec_desired(mut self, rec_desired: bool) -> MessageBuilder {
+        self.header.rec_desired = rec_desired as u8;
+        self
+    }
+
+    #[inline]
+    pub fn rec_avail(mut self, rec_avail: bool) -> MessageBuilder {
+        self.header.rec_avail = rec_avail as u8;
+        self
+    }
+
+    #[inline]
+    pub fn authenticated(mut self, authenticated: bool) -> MessageBuilder {
+        self.header.authenticated = authenticated as u8;
+        self
+    }
+
+    #[inline]
+    pub fn check_disable(mut self, check_disable: bool) -> MessageBuilder {
+        self.header.check_disable = check_disable as u8;
+        self
+    }
+
+    // 大于15的扩展rcode需要OPT伪记录，未设置EDNS时编码会自动附加默认的EDNS
+    #[inline]
+    pub fn rcode(mut self, rcode: u16) -> MessageBuilder {
+        self.rcode = rcode & 0xfff;
+        self.header.rcode = (rcode & 0xf) as u8;
+        self
+    }
+
+    #[inline]
+    pub fn question(mut self, qname: Rc<RawDomain>, qtype: u16, qclass: u16) -> MessageBuilder {
+        self.questions.push(Question {
+            qname,
+            qtype,
+            qclass,
+        });
+        self
+    }
+
+    #[inline]
+    pub fn answer(mut self, record: Record) -> MessageBuilder {
+        self.answers.push(record);
+        self
+    }
+
+    #[inline]
+    pub fn authority(mut self, record: Record) -> MessageBuilder {
+        self.authorities.push(record);
+        self
+    }
+
+    #[inline]
+    pub fn additional(mut self, record: Record) -> MessageBuilder {
+        self.additionals.push(record);
+        self
+    }
+
+    // OPT伪记录总是编码在additional部分的最后
+    #[inline]
+    pub fn edns(mut self, edns: Edns) -> MessageBuilder {
+        self.edns = Some(edns);
+        self
+    }
+
+    fn get_edns(&self) -> Option<Edns> {
+        match self.edns {
+            None if self.rcode > 0xf => Some(Edns::default()),
+            edns => edns,
+        }
+    }
+
+    // 从operator的当前位置写入整个消息
+    pub fn encode(&self, operator: &mut SliceOperator) {
+        let edns = self.get_edns();
+        operator.write_u16(self.header.id);
+        operator.write_u16(self.header.get_flags());
+        operator.write_u16(self.questions.len() as u16);
+        operator.write_u16(self.answers.len() as u16);
+        operator.write_u16(self.authorities.len() as u16);
+        operator.write_u16((self.additionals.len() + edns.is_some() as usize) as u16);
+        for q in &self.questions {
+            q.encode(operator);
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            record.encode(operator);
+        }
+        if let Some(edns) = edns {
+            edns.encode(operator, (self.rcode >> 4) as u8);
+        }
+    }
+
+    pub fn encode_to_udp<'b>(&self, buffer: &'b mut [u8]) -> &'b [u8] {
+        let mut operator = SliceOperator::from_slice(buffer);
+        self.encode(&mut operator);
+        let pos = operator.pos();
+        buffer[..pos].as_ref()
+    }
+
+    // 前两个Bytes为消息长度(RFC 1035 4.2.2)
+    pub fn encode_to_tcp<'b>(&self, buffer: &'b mut [u8]) -> &'b [u8] {
+        let mut operator = SliceOperator::from_slice(buffer);
+        operator.set_pos(2);
+        self.encode(&mut operator);
+        let pos = operator.pos();
+        buffer[0..2].copy_from_slice(((pos - 2) as u16).to_be_bytes().as_ref());
+        buffer[..pos].as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dns::types::base::record::{A, CNAME, NS, SOA, TXT};
+    use crate::dns::types::base::{DnsOpcode, DnsTypeNum, RawDomain, RawString};
+    use crate::dns::types::parts::header::ResponseHeader;
+    use crate::dns::types::parts::{Edns, MessageBuilder, Record, RecordDataType, Response};
+    use crate::dns::utils::SliceReader;
+    use std::net::Ipv4Addr;
+    use std::rc::Rc;
+
+    fn name(s: &str) -> Rc<RawDomain> {
+        Rc::new(RawDomain::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn test_flags() {
+        let mut buf = [0_u8; 512];
+        let message = MessageBuilder::new()
+            .id(0x1234)
+            .response(true)
+            .opcode(DnsOpcode::STATUS)
+            .authoritative(true)
+            .truncated(true)
+            .rec_desired(true)
+            .rec_avail(true)
+            .authenticated(true)
+            .check_disable(true)
+            .rcode(5)
+            .encode_to_udp(&mut buf);
+        assert_eq!(
+            message,
+            [0x12, 0x34, 0b1001_0111, 0b1011_0101, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let header = ResponseHeader::try_from(&mut SliceReader::from_slice(message)).unwrap();
+        assert_eq!(header.id, 0x1234);
+        assert_eq!(header.response, 1);
+        assert_eq!(header.opcode, DnsOpcode::STATUS);
+        assert_eq!(
+            (header.authoritative, header.truncated, header.rec_desired),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            (header.rec_avail, header.authenticated, header.check_disable),
+            (1, 1, 1)
+        );
+        assert_eq!(header.rcode, 5);
+    }
+
+    #[test]
+    fn test_sections() {
+        let origin = name("Example.com");
+        let message = MessageBuilder::new()
+            .id(7)
+            .response(true)
+            .authoritative(true)
+            .question(origin.clone(), DnsTypeNum::A, 1)
+            .question(name("version.bind"), DnsTypeNum::TXT, 3)
+            .answer(Record::from_data(
+                RawDomain::from_str("www.Example.com").unwrap(),
+                1,
+                300,
+                RecordDataType::CNAME(CNAME::new(origin.clone())),
+            ))
+            .answer(Record::from_data(
+                RawDomain::from_str("Example.com").unwrap(),
+                1,
+                300,
+                RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 1))),
+            ))
+            .authority(Record::from_data(
+                RawDomain::from_str("Example.com").unwrap(),
+                1,
+                3600,
+                RecordDataType::SOA(SOA::new(
+                    name("ns1.example.com"),
+                    name("hostmaster.example.com"),
+                    2024010101,
+                    7200,
+                    3600,
+                    1209600,
+                    300,
+                )),
+            ))
+            .additional(Record::from_data(
+                RawDomain::from_str("ns1.example.com").unwrap(),
+                1,
+                3600,
+                RecordDataType::NS(NS::new(name("ns2.example.com"))),
+            ))
+            .additional(Record::from_data(
+                RawDomain::from_str("version.bind").unwrap(),
+                3,
+                0,
+                RecordDataType::TXT(TXT::new(vec![
+                    RawString::new(b"dns-core".to_vec()).unwrap(),
+                ])),
+            ))
+            .edns(Edns {
+                udp_payload_size: 4096,
+                dnssec_ok: true,
+                tcp_keepalive: false,
+            });
+
+        let mut buf = [0_u8; 1024];
+        let udp = message.encode_to_udp(&mut buf).to_vec();
+        let response = Response::from_slice_uncheck(&udp).unwrap();
+        assert_eq!(response.header.questions, 2);
+        assert_eq!(response.header.answer_rrs, 2);
+        assert_eq!(response.header.authority_rrs, 1);
+        assert_eq!(response.header.additional_rrs, 3);
+        assert_eq!(response.question[1].qclass, 3);
+        assert_eq!(*response.question[1].qname, *name("version.bind"));
+
+        let answers: Vec<_> = response.answers().iter().collect();
+        // 大小写原样保留
+        assert_eq!(answers[0].name.to_string().unwrap(), "www.Example.com");
+        assert!(matches!(&answers[1].data, RecordDataType::A(a)
+            if a.get_index() == Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(matches!(
+            response.authorities()[0].data,
+            RecordDataType::SOA(_)
+        ));
+        let opt = response.additionals().last().unwrap();
+        assert_eq!(opt.rtype, DnsTypeNum::OPT);
+        assert_eq!(opt.class, 4096);
+        assert_eq!(opt.ttl, 0x8000);
+
+        // 重新编码解析出的记录应与原消息一致
+        let mut rebuilt = MessageBuilder::new()
+            .id(7)
+            .response(true)
+            .authoritative(true);
+        for q in &response.question {
+            rebuilt = rebuilt.question(q.qname.clone(), q.qtype, q.qclass);
+        }
+        let mut records = response.answer.into_iter();
+        for _ in 0..2 {
+            rebuilt = rebuilt.answer(records.next().unwrap());
+        }
+        rebuilt = rebuilt.authority(records.next().unwrap());
+        for record in records {
+            rebuilt = rebuilt.additional(record);
+        }
+        let mut rebuilt_buf = [0_u8; 1024];
+        assert_eq!(rebuilt.encode_to_udp(&mut rebuilt_buf), udp);
+
+        let mut tcp_buf = [0_u8; 1024];
+        let tcp = message.encode_to_tcp(&mut tcp_buf);
+        assert_eq!(u16::from_be_bytes([tcp[0], tcp[1]]) as usize, udp.len());
+        assert_eq!(&tcp[2..], udp);
+    }
+
+    #[test]
+    fn test_extended_rcode() {
+        let mut buf = [0_u8; 512];
+        // BADVERS(16)：头部只剩0，高8bit放入OPT的TTL
+        let message = MessageBuilder::new()
+            .response(true)
+            .rcode(16)
+            .encode_to_udp(&mut buf);
+        let response = Response::from_slice_uncheck(message).unwrap();
+        assert_eq!(response.header.rcode, 0);
+        assert_eq!(response.header.additional_rrs, 1);
+        let opt = &response.additionals()[0];
+        assert_eq!(opt.class, 1232);
+        assert_eq!(opt.ttl >> 24, 1);
+    }
+
+    #[test]
+    fn test_notify() {
+        let mut buf = [0_u8; 512];
+        let message = MessageBuilder::new()
+            .opcode(DnsOpcode::NOTIFY)
+            .authoritative(true)
+            .question(name("example.com"), DnsTypeNum::SOA, 1)
+            .encode_to_tcp(&mut buf);
+        assert_eq!(message[4] >> 3 & 0xf, DnsOpcode::NOTIFY);
+        let response = Response::from_slice_uncheck(&message[2..]).unwrap();
+        assert_eq!(response.header.response, 0);
+        assert_eq!(response.header.opcode, DnsOpcode::NOTIFY);
+        assert_eq!(response.question[0].qtype, DnsTypeNum::SOA);
+    }
+}
@@ -6,7 +6,7 @@ use crate::dns::types::base::DnsType;
 #[cfg(feature = "fmt")]
 use crate::dns::types::base::DnsClass;
 use crate::dns::types::base::RawDomain;
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader};
 use log::trace;
 #[cfg(feature = "fmt")]
 use std::fmt::Display;
@@ -23,26 +23,37 @@ impl Question {
     pub const FIX_SIZE: usize = 4;
     pub const LEAST_SIZE: usize = Self::FIX_SIZE + 1;
     #[inline]
-    pub fn new(reader: &mut SliceReader) -> Option<Question> {
+    pub fn new(reader: &mut SliceReader) -> Result<Question, DecodeError> {
         #[cfg(debug_assertions)]
         {
             trace!("准备解析Question内的name");
         }
 
-        let len = reader.len();
-        if reader.pos() + Self::FIX_SIZE > len {
+        let qname = Rc::new(RawDomain::from_reader(reader)?);
+        if reader.pos() + Self::FIX_SIZE > reader.len() {
             #[cfg(debug_assertions)]
             {
                 trace!("解析完name后，剩余Slice不足以存放Question的其余部分");
             }
-            return None; //检测出界，防止panic
+            return Err(DecodeError::Truncated {
+                offset: reader.pos(),
+                need: Self::FIX_SIZE,
+            });
         }
-        Some(Question {
-            qname: Rc::new(RawDomain::from_reader(reader)?),
-            qtype: reader.read_u16(),
-            qclass: reader.read_u16(),
+        Ok(Question {
+            qname,
+            qtype: reader.read_u16()?,
+            qclass: reader.read_u16()?,
         })
     }
+
+    #[inline]
+    pub fn encode(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.qname.as_ref().as_ref());
+        operator.write_u8(0x0);
+        operator.write_u16(self.qtype);
+        operator.write_u16(self.qclass);
+    }
 }
 
 #[cfg(feature = "fmt")]
@@ -1,12 +1,15 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
-use crate::dns::types::base::record::{A, AAAA, CNAME, NS, SOA, TXT};
+use crate::dns::types::base::record::{
+    A, AAAA, CNAME, DNSKEY, DS, NS, NSEC, NSEC3, NSEC3PARAM, OPT, RRSIG, SOA, TXT,
+};
 #[cfg(feature = "fmt")]
 use crate::dns::types::base::{DnsClass, DnsTTL};
 use crate::dns::types::base::{DnsTypeNum, RawDomain};
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceOperator, SliceReader};
 #[cfg(feature = "logger")]
 use log::{debug, trace};
+use std::cmp::Ordering;
 #[cfg(feature = "fmt")]
 use std::fmt::Display;
 
@@ -23,7 +26,7 @@ pub struct Record {
 
 impl Record {
     #[inline]
-    pub fn new(reader: &mut SliceReader) -> Option<Record> {
+    pub fn new(reader: &mut SliceReader) -> Result<Record, DecodeError> {
         #[cfg(feature = "logger")]
         {
             trace!("准备解析Record内的name");
@@ -36,12 +39,15 @@ impl Record {
             {
                 trace!("解析完name后，剩余Slice不足以存放Record的其余部分");
             }
-            return None;
+            return Err(DecodeError::Truncated {
+                offset: reader.pos(),
+                need: 10,
+            });
         }
-        let rtype = reader.read_u16();
-        let class = reader.read_u16();
-        let ttl = reader.read_u32();
-        let data_len = reader.read_u16();
+        let rtype = reader.read_u16()?;
+        let class = reader.read_u16()?;
+        let ttl = reader.read_u32()?;
+        let data_len = reader.read_u16()?;
         let data_len_usize = data_len as usize;
 
         if reader.pos() + data_len_usize > len {
@@ -52,7 +58,10 @@ impl Record {
                 reader.pos() + data_len_usize,
                 len
             );
-            return None;
+            return Err(DecodeError::Truncated {
+                offset: reader.pos(),
+                need: data_len_usize,
+            });
         }
 
         macro_rules! match_rtype {
@@ -68,7 +77,7 @@ impl Record {
                 //      _ => {
                 //          #[cfg(feature = "logger")]
                 //          trace!("Unsupported Type: {}", rtype);
-                //          return None;
+                //          return Err(DecodeError::UnsupportedType { .. });
                 //      }
                 // }
                 //
@@ -80,17 +89,20 @@ impl Record {
                     _ => {
                         #[cfg(feature = "logger")]
                         trace!("Unsupported Type: {}", rtype);
-                        return None;
+                        return Err(DecodeError::UnsupportedType {
+                            offset: reader.pos() - 10,
+                            rtype,
+                        });
                     }
                 }
             }
         }
 
-        let data = match_rtype! {A,NS,CNAME,SOA,TXT,AAAA};
+        let data = match_rtype! {A,NS,CNAME,SOA,TXT,AAAA,OPT,DS,RRSIG,NSEC,DNSKEY,NSEC3,NSEC3PARAM};
 
         // todo
 
-        Some(Record {
+        Ok(Record {
             name,
             rtype,
             class,
@@ -101,6 +113,50 @@ impl Record {
         })
     }
 
+    // 由已有的RDATA构造记录，type由数据类型决定，用于构造响应或测试数据
+    pub fn from_data(name: RawDomain, class: u16, ttl: u32, data: RecordDataType) -> Record {
+        Record {
+            name,
+            rtype: data.type_num(),
+            class,
+            ttl,
+            #[cfg(feature = "fmt")]
+            data_len: SliceOperator::encode_to_vec(|operator| data.encode_rdata(operator)).len()
+                as u16,
+            data,
+        }
+    }
+
+    // 编码为完整的资源记录，owner不压缩，RDLENGTH在写完RDATA后回填
+    pub fn encode(&self, operator: &mut SliceOperator) {
+        operator.write_slice(self.name.as_ref());
+        operator.write_u8(0x0);
+        operator.write_u16(self.rtype);
+        operator.write_u16(self.class);
+        operator.write_u32(self.ttl);
+        let len_pos = operator.pos();
+        operator.skip(2);
+        self.data.encode_rdata(operator);
+        let end = operator.pos();
+        operator.set_pos(len_pos);
+        operator.write_u16((end - len_pos - 2) as u16);
+        operator.set_pos(end);
+    }
+
+    // 规范排序(RFC 4034 6.3)：依次比较owner、class、type与规范形式的RDATA，
+    // 不比较TTL，结果为Equal的两条记录在RRset中视为重复
+    pub fn canonical_cmp(&self, other: &Record) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then(self.class.cmp(&other.class))
+            .then(self.rtype.cmp(&other.rtype))
+            .then_with(|| {
+                self.data
+                    .canonical_rdata()
+                    .cmp(&other.data.canonical_rdata())
+            })
+    }
+
     #[cfg(feature = "fmt")]
     pub fn get_fmt_type(&self) -> RecordFmtType {
         match self.data {
@@ -108,8 +164,15 @@ impl Record {
             | RecordDataType::AAAA(_)
             | RecordDataType::CNAME(_)
             | RecordDataType::NS(_)
-            | RecordDataType::TXT(_) => RecordFmtType::Answers,
+            | RecordDataType::TXT(_)
+            | RecordDataType::DS(_)
+            | RecordDataType::RRSIG(_)
+            | RecordDataType::NSEC(_)
+            | RecordDataType::DNSKEY(_)
+            | RecordDataType::NSEC3(_)
+            | RecordDataType::NSEC3PARAM(_) => RecordFmtType::Answers,
             RecordDataType::SOA(_) => RecordFmtType::Authoritative,
+            RecordDataType::OPT(_) => RecordFmtType::Additional,
         }
     }
 }
@@ -159,7 +222,7 @@ impl Display for Record {
             }
         }
 
-        match_data! {A,NS,CNAME,SOA,TXT,AAAA}
+        match_data! {A,NS,CNAME,SOA,TXT,AAAA,OPT,DS,RRSIG,NSEC,DNSKEY,NSEC3,NSEC3PARAM}
     }
 }
 
@@ -180,6 +243,45 @@ macro_rules! impl_record {
         // }
         impl RecordDataType {
 
+            // 按原样编码RDATA，域名不压缩且保留大小写
+            pub fn encode_rdata(&self, operator: &mut SliceOperator) {
+                match self {
+                    $(
+                        Self::$field(v) => v.encode_rdata(operator),
+                    )*
+                }
+            }
+
+            // 脱离报文按类型解析一段完整的RDATA，如RFC 3597的通用格式
+            pub fn from_rdata(rtype: u16, rdata: &[u8]) -> Result<RecordDataType, DecodeError> {
+                let mut reader = SliceReader::from_slice(rdata);
+                let data = match rtype {
+                    $(
+                        DnsTypeNum::$field => Self::$field($field::from_reader_with_size(&mut reader, rdata.len())?),
+                    )*
+                    _ => return Err(DecodeError::UnsupportedType { offset: 0, rtype }),
+                };
+                Ok(data)
+            }
+
+            #[inline]
+            pub fn type_num(&self) -> u16 {
+                match self {
+                    $(
+                        Self::$field(_) => DnsTypeNum::$field,
+                    )*
+                }
+            }
+
+            // RFC 4034 6.2 规范形式的RDATA，域名不压缩且按需转为小写
+            pub fn canonical_rdata(&self) -> Vec<u8> {
+                match self {
+                    $(
+                        Self::$field(v) => SliceOperator::encode_to_vec(|operator| v.encode_canonical(operator)),
+                    )*
+                }
+            }
+
             #[cfg(feature = "fmt")]
             pub fn get_type_info(&self) -> (&'static str, u16) {
                 match self {
@@ -196,6 +298,7 @@ macro_rules! impl_record {
 pub enum RecordFmtType {
     Answers,
     Authoritative,
+    Additional,
 }
 
 // todo
@@ -208,6 +311,122 @@ pub enum RecordDataType {
     SOA(SOA),
     TXT(TXT),
     AAAA(AAAA),
+    OPT(OPT),
+    DS(DS),
+    RRSIG(RRSIG),
+    NSEC(NSEC),
+    DNSKEY(DNSKEY),
+    NSEC3(NSEC3),
+    NSEC3PARAM(NSEC3PARAM),
 }
 
-impl_record! {A,NS,CNAME,SOA,TXT,AAAA}
+impl_record! {A,NS,CNAME,SOA,TXT,AAAA,OPT,DS,RRSIG,NSEC,DNSKEY,NSEC3,NSEC3PARAM}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::utils::DecodeError;
+
+    fn record(slice: &[u8]) -> Record {
+        Record::new(&mut SliceReader::from_slice(slice)).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_rdata() {
+        // www.Example.com CNAME Target.EXAMPLE.com
+        let upper = record(&[
+            3, b'w', b'w', b'w', 7, b'E', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm',
+            0, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x14, 6, b'T', b'a', b'r',
+            b'g', b'e', b't', 7, b'E', b'X', b'A', b'M', b'P', b'L', b'E', 3, b'c', b'o', b'm', 0,
+        ]);
+        let lower = record(&[
+            3, b'w', b'w', b'w', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm',
+            0, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x14, 6, b't', b'a', b'r',
+            b'g', b'e', b't', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+        ]);
+        assert_eq!(
+            upper.data.canonical_rdata(),
+            [
+                6, b't', b'a', b'r', b'g', b'e', b't', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e',
+                3, b'c', b'o', b'm', 0
+            ]
+        );
+        // TTL不同的同一条记录视为重复
+        assert_eq!(upper.canonical_cmp(&lower), Ordering::Equal);
+
+        let a1 = record(&[
+            0, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04, 192, 0, 2, 1,
+        ]);
+        let a2 = record(&[
+            0, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04, 192, 0, 2, 10,
+        ]);
+        assert_eq!(a1.data.canonical_rdata(), [192, 0, 2, 1]);
+        assert_eq!(a1.canonical_cmp(&a2), Ordering::Less);
+        // 根域名排在最前
+        assert_eq!(a1.canonical_cmp(&upper), Ordering::Less);
+    }
+
+    // 根域名下的一条记录，rdlength由调用者指定，RDATA后追加两个字节模拟下一条记录
+    fn decode(rtype: u16, rdlength: u16, rdata: &[u8]) -> Result<Record, DecodeError> {
+        let mut slice = vec![0];
+        slice.extend_from_slice(&rtype.to_be_bytes());
+        slice.extend_from_slice(&[0x00, 0x01, 0, 0, 0, 60]);
+        slice.extend_from_slice(&rdlength.to_be_bytes());
+        slice.extend_from_slice(rdata);
+        slice.extend_from_slice(&[0xc0, 0x00]);
+        Record::new(&mut SliceReader::from_slice(&slice))
+    }
+
+    fn mismatch(rtype: u16, rdlength: usize, consumed: usize) -> Result<(), DecodeError> {
+        Err(DecodeError::RdataLengthMismatch {
+            offset: 11,
+            rtype,
+            rdlength,
+            consumed,
+        })
+    }
+
+    #[test]
+    fn test_rdata_length() {
+        let ns = b"\x02ns\x00";
+        let mut soa = vec![1, b'a', 0, 0];
+        soa.extend_from_slice(&[0; 20]);
+        let mut nsec3param = vec![1, 0, 0, 1, 2, 0xab, 0xcd];
+        let cases: &[(u16, &[u8], usize)] = &[
+            (DnsTypeNum::NS, ns, 4),
+            (DnsTypeNum::CNAME, ns, 4),
+            (DnsTypeNum::SOA, &soa, 24),
+            (DnsTypeNum::TXT, b"\x03abc", 4),
+            (DnsTypeNum::A, &[192, 0, 2, 1], 4),
+            (DnsTypeNum::NSEC3PARAM, &nsec3param, 7),
+        ];
+        for &(rtype, rdata, len) in cases {
+            assert!(decode(rtype, len as u16, rdata).is_ok());
+            // 声明的长度比实际内容短，名称或字符串会越过RDATA
+            assert_eq!(
+                decode(rtype, len as u16 - 1, rdata).map(|_| ()),
+                mismatch(rtype, len - 1, len)
+            );
+        }
+
+        // 声明的长度比实际内容长，多出的部分不能被跳过
+        for (rtype, rdata) in [(DnsTypeNum::NS, &ns[..]), (DnsTypeNum::SOA, &soa)] {
+            let mut padded = rdata.to_vec();
+            padded.extend_from_slice(&[0, 0]);
+            assert_eq!(
+                decode(rtype, padded.len() as u16, &padded).map(|_| ()),
+                mismatch(rtype, padded.len(), rdata.len())
+            );
+        }
+        nsec3param.push(0);
+        assert_eq!(
+            decode(DnsTypeNum::NSEC3PARAM, 8, &nsec3param).map(|_| ()),
+            mismatch(DnsTypeNum::NSEC3PARAM, 8, 7)
+        );
+        // TXT内多出的字节会被当成新的character-string
+        assert_eq!(
+            decode(DnsTypeNum::TXT, 5, b"\x03abc\x05").map(|_| ()),
+            mismatch(DnsTypeNum::TXT, 5, 10)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::{DnsTypeNum, NameRef};
+use crate::dns::types::parts::Record;
+use crate::dns::utils::{DecodeError, SliceReader};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+// 借用原始报文的记录，只校验owner与定长部分，RDATA在访问时才解析
+#[derive(Clone, Copy, Debug)]
+pub struct RecordRef<'a> {
+    packet: &'a [u8],
+    pos: usize,
+    name: NameRef<'a>,
+    rtype: u16,
+    class: u16,
+    ttl: u32,
+    rdata_pos: usize,
+    rdata: &'a [u8],
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn from_reader(reader: &mut SliceReader<'a>) -> Result<RecordRef<'a>, DecodeError> {
+        let pos = reader.pos();
+        let name = NameRef::from_reader(reader)?;
+        if reader.pos() + 10 > reader.len() {
+            return Err(DecodeError::Truncated {
+                offset: reader.pos(),
+                need: 10,
+            });
+        }
+        let rtype = reader.read_u16()?;
+        let class = reader.read_u16()?;
+        let ttl = reader.read_u32()?;
+        let data_len = reader.read_u16()? as usize;
+        let rdata_pos = reader.pos();
+        let rdata = reader.read_slice(data_len)?;
+        Ok(RecordRef {
+            packet: reader.as_ref(),
+            pos,
+            name,
+            rtype,
+            class,
+            ttl,
+            rdata_pos,
+            rdata,
+        })
+    }
+
+    #[inline]
+    pub fn name(&self) -> NameRef<'a> {
+        self.name
+    }
+
+    #[inline]
+    pub fn rtype(&self) -> u16 {
+        self.rtype
+    }
+
+    #[inline]
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+
+    #[inline]
+    pub fn ttl(&self) -> u32 {
+        self.ttl
+    }
+
+    #[inline]
+    pub fn rdata(&self) -> &'a [u8] {
+        self.rdata
+    }
+
+    // 以下访问方法在类型不符或RDATA不合法时返回None，需要具体错误时使用to_record
+
+    #[inline]
+    pub fn a(&self) -> Option<Ipv4Addr> {
+        if self.rtype != DnsTypeNum::A {
+            return None;
+        }
+        <[u8; 4]>::try_from(self.rdata).ok().map(Ipv4Addr::from)
+    }
+
+    #[inline]
+    pub fn aaaa(&self) -> Option<Ipv6Addr> {
+        if self.rtype != DnsTypeNum::AAAA {
+            return None;
+        }
+        <[u8; 16]>::try_from(self.rdata).ok().map(Ipv6Addr::from)
+    }
+
+    // NS与CNAME指向的名称，名称可以压缩
+    pub fn target(&self) -> Option<NameRef<'a>> {
+        if self.rtype != DnsTypeNum::NS && self.rtype != DnsTypeNum::CNAME {
+            return None;
+        }
+        let mut reader = SliceReader::from_slice(self.packet);
+        reader.set_pos(self.rdata_pos);
+        let name = NameRef::from_reader(&mut reader).ok()?;
+        (reader.pos() == self.rdata_pos + self.rdata.len()).then_some(name)
+    }
+
+    // TXT中的各个character-string
+    pub fn txt(&self) -> Option<CharacterStrings<'a>> {
+        if self.rtype != DnsTypeNum::TXT || self.rdata.is_empty() {
+            return None;
+        }
+        let strings = CharacterStrings {
+            remaining: self.rdata,
+        };
+        // 先确认所有character-string恰好占满RDATA
+        let mut rest = self.rdata;
+        while let Some((&len, tail)) = rest.split_first() {
+            rest = tail.get(len as usize..)?;
+        }
+        Some(strings)
+    }
+
+    // 完整解析为Record
+    pub fn to_record(&self) -> Result<Record, DecodeError> {
+        let mut reader = SliceReader::from_slice(self.packet);
+        reader.set_pos(self.pos);
+        Record::new(&mut reader)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CharacterStrings<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for CharacterStrings<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&len, rest) = self.remaining.split_first()?;
+        let (string, rest) = rest.split_at((len as usize).min(rest.len()));
+        self.remaining = rest;
+        Some(string)
+    }
+}
@@ -1,6 +1,7 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
-use crate::dns::types::base::RawDomain;
+use crate::dns::types::base::record::OPT;
+use crate::dns::types::base::{DnsRcodeNum, DnsTypeNum, RawDomain};
 use crate::dns::types::parts::header::RequestHeader;
 use crate::dns::types::parts::question::Question;
 use crate::dns::utils::SliceOperator;
@@ -11,10 +12,54 @@ use std::rc::Rc;
 
 const SUFFIX: &[u8] = "xn--".as_bytes();
 
+// EDNS(0)参数，编码时追加到additional部分的OPT伪记录
+#[derive(Debug, Clone, Copy)]
+pub struct Edns {
+    pub udp_payload_size: u16,
+    pub dnssec_ok: bool,
+    // 携带不含TIMEOUT的edns-tcp-keepalive option，向服务器询问空闲超时(RFC 7828 3.2.1)
+    pub tcp_keepalive: bool,
+}
+
+impl Edns {
+    pub const TCP_KEEPALIVE: u16 = 11;
+}
+
+impl Edns {
+    // 以根域名为owner的OPT伪记录，TTL依次为扩展rcode、版本(0)与DO位(RFC 6891 6.1.3)
+    pub(crate) fn encode(&self, operator: &mut SliceOperator, extended_rcode: u8) {
+        operator.write_u8(0x0);
+        operator.write_u16(DnsTypeNum::OPT);
+        operator.write_u16(self.udp_payload_size);
+        let do_bit = if self.dnssec_ok { OPT::DNSSEC_OK } else { 0 };
+        operator.write_u32((extended_rcode as u32) << 24 | do_bit);
+        if self.tcp_keepalive {
+            operator.write_u16(4);
+            operator.write_u16(Self::TCP_KEEPALIVE);
+            operator.write_u16(0);
+        } else {
+            operator.write_u16(0);
+        }
+    }
+}
+
+impl Default for Edns {
+    fn default() -> Self {
+        Edns {
+            udp_payload_size: 1232,
+            dnssec_ok: false,
+            tcp_keepalive: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Request {
     pub header: RequestHeader,
     pub question: SmallVec<[Question; 1]>,
+    pub edns: Option<Edns>,
+    // 按位记录接受的rcode，其余rcode的响应在check_header中被丢弃
+    pub(crate) accepted_rcodes: u16,
 }
 
 impl Request {
@@ -30,9 +75,41 @@ impl Request {
         Request {
             header: Default::default(),
             question,
+            edns: None,
+            accepted_rcodes: 1 << DnsRcodeNum::NOERROR,
         }
     }
 
+    // 默认只接受NOERROR，调用者需要自行处理NXDOMAIN等否定响应时加入对应的rcode
+    #[inline]
+    pub fn accept_rcode(&mut self, rcode: u8) {
+        self.accepted_rcodes |= 1 << (rcode & 0xf);
+    }
+
+    #[inline]
+    pub(crate) fn accepts_rcode(&self, rcode: u8) -> bool {
+        self.accepted_rcodes & (1 << (rcode & 0xf)) != 0
+    }
+
+    // 设置DO位并关闭上游校验(CD)，由本地完成DNSSEC校验
+    #[inline]
+    pub fn set_dnssec_ok(&mut self) {
+        self.edns = Some(Edns {
+            dnssec_ok: true,
+            ..self.edns.unwrap_or_default()
+        });
+        self.header.check_disable = 1;
+    }
+
+    // 通过TCP发送时请求服务器返回空闲超时
+    #[inline]
+    pub fn set_tcp_keepalive(&mut self) {
+        self.edns = Some(Edns {
+            tcp_keepalive: true,
+            ..self.edns.unwrap_or_default()
+        });
+    }
+
     pub fn encode_to_udp<'b>(&self, buffer: &'b mut [u8]) -> &'b [u8] {
         let mut operator = SliceOperator::from_slice(buffer);
 
@@ -43,8 +120,9 @@ impl Request {
         operator.write_u16(self.header.get_flags());
         operator.write_u16(self.question.len() as u16);
         operator.write_u32(0);
-        operator.write_u16(0);
+        operator.write_u16(self.edns.is_some() as u16);
         self.encode_question(&mut operator);
+        self.encode_edns(&mut operator);
         let pos = operator.pos();
         if pos - 2 > 512 {
             //自动返回tcp的slice
@@ -61,8 +139,9 @@ impl Request {
         operator.write_u16(self.header.get_flags());
         operator.write_u16(self.question.len() as u16);
         operator.write_u32(0);
-        operator.write_u16(0);
+        operator.write_u16(self.edns.is_some() as u16);
         self.encode_question(&mut operator);
+        self.encode_edns(&mut operator);
         let pos = operator.pos();
         buffer[0..2].copy_from_slice(((pos - 2) as u16).to_be_bytes().as_ref());
         buffer[..pos].as_ref()
@@ -70,13 +149,16 @@ impl Request {
 
     fn encode_question(&self, operator: &mut SliceOperator) -> Option<()> {
         for q in &self.question {
-            operator.write_slice(q.qname.as_ref().as_ref());
-            operator.write_u8(0x0);
-            operator.write_u16(q.qtype);
-            operator.write_u16(q.qclass);
+            q.encode(operator);
         }
         Some(())
     }
+
+    fn encode_edns(&self, operator: &mut SliceOperator) {
+        if let Some(edns) = &self.edns {
+            edns.encode(operator, 0);
+        }
+    }
 }
 
 #[cfg(feature = "fmt")]
@@ -116,4 +198,26 @@ mod tests {
             [1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1]
         )
     }
+
+    #[test]
+    fn test_edns() {
+        let mut request = Request::new(Rc::new(RawDomain::from_str(".").unwrap()), DnsTypeNum::NS);
+        request.set_dnssec_ok();
+        let buff = &mut [0; 512];
+        assert_eq!(
+            request.encode_to_udp(buff)[2..],
+            [
+                1, 0x10, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 2, 0, 1, 0, 0, 41, 0x04, 0xd0, 0, 0, 0x80,
+                0, 0, 0
+            ]
+        );
+        request.set_tcp_keepalive();
+        assert_eq!(
+            request.encode_to_udp(buff)[2..],
+            [
+                1, 0x10, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 2, 0, 1, 0, 0, 41, 0x04, 0xd0, 0, 0, 0x80,
+                0, 0, 4, 0, 11, 0, 0
+            ]
+        )
+    }
 }
@@ -6,7 +6,7 @@ use crate::dns::types::parts::Request;
 use crate::dns::types::parts::header::{HEADER_SIZE, ResponseHeader};
 use crate::dns::types::parts::question::Question;
 use crate::dns::types::parts::record::Record;
-use crate::dns::utils::SliceReader;
+use crate::dns::utils::{DecodeError, SliceReader};
 
 #[cfg(feature = "result_error")]
 use crate::dns::error::error_trait;
@@ -28,14 +28,14 @@ impl error_trait::A for Response {}
 
 impl Response {
     #[inline]
-    pub fn from_slice_uncheck(slice: &[u8]) -> Option<Response> {
-        Self::from_slice_check(slice, |_| Some(()))
+    pub fn from_slice_uncheck(slice: &[u8]) -> Result<Response, DecodeError> {
+        Self::from_slice_check(slice, |_| Ok(()))
     }
 
-    fn from_slice_check<F: Fn(&ResponseHeader) -> Option<()>>(
+    fn from_slice_check<F: Fn(&ResponseHeader) -> Result<(), DecodeError>>(
         slice: &[u8],
         check: F,
-    ) -> Option<Response> {
+    ) -> Result<Response, DecodeError> {
         if slice.len() < HEADER_SIZE + Question::LEAST_SIZE {
             #[cfg(feature = "logger")]
             {
@@ -46,14 +46,17 @@ impl Response {
                 );
             }
 
-            return None;
+            return Err(DecodeError::Truncated {
+                offset: slice.len(),
+                need: HEADER_SIZE + Question::LEAST_SIZE - slice.len(),
+            });
         }
         let mut reader = SliceReader::from_slice(slice);
         #[cfg(feature = "logger")]
         {
             trace!("开始解析Header")
         }
-        let header = ResponseHeader::from(&mut reader);
+        let header = ResponseHeader::try_from(&mut reader)?;
         check(&header)?;
 
         let mut questions = SmallVec::new();
@@ -93,73 +96,112 @@ impl Response {
             }
         }
 
-        Some(Response {
+        Ok(Response {
             header,
             question: questions,
             answer: rrs,
         })
     }
 
+    // answer内按顺序存放了answer/authority/additional三部分，按header中的数量切分
     #[inline]
-    pub fn from_slice(slice: &[u8], request: &Request) -> Option<Response> {
-        Self::from_slice_check(slice, |header| {
-            if header.id != request.header.id {
-                #[cfg(feature = "logger")]
-                {
-                    trace!(
-                        "请求id和响应id不同,分别为{},{}",
-                        header.id, request.header.id
-                    );
-                }
-                return None;
-            }
-            if header.response != 0x1 {
-                #[cfg(feature = "logger")]
-                {
-                    trace!("响应的response flag非0x1");
-                }
-                return None;
-            }
-            if header.opcode != request.header.opcode {
-                #[cfg(feature = "logger")]
-                {
-                    trace!(
-                        "请求和响应的opcode不同,分别为{},{}",
-                        header.opcode, request.header.opcode
-                    );
-                }
-                return None;
-            }
-            if header.rec_desired != request.header.rec_desired {
-                #[cfg(feature = "logger")]
-                {
-                    trace!(
-                        "请求和响应的rec_desired不同,分别为{},{}",
-                        header.rec_desired, request.header.rec_desired
-                    );
-                }
-                return None;
-            }
-            if header.rcode != 0x0 {
-                #[cfg(feature = "logger")]
-                {
-                    trace!("响应的opcode不为0x0,而是{}", header.rcode);
-                }
-                return None;
-            }
-            if header.questions != request.question.len() as u16 {
-                #[cfg(feature = "logger")]
-                {
-                    trace!(
-                        "请求与响应的question数不同,分别为{},{}",
-                        request.question.len(),
-                        header.questions
-                    );
-                }
-            }
-            Some(())
-        })
+    pub fn answers(&self) -> &[Record] {
+        let end = (self.header.answer_rrs as usize).min(self.answer.len());
+        &self.answer[..end]
+    }
+
+    #[inline]
+    pub fn authorities(&self) -> &[Record] {
+        let start = (self.header.answer_rrs as usize).min(self.answer.len());
+        let end = (start + self.header.authority_rrs as usize).min(self.answer.len());
+        &self.answer[start..end]
+    }
+
+    #[inline]
+    pub fn additionals(&self) -> &[Record] {
+        let start = (self.header.answer_rrs as usize + self.header.authority_rrs as usize)
+            .min(self.answer.len());
+        &self.answer[start..]
+    }
+
+    #[inline]
+    pub fn from_slice(slice: &[u8], request: &Request) -> Result<Response, DecodeError> {
+        Self::from_slice_check(slice, |header| check_header(header, request))
+    }
+}
+
+// 检查响应与请求是否匹配
+pub(crate) fn check_header(header: &ResponseHeader, request: &Request) -> Result<(), DecodeError> {
+    if header.id != request.header.id {
+        #[cfg(feature = "logger")]
+        {
+            trace!(
+                "请求id和响应id不同,分别为{},{}",
+                header.id, request.header.id
+            );
+        }
+        return Err(DecodeError::UnexpectedResponse {
+            offset: 0,
+            field: "id",
+        });
+    }
+    if header.response != 0x1 {
+        #[cfg(feature = "logger")]
+        {
+            trace!("响应的response flag非0x1");
+        }
+        return Err(DecodeError::UnexpectedResponse {
+            offset: 2,
+            field: "response",
+        });
+    }
+    if header.opcode != request.header.opcode {
+        #[cfg(feature = "logger")]
+        {
+            trace!(
+                "请求和响应的opcode不同,分别为{},{}",
+                header.opcode, request.header.opcode
+            );
+        }
+        return Err(DecodeError::UnexpectedResponse {
+            offset: 2,
+            field: "opcode",
+        });
+    }
+    if header.rec_desired != request.header.rec_desired {
+        #[cfg(feature = "logger")]
+        {
+            trace!(
+                "请求和响应的rec_desired不同,分别为{},{}",
+                header.rec_desired, request.header.rec_desired
+            );
+        }
+        return Err(DecodeError::UnexpectedResponse {
+            offset: 2,
+            field: "rec_desired",
+        });
+    }
+    if !request.accepts_rcode(header.rcode) {
+        #[cfg(feature = "logger")]
+        {
+            trace!("响应的rcode {}不在请求接受的范围内", header.rcode);
+        }
+        return Err(DecodeError::UnexpectedResponse {
+            offset: 3,
+            field: "rcode",
+        });
+    }
+    if header.questions != request.question.len() as u16 {
+        #[cfg(feature = "logger")]
+        {
+            trace!(
+                "请求与响应的question数不同,分别为{},{}",
+                request.question.len(),
+                header.questions
+            );
+        }
     }
+    Ok(())
 }
 
 #[cfg(feature = "fmt")]
@@ -193,3 +235,283 @@ impl Display for Response {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench_func::DECODE_PACKET;
+    use crate::dns::types::base::{DnsRcodeNum, DnsTypeNum, RawDomain};
+    use crate::dns::types::parts::ResponseRef;
+    use crate::dns::utils::SliceOperator;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_decode_error() {
+        let response = Response::from_slice_uncheck(&DECODE_PACKET).unwrap();
+        assert_eq!(response.answers().len(), 3);
+
+        // 任意截断都只返回错误
+        for len in 0..DECODE_PACKET.len() {
+            assert!(Response::from_slice_uncheck(&DECODE_PACKET[..len]).is_err());
+        }
+        assert_eq!(
+            Response::from_slice_uncheck(&DECODE_PACKET[..88]).unwrap_err(),
+            DecodeError::Truncated {
+                offset: 86,
+                need: 4
+            }
+        );
+
+        // 第一条answer的name指向自身
+        let mut packet = DECODE_PACKET;
+        packet[32] = 31;
+        assert_eq!(
+            Response::from_slice_uncheck(&packet).unwrap_err(),
+            DecodeError::BadPointer {
+                offset: 31,
+                target: 31
+            }
+        );
+
+        // CNAME的RDATA长度少报一个字节
+        let mut packet = DECODE_PACKET;
+        packet[42] = 14;
+        assert_eq!(
+            Response::from_slice_uncheck(&packet).unwrap_err(),
+            DecodeError::RdataLengthMismatch {
+                offset: 43,
+                rtype: DnsTypeNum::CNAME,
+                rdlength: 14,
+                consumed: 15
+            }
+        );
+
+        let mut packet = DECODE_PACKET;
+        packet[34] = 99;
+        assert_eq!(
+            Response::from_slice_uncheck(&packet).unwrap_err(),
+            DecodeError::UnsupportedType {
+                offset: 33,
+                rtype: 99
+            }
+        );
+
+        // 逐字节替换为任意值都不会panic
+        for i in 0..DECODE_PACKET.len() {
+            for b in 0..=u8::MAX {
+                let mut packet = DECODE_PACKET;
+                packet[i] = b;
+                let _ = Response::from_slice_uncheck(&packet);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unexpected_response() {
+        let mut request = Request::new(
+            Rc::new(RawDomain::from_str("www.baidu.com").unwrap()),
+            DnsTypeNum::A,
+        );
+        request.header.id = 0xb9de;
+        // 响应中没有设置RD位
+        assert_eq!(
+            Response::from_slice(&DECODE_PACKET, &request).unwrap_err(),
+            DecodeError::UnexpectedResponse {
+                offset: 2,
+                field: "rec_desired"
+            }
+        );
+        request.header.rec_desired = 0;
+        assert!(Response::from_slice(&DECODE_PACKET, &request).is_ok());
+        request.header.id = 0x1234;
+        assert_eq!(
+            Response::from_slice(&DECODE_PACKET, &request).unwrap_err(),
+            DecodeError::UnexpectedResponse {
+                offset: 0,
+                field: "id"
+            }
+        );
+
+        // 默认丢弃NXDOMAIN，请求接受后才返回
+        request.header.id = 0xb9de;
+        let mut packet = DECODE_PACKET;
+        packet[3] = 0x83;
+        assert_eq!(
+            Response::from_slice(&packet, &request).unwrap_err(),
+            DecodeError::UnexpectedResponse {
+                offset: 3,
+                field: "rcode"
+            }
+        );
+        request.accept_rcode(DnsRcodeNum::NXDOMAIN);
+        assert_eq!(
+            Response::from_slice(&packet, &request)
+                .unwrap()
+                .header
+                .rcode,
+            DnsRcodeNum::NXDOMAIN
+        );
+    }
+
+    fn arb_name() -> impl Strategy<Value = Vec<u8>> {
+        vec(vec(prop_oneof![b'a'..=b'z', b'0'..=b'9'], 1..=12), 0..5).prop_map(|labels| {
+            let mut name: Vec<u8> = labels
+                .iter()
+                .flat_map(|label| std::iter::once(label.len() as u8).chain(label.iter().copied()))
+                .collect();
+            name.push(0);
+            name
+        })
+    }
+
+    // 生成(type, RDATA)，名称均为小写，与规范形式一致
+    fn arb_rdata() -> impl Strategy<Value = (u16, Vec<u8>)> {
+        prop_oneof![
+            vec(any::<u8>(), 4).prop_map(|v| (DnsTypeNum::A, v)),
+            vec(any::<u8>(), 16).prop_map(|v| (DnsTypeNum::AAAA, v)),
+            arb_name().prop_map(|v| (DnsTypeNum::NS, v)),
+            arb_name().prop_map(|v| (DnsTypeNum::CNAME, v)),
+            vec(vec(any::<u8>(), 0..40), 1..4).prop_map(|strings| {
+                let rdata = strings
+                    .iter()
+                    .flat_map(|s| std::iter::once(s.len() as u8).chain(s.iter().copied()))
+                    .collect();
+                (DnsTypeNum::TXT, rdata)
+            }),
+            (arb_name(), arb_name(), vec(any::<u8>(), 20))
+                .prop_map(|(m, r, v)| (DnsTypeNum::SOA, [m, r, v].concat())),
+            vec(any::<u8>(), 4..40).prop_map(|v| (DnsTypeNum::DS, v)),
+            vec(any::<u8>(), 4..40).prop_map(|v| (DnsTypeNum::DNSKEY, v)),
+        ]
+    }
+
+    fn arb_message() -> impl Strategy<Value = Vec<u8>> {
+        (
+            any::<u16>(),
+            any::<u16>(),
+            vec((arb_name(), any::<u16>(), any::<u16>()), 0..3),
+            vec((arb_name(), any::<u16>(), any::<u32>(), arb_rdata()), 0..8),
+            any::<(u8, u8)>(),
+        )
+            .prop_map(|(id, flags, questions, records, (a, b))| {
+                let answer = a as usize % (records.len() + 1);
+                let authority = b as usize % (records.len() - answer + 1);
+                let additional = records.len() - answer - authority;
+                let mut packet = Vec::new();
+                for v in [id, flags, questions.len() as u16] {
+                    packet.extend_from_slice(&v.to_be_bytes());
+                }
+                for v in [answer, authority, additional] {
+                    packet.extend_from_slice(&(v as u16).to_be_bytes());
+                }
+                for (name, qtype, qclass) in questions {
+                    packet.extend_from_slice(&name);
+                    packet.extend_from_slice(&qtype.to_be_bytes());
+                    packet.extend_from_slice(&qclass.to_be_bytes());
+                }
+                for (name, class, ttl, (rtype, rdata)) in records {
+                    packet.extend_from_slice(&name);
+                    packet.extend_from_slice(&rtype.to_be_bytes());
+                    packet.extend_from_slice(&class.to_be_bytes());
+                    packet.extend_from_slice(&ttl.to_be_bytes());
+                    packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+                    packet.extend_from_slice(&rdata);
+                }
+                packet
+            })
+    }
+
+    // 将解析结果重新按未压缩格式编码
+    fn encode(response: &Response) -> Vec<u8> {
+        let header = &response.header;
+        let flags = (header.response as u16) << 15
+            | (header.opcode as u16) << 11
+            | (header.authoritative as u16) << 10
+            | (header.truncated as u16) << 9
+            | (header.rec_desired as u16) << 8
+            | (header.rec_avail as u16) << 7
+            | (header.z as u16) << 6
+            | (header.authenticated as u16) << 5
+            | (header.check_disable as u16) << 4
+            | header.rcode as u16;
+        SliceOperator::encode_to_vec(|operator| {
+            for v in [
+                header.id,
+                flags,
+                header.questions,
+                header.answer_rrs,
+                header.authority_rrs,
+                header.additional_rrs,
+            ] {
+                operator.write_u16(v);
+            }
+            for question in &response.question {
+                operator.write_slice(&question.qname.to_canonical_wire());
+                operator.write_u16(question.qtype);
+                operator.write_u16(question.qclass);
+            }
+            for record in &response.answer {
+                let rdata = record.data.canonical_rdata();
+                operator.write_slice(&record.name.to_canonical_wire());
+                operator.write_u16(record.rtype);
+                operator.write_u16(record.class);
+                operator.write_u32(record.ttl);
+                operator.write_u16(rdata.len() as u16);
+                operator.write_slice(&rdata);
+            }
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(packet in arb_message()) {
+            let response = Response::from_slice_uncheck(&packet);
+            // 过短的报文(没有question)不会被接受
+            if packet.len() < HEADER_SIZE + Question::LEAST_SIZE {
+                prop_assert!(response.is_err());
+            } else {
+                prop_assert_eq!(encode(&response.unwrap()), packet);
+            }
+        }
+
+        #[test]
+        fn prop_decode_total(packet in vec(any::<u8>(), 0..600)) {
+            let _ = Response::from_slice_uncheck(&packet);
+        }
+
+        // 借用解析与完整解析的结果一致
+        #[test]
+        fn prop_response_ref(packet in arb_message()) {
+            let (Ok(response), Ok(borrowed)) = (
+                Response::from_slice_uncheck(&packet),
+                ResponseRef::from_slice_uncheck(&packet),
+            ) else {
+                return Ok(());
+            };
+            let records: Vec<_> = borrowed.records().map(Result::unwrap).collect();
+            prop_assert_eq!(records.len(), response.answer.len());
+            for (record, owned) in records.iter().zip(&response.answer) {
+                prop_assert_eq!(&record.name().to_raw_domain(), &owned.name);
+                prop_assert_eq!(record.rtype(), owned.rtype);
+                prop_assert_eq!(record.ttl(), owned.ttl);
+                prop_assert_eq!(record.to_record().unwrap().data.canonical_rdata(), owned.data.canonical_rdata());
+            }
+        }
+
+        // 在合法报文上随机改写若干字节
+        #[test]
+        fn prop_mutated_total(
+            packet in arb_message(),
+            edits in vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+        ) {
+            let mut packet = packet;
+            for (index, byte) in edits {
+                let i = index.index(packet.len());
+                packet[i] = byte;
+            }
+            let _ = Response::from_slice_uncheck(&packet);
+        }
+    }
+}
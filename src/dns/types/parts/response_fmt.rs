@@ -0,0 +1,321 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::record::{OPT, hex_upper, type_name};
+use crate::dns::types::base::{DnsOpcode, DnsRcode, DnsTypeNum};
+use crate::dns::types::parts::{Record, RecordDataType, Response};
+use crate::dns::zone::presentation::{absolute_name, class_name, rdata_text};
+use std::fmt::{Display, Formatter, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+// Response的输出格式，Tree与Display相同，Dig与dig命令的默认输出一致便于对比
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFmtType {
+    #[default]
+    Tree,
+    Dig,
+}
+
+// 由Response::display创建，查询耗时、服务器与报文长度只在Dig格式中输出
+pub struct ResponseDisplay<'a> {
+    response: &'a Response,
+    fmt_type: ResponseFmtType,
+    query_time: Option<Duration>,
+    server: Option<SocketAddr>,
+    message_size: Option<usize>,
+}
+
+impl Response {
+    pub fn display(&self, fmt_type: ResponseFmtType) -> ResponseDisplay<'_> {
+        ResponseDisplay {
+            response: self,
+            fmt_type,
+            query_time: None,
+            server: None,
+            message_size: None,
+        }
+    }
+}
+
+impl ResponseDisplay<'_> {
+    pub fn with_query_time(mut self, query_time: Duration) -> Self {
+        self.query_time = Some(query_time);
+        self
+    }
+
+    pub fn with_server(mut self, server: SocketAddr) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    pub fn with_message_size(mut self, message_size: usize) -> Self {
+        self.message_size = Some(message_size);
+        self
+    }
+
+    fn fmt_dig(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let response = self.response;
+        let header = &response.header;
+        let opt = response
+            .additionals()
+            .iter()
+            .find(|r| r.rtype == DnsTypeNum::OPT);
+        // 扩展rcode的高8bit位于OPT的TTL中
+        let rcode = opt.map_or(0, |r| (r.ttl >> 24) as u16) << 4 | header.rcode as u16;
+        let opcode = match header.opcode {
+            DnsOpcode::QUERY => "QUERY".to_string(),
+            DnsOpcode::IQUERY => "IQUERY".to_string(),
+            DnsOpcode::STATUS => "STATUS".to_string(),
+            DnsOpcode::NOTIFY => "NOTIFY".to_string(),
+            DnsOpcode::UPDATE => "UPDATE".to_string(),
+            other => other.to_string(),
+        };
+        let status = match DnsRcode::get_str(rcode) {
+            "RESERVED" => format!("RESERVED{}", rcode),
+            name => name.to_string(),
+        };
+        writeln!(
+            f,
+            ";; ->>HEADER<<- opcode: {}, status: {}, id: {}",
+            opcode, status, header.id
+        )?;
+
+        let flags: Vec<&str> = [
+            (header.response, "qr"),
+            (header.authoritative, "aa"),
+            (header.truncated, "tc"),
+            (header.rec_desired, "rd"),
+            (header.rec_avail, "ra"),
+            (header.authenticated, "ad"),
+            (header.check_disable, "cd"),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| *bit == 1)
+        .map(|(_, name)| name)
+        .collect();
+        writeln!(
+            f,
+            ";; flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            flags.join(" "),
+            header.questions,
+            header.answer_rrs,
+            header.authority_rrs,
+            header.additional_rrs
+        )?;
+
+        if let Some(opt) = opt {
+            writeln!(f, "\n;; OPT PSEUDOSECTION:")?;
+            let do_flag = if opt.ttl & OPT::DNSSEC_OK != 0 {
+                " do"
+            } else {
+                ""
+            };
+            writeln!(
+                f,
+                "; EDNS: version: {}, flags:{}; udp: {}",
+                (opt.ttl >> 16) & 0xFF,
+                do_flag,
+                opt.class
+            )?;
+            if let RecordDataType::OPT(data) = &opt.data {
+                for (code, value) in data.options() {
+                    match code {
+                        10 => writeln!(f, "; COOKIE: {}", hex_upper(value).to_lowercase())?,
+                        _ => writeln!(f, "; OPT={}: {}", code, hex_upper(value))?,
+                    }
+                }
+            }
+        }
+
+        if !response.question.is_empty() {
+            writeln!(f, "\n;; QUESTION SECTION:")?;
+            for q in &response.question {
+                let mut line = format!(";{}", absolute_name(&q.qname));
+                // 跳过TTL列
+                pad_column(&mut line, 24);
+                pad_column(&mut line, 32);
+                line.push_str(&class_name(q.qclass));
+                pad_column(&mut line, 40);
+                line.push_str(&type_name(q.qtype));
+                writeln!(f, "{}", line)?;
+            }
+        }
+
+        let sections = [
+            ("ANSWER", response.answers()),
+            ("AUTHORITY", response.authorities()),
+            ("ADDITIONAL", response.additionals()),
+        ];
+        for (name, records) in sections {
+            let mut records = records
+                .iter()
+                .filter(|r| r.rtype != DnsTypeNum::OPT)
+                .peekable();
+            if records.peek().is_none() {
+                continue;
+            }
+            writeln!(f, "\n;; {} SECTION:", name)?;
+            for record in records {
+                writeln!(f, "{}", dig_line(record))?;
+            }
+        }
+
+        if self.query_time.is_some() || self.server.is_some() || self.message_size.is_some() {
+            writeln!(f)?;
+        }
+        if let Some(query_time) = self.query_time {
+            writeln!(f, ";; Query time: {} msec", query_time.as_millis())?;
+        }
+        if let Some(server) = self.server {
+            writeln!(
+                f,
+                ";; SERVER: {}#{}({})",
+                server.ip(),
+                server.port(),
+                server.ip()
+            )?;
+        }
+        if let Some(message_size) = self.message_size {
+            writeln!(f, ";; MSG SIZE  rcvd: {}", message_size)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ResponseDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.fmt_type {
+            ResponseFmtType::Tree => Display::fmt(self.response, f),
+            ResponseFmtType::Dig => self.fmt_dig(f),
+        }
+    }
+}
+
+// 与dig相同，用tab对齐到指定列(tab宽度为8)，至少一个tab
+fn pad_column(line: &mut String, column: usize) {
+    let mut width = line.chars().fold(0, |width, c| match c {
+        '\t' => width / 8 * 8 + 8,
+        _ => width + 1,
+    });
+    loop {
+        line.push('\t');
+        width = width / 8 * 8 + 8;
+        if width >= column {
+            break;
+        }
+    }
+}
+
+// 单行的资源记录，如 www.baidu.com.		29	IN	CNAME	www.a.shifen.com.
+fn dig_line(record: &Record) -> String {
+    let mut line = absolute_name(&record.name);
+    pad_column(&mut line, 24);
+    let _ = write!(line, "{}", record.ttl);
+    pad_column(&mut line, 32);
+    line.push_str(&class_name(record.class));
+    pad_column(&mut line, 40);
+    line.push_str(&type_name(record.rtype));
+    pad_column(&mut line, 48);
+    line.push_str(&rdata_text(&record.data, &absolute_name));
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench_func::DECODE_PACKET;
+    use crate::dns::types::base::RawDomain;
+    use crate::dns::types::base::record::{A, NS};
+    use crate::dns::types::parts::{Edns, MessageBuilder};
+    use std::net::Ipv4Addr;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_dig() {
+        let response = Response::from_slice_uncheck(&DECODE_PACKET).unwrap();
+        let text = response
+            .display(ResponseFmtType::Dig)
+            .with_query_time(Duration::from_millis(12))
+            .with_server("8.8.8.8:53".parse().unwrap())
+            .with_message_size(DECODE_PACKET.len())
+            .to_string();
+        assert_eq!(
+            text,
+            concat!(
+                ";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 47582\n",
+                ";; flags: qr ra; QUERY: 1, ANSWER: 3, AUTHORITY: 0, ADDITIONAL: 0\n",
+                "\n",
+                ";; QUESTION SECTION:\n",
+                ";www.baidu.com.\t\t\tIN\tA\n",
+                "\n",
+                ";; ANSWER SECTION:\n",
+                "www.baidu.com.\t\t29\tIN\tCNAME\twww.a.shifen.com.\n",
+                "www.a.shifen.com.\t29\tIN\tA\t183.2.172.185\n",
+                "www.a.shifen.com.\t29\tIN\tA\t183.2.172.42\n",
+                "\n",
+                ";; Query time: 12 msec\n",
+                ";; SERVER: 8.8.8.8#53(8.8.8.8)\n",
+                ";; MSG SIZE  rcvd: 90\n",
+            )
+        );
+        assert_eq!(
+            response.display(ResponseFmtType::Tree).to_string(),
+            response.to_string()
+        );
+    }
+
+    #[test]
+    fn test_dig_sections() {
+        let name = |s: &str| RawDomain::from_str(s).unwrap();
+        let mut buf = [0_u8; 512];
+        let packet = MessageBuilder::new()
+            .id(1)
+            .response(true)
+            .rec_desired(true)
+            .authenticated(true)
+            .rcode(16)
+            .question(
+                Rc::new(name("a-very-long-name.example.org")),
+                DnsTypeNum::NS,
+                1,
+            )
+            .authority(Record::from_data(
+                name("example.org"),
+                1,
+                86400,
+                RecordDataType::NS(NS::new(Rc::new(name("ns.example.org")))),
+            ))
+            .additional(Record::from_data(
+                name("ns.example.org"),
+                1,
+                300,
+                RecordDataType::A(A::new(Ipv4Addr::new(192, 0, 2, 53))),
+            ))
+            .edns(Edns {
+                udp_payload_size: 1232,
+                dnssec_ok: true,
+                tcp_keepalive: false,
+            })
+            .encode_to_udp(&mut buf);
+        let response = Response::from_slice_uncheck(packet).unwrap();
+        assert_eq!(
+            response.display(ResponseFmtType::Dig).to_string(),
+            concat!(
+                ";; ->>HEADER<<- opcode: QUERY, status: BADVERS, id: 1\n",
+                ";; flags: qr rd ad; QUERY: 1, ANSWER: 0, AUTHORITY: 1, ADDITIONAL: 2\n",
+                "\n",
+                ";; OPT PSEUDOSECTION:\n",
+                "; EDNS: version: 0, flags: do; udp: 1232\n",
+                "\n",
+                ";; QUESTION SECTION:\n",
+                ";a-very-long-name.example.org.\t\tIN\tNS\n",
+                "\n",
+                ";; AUTHORITY SECTION:\n",
+                "example.org.\t\t86400\tIN\tNS\tns.example.org.\n",
+                "\n",
+                ";; ADDITIONAL SECTION:\n",
+                "ns.example.org.\t\t300\tIN\tA\t192.0.2.53\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,219 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::NameRef;
+use crate::dns::types::parts::Request;
+use crate::dns::types::parts::header::{HEADER_SIZE, ResponseHeader};
+use crate::dns::types::parts::question::Question;
+use crate::dns::types::parts::record_ref::RecordRef;
+use crate::dns::types::parts::response::check_header;
+use crate::dns::utils::{DecodeError, SliceReader};
+use std::iter::{Skip, Take};
+
+// 借用原始报文的响应，构造时只解析header并校验question，
+// 记录在遍历时才逐条解析，常见的"取出A记录"路径不分配内存
+#[derive(Debug)]
+pub struct ResponseRef<'a> {
+    pub header: ResponseHeader,
+    packet: &'a [u8],
+    records_pos: usize,
+}
+
+impl<'a> ResponseRef<'a> {
+    #[inline]
+    pub fn from_slice_uncheck(slice: &'a [u8]) -> Result<ResponseRef<'a>, DecodeError> {
+        Self::from_slice_check(slice, |_| Ok(()))
+    }
+
+    #[inline]
+    pub fn from_slice(slice: &'a [u8], request: &Request) -> Result<ResponseRef<'a>, DecodeError> {
+        Self::from_slice_check(slice, |header| check_header(header, request))
+    }
+
+    fn from_slice_check<F: Fn(&ResponseHeader) -> Result<(), DecodeError>>(
+        slice: &'a [u8],
+        check: F,
+    ) -> Result<ResponseRef<'a>, DecodeError> {
+        if slice.len() < HEADER_SIZE + Question::LEAST_SIZE {
+            return Err(DecodeError::Truncated {
+                offset: slice.len(),
+                need: HEADER_SIZE + Question::LEAST_SIZE - slice.len(),
+            });
+        }
+        let mut reader = SliceReader::from_slice(slice);
+        let header = ResponseHeader::try_from(&mut reader)?;
+        check(&header)?;
+        for _ in 0..header.questions {
+            QuestionRef::from_reader(&mut reader)?;
+        }
+        Ok(ResponseRef {
+            header,
+            packet: slice,
+            records_pos: reader.pos(),
+        })
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.packet
+    }
+
+    // question在构造时已经校验过
+    pub fn questions(&self) -> impl Iterator<Item = QuestionRef<'a>> + use<'a> {
+        let mut reader = SliceReader::from_slice(self.packet);
+        reader.set_pos(HEADER_SIZE);
+        (0..self.header.questions).map_while(move |_| QuestionRef::from_reader(&mut reader).ok())
+    }
+
+    // 按顺序遍历answer/authority/additional中的全部记录，出错后停止
+    #[inline]
+    pub fn records(&self) -> Records<'a> {
+        let mut reader = SliceReader::from_slice(self.packet);
+        reader.set_pos(self.records_pos);
+        Records {
+            reader,
+            remaining: self.header.answer_rrs as usize
+                + self.header.authority_rrs as usize
+                + self.header.additional_rrs as usize,
+        }
+    }
+
+    #[inline]
+    pub fn answers(&self) -> Take<Records<'a>> {
+        self.records().take(self.header.answer_rrs as usize)
+    }
+
+    #[inline]
+    pub fn authorities(&self) -> Take<Skip<Records<'a>>> {
+        self.records()
+            .skip(self.header.answer_rrs as usize)
+            .take(self.header.authority_rrs as usize)
+    }
+
+    #[inline]
+    pub fn additionals(&self) -> Skip<Records<'a>> {
+        self.records()
+            .skip(self.header.answer_rrs as usize + self.header.authority_rrs as usize)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QuestionRef<'a> {
+    pub qname: NameRef<'a>,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+impl<'a> QuestionRef<'a> {
+    pub fn from_reader(reader: &mut SliceReader<'a>) -> Result<QuestionRef<'a>, DecodeError> {
+        let qname = NameRef::from_reader(reader)?;
+        if reader.pos() + Question::FIX_SIZE > reader.len() {
+            return Err(DecodeError::Truncated {
+                offset: reader.pos(),
+                need: Question::FIX_SIZE,
+            });
+        }
+        Ok(QuestionRef {
+            qname,
+            qtype: reader.read_u16()?,
+            qclass: reader.read_u16()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Records<'a> {
+    reader: SliceReader<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<RecordRef<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let record = RecordRef::from_reader(&mut self.reader);
+        self.remaining = if record.is_ok() {
+            self.remaining - 1
+        } else {
+            0
+        };
+        Some(record)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench_func::DECODE_PACKET;
+    use crate::dns::types::base::{DnsTypeNum, RawDomain};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_response_ref() {
+        let response = ResponseRef::from_slice_uncheck(&DECODE_PACKET).unwrap();
+        let question = response.questions().next().unwrap();
+        assert!(question.qname == RawDomain::from_str("www.baidu.com").unwrap());
+        assert_eq!(question.qtype, DnsTypeNum::A);
+
+        let records: Vec<RecordRef> = response.answers().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(response.authorities().count(), 0);
+        assert_eq!(response.additionals().count(), 0);
+        let cname = records[0].target().unwrap();
+        assert!(cname == RawDomain::from_str("www.a.shifen.com").unwrap());
+        assert!(records[1].name() == cname);
+        assert_eq!(records[0].a(), None);
+        assert_eq!(records[1].a(), Some(Ipv4Addr::new(183, 2, 172, 185)));
+        assert_eq!(records[2].a(), Some(Ipv4Addr::new(183, 2, 172, 42)));
+        assert_eq!(records[2].ttl(), 0x1d);
+
+        // 与Response的解析结果一致
+        let owned = records[0].to_record().unwrap();
+        assert_eq!(owned.rtype, DnsTypeNum::CNAME);
+        assert!(owned.name == records[0].name().to_raw_domain());
+
+        // 记录在遍历时才解析，出错后停止
+        let response = ResponseRef::from_slice_uncheck(&DECODE_PACKET[..80]).unwrap();
+        let mut answers = response.answers();
+        assert!(answers.next().unwrap().is_ok());
+        assert!(answers.next().unwrap().is_ok());
+        assert_eq!(
+            answers.next().unwrap().unwrap_err(),
+            DecodeError::Truncated {
+                offset: 76,
+                need: 10
+            }
+        );
+        assert!(answers.next().is_none());
+
+        // question不完整时构造失败
+        assert!(ResponseRef::from_slice_uncheck(&DECODE_PACKET[..20]).is_err());
+    }
+
+    #[test]
+    fn test_txt() {
+        // TXT "ab" "" "c"，以及一条字符串越过RDATA的TXT
+        let packet = [
+            0x00, 0x01, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x10, 0x00, 0x01, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x06,
+            0x02, b'a', b'b', 0x00, 0x01, b'c', 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x3c, 0x00, 0x02, 0x05, b'a',
+        ];
+        let response = ResponseRef::from_slice_uncheck(&packet).unwrap();
+        let records: Vec<RecordRef> = response.records().map(Result::unwrap).collect();
+        assert_eq!(
+            records[0].txt().unwrap().collect::<Vec<_>>(),
+            [&b"ab"[..], b"", b"c"]
+        );
+        assert!(records[1].txt().is_none());
+        assert!(records[1].to_record().is_err());
+        assert!(records[0].a().is_none());
+    }
+}
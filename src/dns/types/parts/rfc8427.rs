@@ -0,0 +1,644 @@
+#![allow(non_snake_case)]
+
+use crate::dns::types::base::record::{OPT, hex_upper, type_name};
+use crate::dns::types::base::{DnsRcodeNum, DnsTypeNum, RawDomain};
+use crate::dns::types::parts::header::{RequestHeader, ResponseHeader};
+use crate::dns::types::parts::question::Question;
+use crate::dns::types::parts::{Edns, Record, RecordDataType, Request, Response};
+use crate::dns::utils::SliceOperator;
+use crate::dns::zone::presentation::{absolute_name, class_name, rdata_text};
+use crate::dns::zone::{hex_decode, name_from_text, rdata_from_text};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use smallvec::SmallVec;
+use std::rc::Rc;
+
+// RFC 8427 DNS消息的JSON表示，字段名与RFC一致。
+// 名称使用带结尾点的文本形式，RDATA同时给出RDATAHEX与rdata<TYPE>文本，
+// 反序列化时优先使用RDATAHEX，TYPEname/CLASSname仅供阅读
+
+#[derive(Serialize, Deserialize)]
+struct HeaderJson {
+    ID: u16,
+    #[serde(default)]
+    QR: bool,
+    #[serde(default)]
+    Opcode: u8,
+    #[serde(default)]
+    AA: bool,
+    #[serde(default)]
+    TC: bool,
+    #[serde(default)]
+    RD: bool,
+    #[serde(default)]
+    RA: bool,
+    #[serde(default)]
+    AD: bool,
+    #[serde(default)]
+    CD: bool,
+    #[serde(default)]
+    RCODE: u8,
+    #[serde(default)]
+    QDCOUNT: u16,
+    #[serde(default)]
+    ANCOUNT: u16,
+    #[serde(default)]
+    NSCOUNT: u16,
+    #[serde(default)]
+    ARCOUNT: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+struct QuestionJson {
+    NAME: String,
+    TYPE: u16,
+    #[serde(default, skip_deserializing)]
+    TYPEname: String,
+    CLASS: u16,
+    #[serde(default, skip_deserializing)]
+    CLASSname: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct RdataJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    RDLENGTH: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    RDATAHEX: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataA: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataAAAA: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataNS: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataCNAME: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataSOA: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataTXT: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataDS: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataDNSKEY: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataRRSIG: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataNSEC: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataNSEC3: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rdataNSEC3PARAM: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordDataJson {
+    TYPE: u16,
+    #[serde(default, skip_deserializing)]
+    TYPEname: String,
+    #[serde(flatten)]
+    rdata: RdataJson,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordJson {
+    NAME: String,
+    TYPE: u16,
+    #[serde(default, skip_deserializing)]
+    TYPEname: String,
+    CLASS: u16,
+    #[serde(default, skip_deserializing)]
+    CLASSname: String,
+    TTL: u32,
+    #[serde(flatten)]
+    rdata: RdataJson,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MessageJson {
+    #[serde(flatten)]
+    header: HeaderJson,
+    #[serde(default)]
+    questionRRs: Vec<QuestionJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    answerRRs: Vec<RecordJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    authorityRRs: Vec<RecordJson>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    additionalRRs: Vec<RecordJson>,
+}
+
+impl RdataJson {
+    // 有文本格式的类型对应的rdata<TYPE>字段，OPT等没有文本格式的返回None
+    fn text_mut(&mut self, rtype: u16) -> Option<&mut Option<String>> {
+        match rtype {
+            DnsTypeNum::A => Some(&mut self.rdataA),
+            DnsTypeNum::AAAA => Some(&mut self.rdataAAAA),
+            DnsTypeNum::NS => Some(&mut self.rdataNS),
+            DnsTypeNum::CNAME => Some(&mut self.rdataCNAME),
+            DnsTypeNum::SOA => Some(&mut self.rdataSOA),
+            DnsTypeNum::TXT => Some(&mut self.rdataTXT),
+            DnsTypeNum::DS => Some(&mut self.rdataDS),
+            DnsTypeNum::DNSKEY => Some(&mut self.rdataDNSKEY),
+            DnsTypeNum::RRSIG => Some(&mut self.rdataRRSIG),
+            DnsTypeNum::NSEC => Some(&mut self.rdataNSEC),
+            DnsTypeNum::NSEC3 => Some(&mut self.rdataNSEC3),
+            DnsTypeNum::NSEC3PARAM => Some(&mut self.rdataNSEC3PARAM),
+            _ => None,
+        }
+    }
+
+    fn from_data(data: &RecordDataType) -> RdataJson {
+        let rdata = SliceOperator::encode_to_vec(|operator| data.encode_rdata(operator));
+        let mut json = RdataJson {
+            RDLENGTH: Some(rdata.len() as u16),
+            RDATAHEX: Some(hex_upper(&rdata)),
+            ..Default::default()
+        };
+        if let Some(text) = json.text_mut(data.type_num()) {
+            *text = Some(rdata_text(data, &absolute_name));
+        }
+        json
+    }
+
+    fn into_data<E: Error>(mut self, rtype: u16) -> Result<RecordDataType, E> {
+        if let Some(hex) = self.RDATAHEX {
+            let rdata = hex_decode(hex.as_bytes())
+                .ok_or_else(|| E::custom(format!("invalid RDATAHEX {}", hex)))?;
+            if let Some(len) = self.RDLENGTH
+                && len as usize != rdata.len()
+            {
+                return Err(E::custom(format!(
+                    "RDLENGTH {} does not match {} bytes of RDATAHEX",
+                    len,
+                    rdata.len()
+                )));
+            }
+            return RecordDataType::from_rdata(rtype, &rdata).map_err(E::custom);
+        }
+        match self.text_mut(rtype).and_then(Option::take) {
+            Some(text) => rdata_from_text(rtype, &text).map_err(E::custom),
+            None => Err(E::custom(format!(
+                "missing RDATAHEX or rdata{}",
+                type_name(rtype)
+            ))),
+        }
+    }
+}
+
+fn name<E: Error>(text: &str) -> Result<RawDomain, E> {
+    name_from_text(text).map_err(E::custom)
+}
+
+#[inline]
+fn flag(bit: bool) -> u8 {
+    bit as u8
+}
+
+impl Serialize for ResponseHeader {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        HeaderJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseHeader {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HeaderJson::deserialize(deserializer).map(ResponseHeader::from)
+    }
+}
+
+impl From<&ResponseHeader> for HeaderJson {
+    fn from(header: &ResponseHeader) -> Self {
+        HeaderJson {
+            ID: header.id,
+            QR: header.response == 1,
+            Opcode: header.opcode,
+            AA: header.authoritative == 1,
+            TC: header.truncated == 1,
+            RD: header.rec_desired == 1,
+            RA: header.rec_avail == 1,
+            AD: header.authenticated == 1,
+            CD: header.check_disable == 1,
+            RCODE: header.rcode,
+            QDCOUNT: header.questions,
+            ANCOUNT: header.answer_rrs,
+            NSCOUNT: header.authority_rrs,
+            ARCOUNT: header.additional_rrs,
+        }
+    }
+}
+
+impl From<HeaderJson> for ResponseHeader {
+    fn from(json: HeaderJson) -> Self {
+        ResponseHeader {
+            id: json.ID,
+            response: flag(json.QR),
+            opcode: json.Opcode,
+            authoritative: flag(json.AA),
+            truncated: flag(json.TC),
+            rec_desired: flag(json.RD),
+            rec_avail: flag(json.RA),
+            z: 0,
+            authenticated: flag(json.AD),
+            check_disable: flag(json.CD),
+            rcode: json.RCODE,
+            questions: json.QDCOUNT,
+            answer_rrs: json.ANCOUNT,
+            authority_rrs: json.NSCOUNT,
+            additional_rrs: json.ARCOUNT,
+        }
+    }
+}
+
+impl Serialize for Question {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        QuestionJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Question {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        QuestionJson::deserialize(deserializer)?.into_question()
+    }
+}
+
+impl From<&Question> for QuestionJson {
+    fn from(question: &Question) -> Self {
+        QuestionJson {
+            NAME: absolute_name(&question.qname),
+            TYPE: question.qtype,
+            TYPEname: type_name(question.qtype),
+            CLASS: question.qclass,
+            CLASSname: class_name(question.qclass),
+        }
+    }
+}
+
+impl QuestionJson {
+    fn into_question<E: Error>(self) -> Result<Question, E> {
+        Ok(Question {
+            qname: Rc::new(name(&self.NAME)?),
+            qtype: self.TYPE,
+            qclass: self.CLASS,
+        })
+    }
+}
+
+impl Serialize for RecordDataType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RecordDataJson {
+            TYPE: self.type_num(),
+            TYPEname: type_name(self.type_num()),
+            rdata: RdataJson::from_data(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordDataType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = RecordDataJson::deserialize(deserializer)?;
+        json.rdata.into_data(json.TYPE)
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RecordJson::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Record {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        RecordJson::deserialize(deserializer)?.into_record()
+    }
+}
+
+impl From<&Record> for RecordJson {
+    fn from(record: &Record) -> Self {
+        RecordJson {
+            NAME: absolute_name(&record.name),
+            TYPE: record.rtype,
+            TYPEname: type_name(record.rtype),
+            CLASS: record.class,
+            CLASSname: class_name(record.class),
+            TTL: record.ttl,
+            rdata: RdataJson::from_data(&record.data),
+        }
+    }
+}
+
+impl RecordJson {
+    fn into_record<E: Error>(self) -> Result<Record, E> {
+        let data = self.rdata.into_data(self.TYPE)?;
+        Ok(Record::from_data(
+            name(&self.NAME)?,
+            self.CLASS,
+            self.TTL,
+            data,
+        ))
+    }
+}
+
+fn into_records<E: Error>(records: Vec<RecordJson>) -> Result<Vec<Record>, E> {
+    records.into_iter().map(RecordJson::into_record).collect()
+}
+
+fn into_questions<E: Error>(questions: Vec<QuestionJson>) -> Result<SmallVec<[Question; 1]>, E> {
+    questions
+        .into_iter()
+        .map(QuestionJson::into_question)
+        .collect()
+}
+
+impl Serialize for Response {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let records = |records: &[Record]| records.iter().map(RecordJson::from).collect();
+        MessageJson {
+            header: HeaderJson::from(&self.header),
+            questionRRs: self.question.iter().map(QuestionJson::from).collect(),
+            answerRRs: records(self.answers()),
+            authorityRRs: records(self.authorities()),
+            additionalRRs: records(self.additionals()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Response {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = MessageJson::deserialize(deserializer)?;
+        let mut header = ResponseHeader::from(json.header);
+        // 各部分的数量以实际的记录为准，answers()等依赖这些数量切分
+        header.questions = json.questionRRs.len() as u16;
+        header.answer_rrs = json.answerRRs.len() as u16;
+        header.authority_rrs = json.authorityRRs.len() as u16;
+        header.additional_rrs = json.additionalRRs.len() as u16;
+        let mut answer = into_records(json.answerRRs)?;
+        answer.extend(into_records(json.authorityRRs)?);
+        answer.extend(into_records(json.additionalRRs)?);
+        Ok(Response {
+            header,
+            question: into_questions(json.questionRRs)?,
+            answer,
+        })
+    }
+}
+
+// 请求中的EDNS以OPT伪记录的形式放在additionalRRs中
+impl Serialize for Request {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let header = &self.header;
+        let additional: Vec<RecordJson> = self
+            .edns
+            .iter()
+            .map(|edns| {
+                let do_bit = if edns.dnssec_ok { OPT::DNSSEC_OK } else { 0 };
+                let options = match edns.tcp_keepalive {
+                    true => vec![(Edns::TCP_KEEPALIVE, Vec::new())],
+                    false => Vec::new(),
+                };
+                let opt = Record::from_data(
+                    RawDomain::from_raw(Vec::new()),
+                    edns.udp_payload_size,
+                    do_bit,
+                    RecordDataType::OPT(OPT::new(options)),
+                );
+                RecordJson::from(&opt)
+            })
+            .collect();
+        MessageJson {
+            header: HeaderJson {
+                ID: header.id,
+                QR: header.response == 1,
+                Opcode: header.opcode,
+                AA: false,
+                TC: header.truncated == 1,
+                RD: header.rec_desired == 1,
+                RA: false,
+                AD: false,
+                CD: header.check_disable == 1,
+                RCODE: 0,
+                QDCOUNT: self.question.len() as u16,
+                ANCOUNT: 0,
+                NSCOUNT: 0,
+                ARCOUNT: additional.len() as u16,
+            },
+            questionRRs: self.question.iter().map(QuestionJson::from).collect(),
+            answerRRs: Vec::new(),
+            authorityRRs: Vec::new(),
+            additionalRRs: additional,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Request {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = MessageJson::deserialize(deserializer)?;
+        if !json.answerRRs.is_empty() || !json.authorityRRs.is_empty() {
+            return Err(D::Error::custom("request with answer or authority records"));
+        }
+        let mut edns = None;
+        for record in into_records::<D::Error>(json.additionalRRs)? {
+            if record.rtype != DnsTypeNum::OPT || edns.is_some() {
+                return Err(D::Error::custom(format!(
+                    "unexpected additional record {} in request",
+                    type_name(record.rtype)
+                )));
+            }
+            let tcp_keepalive = match &record.data {
+                RecordDataType::OPT(opt) => opt
+                    .options()
+                    .iter()
+                    .any(|(code, _)| *code == Edns::TCP_KEEPALIVE),
+                _ => false,
+            };
+            edns = Some(Edns {
+                udp_payload_size: record.class,
+                dnssec_ok: record.ttl & OPT::DNSSEC_OK != 0,
+                tcp_keepalive,
+            });
+        }
+        let question = into_questions(json.questionRRs)?;
+        let header = RequestHeader {
+            id: json.header.ID,
+            response: flag(json.header.QR),
+            opcode: json.header.Opcode,
+            truncated: flag(json.header.TC),
+            rec_desired: flag(json.header.RD),
+            z: 0,
+            check_disable: flag(json.header.CD),
+            questions: question.len() as u16,
+            answer_rrs: 0,
+            authority_rrs: 0,
+            additional_rrs: edns.is_some() as u16,
+        };
+        Ok(Request {
+            header,
+            question,
+            edns,
+            accepted_rcodes: 1 << DnsRcodeNum::NOERROR,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench_func::DECODE_PACKET;
+    use crate::dns::zone::ZoneParser;
+    use serde_json::{Value, json};
+
+    fn wire(record: &Record) -> (Vec<u8>, u16, u16, u32, Vec<u8>) {
+        (
+            record.name.to_canonical_wire(),
+            record.rtype,
+            record.class,
+            record.ttl,
+            record.data.canonical_rdata(),
+        )
+    }
+
+    #[test]
+    fn test_response() {
+        let response = Response::from_slice_uncheck(&DECODE_PACKET).unwrap();
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "ID": 47582, "QR": true, "Opcode": 0, "AA": false, "TC": false, "RD": false,
+                "RA": true, "AD": false, "CD": false, "RCODE": 0,
+                "QDCOUNT": 1, "ANCOUNT": 3, "NSCOUNT": 0, "ARCOUNT": 0,
+                "questionRRs": [
+                    {"NAME": "www.baidu.com.", "TYPE": 1, "TYPEname": "A", "CLASS": 1, "CLASSname": "IN"}
+                ],
+                "answerRRs": [
+                    {
+                        "NAME": "www.baidu.com.", "TYPE": 5, "TYPEname": "CNAME",
+                        "CLASS": 1, "CLASSname": "IN", "TTL": 29, "RDLENGTH": 18,
+                        "RDATAHEX": "0377777701610673686966656E03636F6D00",
+                        "rdataCNAME": "www.a.shifen.com."
+                    },
+                    {
+                        "NAME": "www.a.shifen.com.", "TYPE": 1, "TYPEname": "A",
+                        "CLASS": 1, "CLASSname": "IN", "TTL": 29, "RDLENGTH": 4,
+                        "RDATAHEX": "B702ACB9", "rdataA": "183.2.172.185"
+                    },
+                    {
+                        "NAME": "www.a.shifen.com.", "TYPE": 1, "TYPEname": "A",
+                        "CLASS": 1, "CLASSname": "IN", "TTL": 29, "RDLENGTH": 4,
+                        "RDATAHEX": "B702AC2A", "rdataA": "183.2.172.42"
+                    }
+                ]
+            })
+        );
+
+        let parsed: Response = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+        assert_eq!(parsed.answers().len(), 3);
+    }
+
+    #[test]
+    fn test_record_text() {
+        // 只有rdata<TYPE>文本时按主文件格式解析
+        let record: Record = serde_json::from_value(json!({
+            "NAME": "example.com.", "TYPE": 6, "CLASS": 1, "TTL": 3600,
+            "rdataSOA": "ns1.example.com. hostmaster.example.com. 1 7200 3600 1209600 300"
+        }))
+        .unwrap();
+        let zone = "example.com. 3600 IN SOA ns1.example.com. hostmaster.example.com. 1 7200 3600 1209600 300";
+        let expected = ZoneParser::new().parse_str(zone).unwrap();
+        assert_eq!(wire(&record), wire(&expected[0]));
+
+        let error = serde_json::from_value::<Record>(json!({
+            "NAME": "example.com.", "TYPE": 1, "CLASS": 1, "TTL": 1, "rdataA": "192.0.2.1 x"
+        }));
+        assert!(error.is_err());
+        let error = serde_json::from_value::<Record>(json!({
+            "NAME": "example.com.", "TYPE": 1, "CLASS": 1, "TTL": 1,
+            "RDLENGTH": 5, "RDATAHEX": "C0000201"
+        }));
+        assert!(error.is_err());
+        let error = serde_json::from_value::<Record>(json!({
+            "NAME": "example.com.", "TYPE": 1, "CLASS": 1, "TTL": 1
+        }));
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_all_types() {
+        let zone = r#"
+$ORIGIN example.com.
+$TTL 3600
+@   SOA   ns1 hostmaster 1 7200 3600 1209600 300
+@   NS    ns1
+ns1 A     192.0.2.1
+ns1 AAAA  2001:db8::53
+www CNAME ns1
+txt TXT   "semi;colon" "quote\"back\\slash"
+sec DNSKEY 257 3 8 AwEAAag=
+sec DS 60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118
+sec RRSIG A 8 3 3600 20030322173103 20030220173103 2642 example.com. oJB1W6WNGv+ldvQ3
+sec NSEC \@.example.com. A RRSIG NSEC TYPE1234
+sec NSEC3 1 1 12 AABBCCDD 2T7B4G4VSA5SMI47K61MV5BV1A22BOJR A RRSIG
+sec NSEC3PARAM 1 0 0 -
+"#;
+        let mut records = ZoneParser::new().parse_str(zone).unwrap();
+        records.push(Record::from_data(
+            RawDomain::from_raw(Vec::new()),
+            1232,
+            OPT::DNSSEC_OK,
+            RecordDataType::OPT(OPT::new(vec![(10, vec![1, 2, 3, 4, 5, 6, 7, 8])])),
+        ));
+        for record in &records {
+            let mut value = serde_json::to_value(record).unwrap();
+            let parsed: Record = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(wire(&parsed), wire(record), "{}", value);
+
+            // 去掉RDATAHEX后由文本解析的结果相同，OPT没有文本格式
+            let object = value.as_object_mut().unwrap();
+            object.remove("RDATAHEX");
+            let text = object.keys().find(|k| k.starts_with("rdata")).cloned();
+            match text {
+                Some(_) => {
+                    let parsed: Record = serde_json::from_value(value).unwrap();
+                    assert_eq!(wire(&parsed), wire(record));
+                }
+                None => assert_eq!(record.rtype, DnsTypeNum::OPT),
+            }
+
+            let data = serde_json::to_value(&record.data).unwrap();
+            assert_eq!(data["TYPE"], Value::from(record.rtype));
+            let parsed: RecordDataType = serde_json::from_value(data).unwrap();
+            assert_eq!(parsed.canonical_rdata(), record.data.canonical_rdata());
+        }
+    }
+
+    #[test]
+    fn test_request() {
+        let mut request = Request::new(Rc::new(RawDomain::from_str("example.com").unwrap()), 28);
+        request.header.id = 7;
+        request.header.rec_desired = 1;
+        request.set_dnssec_ok();
+        request.set_tcp_keepalive();
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["CD"], Value::Bool(true));
+        assert_eq!(value["ARCOUNT"], Value::from(1));
+        assert_eq!(value["additionalRRs"][0]["CLASS"], Value::from(1232));
+        assert_eq!(
+            value["additionalRRs"][0]["TTL"],
+            Value::from(OPT::DNSSEC_OK)
+        );
+
+        let parsed: Request = serde_json::from_value(value).unwrap();
+        let (mut a, mut b) = ([0_u8; 512], [0_u8; 512]);
+        assert_eq!(parsed.encode_to_tcp(&mut a), request.encode_to_tcp(&mut b));
+
+        let value = json!({
+            "ID": 1, "questionRRs": [],
+            "additionalRRs": [{"NAME": ".", "TYPE": 1, "CLASS": 1, "TTL": 0, "rdataA": "192.0.2.1"}]
+        });
+        assert!(serde_json::from_value::<Request>(value).is_err());
+    }
+}
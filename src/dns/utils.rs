@@ -1,9 +1,15 @@
+mod decode_error;
+mod dns_stamp;
 mod server_type;
 mod slice_operator;
 mod slice_reader;
 
 pub use slice_reader::SliceReader;
 
+pub use decode_error::DecodeError;
+pub(crate) use decode_error::{rdata_consumed, rdata_end, rdata_within};
+
 pub use slice_operator::SliceOperator;
 
-pub use server_type::ServerType;
+#[allow(unused_imports)]
+pub use server_type::{DnsCryptProvider, ServerAddr, ServerHost, ServerParseError, ServerType};
@@ -0,0 +1,124 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::utils::SliceReader;
+use snafu::Snafu;
+
+// 解码报文失败的原因，offset均为出错位置在整个报文中的偏移
+#[derive(Snafu, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    #[snafu(display("Truncated, offset: {}, need {} more bytes", offset, need))]
+    Truncated { offset: usize, need: usize },
+    // 指针越界或没有指向更前面的位置(会导致循环)
+    #[snafu(display("BadPointer, offset: {}, target: {}", offset, target))]
+    BadPointer { offset: usize, target: usize },
+    // label长度字节的高两位为01或10
+    #[snafu(display("LabelTooLong, offset: {}, length byte: {:#04x}", offset, len))]
+    LabelTooLong { offset: usize, len: u8 },
+    #[snafu(display("NameTooLong, offset: {}", offset))]
+    NameTooLong { offset: usize },
+    #[snafu(display(
+        "RdataLengthMismatch, offset: {}, type: {}, rdlength: {}, consumed: {}",
+        offset,
+        rtype,
+        rdlength,
+        consumed
+    ))]
+    RdataLengthMismatch {
+        offset: usize,
+        rtype: u16,
+        rdlength: usize,
+        consumed: usize,
+    },
+    #[snafu(display(
+        "MalformedRdata, offset: {}, type: {}, info: {}",
+        offset,
+        rtype,
+        reason
+    ))]
+    MalformedRdata {
+        offset: usize,
+        rtype: u16,
+        reason: &'static str,
+    },
+    #[snafu(display("UnsupportedType, offset: {}, type: {}", offset, rtype))]
+    UnsupportedType { offset: usize, rtype: u16 },
+    // 响应与请求不匹配，如id、opcode不同
+    #[snafu(display("UnexpectedResponse, offset: {}, field: {}", offset, field))]
+    UnexpectedResponse { offset: usize, field: &'static str },
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            DecodeError::Truncated { offset, .. }
+            | DecodeError::BadPointer { offset, .. }
+            | DecodeError::LabelTooLong { offset, .. }
+            | DecodeError::NameTooLong { offset }
+            | DecodeError::RdataLengthMismatch { offset, .. }
+            | DecodeError::MalformedRdata { offset, .. }
+            | DecodeError::UnsupportedType { offset, .. }
+            | DecodeError::UnexpectedResponse { offset, .. } => offset,
+        }
+    }
+}
+
+// 检查RDATA没有超出报文且不短于该类型的固定部分，返回RDATA的结束位置
+pub(crate) fn rdata_end(
+    reader: &SliceReader,
+    raw_len: usize,
+    least: usize,
+    rtype: u16,
+) -> Result<usize, DecodeError> {
+    let offset = reader.pos();
+    if offset + raw_len > reader.len() {
+        return Err(DecodeError::Truncated {
+            offset,
+            need: raw_len,
+        });
+    }
+    if raw_len < least {
+        return Err(DecodeError::RdataLengthMismatch {
+            offset,
+            rtype,
+            rdlength: raw_len,
+            consumed: least,
+        });
+    }
+    Ok(offset + raw_len)
+}
+
+// 已读取的部分不能超出RDATA，start为RDATA的起始位置
+pub(crate) fn rdata_within(
+    reader: &SliceReader,
+    start: usize,
+    raw_len: usize,
+    rtype: u16,
+) -> Result<(), DecodeError> {
+    if reader.pos() > start + raw_len {
+        return Err(DecodeError::RdataLengthMismatch {
+            offset: start,
+            rtype,
+            rdlength: raw_len,
+            consumed: reader.pos() - start,
+        });
+    }
+    Ok(())
+}
+
+// 解析完成后必须恰好读完RDATA，否则后续记录会从错位的位置开始解析
+pub(crate) fn rdata_consumed(
+    reader: &SliceReader,
+    start: usize,
+    raw_len: usize,
+    rtype: u16,
+) -> Result<(), DecodeError> {
+    if reader.pos() != start + raw_len {
+        return Err(DecodeError::RdataLengthMismatch {
+            offset: start,
+            rtype,
+            rdlength: raw_len,
+            consumed: reader.pos() - start,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use crate::dns::utils::server_type::{
+    DnsCryptProvider, ServerAddr, ServerHost, ServerParseError, ServerType,
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::net::{IpAddr, SocketAddr};
+
+// DNS Stamps(sdns://)中的协议标识
+const STAMP_PLAIN: u8 = 0x00;
+const STAMP_DNSCRYPT: u8 = 0x01;
+const STAMP_DOH: u8 = 0x02;
+const STAMP_DOT: u8 = 0x03;
+const STAMP_DOQ: u8 = 0x04;
+
+// 按 https://dnscrypt.info/stamps-specifications 解析，跳过props与证书hash
+pub(crate) fn parse_stamp(stamp: &str) -> Result<ServerType, ServerParseError> {
+    let invalid = |reason: &str| ServerParseError::InvalidStamp {
+        stamp: stamp.to_string(),
+        reason: reason.to_string(),
+    };
+    let data = URL_SAFE_NO_PAD
+        .decode(stamp.trim_end_matches('='))
+        .map_err(|_| invalid("invalid base64"))?;
+    let mut reader = StampReader {
+        data: &data,
+        pos: 0,
+    };
+    let protocol = reader.u8().ok_or_else(|| invalid("empty stamp"))?;
+    // props为8字节的小端序标志位，对客户端只是提示
+    reader.bytes(8).ok_or_else(|| invalid("truncated props"))?;
+    let truncated = || invalid("truncated stamp");
+
+    let server = match protocol {
+        STAMP_PLAIN => {
+            let addr = reader.lp().ok_or_else(truncated)?;
+            let addr = stamp_addr(addr, 53).ok_or_else(|| invalid("invalid address"))?;
+            ServerType::Udp(ServerAddr::from_socket_addr(addr))
+        }
+        STAMP_DNSCRYPT => {
+            let addr = reader.lp().ok_or_else(truncated)?;
+            let addr = stamp_addr(addr, 443).ok_or_else(|| invalid("invalid address"))?;
+            let public_key = reader.lp().ok_or_else(truncated)?;
+            let public_key: [u8; 32] = public_key
+                .try_into()
+                .map_err(|_| invalid("public key is not 32 bytes"))?;
+            let name = reader.lp().ok_or_else(truncated)?;
+            let name =
+                String::from_utf8(name.to_vec()).map_err(|_| invalid("invalid provider name"))?;
+            ServerType::DnsCrypt(
+                ServerAddr::from_socket_addr(addr),
+                DnsCryptProvider { name, public_key },
+            )
+        }
+        STAMP_DOH | STAMP_DOT | STAMP_DOQ => {
+            let addr = reader.lp().ok_or_else(truncated)?;
+            reader.vlp().ok_or_else(truncated)?;
+            let host = reader.lp().ok_or_else(truncated)?;
+            let default_port = if protocol == STAMP_DOH { 443 } else { 853 };
+            let (name, port) =
+                stamp_host(host, default_port).ok_or_else(|| invalid("invalid host name"))?;
+            let path = if protocol == STAMP_DOH {
+                let path = reader.lp().ok_or_else(truncated)?;
+                String::from_utf8(path.to_vec()).map_err(|_| invalid("invalid path"))?
+            } else {
+                String::new()
+            };
+            // addr为空时需要自行解析host，否则直接连接该地址
+            let bootstrap = if addr.is_empty() {
+                None
+            } else {
+                let addr = stamp_addr(addr, port).ok_or_else(|| invalid("invalid address"))?;
+                Some(addr.ip())
+            };
+            let addr = ServerAddr {
+                host: ServerHost::Name(name),
+                port,
+                path,
+                bootstrap,
+                ..ServerAddr::default()
+            };
+            match protocol {
+                STAMP_DOH => ServerType::Https(addr),
+                STAMP_DOT => ServerType::Tls(addr),
+                _ => ServerType::Quic(addr),
+            }
+        }
+        other => {
+            return Err(invalid(&format!("unsupported protocol {:#04x}", other)));
+        }
+    };
+    Ok(server)
+}
+
+// 与parse_stamp相反，props为空。地址总是带端口，IPv6地址带[]
+pub(crate) fn dnscrypt_stamp(addr: &ServerAddr, provider: &DnsCryptProvider) -> String {
+    let host = format!("{}:{}", addr.host, addr.port);
+    let mut data = vec![STAMP_DNSCRYPT];
+    data.extend_from_slice(&[0; 8]);
+    for field in [
+        host.as_bytes(),
+        &provider.public_key,
+        provider.name.as_bytes(),
+    ] {
+        data.push(field.len() as u8);
+        data.extend_from_slice(field);
+    }
+    format!("sdns://{}", URL_SAFE_NO_PAD.encode(data))
+}
+
+// 地址可以是 ip、ip:port 或 [ipv6]:port
+fn stamp_addr(addr: &[u8], default_port: u16) -> Option<SocketAddr> {
+    let addr = std::str::from_utf8(addr).ok()?;
+    if let Ok(ip) = addr.parse::<IpAddr>() {
+        return Some(SocketAddr::new(ip, default_port));
+    }
+    if let Some(ip) = addr.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return Some(SocketAddr::new(ip.parse().ok()?, default_port));
+    }
+    addr.parse().ok()
+}
+
+fn stamp_host(host: &[u8], default_port: u16) -> Option<(String, u16)> {
+    let host = std::str::from_utf8(host).ok()?;
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) => (name, port.parse().ok()?),
+        None => (host, default_port),
+    };
+    (!name.is_empty()).then(|| (name.to_string(), port))
+}
+
+struct StampReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StampReader<'a> {
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    // 一字节长度加内容
+    fn lp(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    // 长度字节最高位为1时后面还有下一项
+    fn vlp(&mut self) -> Option<Vec<&'a [u8]>> {
+        let mut items = Vec::new();
+        loop {
+            let len = self.u8()?;
+            items.push(self.bytes((len & 0x7f) as usize)?);
+            if len & 0x80 == 0 {
+                return Some(items);
+            }
+        }
+    }
+}
@@ -1,83 +1,489 @@
-use std::net::{AddrParseError, SocketAddr};
+use crate::dns::utils::dns_stamp::{dnscrypt_stamp, parse_stamp};
+use snafu::Snafu;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
-use stringzilla::sz::rfind_char_from;
+use std::time::Duration;
 
+// 上游服务器，格式为 [scheme://]host[:port][/path][?key=value&...]，
+// scheme缺省为udp，host可以是IPv4、IPv6(带端口时需要[])或域名，
+// 支持的参数: timeout=5s|500ms、bootstrap=ip(域名的连接地址)、sni=name(TLS SNI)
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServerType {
-    Tcp(SocketAddr),
-    Udp(SocketAddr),
+    Udp(ServerAddr),
+    Tcp(ServerAddr),
+    Tls(ServerAddr),
+    Https(ServerAddr),
+    Quic(ServerAddr),
+    DnsCrypt(ServerAddr, DnsCryptProvider),
 }
 
-impl ServerType {
-    pub fn from_string(s: &mut String) -> Result<ServerType, AddrParseError> {
-        Self::check_colon(s);
-        if s.starts_with("tcp://") {
-            Ok(ServerType::Tcp(SocketAddr::from_str(&s[6..])?))
-        } else if s.starts_with("udp://") {
-            Ok(ServerType::Udp(SocketAddr::from_str(&s[6..])?))
-        } else {
-            Ok(ServerType::Udp(SocketAddr::from_str(&s[..])?))
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerHost {
+    Ip(IpAddr),
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddr {
+    pub host: ServerHost,
+    pub port: u16,
+    // 仅https使用，如/dns-query
+    pub path: String,
+    pub tls_name: Option<String>,
+    pub timeout: Option<Duration>,
+    pub bootstrap: Option<IpAddr>,
+}
+
+// DNSCrypt服务器的provider name与证书签名公钥，来自sdns://
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsCryptProvider {
+    pub name: String,
+    pub public_key: [u8; 32],
+}
+
+#[derive(Snafu, Debug, Clone, PartialEq, Eq)]
+pub enum ServerParseError {
+    #[snafu(display("UnknownScheme, server: {}, scheme: {}", server, scheme))]
+    UnknownScheme { server: String, scheme: String },
+    // 可以识别但尚未实现的类型，如h3
+    #[snafu(display(
+        "UnsupportedScheme, server: {}, scheme: {} is not supported",
+        server,
+        scheme
+    ))]
+    UnsupportedScheme { server: String, scheme: String },
+    #[snafu(display("InvalidHost, server: {}, host: {}", server, host))]
+    InvalidHost { server: String, host: String },
+    #[snafu(display("InvalidPort, server: {}, port: {}", server, port))]
+    InvalidPort { server: String, port: String },
+    // 只有https可以带路径
+    #[snafu(display("UnexpectedPath, server: {}, path: {}", server, path))]
+    UnexpectedPath { server: String, path: String },
+    #[snafu(display("UnknownOption, server: {}, option: {}", server, key))]
+    UnknownOption { server: String, key: String },
+    #[snafu(display("InvalidOption, server: {}, option: {}, value: {}", server, key, value))]
+    InvalidOption {
+        server: String,
+        key: String,
+        value: String,
+    },
+    #[snafu(display("InvalidStamp, stamp: {}, info: {}", stamp, reason))]
+    InvalidStamp { stamp: String, reason: String },
+}
+
+impl Default for ServerAddr {
+    fn default() -> Self {
+        ServerAddr {
+            host: ServerHost::Ip(IpAddr::from([0, 0, 0, 0])),
+            port: 53,
+            path: String::new(),
+            tls_name: None,
+            timeout: None,
+            bootstrap: None,
         }
     }
+}
 
-    fn check_colon(s: &mut String) {
-        if rfind_char_from(&s, [0x4c_u8]).is_none() {
-            s.push_str(":53");
+impl ServerAddr {
+    pub fn from_socket_addr(addr: SocketAddr) -> ServerAddr {
+        ServerAddr {
+            host: ServerHost::Ip(addr.ip()),
+            port: addr.port(),
+            ..ServerAddr::default()
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::dns::utils::server_type::ServerType;
+    // 域名没有bootstrap地址时返回None，需要先解析
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match (&self.host, &self.bootstrap) {
+            (ServerHost::Ip(ip), _) | (ServerHost::Name(_), Some(ip)) => {
+                Some(SocketAddr::new(*ip, self.port))
+            }
+            (ServerHost::Name(_), None) => None,
+        }
+    }
 
-    #[test]
-    fn test_check_colon() {
-        let server = &mut "223.5.5.5".to_string();
-        ServerType::check_colon(server);
-        assert_eq!(server, "223.5.5.5:53");
+    // TLS握手使用的名称，未指定sni时使用host中的域名
+    #[cfg_attr(debug_assertions, allow(dead_code))]
+    pub fn server_name(&self) -> Option<&str> {
+        match (&self.tls_name, &self.host) {
+            (Some(name), _) | (None, ServerHost::Name(name)) => Some(name),
+            (None, ServerHost::Ip(_)) => None,
+        }
+    }
+}
 
-        let server = &mut "tcp://223.5.5.5".to_string();
-        ServerType::check_colon(server);
-        assert_eq!(server, "tcp://223.5.5.5:53");
+impl Display for ServerHost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerHost::Ip(IpAddr::V6(ip)) => write!(f, "[{}]", ip),
+            ServerHost::Ip(ip) => write!(f, "{}", ip),
+            ServerHost::Name(name) => f.write_str(name),
+        }
+    }
+}
 
-        let server = &mut "udp://223.5.5.5".to_string();
-        ServerType::check_colon(server);
-        assert_eq!(server, "udp://223.5.5.5:53");
+impl Display for ServerAddr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}{}", self.host, self.port, self.path)
     }
+}
 
-    #[test]
-    fn test_server_type() {
-        let server = &mut "223.5.5.5".to_string();
-        let server_type = ServerType::from_string(server).unwrap();
-        match server_type {
-            ServerType::Tcp(_) => {
-                panic!();
-            }
-            ServerType::Udp(addr) => {
-                assert_eq!(addr.to_string(), "223.5.5.5:53");
+impl Display for ServerType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (scheme, addr) = match self {
+            ServerType::Udp(addr) => ("udp", addr),
+            ServerType::Tcp(addr) => ("tcp", addr),
+            ServerType::Tls(addr) => ("tls", addr),
+            ServerType::Https(addr) => ("https", addr),
+            ServerType::Quic(addr) => ("quic", addr),
+            // 没有对应的scheme，输出可以重新解析的stamp
+            ServerType::DnsCrypt(addr, provider) => {
+                return f.write_str(&dnscrypt_stamp(addr, provider));
             }
+        };
+        write!(f, "{}://{}", scheme, addr)
+    }
+}
+
+impl FromStr for ServerType {
+    type Err = ServerParseError;
+
+    fn from_str(s: &str) -> Result<ServerType, ServerParseError> {
+        Self::from_string(s)
+    }
+}
+
+impl ServerType {
+    pub fn from_string(s: &str) -> Result<ServerType, ServerParseError> {
+        let s = s.trim();
+        let (scheme, rest) = match s.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+            None => ("udp".to_string(), s),
+        };
+        if scheme == "sdns" {
+            return parse_stamp(rest);
         }
 
-        let server = &mut "udp://223.5.5.5".to_string();
-        let server_type = ServerType::from_string(server).unwrap();
-        match server_type {
-            ServerType::Tcp(_) => {
-                panic!();
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+        let (default_port, http): (u16, bool) = match scheme.as_str() {
+            "udp" | "tcp" => (53, false),
+            "tls" | "quic" => (853, false),
+            "https" => (443, true),
+            "h3" => {
+                return Err(ServerParseError::UnsupportedScheme {
+                    server: s.to_string(),
+                    scheme,
+                });
             }
-            ServerType::Udp(addr) => {
-                assert_eq!(addr.to_string(), "223.5.5.5:53");
+            _ => {
+                return Err(ServerParseError::UnknownScheme {
+                    server: s.to_string(),
+                    scheme,
+                });
             }
-        }
+        };
+        let path = match (http, path) {
+            (true, "" | "/") => "/dns-query".to_string(),
+            (true, path) => path.to_string(),
+            (false, "") => String::new(),
+            (false, path) => {
+                return Err(ServerParseError::UnexpectedPath {
+                    server: s.to_string(),
+                    path: path.to_string(),
+                });
+            }
+        };
 
-        let server = &mut "tcp://223.5.5.5".to_string();
-        let server_type = ServerType::from_string(server).unwrap();
-        match server_type {
-            ServerType::Tcp(addr) => {
-                assert_eq!(addr.to_string(), "223.5.5.5:53");
+        let (host, port) = split_host_port(s, authority)?;
+        let mut addr = ServerAddr {
+            host,
+            port: port.unwrap_or(default_port),
+            path,
+            ..ServerAddr::default()
+        };
+        for option in query.into_iter().flat_map(|q| q.split('&')) {
+            if option.is_empty() {
+                continue;
             }
-            ServerType::Udp(_) => {
-                panic!();
+            apply_option(s, option, &mut addr)?;
+        }
+
+        Ok(match scheme.as_str() {
+            "udp" => ServerType::Udp(addr),
+            "tcp" => ServerType::Tcp(addr),
+            "tls" => ServerType::Tls(addr),
+            "quic" => ServerType::Quic(addr),
+            _ => ServerType::Https(addr),
+        })
+    }
+
+    pub fn addr(&self) -> &ServerAddr {
+        match self {
+            ServerType::Udp(addr)
+            | ServerType::Tcp(addr)
+            | ServerType::Tls(addr)
+            | ServerType::Https(addr)
+            | ServerType::Quic(addr)
+            | ServerType::DnsCrypt(addr, _) => addr,
+        }
+    }
+}
+
+// [v6]:port、v6、v4:port、v4、name:port、name
+fn split_host_port(
+    server: &str,
+    authority: &str,
+) -> Result<(ServerHost, Option<u16>), ServerParseError> {
+    let invalid_host = |host: &str| ServerParseError::InvalidHost {
+        server: server.to_string(),
+        host: host.to_string(),
+    };
+    let parse_port = |port: &str| {
+        port.parse::<u16>()
+            .ok()
+            .filter(|p| *p != 0)
+            .ok_or_else(|| ServerParseError::InvalidPort {
+                server: server.to_string(),
+                port: port.to_string(),
+            })
+    };
+
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (ip, port) = rest
+            .split_once(']')
+            .ok_or_else(|| invalid_host(authority))?;
+        let ip = ip.parse().map_err(|_| invalid_host(ip))?;
+        let port = match port {
+            "" => None,
+            _ => Some(parse_port(
+                port.strip_prefix(':')
+                    .ok_or_else(|| invalid_host(authority))?,
+            )?),
+        };
+        return Ok((ServerHost::Ip(IpAddr::V6(ip)), port));
+    }
+    // 不带[]的IPv6地址不能指定端口
+    if let Ok(ip) = authority.parse::<IpAddr>() {
+        return Ok((ServerHost::Ip(ip), None));
+    }
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, Some(parse_port(port)?)),
+        None => (authority, None),
+    };
+    if let Ok(ip) = host.parse::<std::net::Ipv4Addr>() {
+        return Ok((ServerHost::Ip(IpAddr::V4(ip)), port));
+    }
+    if !is_host_name(host) {
+        return Err(invalid_host(host));
+    }
+    Ok((
+        ServerHost::Name(host.trim_end_matches('.').to_ascii_lowercase()),
+        port,
+    ))
+}
+
+fn is_host_name(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+fn apply_option(server: &str, option: &str, addr: &mut ServerAddr) -> Result<(), ServerParseError> {
+    let (key, value) = option.split_once('=').unwrap_or((option, ""));
+    let invalid = || ServerParseError::InvalidOption {
+        server: server.to_string(),
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    match key {
+        "timeout" => addr.timeout = Some(parse_timeout(value).ok_or_else(invalid)?),
+        "bootstrap" => addr.bootstrap = Some(value.parse().map_err(|_| invalid())?),
+        "sni" => {
+            if !is_host_name(value) {
+                return Err(invalid());
             }
+            addr.tls_name = Some(value.trim_end_matches('.').to_ascii_lowercase());
+        }
+        _ => {
+            return Err(ServerParseError::UnknownOption {
+                server: server.to_string(),
+                key: key.to_string(),
+            });
         }
     }
+    Ok(())
+}
+
+// 不带单位时为秒
+fn parse_timeout(value: &str) -> Option<Duration> {
+    let timeout = if let Some(ms) = value.strip_suffix("ms") {
+        Duration::from_millis(ms.parse().ok()?)
+    } else {
+        Duration::from_secs(value.strip_suffix('s').unwrap_or(value).parse().ok()?)
+    };
+    (!timeout.is_zero()).then_some(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    fn parse(s: &str) -> ServerType {
+        ServerType::from_string(s).unwrap()
+    }
+
+    #[test]
+    fn test_server_type() {
+        let udp = ServerType::Udp(ServerAddr::from_socket_addr(
+            "223.5.5.5:53".parse().unwrap(),
+        ));
+        assert_eq!(parse("223.5.5.5"), udp);
+        assert_eq!(parse("udp://223.5.5.5"), udp);
+        assert_eq!(parse("udp://223.5.5.5:53"), udp);
+        let tcp = ServerType::Tcp(ServerAddr::from_socket_addr(
+            "223.5.5.5:5353".parse().unwrap(),
+        ));
+        assert_eq!(parse("tcp://223.5.5.5:5353"), tcp);
+        assert_eq!(
+            parse("TCP://223.5.5.5:5353").to_string(),
+            "tcp://223.5.5.5:5353"
+        );
+
+        // IPv6地址带端口时必须使用[]
+        for (s, port) in [
+            ("2001:db8::1", 53),
+            ("[2001:db8::1]", 53),
+            ("[2001:db8::1]:5353", 5353),
+        ] {
+            let server = parse(s);
+            assert_eq!(
+                server.addr().socket_addr(),
+                Some(SocketAddr::new("2001:db8::1".parse().unwrap(), port))
+            );
+            assert!(matches!(server, ServerType::Udp(_)));
+        }
+        assert_eq!(parse("tcp://[::1]:53").to_string(), "tcp://[::1]:53");
+    }
+
+    #[test]
+    fn test_encrypted() {
+        let server = parse("tls://dns.google?bootstrap=8.8.8.8&timeout=3s");
+        let ServerType::Tls(addr) = &server else {
+            panic!("{:?}", server);
+        };
+        assert_eq!(addr.host, ServerHost::Name("dns.google".to_string()));
+        assert_eq!(addr.port, 853);
+        assert_eq!(addr.server_name(), Some("dns.google"));
+        assert_eq!(addr.socket_addr(), Some("8.8.8.8:853".parse().unwrap()));
+        assert_eq!(addr.timeout, Some(Duration::from_secs(3)));
+
+        let server = parse("tls://1.1.1.1?sni=cloudflare-dns.com");
+        assert_eq!(server.addr().server_name(), Some("cloudflare-dns.com"));
+        assert_eq!(parse("tls://1.1.1.1").addr().server_name(), None);
+
+        let server = parse("https://dns.example.com");
+        assert!(matches!(server, ServerType::Https(_)));
+        assert_eq!(server.to_string(), "https://dns.example.com:443/dns-query");
+        assert_eq!(server.addr().socket_addr(), None);
+        let server = parse("https://[2606:4700::1111]:8443/resolve?timeout=500ms");
+        assert_eq!(server.addr().path, "/resolve");
+        assert_eq!(server.addr().timeout, Some(Duration::from_millis(500)));
+        assert_eq!(server.to_string(), "https://[2606:4700::1111]:8443/resolve");
+        assert!(matches!(parse("quic://dns.adguard.com"), ServerType::Quic(a) if a.port == 853));
+    }
+
+    #[test]
+    fn test_stamp() {
+        // Cloudflare的DoH stamp
+        let server =
+            parse("sdns://AgcAAAAAAAAABzEuMC4wLjEAEmRucy5jbG91ZGZsYXJlLmNvbQovZG5zLXF1ZXJ5");
+        let ServerType::Https(addr) = &server else {
+            panic!("{:?}", server);
+        };
+        assert_eq!(addr.server_name(), Some("dns.cloudflare.com"));
+        assert_eq!(addr.path, "/dns-query");
+        assert_eq!(addr.socket_addr(), Some("1.0.0.1:443".parse().unwrap()));
+
+        let mut stamp = vec![0x01, 1, 0, 0, 0, 0, 0, 0, 0];
+        stamp.push(14);
+        stamp.extend_from_slice(b"[2001:db8::53]");
+        stamp.push(32);
+        stamp.extend_from_slice(&[7; 32]);
+        stamp.push(22);
+        stamp.extend_from_slice(b"2.dnscrypt-cert.exampl");
+        let server = parse(&format!("sdns://{}", URL_SAFE_NO_PAD.encode(&stamp)));
+        let ServerType::DnsCrypt(addr, provider) = &server else {
+            panic!("{:?}", server);
+        };
+        assert_eq!(
+            addr.socket_addr(),
+            Some("[2001:db8::53]:443".parse().unwrap())
+        );
+        assert_eq!(provider.name, "2.dnscrypt-cert.exampl");
+        assert_eq!(provider.public_key, [7; 32]);
+        // 输出的stamp可以解析回相同的服务器
+        let printed = server.to_string();
+        assert!(printed.starts_with("sdns://"));
+        assert_eq!(parse(&printed), server);
+
+        stamp.pop();
+        let error = ServerType::from_string(&format!("sdns://{}", URL_SAFE_NO_PAD.encode(&stamp)));
+        assert!(matches!(error, Err(ServerParseError::InvalidStamp { .. })));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str| ServerType::from_string(s).unwrap_err().to_string();
+        assert_eq!(
+            error("ftp://1.1.1.1"),
+            "UnknownScheme, server: ftp://1.1.1.1, scheme: ftp"
+        );
+        assert_eq!(
+            error("h3://dns.google"),
+            "UnsupportedScheme, server: h3://dns.google, scheme: h3 is not supported"
+        );
+        assert_eq!(
+            error("1.1.1.1:99999"),
+            "InvalidPort, server: 1.1.1.1:99999, port: 99999"
+        );
+        assert_eq!(error("[::1"), "InvalidHost, server: [::1, host: [::1");
+        assert_eq!(
+            error("bad host"),
+            "InvalidHost, server: bad host, host: bad host"
+        );
+        assert_eq!(
+            error("tls://1.1.1.1/dns-query"),
+            "UnexpectedPath, server: tls://1.1.1.1/dns-query, path: /dns-query"
+        );
+        assert_eq!(
+            error("tls://1.1.1.1?retry=3"),
+            "UnknownOption, server: tls://1.1.1.1?retry=3, option: retry"
+        );
+        assert_eq!(
+            error("tls://dns.google?bootstrap=dns"),
+            "InvalidOption, server: tls://dns.google?bootstrap=dns, option: bootstrap, value: dns"
+        );
+        assert_eq!(
+            error("udp://1.1.1.1?timeout=0"),
+            "InvalidOption, server: udp://1.1.1.1?timeout=0, option: timeout, value: 0"
+        );
+    }
 }
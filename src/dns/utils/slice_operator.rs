@@ -136,4 +136,16 @@ impl<'a> SliceOperator<'a> {
     pub fn from_slice(slice: &'a mut [u8]) -> Self {
         SliceOperator { slice, pos: 0 }
     }
+
+    // 在临时缓冲区中编码，返回实际写入的部分，缓冲区按rdata的最大长度分配
+    pub fn encode_to_vec<F: FnOnce(&mut SliceOperator)>(encode: F) -> Vec<u8> {
+        let mut buf = vec![0_u8; u16::MAX as usize];
+        let len = {
+            let mut operator = SliceOperator::from_slice(&mut buf);
+            encode(&mut operator);
+            operator.pos()
+        };
+        buf.truncate(len);
+        buf
+    }
 }
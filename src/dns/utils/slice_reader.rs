@@ -1,8 +1,9 @@
 #![cfg_attr(debug_assertions, allow(dead_code))]
 
+use crate::dns::utils::DecodeError;
 use std::slice::Iter;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SliceReader<'a> {
     slice: &'a [u8],
     pos: usize,
@@ -21,58 +22,79 @@ impl<'a> SliceReader<'a> {
         self.pos
     }
 
+    // 剩余字节数
     #[inline]
-    pub fn peek_u8(&self) -> u8 {
-        self.slice[self.pos]
+    pub fn remaining(&self) -> usize {
+        self.slice.len().saturating_sub(self.pos)
     }
 
     #[inline]
-    pub fn peek_u16(&self) -> u16 {
-        u16::from_be_bytes([self.slice[self.pos], self.slice[self.pos + 1]])
+    fn peek_array<const N: usize>(&self) -> Result<[u8; N], DecodeError> {
+        self.slice
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..N))
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(DecodeError::Truncated {
+                offset: self.pos,
+                need: N,
+            })
+    }
+
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let array = self.peek_array()?;
+        self.pos += N;
+        Ok(array)
+    }
+
+    #[inline]
+    pub fn peek_u8(&self) -> Result<u8, DecodeError> {
+        self.peek_array::<1>().map(|[b]| b)
+    }
+
+    #[inline]
+    pub fn peek_u16(&self) -> Result<u16, DecodeError> {
+        self.peek_array().map(u16::from_be_bytes)
     }
 
     #[inline]
-    pub fn peek_u32(&self) -> u32 {
-        u32::from_be_bytes(self.slice[self.pos..self.pos + 4].try_into().unwrap())
+    pub fn peek_u32(&self) -> Result<u32, DecodeError> {
+        self.peek_array().map(u32::from_be_bytes)
     }
 
     #[inline]
-    pub fn peek_u64(&self) -> u64 {
-        u64::from_be_bytes(self.slice[self.pos..self.pos + 8].try_into().unwrap())
+    pub fn peek_u64(&self) -> Result<u64, DecodeError> {
+        self.peek_array().map(u64::from_be_bytes)
     }
 
     #[inline]
-    pub fn read_u8(&mut self) -> u8 {
-        self.pos += 1;
-        self.slice[self.pos - 1]
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.read_array::<1>().map(|[b]| b)
     }
 
     #[inline]
-    pub fn read_u16(&mut self) -> u16 {
-        self.pos += 2;
-        u16::from_be_bytes(self.slice[self.pos - 2..self.pos].try_into().unwrap())
+    pub fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        self.read_array().map(u16::from_be_bytes)
     }
 
     #[inline]
-    pub fn read_u32(&mut self) -> u32 {
-        self.pos += 4;
-        u32::from_be_bytes(self.slice[self.pos - 4..self.pos].try_into().unwrap())
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        self.read_array().map(u32::from_be_bytes)
     }
 
     #[inline]
-    pub fn read_u64(&mut self) -> u64 {
-        self.pos += 8;
-        u64::from_be_bytes(self.slice[self.pos - 8..self.pos].try_into().unwrap())
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        self.read_array().map(u64::from_be_bytes)
     }
 
     #[inline]
-    pub fn iter_from_current_pos(&self) -> Iter<u8> {
-        self.slice[self.pos..].iter()
+    pub fn iter_from_current_pos(&self) -> Iter<'_, u8> {
+        self.slice.get(self.pos..).unwrap_or_default().iter()
     }
 
     #[inline]
-    pub fn skip(&mut self, n: usize) {
-        self.pos += n;
+    pub fn skip(&mut self, n: usize) -> Result<(), DecodeError> {
+        self.read_slice(n).map(|_| ())
     }
 
     #[inline]
@@ -81,7 +103,7 @@ impl<'a> SliceReader<'a> {
     }
 
     #[inline]
-    pub fn as_ref(&self) -> &[u8] {
+    pub fn as_ref(&self) -> &'a [u8] {
         self.slice
     }
 
@@ -96,9 +118,17 @@ impl<'a> SliceReader<'a> {
     }
 
     #[inline]
-    pub fn read_slice(&mut self, len: usize) -> &'a [u8] {
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let slice = self
+            .slice
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(DecodeError::Truncated {
+                offset: self.pos,
+                need: len,
+            })?;
         self.pos += len;
-        &self.slice[self.pos - len..self.pos]
+        Ok(slice)
     }
 
     #[inline]
@@ -125,23 +155,23 @@ mod tests {
             ]
         );
         assert_eq!(reader.pos(), 0);
-        assert_eq!(reader.peek_u8(), 0u8);
-        assert_eq!(reader.read_u8(), 0);
+        assert_eq!(reader.peek_u8().unwrap(), 0u8);
+        assert_eq!(reader.read_u8().unwrap(), 0);
         assert_eq!(reader.pos(), 1);
-        assert_eq!(reader.peek_u16(), 1u16 << 8 | 2u16);
-        assert_eq!(reader.read_u16(), 1u16 << 8 | 2u16);
+        assert_eq!(reader.peek_u16().unwrap(), 1u16 << 8 | 2u16);
+        assert_eq!(reader.read_u16().unwrap(), 1u16 << 8 | 2u16);
         assert_eq!(reader.pos(), 3);
         assert_eq!(
-            reader.peek_u32(),
+            reader.peek_u32().unwrap(),
             3u32 << 24 | 4u32 << 16 | 5u32 << 8 | 6u32
         );
         assert_eq!(
-            reader.read_u32(),
+            reader.read_u32().unwrap(),
             3u32 << 24 | 4u32 << 16 | 5u32 << 8 | 6u32
         );
         assert_eq!(reader.pos(), 7);
         assert_eq!(
-            reader.peek_u64(),
+            reader.peek_u64().unwrap(),
             7u64 << 56
                 | 8u64 << 48
                 | 9u64 << 40
@@ -152,7 +182,7 @@ mod tests {
                 | 14u64
         );
         assert_eq!(
-            reader.read_u64(),
+            reader.read_u64().unwrap(),
             7u64 << 56
                 | 8u64 << 48
                 | 9u64 << 40
@@ -166,11 +196,31 @@ mod tests {
         assert_eq!(reader.slice, reader.as_ref());
         reader.set_pos(1);
         assert_eq!(reader.pos, 1);
-        reader.skip(2);
+        reader.skip(2).unwrap();
         assert_eq!(reader.pos, 3);
-        assert_eq!(reader.read_slice(2), &slice[3..5]);
+        assert_eq!(reader.read_slice(2).unwrap(), &slice[3..5]);
         assert_eq!(reader.pos(), 5);
-        assert_eq!(reader.read_slice(4), &slice[5..9]);
+        assert_eq!(reader.read_slice(4).unwrap(), &slice[5..9]);
         assert_eq!(reader.pos(), 9);
     }
+
+    #[test]
+    fn test_truncated() {
+        let slice = [1u8, 2, 3];
+        let mut reader = SliceReader::from_slice(&slice);
+        assert_eq!(
+            reader.read_u32(),
+            Err(DecodeError::Truncated { offset: 0, need: 4 })
+        );
+        // 出错时位置不变
+        assert_eq!(reader.pos(), 0);
+        assert_eq!(reader.read_u16(), Ok(0x0102));
+        assert_eq!(reader.remaining(), 1);
+        assert!(reader.read_slice(2).is_err());
+        assert!(reader.skip(usize::MAX).is_err());
+        reader.set_pos(10);
+        assert!(reader.peek_u8().is_err());
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.iter_from_current_pos().count(), 0);
+    }
 }
@@ -0,0 +1,10 @@
+mod parser;
+pub(crate) mod presentation;
+mod writer;
+
+#[allow(unused_imports)]
+pub use parser::{ZoneError, ZoneParser};
+#[cfg(feature = "serde")]
+pub(crate) use parser::{hex_decode, name_from_text, rdata_from_text};
+#[allow(unused_imports)]
+pub use writer::ZoneWriter;
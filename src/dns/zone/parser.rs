@@ -0,0 +1,1345 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::record::{
+    A, AAAA, CNAME, DNSKEY, DS, NS, NSEC, NSEC3, NSEC3PARAM, RRSIG, SOA, TXT, TypeBitmap,
+    base32hex_decode,
+};
+use crate::dns::types::base::{
+    DnsType, DnsTypeNum, MAX_LABEL_LEN, MAX_NAME_LEN, RawDomain, RawString,
+};
+use crate::dns::types::parts::{Record, RecordDataType};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::NaiveDateTime;
+use snafu::Snafu;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// $INCLUDE的最大嵌套深度，防止文件互相包含导致无限递归
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// 解析区域文件失败的原因，line与column均从1开始，指向出错的token
+#[derive(Snafu, Debug)]
+pub enum ZoneError {
+    #[snafu(display(
+        "ZoneSyntaxError, file: {}, line: {}, column: {}, info: {}",
+        file,
+        line,
+        column,
+        reason
+    ))]
+    Syntax {
+        file: String,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    // 读取顶层文件失败时line与column为0，否则指向$INCLUDE
+    #[snafu(display(
+        "ZoneReadError, file: {}, line: {}, column: {}, path: {}, info: {}",
+        file,
+        line,
+        column,
+        path.display(),
+        source
+    ))]
+    Read {
+        file: String,
+        line: usize,
+        column: usize,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl ZoneError {
+    pub fn line(&self) -> usize {
+        match *self {
+            ZoneError::Syntax { line, .. } | ZoneError::Read { line, .. } => line,
+        }
+    }
+
+    pub fn column(&self) -> usize {
+        match *self {
+            ZoneError::Syntax { column, .. } | ZoneError::Read { column, .. } => column,
+        }
+    }
+}
+
+// RFC 1035 5.1 主文件格式的解析器，生成的记录按出现顺序排列。
+// 未写TTL时依次使用$TTL与上一条记录显式给出的TTL，未写class时沿用上一条记录的class
+#[derive(Debug, Clone)]
+pub struct ZoneParser {
+    origin: Option<Rc<RawDomain>>,
+    default_ttl: Option<u32>,
+    class: u16,
+}
+
+impl Default for ZoneParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZoneParser {
+    pub fn new() -> ZoneParser {
+        ZoneParser {
+            origin: None,
+            default_ttl: None,
+            class: 1,
+        }
+    }
+
+    // 文件中没有$ORIGIN时相对名称基于此补全
+    pub fn with_origin(mut self, origin: RawDomain) -> ZoneParser {
+        self.origin = Some(Rc::new(origin));
+        self
+    }
+
+    pub fn with_default_ttl(mut self, ttl: u32) -> ZoneParser {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    // 第一条记录未写class时使用，默认为IN
+    pub fn with_class(mut self, class: u16) -> ZoneParser {
+        self.class = class;
+        self
+    }
+
+    // $INCLUDE中的相对路径基于当前工作目录
+    pub fn parse_str(&self, text: &str) -> Result<Vec<Record>, ZoneError> {
+        let mut records = Vec::new();
+        self.parse_source(
+            &mut self.state(),
+            text.as_bytes(),
+            "<input>",
+            Path::new(""),
+            0,
+            &mut records,
+        )?;
+        Ok(records)
+    }
+
+    // $INCLUDE中的相对路径基于所在文件的目录
+    pub fn parse_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Record>, ZoneError> {
+        let path = path.as_ref();
+        let file = path.display().to_string();
+        let text = std::fs::read(path).map_err(|source| ZoneError::Read {
+            file: file.clone(),
+            line: 0,
+            column: 0,
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut records = Vec::new();
+        self.parse_source(
+            &mut self.state(),
+            &text,
+            &file,
+            path.parent().unwrap_or(Path::new("")),
+            0,
+            &mut records,
+        )?;
+        Ok(records)
+    }
+
+    fn state(&self) -> State {
+        State {
+            origin: self.origin.clone(),
+            default_ttl: self.default_ttl,
+            last_ttl: None,
+            last_owner: None,
+            last_class: self.class,
+        }
+    }
+
+    fn parse_source(
+        &self,
+        state: &mut State,
+        text: &[u8],
+        file: &str,
+        dir: &Path,
+        depth: usize,
+        records: &mut Vec<Record>,
+    ) -> Result<(), ZoneError> {
+        let mut lexer = Lexer::new(text);
+        while let Some(entry) = lexer.next_entry().map_err(|e| e.into_error(file))? {
+            let is_directive = !entry.blank_owner
+                && !entry.tokens[0].quoted
+                && entry.tokens[0].text.starts_with(b"$");
+            if !is_directive {
+                let record = state.record(entry).map_err(|e| e.into_error(file))?;
+                records.push(record);
+                continue;
+            }
+
+            let mut cursor = Cursor::new(entry.tokens);
+            let directive = cursor.next().map_err(|e| e.into_error(file))?;
+            let (line, column) = (directive.line, directive.column);
+            match directive.text.to_ascii_uppercase().as_slice() {
+                b"$ORIGIN" => {
+                    let origin = cursor
+                        .next()
+                        .and_then(|t| parse_name(t, state.origin.as_deref()))
+                        .map_err(|e| e.into_error(file))?;
+                    state.origin = Some(Rc::new(origin));
+                }
+                b"$TTL" => {
+                    let ttl = cursor
+                        .next()
+                        .and_then(parse_ttl)
+                        .map_err(|e| e.into_error(file))?;
+                    state.default_ttl = Some(ttl);
+                }
+                b"$INCLUDE" => {
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(ZoneError::Syntax {
+                            file: file.to_string(),
+                            line,
+                            column,
+                            reason: "$INCLUDE nested too deeply".to_string(),
+                        });
+                    }
+                    let path = cursor
+                        .next()
+                        .and_then(|t| {
+                            String::from_utf8(unescape(t)?)
+                                .map_err(|_| t.error("path is not valid UTF-8"))
+                        })
+                        .map(|p| dir.join(p))
+                        .map_err(|e| e.into_error(file))?;
+                    // 被包含文件有独立的状态，结束后$ORIGIN等恢复原值(RFC 1035 5.1)
+                    let mut child = state.clone();
+                    if let Some(origin) = cursor.peek() {
+                        let origin = parse_name(origin, state.origin.as_deref())
+                            .map_err(|e| e.into_error(file))?;
+                        child.origin = Some(Rc::new(origin));
+                        cursor.skip();
+                    }
+                    cursor.finish().map_err(|e| e.into_error(file))?;
+                    let text = std::fs::read(&path).map_err(|source| ZoneError::Read {
+                        file: file.to_string(),
+                        line,
+                        column,
+                        path: path.clone(),
+                        source,
+                    })?;
+                    let include_file = path.display().to_string();
+                    self.parse_source(
+                        &mut child,
+                        &text,
+                        &include_file,
+                        path.parent().unwrap_or(Path::new("")),
+                        depth + 1,
+                        records,
+                    )?;
+                    continue;
+                }
+                other => {
+                    return Err(ZoneError::Syntax {
+                        file: file.to_string(),
+                        line,
+                        column,
+                        reason: format!("unknown directive {}", String::from_utf8_lossy(other)),
+                    });
+                }
+            }
+            cursor.finish().map_err(|e| e.into_error(file))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    origin: Option<Rc<RawDomain>>,
+    default_ttl: Option<u32>,
+    last_ttl: Option<u32>,
+    last_owner: Option<Rc<RawDomain>>,
+    last_class: u16,
+}
+
+impl State {
+    fn record(&mut self, entry: Entry) -> Result<Record, Located> {
+        let mut cursor = Cursor::new(entry.tokens);
+        // 行首为空白时沿用上一条记录的owner
+        let owner = if entry.blank_owner {
+            let first = cursor.peek().expect("entry has at least one token");
+            self.last_owner
+                .clone()
+                .ok_or_else(|| first.error("no previous owner name"))?
+        } else {
+            Rc::new(parse_name(cursor.next()?, self.origin.as_deref())?)
+        };
+
+        // TTL与class都可以省略且顺序任意
+        let mut ttl = None;
+        let mut class = None;
+        let rtype = loop {
+            let token = cursor.next()?;
+            if let (None, Some(c)) = (class, parse_class(&token.text)) {
+                class = Some(c);
+                continue;
+            }
+            if ttl.is_none() && token.text.first().is_some_and(u8::is_ascii_digit) {
+                ttl = Some(parse_ttl(token)?);
+                continue;
+            }
+            break parse_type(&token.text).ok_or_else(|| {
+                token.error(format!(
+                    "unknown class or type {}",
+                    String::from_utf8_lossy(&token.text)
+                ))
+            })?;
+        };
+
+        if ttl.is_some() {
+            self.last_ttl = ttl;
+        }
+        let ttl = match ttl.or(self.default_ttl).or(self.last_ttl) {
+            Some(ttl) => ttl,
+            None => {
+                let first = &cursor.tokens[0];
+                return Err(first.error("no TTL specified and no $TTL in effect"));
+            }
+        };
+        let class = class.unwrap_or(self.last_class);
+
+        let data = parse_rdata(&mut cursor, rtype, self.origin.as_deref())?;
+        cursor.finish()?;
+
+        self.last_owner = Some(owner.clone());
+        self.last_class = class;
+        Ok(Record::from_data(
+            RawDomain::from_raw(owner.as_ref().as_ref().clone()),
+            class,
+            ttl,
+            data,
+        ))
+    }
+}
+
+fn parse_rdata(
+    cursor: &mut Cursor,
+    rtype: u16,
+    origin: Option<&RawDomain>,
+) -> Result<RecordDataType, Located> {
+    let type_token = &cursor.tokens[cursor.next - 1];
+    if rtype == DnsTypeNum::OPT {
+        return Err(type_token.error("OPT is not allowed in zone files"));
+    }
+    // RFC 3597 通用格式：\# 长度 十六进制
+    if cursor.peek().is_some_and(|t| !t.quoted && t.text == b"\\#") {
+        let marker = cursor.next()?;
+        let (line, column) = (marker.line, marker.column);
+        let len: usize = number(cursor.next()?)?;
+        let rdata = cursor.hex()?;
+        let located = |reason: String| Located {
+            line,
+            column,
+            reason,
+        };
+        if rdata.len() != len {
+            return Err(located(format!(
+                "RDATA length {} does not match {} bytes of data",
+                len,
+                rdata.len()
+            )));
+        }
+        return RecordDataType::from_rdata(rtype, &rdata)
+            .map_err(|e| located(format!("invalid RDATA: {}", e)));
+    }
+
+    let data = match rtype {
+        DnsTypeNum::A => {
+            let token = cursor.next()?;
+            RecordDataType::A(A::new(parse_str(token)?))
+        }
+        DnsTypeNum::AAAA => {
+            let token = cursor.next()?;
+            RecordDataType::AAAA(AAAA::new(parse_str(token)?))
+        }
+        DnsTypeNum::NS => RecordDataType::NS(NS::new(Rc::new(parse_name(cursor.next()?, origin)?))),
+        DnsTypeNum::CNAME => {
+            RecordDataType::CNAME(CNAME::new(Rc::new(parse_name(cursor.next()?, origin)?)))
+        }
+        DnsTypeNum::SOA => RecordDataType::SOA(SOA::new(
+            Rc::new(parse_name(cursor.next()?, origin)?),
+            Rc::new(parse_name(cursor.next()?, origin)?),
+            number(cursor.next()?)?,
+            parse_ttl(cursor.next()?)?,
+            parse_ttl(cursor.next()?)?,
+            parse_ttl(cursor.next()?)?,
+            parse_ttl(cursor.next()?)?,
+        )),
+        DnsTypeNum::TXT => {
+            let mut strings = vec![character_string(cursor.next()?)?];
+            while cursor.peek().is_some() {
+                strings.push(character_string(cursor.next()?)?);
+            }
+            RecordDataType::TXT(TXT::new(strings))
+        }
+        DnsTypeNum::DS => RecordDataType::DS(DS::new(
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            cursor.hex()?,
+        )),
+        DnsTypeNum::DNSKEY => RecordDataType::DNSKEY(DNSKEY::new(
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            cursor.base64()?,
+        )),
+        DnsTypeNum::RRSIG => {
+            let token = cursor.next()?;
+            let type_covered =
+                parse_type(&token.text).ok_or_else(|| token.error("unknown type covered"))?;
+            RecordDataType::RRSIG(RRSIG::new(
+                type_covered,
+                number(cursor.next()?)?,
+                number(cursor.next()?)?,
+                parse_ttl(cursor.next()?)?,
+                parse_time(cursor.next()?)?,
+                parse_time(cursor.next()?)?,
+                number(cursor.next()?)?,
+                Rc::new(parse_name(cursor.next()?, origin)?),
+                cursor.base64()?,
+            ))
+        }
+        DnsTypeNum::NSEC => RecordDataType::NSEC(NSEC::new(
+            Rc::new(parse_name(cursor.next()?, origin)?),
+            cursor.types()?,
+        )),
+        DnsTypeNum::NSEC3 => RecordDataType::NSEC3(NSEC3::new(
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            salt(cursor.next()?)?,
+            {
+                let token = cursor.next()?;
+                base32hex_decode(&token.text)
+                    .filter(|hash| !hash.is_empty())
+                    .ok_or_else(|| token.error("invalid base32hex"))?
+            },
+            cursor.types()?,
+        )),
+        DnsTypeNum::NSEC3PARAM => RecordDataType::NSEC3PARAM(NSEC3PARAM::new(
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            number(cursor.next()?)?,
+            salt(cursor.next()?)?,
+        )),
+        _ => {
+            let type_token = &cursor.tokens[cursor.next - 1];
+            return Err(type_token.error(format!(
+                "unsupported type {}",
+                String::from_utf8_lossy(&type_token.text)
+            )));
+        }
+    };
+    Ok(data)
+}
+
+// 单个绝对名称的文本表示，供JSON等其他文本格式复用
+#[cfg(feature = "serde")]
+pub(crate) fn name_from_text(text: &str) -> Result<RawDomain, ZoneError> {
+    let token = Token {
+        text: text.as_bytes().to_vec(),
+        quoted: false,
+        line: 1,
+        column: 1,
+    };
+    parse_name(&token, None).map_err(|e| e.into_error("<input>"))
+}
+
+// 单条RDATA的文本表示，名称必须是绝对名称
+#[cfg(feature = "serde")]
+pub(crate) fn rdata_from_text(rtype: u16, text: &str) -> Result<RecordDataType, ZoneError> {
+    let mut lexer = Lexer::new(text.as_bytes());
+    let mut parse = || {
+        let mut tokens = lexer.next_entry()?.map_or_else(Vec::new, |e| e.tokens);
+        if let Some(extra) = lexer.next_entry()? {
+            return Err(extra.tokens[0].error("RDATA spans multiple records"));
+        }
+        // parse_rdata从前一个token取得类型的位置用于报错
+        tokens.insert(
+            0,
+            Token {
+                text: Vec::new(),
+                quoted: false,
+                line: 1,
+                column: 1,
+            },
+        );
+        let mut cursor = Cursor::new(tokens);
+        cursor.skip();
+        let data = parse_rdata(&mut cursor, rtype, None)?;
+        cursor.finish()?;
+        Ok(data)
+    };
+    parse().map_err(|e: Located| e.into_error("<input>"))
+}
+
+// 出错位置与原因，由调用者补上文件名
+#[derive(Debug)]
+struct Located {
+    line: usize,
+    column: usize,
+    reason: String,
+}
+
+impl Located {
+    fn into_error(self, file: &str) -> ZoneError {
+        ZoneError::Syntax {
+            file: file.to_string(),
+            line: self.line,
+            column: self.column,
+            reason: self.reason,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    // 去掉引号后的原始内容，转义序列保持原样，由使用者按需处理
+    text: Vec<u8>,
+    quoted: bool,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error<T: Into<String>>(&self, reason: T) -> Located {
+        Located {
+            line: self.line,
+            column: self.column,
+            reason: reason.into(),
+        }
+    }
+}
+
+// 一条逻辑记录，括号内的换行已合并
+#[derive(Debug)]
+struct Entry {
+    blank_owner: bool,
+    tokens: Vec<Token>,
+}
+
+struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a [u8]) -> Lexer<'a> {
+        Lexer {
+            input,
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn located<T: Into<String>>(&self, line: usize, column: usize, reason: T) -> Located {
+        Located {
+            line,
+            column,
+            reason: reason.into(),
+        }
+    }
+
+    fn next_entry(&mut self) -> Result<Option<Entry>, Located> {
+        let mut tokens = Vec::new();
+        let mut blank_owner = false;
+        let mut line_start = self.column == 1;
+        // 最外层左括号的位置，用于报告未闭合的括号
+        let mut open = None;
+        let mut depth = 0_usize;
+        loop {
+            let Some(c) = self.peek() else {
+                if let Some((line, column)) = open {
+                    return Err(self.located(line, column, "unbalanced parenthesis"));
+                }
+                return Ok((!tokens.is_empty()).then_some(Entry {
+                    blank_owner,
+                    tokens,
+                }));
+            };
+            if line_start && depth == 0 && tokens.is_empty() {
+                blank_owner = c == b' ' || c == b'\t';
+            }
+            line_start = false;
+            match c {
+                b' ' | b'\t' | b'\r' => {
+                    self.bump();
+                }
+                b';' => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.bump();
+                    }
+                }
+                b'\n' => {
+                    self.bump();
+                    if depth == 0 {
+                        if !tokens.is_empty() {
+                            return Ok(Some(Entry {
+                                blank_owner,
+                                tokens,
+                            }));
+                        }
+                        line_start = true;
+                    }
+                }
+                b'(' => {
+                    if depth == 0 {
+                        open = Some((self.line, self.column));
+                    }
+                    depth += 1;
+                    self.bump();
+                }
+                b')' => {
+                    if depth == 0 {
+                        return Err(self.located(self.line, self.column, "unbalanced parenthesis"));
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        open = None;
+                    }
+                    self.bump();
+                }
+                b'"' => tokens.push(self.quoted()?),
+                _ => tokens.push(self.word()),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<Token, Located> {
+        let (line, column) = (self.line, self.column);
+        self.bump();
+        let mut text = Vec::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.located(line, column, "unterminated quoted string")),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    text.push(b'\\');
+                    if let Some(c) = self.bump() {
+                        text.push(c);
+                    }
+                }
+                Some(c) => text.push(c),
+            }
+        }
+        Ok(Token {
+            text,
+            quoted: true,
+            line,
+            column,
+        })
+    }
+
+    fn word(&mut self) -> Token {
+        let (line, column) = (self.line, self.column);
+        let mut text = Vec::new();
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\r' | b'\n' | b';' | b'(' | b')' | b'"' => break,
+                b'\\' => {
+                    text.push(c);
+                    self.bump();
+                    if let Some(c) = self.bump() {
+                        text.push(c);
+                    }
+                }
+                _ => {
+                    text.push(c);
+                    self.bump();
+                }
+            }
+        }
+        Token {
+            text,
+            quoted: false,
+            line,
+            column,
+        }
+    }
+}
+
+struct Cursor {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Cursor {
+    fn new(tokens: Vec<Token>) -> Cursor {
+        Cursor { tokens, next: 0 }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    #[inline]
+    fn skip(&mut self) {
+        self.next += 1;
+    }
+
+    fn next(&mut self) -> Result<&Token, Located> {
+        if self.next >= self.tokens.len() {
+            let last = self.tokens.last().expect("entry has at least one token");
+            return Err(Located {
+                line: last.line,
+                column: last.column + last.text.len(),
+                reason: "unexpected end of record".to_string(),
+            });
+        }
+        self.next += 1;
+        Ok(&self.tokens[self.next - 1])
+    }
+
+    fn finish(&self) -> Result<(), Located> {
+        match self.peek() {
+            Some(token) => Err(token.error(format!(
+                "unexpected {}",
+                String::from_utf8_lossy(&token.text)
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    // 剩余的token拼接在一起，允许base64、十六进制数据中间有空白
+    fn rest(&mut self) -> (Vec<u8>, Option<(usize, usize)>) {
+        let rest = &self.tokens[self.next.min(self.tokens.len())..];
+        let position = rest.first().map(|t| (t.line, t.column));
+        let text = rest.iter().flat_map(|t| t.text.iter().copied()).collect();
+        self.next = self.tokens.len();
+        (text, position)
+    }
+
+    fn rest_error(&self, position: Option<(usize, usize)>, reason: &str) -> Located {
+        let (line, column) = position.unwrap_or_else(|| {
+            let last = &self.tokens[self.tokens.len() - 1];
+            (last.line, last.column)
+        });
+        Located {
+            line,
+            column,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn hex(&mut self) -> Result<Vec<u8>, Located> {
+        let (text, position) = self.rest();
+        hex_decode(&text).ok_or_else(|| self.rest_error(position, "invalid hex"))
+    }
+
+    fn base64(&mut self) -> Result<Vec<u8>, Located> {
+        let (text, position) = self.rest();
+        match STANDARD.decode(&text) {
+            Ok(bytes) if !bytes.is_empty() => Ok(bytes),
+            _ => Err(self.rest_error(position, "invalid base64")),
+        }
+    }
+
+    fn types(&mut self) -> Result<TypeBitmap, Located> {
+        let mut types = Vec::new();
+        while let Some(token) = self.peek() {
+            let t = parse_type(&token.text).ok_or_else(|| {
+                token.error(format!(
+                    "unknown type {}",
+                    String::from_utf8_lossy(&token.text)
+                ))
+            })?;
+            types.push(t);
+            self.skip();
+        }
+        Ok(TypeBitmap::from_types(types))
+    }
+}
+
+// 处理 \X 与 \DDD 转义，返回解出的字节与消耗的长度
+fn unescape_at(text: &[u8], i: usize) -> Result<(u8, usize), &'static str> {
+    match text.get(i + 1) {
+        None => Err("dangling escape"),
+        Some(c) if c.is_ascii_digit() => {
+            let digits = text
+                .get(i + 1..i + 4)
+                .filter(|d| d.iter().all(u8::is_ascii_digit))
+                .ok_or("escape \\DDD needs three digits")?;
+            let value = digits
+                .iter()
+                .fold(0_u16, |acc, d| acc * 10 + (d - b'0') as u16);
+            u8::try_from(value)
+                .map(|v| (v, 4))
+                .map_err(|_| "escape \\DDD larger than 255")
+        }
+        Some(&c) => Ok((c, 2)),
+    }
+}
+
+fn unescape(token: &Token) -> Result<Vec<u8>, Located> {
+    let text = &token.text;
+    let mut out = Vec::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        if text[i] == b'\\' {
+            let (c, n) = unescape_at(text, i).map_err(|e| token.error(e))?;
+            out.push(c);
+            i += n;
+        } else {
+            out.push(text[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+// @代表当前origin，不以.结尾的名称是相对名称，需要补上origin
+fn parse_name(token: &Token, origin: Option<&RawDomain>) -> Result<RawDomain, Located> {
+    let text = &token.text;
+    let copy_origin = |reason| {
+        origin
+            .map(|o| RawDomain::from_raw(o.as_ref().clone()))
+            .ok_or_else(|| token.error(reason))
+    };
+    if !token.quoted && text == b"@" {
+        return copy_origin("@ used without $ORIGIN");
+    }
+    if text == b"." {
+        return Ok(RawDomain::from_raw(Vec::new()));
+    }
+
+    let mut domain = Vec::with_capacity(text.len() + 1);
+    let mut label = Vec::new();
+    let mut absolute = false;
+    let push_label = |domain: &mut Vec<u8>, label: &mut Vec<u8>| {
+        if label.is_empty() {
+            return Err(token.error("empty label"));
+        }
+        if label.len() > MAX_LABEL_LEN {
+            return Err(token.error("label longer than 63 bytes"));
+        }
+        domain.push(label.len() as u8);
+        domain.append(label);
+        Ok(())
+    };
+    let mut i = 0;
+    while i < text.len() {
+        match text[i] {
+            b'.' => {
+                push_label(&mut domain, &mut label)?;
+                i += 1;
+                absolute = i == text.len();
+            }
+            b'\\' => {
+                let (c, n) = unescape_at(text, i).map_err(|e| token.error(e))?;
+                label.push(c);
+                i += n;
+            }
+            c => {
+                label.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !absolute {
+        push_label(&mut domain, &mut label)?;
+    }
+
+    let name = RawDomain::from_raw(domain);
+    let name = if absolute {
+        name
+    } else {
+        let origin = copy_origin("relative name without $ORIGIN")?;
+        name.append(&origin)
+            .ok_or_else(|| token.error("name longer than 255 bytes"))?
+    };
+    if name.as_ref().len() + 1 > MAX_NAME_LEN {
+        return Err(token.error("name longer than 255 bytes"));
+    }
+    Ok(name)
+}
+
+fn character_string(token: &Token) -> Result<RawString, Located> {
+    RawString::new(unescape(token)?)
+        .ok_or_else(|| token.error("character-string longer than 255 bytes"))
+}
+
+fn parse_str<T: std::str::FromStr>(token: &Token) -> Result<T, Located> {
+    std::str::from_utf8(&token.text)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| {
+            token.error(format!(
+                "invalid value {}",
+                String::from_utf8_lossy(&token.text)
+            ))
+        })
+}
+
+// 只接受十进制数字，避免FromStr接受的+号等写法
+fn number<T: std::str::FromStr>(token: &Token) -> Result<T, Located> {
+    if token.text.is_empty() || !token.text.iter().all(u8::is_ascii_digit) {
+        return Err(token.error(format!(
+            "invalid number {}",
+            String::from_utf8_lossy(&token.text)
+        )));
+    }
+    parse_str(token)
+}
+
+fn parse_ttl(token: &Token) -> Result<u32, Located> {
+    ttl_value(&token.text).ok_or_else(|| {
+        token.error(format!(
+            "invalid TTL {}",
+            String::from_utf8_lossy(&token.text)
+        ))
+    })
+}
+
+// 纯数字或带单位的组合，如 1h30m、1W2D，单位不区分大小写
+fn ttl_value(text: &[u8]) -> Option<u32> {
+    if text.is_empty() {
+        return None;
+    }
+    if text.iter().all(u8::is_ascii_digit) {
+        return std::str::from_utf8(text).ok()?.parse().ok();
+    }
+    let mut total = 0_u32;
+    let mut value: Option<u32> = None;
+    for c in text {
+        if c.is_ascii_digit() {
+            let digit = (c - b'0') as u32;
+            value = Some(value.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            b's' => 1,
+            b'm' => 60,
+            b'h' => 3600,
+            b'd' => 86400,
+            b'w' => 604800,
+            _ => return None,
+        };
+        total = total.checked_add(value.take()?.checked_mul(unit)?)?;
+    }
+    // 最后一段必须带单位
+    value.is_none().then_some(total)
+}
+
+// RRSIG中的时间，YYYYMMDDHHmmSS格式或直接的秒数(RFC 4034 3.2)
+fn parse_time(token: &Token) -> Result<u32, Located> {
+    if token.text.len() == 14 && token.text.iter().all(u8::is_ascii_digit) {
+        let text = std::str::from_utf8(&token.text).unwrap_or_default();
+        let time = NaiveDateTime::parse_from_str(text, "%Y%m%d%H%M%S")
+            .map_err(|_| token.error("invalid time"))?;
+        // 按序列号算术取模2^32
+        return Ok(time.and_utc().timestamp().rem_euclid(1 << 32) as u32);
+    }
+    number(token)
+}
+
+// NSEC3中的salt，-表示空
+fn salt(token: &Token) -> Result<Vec<u8>, Located> {
+    if token.text == b"-" {
+        return Ok(Vec::new());
+    }
+    hex_decode(&token.text)
+        .filter(|salt| !salt.is_empty() && salt.len() <= u8::MAX as usize)
+        .ok_or_else(|| token.error("invalid salt"))
+}
+
+pub(crate) fn hex_decode(text: &[u8]) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks(2)
+        .map(|pair| {
+            let high = (pair[0] as char).to_digit(16)?;
+            let low = (pair[1] as char).to_digit(16)?;
+            Some((high << 4 | low) as u8)
+        })
+        .collect()
+}
+
+fn parse_class(text: &[u8]) -> Option<u16> {
+    match text.to_ascii_uppercase().as_slice() {
+        b"IN" => Some(1),
+        b"CS" => Some(2),
+        b"CH" => Some(3),
+        b"HS" => Some(4),
+        upper => generic_number(upper, b"CLASS"),
+    }
+}
+
+// 助记符或RFC 3597的TYPEnnn
+fn parse_type(text: &[u8]) -> Option<u16> {
+    let name = std::str::from_utf8(text).ok()?;
+    if let Some(dns_type) = DnsType::from_name(name) {
+        return Some(dns_type.into());
+    }
+    generic_number(&text.to_ascii_uppercase(), b"TYPE")
+}
+
+fn generic_number(upper: &[u8], prefix: &[u8]) -> Option<u16> {
+    let digits = upper.strip_prefix(prefix)?;
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::types::base::record::SOA;
+    use crate::dns::utils::SliceOperator;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h30m
+; 多行SOA，括号内可以有注释
+@   IN  SOA ns1 hostmaster.example.com. (
+        2024010101 ; serial
+        2h         ; refresh
+        1h         ; retry
+        2w         ; expire
+        300 )      ; minimum
+    IN  NS  ns1
+    NS  ns2.example.net.
+ns1 3600 IN A 192.0.2.1
+    IN 7200 AAAA 2001:db8::1
+www CNAME @
+txt TXT "hello \"world\"" "a\059b" plain \065BC
+dskey 86400 DNSKEY 256 3 5 ( AQOeiiR0GOMYkDshWoSKz9Xz
+                             fwJr1AYtsmx3TGkJaNXVbfi/
+                             2pHm822aJ5iI9BMzNXxeYCmZ
+                             DRD99WYwYqUSdjMmmAphXdvx
+                             egXd/M5+X7OrzKBaMbCVdFLU
+                             Uh6DhweJBjEVv5f2wwjM9Xzc
+                             nOf+EPbtG9DMBmADjFDc2w/r
+                             ljwvFw== )
+dskey DS 60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118
+* TYPE1 \# 4 C0000202
+"#;
+
+    fn rdata(data: &RecordDataType) -> Vec<u8> {
+        SliceOperator::encode_to_vec(|operator| data.encode_rdata(operator))
+    }
+
+    fn name(s: &str) -> RawDomain {
+        RawDomain::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        let records = ZoneParser::new().parse_str(ZONE).unwrap();
+        let owners: Vec<_> = records
+            .iter()
+            .map(|r| r.name.to_string().unwrap())
+            .collect();
+        assert_eq!(
+            owners,
+            [
+                "example.com",
+                "example.com",
+                "example.com",
+                "ns1.example.com",
+                "ns1.example.com",
+                "www.example.com",
+                "txt.example.com",
+                "dskey.example.com",
+                "dskey.example.com",
+                "*.example.com",
+            ]
+        );
+        let types: Vec<_> = records.iter().map(|r| r.rtype).collect();
+        assert_eq!(
+            types,
+            [
+                DnsTypeNum::SOA,
+                DnsTypeNum::NS,
+                DnsTypeNum::NS,
+                DnsTypeNum::A,
+                DnsTypeNum::AAAA,
+                DnsTypeNum::CNAME,
+                DnsTypeNum::TXT,
+                DnsTypeNum::DNSKEY,
+                DnsTypeNum::DS,
+                DnsTypeNum::A,
+            ]
+        );
+        // $TTL 1h30m，显式TTL只作用于所在记录
+        let ttls: Vec<_> = records.iter().map(|r| r.ttl).collect();
+        assert_eq!(
+            ttls,
+            [5400, 5400, 5400, 3600, 7200, 5400, 5400, 86400, 5400, 5400]
+        );
+        assert!(records.iter().all(|r| r.class == 1));
+
+        let soa = SOA::new(
+            Rc::new(name("ns1.example.com")),
+            Rc::new(name("hostmaster.example.com")),
+            2024010101,
+            7200,
+            3600,
+            1209600,
+            300,
+        );
+        assert_eq!(rdata(&records[0].data), rdata(&RecordDataType::SOA(soa)));
+        assert_eq!(
+            rdata(&records[2].data),
+            rdata(&RecordDataType::NS(NS::new(Rc::new(name(
+                "ns2.example.net"
+            )))))
+        );
+        assert!(matches!(&records[3].data, RecordDataType::A(a)
+            if a.get_index() == Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(matches!(&records[4].data, RecordDataType::AAAA(a)
+            if a.get_index() == "2001:db8::1".parse::<Ipv6Addr>().unwrap()));
+        assert_eq!(
+            rdata(&records[5].data),
+            rdata(&RecordDataType::CNAME(CNAME::new(Rc::new(name(
+                "example.com"
+            )))))
+        );
+        let RecordDataType::TXT(txt) = &records[6].data else {
+            panic!("not TXT");
+        };
+        let strings: Vec<_> = txt.get_index().iter().map(|s| s.to_string()).collect();
+        assert_eq!(strings, ["hello \"world\"", "a;b", "plain", "ABC"]);
+
+        // RFC 4034 5.4 的DNSKEY与DS
+        let RecordDataType::DNSKEY(key) = &records[7].data else {
+            panic!("not DNSKEY");
+        };
+        assert_eq!(key.key_tag(), 60485);
+        let RecordDataType::DS(ds) = &records[8].data else {
+            panic!("not DS");
+        };
+        assert_eq!(
+            (ds.key_tag(), ds.algorithm(), ds.digest_type()),
+            (60485, 5, 1)
+        );
+        assert_eq!(ds.digest().len(), 20);
+        assert!(matches!(&records[9].data, RecordDataType::A(a)
+            if a.get_index() == Ipv4Addr::new(192, 0, 2, 2)));
+    }
+
+    #[test]
+    fn test_dnssec_records() {
+        let zone = r"
+$ORIGIN example.
+$TTL 3600
+host RRSIG A 5 3 86400 20030322173103 ( 20030220173103 2642 example.
+            oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTr
+            PYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6o
+            B9wfuh3DTJXUAfI/M0zmO/zz8bW0Rznl8O3t
+            GNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkG
+            J5D6fwFm8nN+6pBzeDQfsS3Ap3o= )
+a NSEC host A MX RRSIG NSEC TYPE1234
+0p9mhaveqvm6t7vbl5lop2u3t2rp3tom NSEC3 1 1 12 aabbccdd (
+    2t7b4g4vsa5smi47k61mv5bv1a22bojr MX DNSKEY NS
+    SOA NSEC3PARAM RRSIG )
+@ NSEC3PARAM 1 0 12 -
+";
+        let records = ZoneParser::new().parse_str(zone).unwrap();
+        assert_eq!(records.len(), 4);
+        let RecordDataType::RRSIG(sig) = &records[0].data else {
+            panic!("not RRSIG");
+        };
+        assert_eq!(sig.type_covered(), DnsTypeNum::A);
+        assert_eq!(sig.original_ttl(), 86400);
+        assert_eq!(sig.sig_expiration(), 1048354263);
+        assert_eq!(sig.sig_inception(), 1045762263);
+        assert_eq!(sig.key_tag(), 2642);
+        assert_eq!(*sig.signer_name(), name("example"));
+        assert_eq!(sig.signature().len(), 128);
+
+        assert_eq!(
+            rdata(&records[1].data),
+            rdata(&RecordDataType::NSEC(NSEC::new(
+                Rc::new(name("host.example")),
+                TypeBitmap::from_types([1, 15, 46, 47, 1234]),
+            )))
+        );
+
+        let RecordDataType::NSEC3(nsec3) = &records[2].data else {
+            panic!("not NSEC3");
+        };
+        assert_eq!(nsec3.iterations(), 12);
+        assert_eq!(nsec3.salt(), [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert!(nsec3.types().contains(DnsTypeNum::NSEC3PARAM));
+        assert_eq!(nsec3.next_hashed_owner().len(), 20);
+        assert_eq!(
+            rdata(&records[3].data),
+            rdata(&RecordDataType::NSEC3PARAM(NSEC3PARAM::new(
+                1,
+                0,
+                12,
+                Vec::new()
+            )))
+        );
+    }
+
+    #[test]
+    fn test_ttl_value() {
+        assert_eq!(ttl_value(b"90"), Some(90));
+        assert_eq!(ttl_value(b"1h30m"), Some(5400));
+        assert_eq!(ttl_value(b"1W2d"), Some(777600));
+        assert_eq!(ttl_value(b"4294967295"), Some(u32::MAX));
+        assert_eq!(ttl_value(b"1h30"), None);
+        assert_eq!(ttl_value(b"h"), None);
+        assert_eq!(ttl_value(b"1y"), None);
+        assert_eq!(ttl_value(b"4294967296"), None);
+        assert_eq!(ttl_value(b"100000w"), None);
+        assert_eq!(ttl_value(b""), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |zone: &str| {
+            let e = ZoneParser::new().parse_str(zone).unwrap_err();
+            (e.line(), e.column())
+        };
+        // 未知类型
+        assert_eq!(error("$TTL 60\na.example. IN FOO 1\n"), (2, 15));
+        // 未闭合的括号
+        assert_eq!(error("$TTL 60\n\na.example. TXT ( \"x\"\n"), (3, 16));
+        assert_eq!(error("a.example. 60 TXT x )\n"), (1, 21));
+        // 没有$ORIGIN时的相对名称与@
+        assert_eq!(error("$TTL 60\nwww A 192.0.2.1\n"), (2, 1));
+        assert_eq!(error("$TTL 60\na.example. CNAME @\n"), (2, 18));
+        assert_eq!(error("a.example. 1x A 192.0.2.1\n"), (1, 12));
+        assert_eq!(error("a.example. A 192.0.2.1\n"), (1, 1));
+        assert_eq!(error("a.example. 60 A 192.0.2.256\n"), (1, 17));
+        assert_eq!(error("a.example. 60 A 192.0.2.1 x\n"), (1, 27));
+        assert_eq!(error("a.example. 60 TXT \"x\n"), (1, 19));
+        assert_eq!(
+            error(&format!("{}.example. 60 A 192.0.2.1", "a".repeat(64))),
+            (1, 1)
+        );
+        assert_eq!(error("a..example. 60 A 192.0.2.1"), (1, 1));
+        assert_eq!(error("a.example. 60 TXT \"\\256\""), (1, 19));
+        assert_eq!(error("a.example. 60 A \\# 4 C00002"), (1, 17));
+        assert_eq!(error("a.example. 60 MX 10 mail.example."), (1, 15));
+        assert_eq!(error("\n\n  $TTL 60"), (3, 3));
+        assert_eq!(error("$GENERATE 1-2 a A 192.0.2.$"), (1, 1));
+        // 字段不足时指向最后一个token之后
+        assert_eq!(
+            error("$ORIGIN example.\n$TTL 60\n@ SOA ns1 host 1 2 3 4"),
+            (3, 23)
+        );
+
+        let e = ZoneParser::new()
+            .parse_str("a.example. 60 A 192.0.2.256")
+            .unwrap_err();
+        assert!(matches!(&e, ZoneError::Syntax { file, .. } if file == "<input>"));
+    }
+
+    #[test]
+    fn test_escaped_name() {
+        let zone = "$ORIGIN example.\n$TTL 60\na\\.b\\032c A 192.0.2.1\n\\@ A 192.0.2.1";
+        let records = ZoneParser::new().parse_str(zone).unwrap();
+        let labels: Vec<_> = records[0].name.labels().collect();
+        assert_eq!(labels, [b"a.b c".as_ref(), b"example"]);
+        let labels: Vec<_> = records[1].name.labels().collect();
+        assert_eq!(labels, [b"@".as_ref(), b"example"]);
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("dns-core-zone-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("hosts.zone"),
+            "$TTL 120\nhost A 192.0.2.10\n$ORIGIN other.\nx A 192.0.2.11\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.zone"),
+            "$ORIGIN example.\n$TTL 60\n$INCLUDE hosts.zone sub.example.\nafter A 192.0.2.12\n\
+             $INCLUDE missing.zone\n",
+        )
+        .unwrap();
+
+        let e = ZoneParser::new()
+            .parse_file(dir.join("main.zone"))
+            .unwrap_err();
+        assert!(matches!(
+            e,
+            ZoneError::Read {
+                line: 5,
+                column: 1,
+                ..
+            }
+        ));
+
+        std::fs::write(
+            dir.join("main.zone"),
+            "$ORIGIN example.\n$TTL 60\n$INCLUDE hosts.zone sub.example.\nafter A 192.0.2.12\n",
+        )
+        .unwrap();
+        let records = ZoneParser::new().parse_file(dir.join("main.zone")).unwrap();
+        let owners: Vec<_> = records
+            .iter()
+            .map(|r| (r.name.to_string().unwrap(), r.ttl))
+            .collect();
+        // 被包含文件中的$ORIGIN与$TTL不影响外层
+        assert_eq!(
+            owners,
+            [
+                ("host.sub.example".to_string(), 120),
+                ("x.other".to_string(), 120),
+                ("after.example".to_string(), 60),
+            ]
+        );
+
+        // 包含自身时在达到深度上限后报错
+        std::fs::write(dir.join("loop.zone"), "$INCLUDE loop.zone\n").unwrap();
+        let e = ZoneParser::new()
+            .parse_file(dir.join("loop.zone"))
+            .unwrap_err();
+        assert!(matches!(&e, ZoneError::Syntax { reason, .. } if reason.contains("nested")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parser_options() {
+        let records = ZoneParser::new()
+            .with_origin(name("example"))
+            .with_default_ttl(30)
+            .with_class(3)
+            .parse_str("@ A 192.0.2.1\nwww IN A 192.0.2.2\nfoo A 192.0.2.3")
+            .unwrap();
+        let fields: Vec<_> = records.iter().map(|r| (r.class, r.ttl)).collect();
+        // class沿用上一条记录
+        assert_eq!(fields, [(3, 30), (1, 30), (1, 30)]);
+        assert_eq!(records[0].name, name("example"));
+    }
+}
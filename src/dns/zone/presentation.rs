@@ -0,0 +1,211 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::RawDomain;
+use crate::dns::types::base::record::{base32hex_encode, hex_upper, type_name};
+use crate::dns::types::parts::RecordDataType;
+use crate::dns::utils::SliceOperator;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::DateTime;
+use std::fmt::Write;
+
+// RFC 1035 5.1 主文件格式中的文本表示，供区域文件与dig风格的输出共用
+
+// 对主文件有特殊含义的字符加反斜杠，不可打印字符与空格转为\DDD
+fn escape_byte(byte: u8, specials: &[u8], out: &mut String) {
+    if specials.contains(&byte) {
+        out.push('\\');
+        out.push(byte as char);
+    } else if byte.is_ascii_graphic() {
+        out.push(byte as char);
+    } else {
+        let _ = write!(out, "\\{:03}", byte);
+    }
+}
+
+fn push_label(label: &[u8], out: &mut String) {
+    for byte in label {
+        escape_byte(*byte, b".\\\"();@$", out);
+    }
+}
+
+fn push_labels<'a, I: Iterator<Item = &'a [u8]>>(labels: I, out: &mut String) {
+    for (i, label) in labels.enumerate() {
+        if i > 0 {
+            out.push('.');
+        }
+        push_label(label, out);
+    }
+}
+
+// 带结尾点的绝对名称，根域名为 .
+pub(crate) fn absolute_name(name: &RawDomain) -> String {
+    let mut out = String::with_capacity(name.as_ref().len() + 1);
+    push_labels(name.labels(), &mut out);
+    out.push('.');
+    out
+}
+
+// origin自身写作@，其下的名称去掉origin部分，其余名称使用绝对形式
+pub(crate) fn relative_name(name: &RawDomain, origin: Option<&RawDomain>) -> String {
+    match origin {
+        Some(origin) if name.is_subdomain_of(origin) => {
+            let n = name.num_labels() - origin.num_labels();
+            if n == 0 {
+                return "@".to_string();
+            }
+            let mut out = String::with_capacity(name.as_ref().len());
+            push_labels(name.labels().take(n), &mut out);
+            out
+        }
+        _ => absolute_name(name),
+    }
+}
+
+// 总是带引号，引号内只需转义 " 与 \
+pub(crate) fn character_string(bytes: &[u8], out: &mut String) {
+    out.push('"');
+    for byte in bytes {
+        match byte {
+            b' ' => out.push(' '),
+            _ => escape_byte(*byte, b"\"\\", out),
+        }
+    }
+    out.push('"');
+}
+
+pub(crate) fn class_name(class: u16) -> String {
+    match class {
+        1 => "IN".to_string(),
+        2 => "CS".to_string(),
+        3 => "CH".to_string(),
+        4 => "HS".to_string(),
+        _ => format!("CLASS{}", class),
+    }
+}
+
+// RRSIG中的时间使用YYYYMMDDHHmmSS格式(RFC 4034 3.2)
+fn rrsig_time(time: u32) -> String {
+    match DateTime::from_timestamp(time as i64, 0) {
+        Some(time) => time.format("%Y%m%d%H%M%S").to_string(),
+        None => time.to_string(),
+    }
+}
+
+fn salt(salt: &[u8]) -> String {
+    if salt.is_empty() {
+        "-".to_string()
+    } else {
+        hex_upper(salt)
+    }
+}
+
+fn types(types: &[u16]) -> String {
+    let names: Vec<String> = types.iter().map(|t| type_name(*t)).collect();
+    names.join(" ")
+}
+
+// name决定RDATA中域名的写法，如相对origin或绝对形式
+pub(crate) fn rdata_text(data: &RecordDataType, name: &dyn Fn(&RawDomain) -> String) -> String {
+    match data {
+        RecordDataType::A(v) => v.get_index().to_string(),
+        RecordDataType::AAAA(v) => v.get_index().to_string(),
+        RecordDataType::NS(v) => name(&v.get_index()),
+        RecordDataType::CNAME(v) => name(&v.get_index()),
+        RecordDataType::SOA(v) => format!(
+            "{} {} {} {} {} {} {}",
+            name(&v.primary_name()),
+            name(&v.rname()),
+            v.serial_number(),
+            v.refresh_interval(),
+            v.retry_interval(),
+            v.expire_limit(),
+            v.minimum_ttl()
+        ),
+        RecordDataType::TXT(v) => {
+            let mut out = String::new();
+            for (i, s) in v.get_index().iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                character_string(s.as_bytes(), &mut out);
+            }
+            out
+        }
+        RecordDataType::DS(v) => format!(
+            "{} {} {} {}",
+            v.key_tag(),
+            v.algorithm(),
+            v.digest_type(),
+            hex_upper(v.digest())
+        ),
+        RecordDataType::DNSKEY(v) => format!(
+            "{} {} {} {}",
+            v.flags(),
+            v.protocol(),
+            v.algorithm(),
+            STANDARD.encode(v.public_key())
+        ),
+        RecordDataType::RRSIG(v) => format!(
+            "{} {} {} {} {} {} {} {} {}",
+            type_name(v.type_covered()),
+            v.algorithm(),
+            v.labels(),
+            v.original_ttl(),
+            rrsig_time(v.sig_expiration()),
+            rrsig_time(v.sig_inception()),
+            v.key_tag(),
+            name(&v.signer_name()),
+            STANDARD.encode(v.signature())
+        ),
+        RecordDataType::NSEC(v) => {
+            format!("{} {}", name(&v.next_domain()), types(v.types().types()))
+        }
+        RecordDataType::NSEC3(v) => format!(
+            "{} {} {} {} {} {}",
+            v.hash_algorithm(),
+            v.flags(),
+            v.iterations(),
+            salt(v.salt()),
+            base32hex_encode(v.next_hashed_owner()),
+            types(v.types().types())
+        ),
+        RecordDataType::NSEC3PARAM(v) => format!(
+            "{} {} {} {}",
+            v.hash_algorithm(),
+            v.flags(),
+            v.iterations(),
+            salt(v.salt())
+        ),
+        // OPT没有文本格式，按RFC 3597的通用格式输出
+        RecordDataType::OPT(_) => {
+            let rdata = SliceOperator::encode_to_vec(|operator| data.encode_rdata(operator));
+            if rdata.is_empty() {
+                "\\# 0".to_string()
+            } else {
+                format!("\\# {} {}", rdata.len(), hex_upper(&rdata))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        let name = RawDomain::from_raw(b"\x05a.b c\x02@\x01\x07example".to_vec());
+        assert_eq!(absolute_name(&name), "a\\.b\\032c.\\@\\001.example.");
+        let origin = RawDomain::from_str("Example").unwrap();
+        assert_eq!(relative_name(&name, Some(&origin)), "a\\.b\\032c.\\@\\001");
+        assert_eq!(relative_name(&origin, Some(&origin)), "@");
+        let other = RawDomain::from_str("example.net").unwrap();
+        assert_eq!(relative_name(&other, Some(&origin)), "example.net.");
+        assert_eq!(absolute_name(&RawDomain::from_raw(Vec::new())), ".");
+
+        let mut out = String::new();
+        character_string(b"say \"hi\"; a\\b\x7f", &mut out);
+        assert_eq!(out, "\"say \\\"hi\\\"; a\\\\b\\127\"");
+    }
+}
@@ -0,0 +1,191 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+use crate::dns::types::base::record::type_name;
+use crate::dns::types::base::{DnsTypeNum, RawDomain};
+use crate::dns::types::parts::Record;
+use crate::dns::zone::presentation::{class_name, rdata_text, relative_name};
+use std::fmt::Write;
+
+// 把记录写成BIND/NSD可以读取的主文件文本：
+// 记录按RRset规范排序(同一owner下SOA在最前)并去重，OPT伪记录被忽略，
+// 每行都写出owner、TTL与class，各列按最长的内容对齐
+#[derive(Debug, Default)]
+pub struct ZoneWriter {
+    origin: Option<RawDomain>,
+}
+
+impl ZoneWriter {
+    pub fn new() -> ZoneWriter {
+        ZoneWriter { origin: None }
+    }
+
+    // 设置后开头写出$ORIGIN，origin之下的名称写成相对形式
+    pub fn with_origin(mut self, origin: RawDomain) -> ZoneWriter {
+        self.origin = Some(origin);
+        self
+    }
+
+    pub fn write<'a, I, W>(&self, records: I, out: &mut W) -> std::fmt::Result
+    where
+        I: IntoIterator<Item = &'a Record>,
+        W: Write,
+    {
+        let origin = self.origin.as_ref();
+        let name = |name: &RawDomain| relative_name(name, origin);
+
+        let mut sorted: Vec<(&Record, Vec<u8>)> = records
+            .into_iter()
+            .filter(|r| r.rtype != DnsTypeNum::OPT)
+            .map(|r| (r, r.data.canonical_rdata()))
+            .collect();
+        sorted.sort_by(|(a, a_rdata), (b, b_rdata)| {
+            a.name
+                .cmp(&b.name)
+                .then(a.class.cmp(&b.class))
+                .then(rrset_order(a.rtype).cmp(&rrset_order(b.rtype)))
+                .then_with(|| a_rdata.cmp(b_rdata))
+        });
+        sorted.dedup_by(|(a, a_rdata), (b, b_rdata)| {
+            a.name == b.name && a.class == b.class && a.rtype == b.rtype && a_rdata == b_rdata
+        });
+
+        let rows: Vec<[String; 5]> = sorted
+            .iter()
+            .map(|(r, _)| {
+                [
+                    name(&r.name),
+                    r.ttl.to_string(),
+                    class_name(r.class),
+                    type_name(r.rtype),
+                    rdata_text(&r.data, &name),
+                ]
+            })
+            .collect();
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len());
+            }
+        }
+
+        if let Some(origin) = origin {
+            writeln!(out, "$ORIGIN {}", relative_name(origin, None))?;
+        }
+        for [owner, ttl, class, rtype, rdata] in &rows {
+            writeln!(
+                out,
+                "{:<w0$} {:<w1$} {:<w2$} {:<w3$} {}",
+                owner,
+                ttl,
+                class,
+                rtype,
+                rdata,
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn format<'a, I>(&self, records: I) -> String
+    where
+        I: IntoIterator<Item = &'a Record>,
+    {
+        let mut out = String::new();
+        // 写入String不会失败
+        let _ = self.write(records, &mut out);
+        out
+    }
+}
+
+// 同一owner下SOA排在最前，其余按类型号升序
+#[inline]
+fn rrset_order(rtype: u16) -> (bool, u16) {
+    (rtype != DnsTypeNum::SOA, rtype)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::zone::ZoneParser;
+
+    const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 3600
+www     A     192.0.2.2
+www     A     192.0.2.1
+www     A     192.0.2.1
+@       NS    ns1
+@       SOA   ns1 hostmaster 1 7200 3600 1209600 300
+txt     TXT   "semi;colon" "quote\"back\\slash" "tab\009"
+a\.b    CNAME other.example.net.
+ns1     60 AAAA  2001:db8::53
+_sip._tcp.Sub CH TXT "x"
+"#;
+
+    #[test]
+    fn test_write_zone() {
+        let records = ZoneParser::new().parse_str(ZONE).unwrap();
+        let origin = RawDomain::from_str("example.com").unwrap();
+        let text = ZoneWriter::new().with_origin(origin).format(&records);
+        assert_eq!(
+            text,
+            concat!(
+                "$ORIGIN example.com.\n",
+                "@             3600 IN SOA   ns1 hostmaster 1 7200 3600 1209600 300\n",
+                "@             3600 IN NS    ns1\n",
+                "a\\.b          3600 IN CNAME other.example.net.\n",
+                "ns1           60   IN AAAA  2001:db8::53\n",
+                "_sip._tcp.Sub 3600 CH TXT   \"x\"\n",
+                "txt           3600 IN TXT   \"semi;colon\" \"quote\\\"back\\\\slash\" \"tab\\009\"\n",
+                "www           3600 IN A     192.0.2.1\n",
+                "www           3600 IN A     192.0.2.2\n",
+            )
+        );
+    }
+
+    fn canonical(records: &[Record]) -> Vec<(Vec<u8>, u16, u16, u32, Vec<u8>)> {
+        let mut v: Vec<_> = records
+            .iter()
+            .map(|r| {
+                (
+                    r.name.to_canonical_wire(),
+                    r.class,
+                    r.rtype,
+                    r.ttl,
+                    r.data.canonical_rdata(),
+                )
+            })
+            .collect();
+        v.sort();
+        v.dedup();
+        v
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let zone = format!(
+            "{}{}",
+            ZONE,
+            r"
+sec IN DNSKEY 257 3 8 AwEAAag=
+sec DS 60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118
+sec RRSIG A 8 3 3600 20030322173103 20030220173103 2642 example.com. oJB1W6WNGv+ldvQ3
+sec NSEC \@.example.com. A RRSIG NSEC TYPE1234
+sec NSEC3 1 1 12 AABBCCDD 2T7B4G4VSA5SMI47K61MV5BV1A22BOJR A RRSIG
+sec NSEC3PARAM 1 0 0 -
+"
+        );
+        let records = ZoneParser::new().parse_str(&zone).unwrap();
+        let origin = RawDomain::from_str("example.com").unwrap();
+        for writer in [ZoneWriter::new(), ZoneWriter::new().with_origin(origin)] {
+            let text = writer.format(&records);
+            let reparsed = ZoneParser::new().parse_str(&text).unwrap();
+            assert_eq!(canonical(&reparsed), canonical(&records), "{}", text);
+            // 再次写出的文本保持不变
+            assert_eq!(writer.format(&reparsed), text);
+        }
+    }
+}
@@ -2,6 +2,9 @@ extern crate core;
 
 use dns_core::test_decode_from;
 
+#[cfg(test)]
+#[allow(dead_code)]
+mod bench_func;
 mod dns;
 fn main() {
     test_decode_from();